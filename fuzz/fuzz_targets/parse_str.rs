@@ -0,0 +1,16 @@
+#![no_main]
+//! Feeds arbitrary source text to the comment state machine, which must never panic: every
+//! input either stores its blocks or is reported as an error.
+
+use get_comments::Comments;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    for (start, end) in [("//#", None), ("/*#", Some("*/"))] {
+        let mut comments = Comments::new()
+            .with_start_marker(start)
+            .with_end_marker(end)
+            .with_path_prefixes("EPIC.ITEM.TASK");
+        let _ = comments.parse_str("fuzz.rs", source);
+    }
+});
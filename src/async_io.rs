@@ -0,0 +1,196 @@
+use crate::encoding::{Decoded, SourceEncodings};
+use crate::events::{Event, Events};
+use crate::exclude::Exclusions;
+use std::collections::VecDeque;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs;
+use tokio::task::JoinHandle;
+
+/// The number of source files that are read at the same time.
+const READ_CONCURRENCY: usize = 16;
+
+/// Finds every file below `root` whose name ends with one of `extensions`, within the limits
+/// of `exclusions` as `pipeline::read_source_files` does. Symbolic links are followed unless
+/// the exclusions say otherwise, but never back to a folder the walk is in.
+///
+/// Files are returned in the order `WalkDir` visits them: depth first, and with every folder's
+/// entries sorted by file name. After every file found the walk waits for
+/// `pause`, which throttles background runs. The entries that are left out are sent to
+/// `events` as `Event::EntrySkipped`.
+pub async fn find_files(
+    root: &str,
+    extensions: &[String],
+    exclusions: &Exclusions,
+    pause: Duration,
+    events: &Events,
+) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    // every path still to visit, with its level and the resolved folders above it
+    let mut pending = vec![(PathBuf::from(root), 0, Vec::new())];
+    while let Some((path, depth, ancestors)) = pending.pop() {
+        if exclusions.is_excluded(&path) {
+            continue;
+        }
+        if depth > 0
+            && !exclusions.follows_links()
+            && fs::symlink_metadata(&path)
+                .await
+                .is_ok_and(|metadata| metadata.file_type().is_symlink())
+        {
+            skipped(events, &path, "symbolic link not followed");
+            continue;
+        }
+        let metadata = match fs::metadata(&path).await {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                skipped(events, &path, &error.to_string());
+                continue;
+            }
+        };
+        if metadata.is_file() {
+            if path.file_name().is_some_and(|name| {
+                let name = name.to_string_lossy();
+                extensions.iter().any(|extension| name.ends_with(extension))
+            }) {
+                files.push(path);
+                if !pause.is_zero() {
+                    tokio::time::sleep(pause).await;
+                }
+            }
+        } else if metadata.is_dir() {
+            if exclusions.max_depth() == Some(depth) {
+                skipped(events, &path, "folder below -max-depth");
+                continue;
+            }
+            let Ok(folder) = fs::canonicalize(&path).await else {
+                continue;
+            };
+            if ancestors.contains(&folder) {
+                let reason = format!("symbolic link loop back to {}", folder.display());
+                skipped(events, &path, &reason);
+                continue;
+            }
+            if let Ok(mut entries) = fs::read_dir(&path).await {
+                let mut children = Vec::new();
+                while let Ok(Some(entry)) = entries.next_entry().await {
+                    children.push(entry.path());
+                }
+                children.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+                let mut ancestors = ancestors;
+                ancestors.push(folder);
+                pending.extend(
+                    children
+                        .into_iter()
+                        .rev()
+                        .map(|child| (child, depth + 1, ancestors.clone())),
+                );
+            }
+        }
+    }
+    files
+}
+
+/// Tells the subscriber of the run that the walk left out an entry.
+fn skipped(events: &Events, path: &Path, reason: &str) {
+    events.emit(Event::EntrySkipped { path, reason });
+}
+
+/// The reads of a list of files, at most `READ_CONCURRENCY` at a time. The content is handed
+/// out in the order of the paths, transcoded to UTF-8, and only the files read ahead of the
+/// parser are held in memory.
+pub struct FileReads {
+    paths: std::vec::IntoIter<PathBuf>,
+    reads: VecDeque<(PathBuf, JoinHandle<Result<Decoded, Error>>)>,
+    encodings: Arc<SourceEncodings>,
+}
+
+impl FileReads {
+    /// Starts reading the first files of `paths`, transcoded with `encodings`.
+    pub fn new(paths: Vec<PathBuf>, encodings: &SourceEncodings) -> FileReads {
+        let mut reads = FileReads {
+            paths: paths.into_iter(),
+            reads: VecDeque::new(),
+            encodings: Arc::new(encodings.clone()),
+        };
+        reads.read_ahead();
+        reads
+    }
+    /// Returns the next file and its content, and starts the read of another file, or `None`
+    /// when every file was handed out.
+    pub async fn next(&mut self) -> Option<(PathBuf, Result<Decoded, Error>)> {
+        let (path, read) = self.reads.pop_front()?;
+        self.read_ahead();
+        let content = read
+            .await
+            .unwrap_or_else(|_| Err(Error::other("read task failed")));
+        Some((path, content))
+    }
+    /// Starts reads until `READ_CONCURRENCY` files are being read or all reads are started.
+    fn read_ahead(&mut self) {
+        while self.reads.len() < READ_CONCURRENCY
+            && let Some(path) = self.paths.next()
+        {
+            let encodings = Arc::clone(&self.encodings);
+            let read_path = path.clone();
+            let read = tokio::spawn(async move {
+                fs::read(&read_path)
+                    .await
+                    .map(|content| encodings.decode(&read_path, content))
+            });
+            self.reads.push_back((path, read));
+        }
+    }
+}
+
+/// Writes `content` to the file at `path` unless the file already holds it, creating the
+/// file and its folders when needed.
+///
+/// # Returns:
+/// - `Ok(bool)` telling whether the file was written
+pub async fn write_file_if_changed(path: &Path, content: &[u8]) -> Result<bool, Error> {
+    if fs::read(path)
+        .await
+        .is_ok_and(|existing| existing == content)
+    {
+        return Ok(false);
+    }
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder).await?;
+    }
+    fs::write(path, content).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+#[test]
+fn test_find_and_read_files() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let files = runtime.block_on(find_files(
+        "testdata",
+        &[".txt".to_string()],
+        &Exclusions::default(),
+        Duration::ZERO,
+        &Events::default(),
+    ));
+    let walked: Vec<PathBuf> = walkdir::WalkDir::new("testdata")
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name().to_string_lossy().ends_with(".txt"))
+        .map(|e| e.into_path())
+        .collect();
+    assert_eq!(files, walked);
+    let read = runtime.block_on(async {
+        let mut reads = FileReads::new(files.clone(), &SourceEncodings::default());
+        let mut read = Vec::new();
+        while let Some((path, content)) = reads.next().await {
+            assert!(content.is_ok());
+            read.push(path);
+        }
+        read
+    });
+    assert_eq!(read, files);
+}
@@ -0,0 +1,65 @@
+use std::io::Error;
+use std::process::Command;
+use std::time::Duration;
+
+/// The pause between two files in the walker and writer stages of a background run.
+pub const FILE_PAUSE: Duration = Duration::from_millis(5);
+
+/// Lowers the CPU and I/O priority of this process for a background run.
+///
+/// On Unix the CPU priority is lowered with `renice` and, where it exists, the I/O priority
+/// with `ionice` in the idle class. On Windows the priority class is set to below normal
+/// through PowerShell.
+///
+/// # Returns:
+/// - `Err(Error)` naming every tool that could not be started or failed
+pub fn lower_priority() -> Result<(), Error> {
+    let pid = std::process::id().to_string();
+    if cfg!(windows) {
+        let script = format!("(Get-Process -Id {pid}).PriorityClass = 'BelowNormal'");
+        return run("powershell", &["-NoProfile", "-Command", &script]);
+    }
+    let mut failures = vec![];
+    if let Err(error) = run("renice", &["-n", "10", "-p", &pid]) {
+        failures.push(error.to_string());
+    }
+    if cfg!(target_os = "linux")
+        && let Err(error) = run("ionice", &["-c", "3", "-p", &pid])
+    {
+        failures.push(error.to_string());
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::other(failures.join("; ")))
+    }
+}
+
+/// Runs a priority tool and turns a failed start or a non zero exit status into an error.
+fn run(program: &str, arguments: &[&str]) -> Result<(), Error> {
+    let output = Command::new(program)
+        .args(arguments)
+        .output()
+        .map_err(|error| Error::other(format!("{program} could not be started: {error}")))?;
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+#[test]
+fn test_run_priority_tool() {
+    assert!(run("true", &[]).is_ok());
+    let error = run("false", &[]).unwrap_err();
+    assert!(error.to_string().starts_with("false failed"));
+    let error = run("get-comments-no-such-tool", &[]).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("get-comments-no-such-tool could not be started")
+    );
+}
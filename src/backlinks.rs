@@ -0,0 +1,132 @@
+use crate::block::{Sequences, relative_name};
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// A `[[wiki link]]` in a block that names no generated file.
+#[derive(Debug, PartialEq)]
+pub struct DanglingLink {
+    pub target: String,
+    pub source_file: Arc<str>,
+    pub line: usize,
+}
+
+/// The wiki links between the generated files of a run.
+#[derive(Debug, Default)]
+pub struct CrossReferences {
+    /// The names of the files linking to a file, keyed by the history key of the linked file.
+    pub backlinks: HashMap<String, BTreeSet<String>>,
+    /// The links that did not resolve, in history order.
+    pub dangling: Vec<DanglingLink>,
+}
+
+//#EPIC Get Lines.ITEM Resolve cross references [0]
+//#
+//## Resolve cross references
+//#Blocks refer to each other with `[[docs/EPIC A/ITEM B.md]]` wiki links. Every link is looked up
+//#among the generated files: a link that names no generated file is reported as dangling, and
+//#every generated file ends with the list of files that link to it.
+/// Resolves the `[[wiki links]]` in the block bodies against the generated files.
+///
+/// A link target is the path of a generated file, with or without the `.md` extension, either
+/// below the document root, as in `[[EPIC A/ITEM B]]`, or starting with the document root, as
+/// in `[[docs/EPIC A/ITEM B.md]]`. A `#heading` or `|alias` after the target is ignored. Raw
+/// lines are not searched, and a file linking to itself gets no backlink. `root` is the
+/// document root as the links name it, which differs from the `doc_root` of the history keys
+/// when the run writes into a staging folder.
+pub fn resolve_links(
+    history: &HashMap<String, Sequences>,
+    doc_root: &str,
+    root: &str,
+) -> CrossReferences {
+    let link_pattern = Regex::new(r"\[\[([^\]|#]+)[^\]]*\]\]").unwrap();
+    let root = root.trim_start_matches("./").trim_end_matches('/');
+    let files: HashMap<String, &String> = history
+        .keys()
+        .map(|key| (relative_name(key, doc_root).replace('.', "/"), key))
+        .collect();
+    let mut keys: Vec<&String> = history.keys().collect();
+    keys.sort();
+    let mut references = CrossReferences::default();
+    for key in keys {
+        let source = relative_name(key, doc_root);
+        for block in history[key].values() {
+            for (index, line) in block.lines.iter().enumerate() {
+                if block.raw.contains(&index) {
+                    continue;
+                }
+                for link in link_pattern.captures_iter(line) {
+                    let target = link[1].trim();
+                    let path = target.strip_suffix(".md").unwrap_or(target);
+                    let path = path.trim_start_matches("./");
+                    let path = match files.contains_key(path) {
+                        true => path,
+                        false => path
+                            .strip_prefix(root)
+                            .and_then(|rest| rest.strip_prefix('/'))
+                            .unwrap_or(path),
+                    };
+                    match files.get(path) {
+                        Some(linked) if *linked != key => {
+                            references
+                                .backlinks
+                                .entry(linked.trim().to_string())
+                                .or_default()
+                                .insert(source.to_string());
+                        }
+                        Some(_) => {}
+                        None => references.dangling.push(DanglingLink {
+                            target: target.to_string(),
+                            source_file: block.source_file.clone(),
+                            line: block.start_line + index,
+                        }),
+                    }
+                }
+            }
+        }
+    }
+    references
+}
+
+#[cfg(test)]
+#[test]
+fn test_resolve_links() {
+    use crate::block::Block;
+    use crate::sequence::Sequence;
+    let block = |lines: &[&str]| {
+        let lines = lines.iter().map(|line| line.to_string()).collect();
+        Block::new("a.rs".into(), 2, lines, &BTreeSet::new())
+    };
+    let history: HashMap<String, Sequences> = [
+        (
+            "docs.EPIC A",
+            block(&["See [[docs/EPIC A/ITEM B.md]] and [[EPIC A/ITEM B#Usage|usage]]"]),
+        ),
+        ("docs.EPIC A.ITEM B", block(&["", "Back to [[EPIC A]], [[EPIC Z]]"])),
+    ]
+    .into_iter()
+    .map(|(key, block)| {
+        (
+            key.to_string(),
+            Sequences::from([(Sequence::from(0), block)]),
+        )
+    })
+    .collect();
+    let references = resolve_links(&history, "docs", "docs");
+    assert_eq!(
+        references.backlinks["docs.EPIC A.ITEM B"],
+        BTreeSet::from(["EPIC A".to_string()])
+    );
+    assert_eq!(
+        references.backlinks["docs.EPIC A"],
+        BTreeSet::from(["EPIC A.ITEM B".to_string()])
+    );
+    assert_eq!(
+        references.dangling,
+        [DanglingLink {
+            target: "EPIC Z".to_string(),
+            source_file: "a.rs".into(),
+            line: 3,
+        }]
+    );
+}
@@ -0,0 +1,322 @@
+use crate::schema::FieldSchema;
+use crate::sequence::Sequence;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufWriter, Error, Write};
+use std::sync::{Arc, LazyLock};
+use std::time::SystemTime;
+
+/// All sequences of one block name, ordered by sequence number.
+pub type Sequences = BTreeMap<Sequence, Block>;
+
+/// Returns the dot delimited block name of a history key, without the document root.
+pub fn relative_name<'k>(key: &'k str, doc_root: &str) -> &'k str {
+    let key = key.trim();
+    key.strip_prefix(doc_root)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .unwrap_or(key)
+}
+
+/// The directives read by get-comments itself, which are lifted out of every block body.
+pub const KNOWN_DIRECTIVES: [&str; 6] = [
+    "path",
+    "include-code",
+    "requirement",
+    "tags",
+    "status",
+    "owner",
+];
+
+/// A body line of the form `@name: value`.
+static DIRECTIVE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*@([A-Za-z][\w-]*):\s*(.*)$").unwrap());
+
+/// Returns the lower case name and the value of a `@name: value` line when `name` is one of
+/// the `KNOWN_DIRECTIVES` or a field declared in `fields`. Other such lines are body text.
+pub fn directive(line: &str, fields: Option<&FieldSchema>) -> Option<(String, String)> {
+    let capture = DIRECTIVE.captures(line)?;
+    let name = capture[1].to_lowercase();
+    (KNOWN_DIRECTIVES.contains(&name.as_str())
+        || fields.is_some_and(|fields| fields.field_type(&name).is_some()))
+    .then(|| (name, capture[2].trim().to_string()))
+}
+
+/// The last commit that changed the lines of a block, as found by `git blame`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LastCommit {
+    /// The abbreviated commit hash.
+    pub commit: String,
+    pub author: String,
+    /// The author date, as `YYYY-MM-DD` in UTC.
+    pub date: String,
+}
+
+/// One sequence of a line block as found in a source file.
+///
+/// A block is created when the parser leaves a line block. It remembers where the block was
+/// found so the `[SOURCE FILE:]` link can be written, the body lines after the comment marker
+/// was stripped and the filters were applied, and the `@key: value` directives that were
+/// lifted out of the body.
+///
+/// Blocks are `Send + Sync` and their strings are shared through `Arc`, so a block, and with
+/// it the whole comment history, is cheap to clone and can be handed to other threads.
+///
+/// Blocks are `Serialize` and `Deserialize`, so a history can be persisted and exchanged in any
+/// serde format. Fields missing from the input get their default value.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Block {
+    /// Path of the source file as it must appear in the generated output.
+    pub source_file: Arc<str>,
+    /// Path of the source file on disk.
+    pub path: Arc<str>,
+    /// Line number of the first line of the block in the source file.
+    pub start_line: usize,
+    /// Line number of the last line of the block in the source file.
+    pub end_line: usize,
+    /// Body lines of the block, after the directives were lifted out and the filters were
+    /// applied. This is the text that is rendered, see `rendered_text`.
+    pub lines: Arc<[String]>,
+    /// The lines of the block as found in the source file with only the comment marker
+    /// stripped: before directives were lifted out and before filters, see `source_text`.
+    /// Empty for blocks read back from a blocks file.
+    pub source_lines: Arc<[String]>,
+    /// Indices in `lines` of raw lines, which are passed through without filters or rewriting.
+    pub raw: BTreeSet<usize>,
+    /// Directives such as `@status: beta` or `@owner: Jan`, keyed by lower case name.
+    pub metadata: BTreeMap<String, String>,
+    /// Last modification time of the source file, if it could be read. It is machine specific,
+    /// so it is not serialized and reproducible output stays the same across checkouts.
+    #[serde(skip)]
+    pub modified: Option<SystemTime>,
+    /// The first line of code following the block, which is the code the block documents.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_anchor: Option<Arc<str>>,
+    /// Whether the block was found in a machine-generated source file, see
+    /// `Comments::with_generated_sources`. Its blocks are written after the hand-written ones.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub generated: bool,
+    /// The last commit that changed the lines of the block, see
+    /// `Comments::with_git_metadata`. Written after the line range of the `[SOURCE FILE:]`
+    /// line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_commit: Option<LastCommit>,
+}
+
+impl Block {
+    /// Creates a block from the marker stripped lines of a line block, lifting out the
+    /// `KNOWN_DIRECTIVES` only, see `with_fields`.
+    pub fn new(
+        source_file: Arc<str>,
+        start_line: usize,
+        lines: Vec<String>,
+        raw: &BTreeSet<usize>,
+    ) -> Block {
+        Block::with_fields(source_file, start_line, lines, raw, None)
+    }
+    /// Creates a block from the marker stripped lines of a line block.
+    ///
+    /// Every body line `@name: value` naming a known directive or a field declared in
+    /// `fields` is removed from the body and stored in `metadata` under the lower case
+    /// `name`, see `directive`. Other `@name: value` lines, and lines such as
+    /// `@badge[status=beta]`, stay in the body. Lines whose index is in `raw` are never
+    /// directives.
+    pub fn with_fields(
+        source_file: Arc<str>,
+        start_line: usize,
+        lines: Vec<String>,
+        raw: &BTreeSet<usize>,
+        fields: Option<&FieldSchema>,
+    ) -> Block {
+        let source_lines: Arc<[String]> = lines.clone().into();
+        let mut metadata = BTreeMap::new();
+        let mut body = Vec::with_capacity(lines.len());
+        let mut body_raw = BTreeSet::new();
+        for (index, line) in lines.into_iter().enumerate() {
+            if raw.contains(&index) {
+                body_raw.insert(body.len());
+                body.push(line);
+            } else if let Some((name, value)) = directive(&line, fields) {
+                metadata.insert(name, value);
+            } else {
+                body.push(line);
+            }
+        }
+        Block {
+            source_file,
+            start_line,
+            lines: body.into(),
+            source_lines,
+            raw: body_raw,
+            metadata,
+            ..Default::default()
+        }
+    }
+    /// Appends the body of a block continuing this one from another file, see
+    /// `HeaderGrammar::is_continuation`. The directives of the continuation are added where
+    /// this block does not set them.
+    pub fn append(&mut self, continuation: Block) {
+        let offset = self.lines.len();
+        self.raw
+            .extend(continuation.raw.iter().map(|index| index + offset));
+        self.lines = self
+            .lines
+            .iter()
+            .chain(continuation.lines.iter())
+            .cloned()
+            .collect();
+        self.source_lines = self
+            .source_lines
+            .iter()
+            .chain(continuation.source_lines.iter())
+            .cloned()
+            .collect();
+        for (name, value) in continuation.metadata {
+            self.metadata.entry(name).or_insert(value);
+        }
+        self.modified = self.modified.max(continuation.modified);
+    }
+    /// Returns the value of the `@path` directive, which places the block in that file below
+    /// the document root instead of the file derived from its name.
+    pub fn path_override(&self) -> Option<&str> {
+        self.metadata.get("path").map(String::as_str)
+    }
+    /// Returns the tags of the `@tags: a, b` directive, trimmed and without empty ones.
+    pub fn tags(&self) -> Vec<&str> {
+        self.metadata
+            .get("tags")
+            .map(|tags| {
+                tags.split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// Returns the marker stripped lines as found in the source file, one per line, for
+    /// consumers such as search indexers that want the text as the author wrote it.
+    pub fn source_text(&self) -> String {
+        self.source_lines.join("\n")
+    }
+    /// Returns the body as it is rendered into the generated file, one line per line, without
+    /// the `[SOURCE FILE:]` link. Glossary links are only added when the file is written.
+    pub fn rendered_text(&self) -> String {
+        self.lines.join("\n")
+    }
+    /// Returns the lines of the block in its source file, as `start-end`, or as `start` for a
+    /// block of one line or a block read back without its end line.
+    pub fn line_range(&self) -> String {
+        match self.end_line > self.start_line {
+            true => format!("{}-{}", self.start_line, self.end_line),
+            false => self.start_line.to_string(),
+        }
+    }
+    /// Returns the last commit of the block as written after the line range of the
+    /// `[SOURCE FILE:]` line, such as ` COMMIT: 1a2b3c4 AUTHOR: Jan DATE: 2024-05-01`, or an
+    /// empty string when the block has no last commit.
+    pub fn commit_note(&self) -> String {
+        self.last_commit
+            .as_ref()
+            .map(|last| {
+                format!(
+                    " COMMIT: {} AUTHOR: {} DATE: {}",
+                    last.commit, last.author, last.date
+                )
+            })
+            .unwrap_or_default()
+    }
+    /// Writes the `[SOURCE FILE:]` link followed by the body lines and a trailing blank line.
+    pub fn write_to<W: Write>(&self, writer: &mut BufWriter<W>) -> Result<(), Error> {
+        self.write_with_link(writer, Some(&format!("file:///{}", self.source_file)))
+    }
+    /// Writes the `[SOURCE FILE:]` link with the given target, or the source file as text
+    /// when there is no target, followed by the body lines and a trailing blank line.
+    pub fn write_with_link<W: Write>(
+        &self,
+        writer: &mut BufWriter<W>,
+        link: Option<&str>,
+    ) -> Result<(), Error> {
+        self.write_source_line(writer, link)?;
+        for line in self.lines.iter() {
+            writeln!(writer, "{}", line)?;
+        }
+        writeln!(writer)?;
+        Ok(())
+    }
+    /// Writes the Markdown `[SOURCE FILE:]` link with the given target, or the source file as
+    /// text when there is no target, followed by a blank line.
+    pub fn write_source_line<W: Write>(
+        &self,
+        writer: &mut BufWriter<W>,
+        link: Option<&str>,
+    ) -> Result<(), Error> {
+        match link {
+            Some(link) => writeln!(
+                writer,
+                "[SOURCE FILE:]({link}) LINE: {}{}\n",
+                self.line_range(),
+                self.commit_note()
+            )?,
+            None => writeln!(
+                writer,
+                "SOURCE FILE: {} LINE: {}{}\n",
+                self.source_file,
+                self.line_range(),
+                self.commit_note()
+            )?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_block_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync + Clone>() {}
+    assert_send_sync::<Block>();
+    assert_send_sync::<Sequences>();
+    let block = Block::new("a.rs".into(), 1, vec!["text".to_string()], &BTreeSet::new());
+    let copy = block.clone();
+    assert!(Arc::ptr_eq(&block.lines, &copy.lines));
+    let lines = vec!["@status: beta".to_string(), "text".to_string()];
+    let block = Block::new("a.rs".into(), 1, lines, &BTreeSet::new());
+    assert_eq!(block.source_text(), "@status: beta\ntext");
+    assert_eq!(block.rendered_text(), "text");
+    let lines = vec!["@due: 2024-05-01".to_string(), "@see: a.rs".to_string()];
+    let fields = FieldSchema::parse("due = \"date\"\n").unwrap();
+    let declared = Block::with_fields(
+        "a.rs".into(),
+        1,
+        lines.clone(),
+        &BTreeSet::new(),
+        Some(&fields),
+    );
+    assert_eq!(declared.metadata["due"], "2024-05-01");
+    assert_eq!(declared.rendered_text(), "@see: a.rs");
+    assert!(
+        Block::new("a.rs".into(), 1, lines, &BTreeSet::new())
+            .metadata
+            .is_empty()
+    );
+    let block = Block {
+        start_line: 70_000,
+        end_line: 70_002,
+        ..block
+    };
+    assert_eq!(block.line_range(), "70000-70002");
+    assert_eq!(Block::default().line_range(), "0");
+    let block = Block {
+        last_commit: Some(LastCommit {
+            commit: "1a2b3c4".into(),
+            author: "Jan".into(),
+            date: "2024-05-01".into(),
+        }),
+        ..block
+    };
+    assert_eq!(
+        block.commit_note(),
+        " COMMIT: 1a2b3c4 AUTHOR: Jan DATE: 2024-05-01"
+    );
+}
@@ -0,0 +1,77 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The source of the current time of a run, see `Comments::with_clock`. Checks that depend on
+/// the time, such as the stale check, read it from a clock so they can be run at a fixed time.
+///
+/// Any `Fn() -> SystemTime` is a clock, such as `SystemTime::now`.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+impl<F: Fn() -> SystemTime + Send + Sync> Clock for F {
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
+
+/// Formats a point in time as an ISO 8601 calendar date (`YYYY-MM-DD`) in UTC.
+pub fn format_date(time: SystemTime) -> String {
+    let (year, month, day) = civil_from_days(seconds_since_epoch(time).div_euclid(86_400));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Formats a point in time as an ISO 8601 timestamp (`YYYY-MM-DDTHH:MM:SSZ`) in UTC.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let seconds = seconds_since_epoch(time);
+    let of_day = seconds.rem_euclid(86_400);
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_date(time),
+        of_day / 3_600,
+        of_day % 3_600 / 60,
+        of_day % 60
+    )
+}
+
+/// Returns the seconds from 1970-01-01 UTC to a point in time, negative before it.
+pub(crate) fn seconds_since_epoch(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(error) => -(error.duration().as_secs() as i64),
+    }
+}
+
+/// Converts a day count relative to 1970-01-01 to a (year, month, day) triple in the
+/// proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+#[test]
+fn test_format_date_and_timestamp() {
+    use std::time::Duration;
+    assert_eq!(format_date(UNIX_EPOCH), "1970-01-01");
+    assert_eq!(
+        format_date(UNIX_EPOCH + Duration::from_secs(1_709_164_800)),
+        "2024-02-29"
+    );
+    assert_eq!(
+        format_timestamp(UNIX_EPOCH + Duration::from_secs(1_709_164_800 + 3_723)),
+        "2024-02-29T01:02:03Z"
+    );
+}
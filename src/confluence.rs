@@ -0,0 +1,381 @@
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd, html::push_html};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs::{File, remove_file};
+use std::io::{Error, ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// A Confluence space the pages are published to, below an optional parent page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfluenceTarget {
+    /// The base URL of the REST API, such as `https://example.atlassian.net/wiki`.
+    url: String,
+    space: String,
+    parent: Option<String>,
+}
+
+/// One level of the block hierarchy, such as an EPIC with its ITEMs, published as one page.
+#[derive(Default)]
+struct PageNode<'p> {
+    /// The Markdown of the page, `None` for a level without own blocks.
+    markdown: Option<&'p str>,
+    children: BTreeMap<&'p str, PageNode<'p>>,
+}
+
+/// The number of pages created and updated by `publish`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Published {
+    pub created: usize,
+    pub updated: usize,
+}
+
+impl ConfluenceTarget {
+    /// Creates the target of a Confluence site, a space key and the id of the page the tree is
+    /// published under, the home page of the space when `None`.
+    pub fn new(url: &str, space: &str, parent: Option<&str>) -> ConfluenceTarget {
+        ConfluenceTarget {
+            url: url.trim_end_matches('/').to_string(),
+            space: space.to_string(),
+            parent: parent.map(str::to_string),
+        }
+    }
+    //#EPIC Get Lines.ITEM Publish to Confluence [0]
+    //#
+    //## Publish to Confluence
+    //#`publish confluence` converts the page of every block name to the Confluence storage
+    //#format and creates or updates it through the REST API. The pages form a tree like the
+    //#document root: an ITEM page is a child of its EPIC page, and a level without own blocks
+    //#gets an empty page so the tree stays intact.
+    /// Creates or updates a page for every block name, and for every level above it, so the
+    /// pages mirror the block hierarchy. A page is found again by its title below its parent
+    /// page; a page of that title elsewhere in the space is not touched, and fails the run.
+    ///
+    /// # Parameters:
+    /// - `pages`: The dot delimited block name of every page, without the document root, with
+    ///   its Markdown content
+    ///
+    /// # Returns:
+    /// - `Err(Error)` when `CONFLUENCE_TOKEN` is not set or a request fails
+    pub fn publish(&self, pages: &BTreeMap<String, String>) -> Result<Published, Error> {
+        let authentication = Credentials::from_environment()?;
+        let parent = match &self.parent {
+            Some(parent) => parent.clone(),
+            None => self.home_page(&authentication)?,
+        };
+        let mut tree = PageNode::default();
+        for (name, markdown) in pages {
+            let mut node = &mut tree;
+            for level in name.split('.') {
+                node = node.children.entry(level).or_default();
+            }
+            node.markdown = Some(markdown.as_str());
+        }
+        let titles = page_titles(pages.keys().map(String::as_str));
+        let mut published = Published::default();
+        self.publish_children(&tree, "", &parent, &titles, &authentication, &mut published)?;
+        Ok(published)
+    }
+    /// Publishes the children of a node below the page `parent`, and their children in turn.
+    /// `prefix` is the block name of the node.
+    fn publish_children(
+        &self,
+        node: &PageNode,
+        prefix: &str,
+        parent: &str,
+        titles: &HashMap<String, String>,
+        authentication: &Credentials,
+        published: &mut Published,
+    ) -> Result<(), Error> {
+        for (level, child) in &node.children {
+            let name = match prefix.is_empty() {
+                true => level.to_string(),
+                false => format!("{prefix}.{level}"),
+            };
+            let title = titles.get(&name).map_or(*level, String::as_str);
+            let body = storage_format(child.markdown.unwrap_or_default());
+            let id = self.put_page(title, &body, parent, authentication, published)?;
+            self.publish_children(child, &name, &id, titles, authentication, published)?;
+        }
+        Ok(())
+    }
+    /// Returns the id of the home page of the space, the parent of the top pages when no
+    /// `-parent` is given.
+    fn home_page(&self, authentication: &Credentials) -> Result<String, Error> {
+        let space = self.request(
+            authentication,
+            &["-G", "--data-urlencode", "expand=homepage"],
+            &format!("rest/api/space/{}", self.space),
+            None,
+        )?;
+        match space["homepage"]["id"].as_str() {
+            Some(id) => Ok(id.to_string()),
+            None => Err(Error::other(format!(
+                "Space {} has no home page, give -parent",
+                self.space
+            ))),
+        }
+    }
+    /// Creates the page with the given title below `parent`, or updates it when it is there
+    /// already.
+    ///
+    /// # Returns:
+    /// - The id of the page
+    /// - `Err(Error)` when a page with the title exists in the space below another page, as
+    ///   titles are unique in a space and the page is not one of the published tree
+    fn put_page(
+        &self,
+        title: &str,
+        body: &str,
+        parent: &str,
+        authentication: &Credentials,
+        published: &mut Published,
+    ) -> Result<String, Error> {
+        let found = self.request(
+            authentication,
+            &[
+                "-G",
+                "--data-urlencode",
+                &format!("spaceKey={}", self.space),
+                "--data-urlencode",
+                &format!("title={title}"),
+                "--data-urlencode",
+                "expand=version,ancestors",
+            ],
+            "rest/api/content",
+            None,
+        )?;
+        let mut page = json!({
+            "type": "page",
+            "title": title,
+            "space": { "key": self.space },
+            "body": { "storage": { "value": body, "representation": "storage" } },
+            "ancestors": [{ "id": parent }],
+        });
+        let response = match &found["results"][0] {
+            Value::Null => {
+                published.created += 1;
+                self.request(
+                    authentication,
+                    &["-X", "POST"],
+                    "rest/api/content",
+                    Some(&page),
+                )?
+            }
+            existing if parent_id(existing) != Some(parent) => {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!(
+                        "{title}: a page with this title exists in space {} outside the published tree",
+                        self.space
+                    ),
+                ));
+            }
+            existing => {
+                let id = existing["id"].as_str().unwrap_or_default().to_string();
+                let version = existing["version"]["number"].as_u64().unwrap_or(0);
+                page["version"] = json!({ "number": version + 1 });
+                published.updated += 1;
+                self.request(
+                    authentication,
+                    &["-X", "PUT"],
+                    &format!("rest/api/content/{id}"),
+                    Some(&page),
+                )?
+            }
+        };
+        match response["id"].as_str() {
+            Some(id) => Ok(id.to_string()),
+            None => Err(Error::other(format!(
+                "{title}: Confluence returned no page id"
+            ))),
+        }
+    }
+    /// Runs `curl` on a path of the REST API, sending `body` as JSON, and returns the JSON
+    /// answer.
+    fn request(
+        &self,
+        authentication: &Credentials,
+        arguments: &[&str],
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<Value, Error> {
+        let url = format!("{}/{path}", self.url);
+        let mut command = Command::new("curl");
+        command
+            .args(["-s", "-S", "-f", "--max-time", "60", "-K"])
+            .arg(&authentication.config)
+            .args(arguments)
+            .arg(&url)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if body.is_some() {
+            command
+                .args([
+                    "-H",
+                    "Content-Type: application/json",
+                    "--data-binary",
+                    "@-",
+                ])
+                .stdin(Stdio::piped());
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|error| Error::other(format!("could not run curl: {error}")))?;
+        if let (Some(body), Some(mut stdin)) = (body, child.stdin.take()) {
+            stdin.write_all(body.to_string().as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Error::other(format!(
+                "{url}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        serde_json::from_slice(&output.stdout)
+            .map_err(|error| Error::other(format!("{url}: {error}")))
+    }
+}
+
+/// Returns the id of the page a page found by the REST API is a child of, the last of its
+/// ancestors.
+fn parent_id(page: &Value) -> Option<&str> {
+    page["ancestors"].as_array()?.last()?["id"].as_str()
+}
+
+/// A `curl` config file authenticating the requests: `CONFLUENCE_USER` and the API token
+/// `CONFLUENCE_TOKEN` for Confluence Cloud, or `CONFLUENCE_TOKEN` alone as the personal access
+/// token of a Confluence server. The token is kept off the command line of `curl`, where other
+/// users could see it; the file can only be read by its owner and is removed when dropped.
+struct Credentials {
+    config: PathBuf,
+}
+
+impl Credentials {
+    fn from_environment() -> Result<Credentials, Error> {
+        let Ok(token) = env::var("CONFLUENCE_TOKEN") else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "CONFLUENCE_TOKEN is not set",
+            ));
+        };
+        let line = match env::var("CONFLUENCE_USER") {
+            Ok(user) => format!("user = {}\n", config_string(&format!("{user}:{token}"))),
+            Err(_) => format!(
+                "header = {}\n",
+                config_string(&format!("Authorization: Bearer {token}"))
+            ),
+        };
+        let config = env::temp_dir().join(format!(
+            "get-comments-confluence-{}.conf",
+            std::process::id()
+        ));
+        let mut options = File::options();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&config)?;
+        let credentials = Credentials { config };
+        file.write_all(line.as_bytes())?;
+        Ok(credentials)
+    }
+}
+
+impl Drop for Credentials {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.config);
+    }
+}
+
+/// Quotes a value for a `curl` config file.
+fn config_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Returns the title of every page that may not be titled by its last level, as page titles
+/// are unique in a space: a level that occurs more than once is titled by its whole block
+/// name, as in `EPIC A / ITEM B`.
+fn page_titles<'n>(names: impl IntoIterator<Item = &'n str>) -> HashMap<String, String> {
+    let mut levels: HashMap<String, Vec<String>> = HashMap::new();
+    for name in names {
+        let parts: Vec<&str> = name.split('.').collect();
+        for end in 1..=parts.len() {
+            let level = parts[..end].join(".");
+            let names = levels.entry(parts[end - 1].to_string()).or_default();
+            if !names.contains(&level) {
+                names.push(level);
+            }
+        }
+    }
+    levels
+        .into_values()
+        .filter(|names| names.len() > 1)
+        .flatten()
+        .map(|name| (name.clone(), name.replace('.', " / ")))
+        .collect()
+}
+
+/// Converts Markdown to the Confluence storage format: XHTML, with code blocks in the `code`
+/// macro so Confluence highlights them.
+fn storage_format(markdown: &str) -> String {
+    let mut events = Vec::new();
+    let mut code: Option<(String, String)> = None;
+    for event in Parser::new_ext(markdown, Options::all()) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((language, String::new()));
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, body)) = &mut code {
+                    body.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                let (language, body) = code.take().unwrap_or_default();
+                let language = match language.is_empty() {
+                    true => String::new(),
+                    false => {
+                        format!("<ac:parameter ac:name=\"language\">{language}</ac:parameter>")
+                    }
+                };
+                events.push(Event::Html(
+                    format!(
+                        "<ac:structured-macro ac:name=\"code\">{language}<ac:plain-text-body><![CDATA[{}]]></ac:plain-text-body></ac:structured-macro>\n",
+                        body.replace("]]>", "]]]]><![CDATA[>")
+                    )
+                    .into(),
+                ));
+            }
+            event => events.push(event),
+        }
+    }
+    let mut storage = String::new();
+    push_html(&mut storage, events.into_iter());
+    storage
+}
+
+#[cfg(test)]
+#[test]
+fn test_storage_format() {
+    assert_eq!(
+        storage_format("## Title\n\n```rust\nlet a = 1;\n```\n"),
+        "<h2>Title</h2>\n<ac:structured-macro ac:name=\"code\"><ac:parameter ac:name=\"language\">rust</ac:parameter><ac:plain-text-body><![CDATA[let a = 1;\n]]></ac:plain-text-body></ac:structured-macro>\n"
+    );
+    let titles = page_titles(["EPIC A.ITEM B", "EPIC C.ITEM B", "EPIC C.ITEM D"]);
+    assert_eq!(titles.len(), 2);
+    assert_eq!(titles["EPIC C.ITEM B"], "EPIC C / ITEM B");
+    assert_eq!(config_string("me:t\"o\\k"), "\"me:t\\\"o\\\\k\"");
+    let page = json!({ "ancestors": [{ "id": "1" }, { "id": "42" }] });
+    assert_eq!(parent_id(&page), Some("42"));
+    assert_eq!(parent_id(&json!({ "ancestors": [] })), None);
+}
@@ -0,0 +1,214 @@
+use crate::block::Sequences;
+use crate::clock::format_date;
+use crate::git::blame;
+use crate::outputs::write_if_changed;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Who wrote the lines of one documented item and who touched it last.
+#[derive(Default)]
+struct ItemAuthors {
+    /// Number of block lines per author.
+    lines: BTreeMap<String, usize>,
+    /// Author time and author of the most recent change.
+    last: Option<(i64, String)>,
+}
+
+//#EPIC Get Lines.ITEM Write contributor pages [0]
+//#
+//## Write contributor pages
+//#Blame the lines of every block and write a `contributors.md` per EPIC listing who wrote and who
+//#last touched each documented item.
+/// Writes a `contributors.md` per EPIC built from the `git blame` data of every block.
+///
+/// The EPIC is the first path component after the document root, and the page is written to
+/// the folder of that EPIC. Each page holds a table of authors with the number of block lines
+/// they wrote and the items they contributed to, followed by a table of items with their
+/// authors and the author and date of the most recent change. A page that already holds its
+/// tables is not written again.
+///
+/// # Parameters:
+/// - `history`: The comment history, keyed by dot delimited file path and sequence number
+/// - `doc_root`: The document root the pages are written under
+///
+/// # Returns:
+/// - `Ok((Vec<PathBuf>, Vec<String>))` with every page, written or not, and a warning for
+///   every source file that has no git history
+/// - `Err(Error)` when a page could not be written
+pub fn write_contributor_pages(
+    history: &HashMap<String, Sequences>,
+    doc_root: &str,
+) -> Result<(Vec<PathBuf>, Vec<String>), Error> {
+    let mut warnings = BTreeSet::new();
+    let mut epics: BTreeMap<String, BTreeMap<String, ItemAuthors>> = BTreeMap::new();
+    for (key, blocks) in history {
+        let path: Vec<&str> = key.trim().split(".").skip(1).collect();
+        let Some(epic) = path.first() else { continue };
+        let item = epics
+            .entry(epic.to_string())
+            .or_default()
+            .entry(path.join("/"))
+            .or_default();
+        for block in blocks.values() {
+            let start = block.start_line.saturating_sub(1).max(1);
+            match blame(&block.path, start, block.end_line) {
+                Ok(lines) => {
+                    for line in lines {
+                        *item.lines.entry(line.author.clone()).or_default() += 1;
+                        if item.last.as_ref().is_none_or(|(time, _)| line.time > *time) {
+                            item.last = Some((line.time, line.author));
+                        }
+                    }
+                }
+                Err(error) => {
+                    warnings.insert(format!("No git history for {}: {error}", block.path));
+                }
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    for (epic, items) in epics {
+        let mut authors: BTreeMap<&str, (usize, Vec<&str>)> = BTreeMap::new();
+        for (item, item_authors) in &items {
+            for (author, lines) in &item_authors.lines {
+                let entry = authors.entry(author.as_str()).or_default();
+                entry.0 += lines;
+                entry.1.push(item.as_str());
+            }
+        }
+        let mut writer = Vec::new();
+        writeln!(writer, "# Contributors to {epic}\n")?;
+        writeln!(writer, "| Author | Lines | Items |")?;
+        writeln!(writer, "| --- | --- | --- |")?;
+        for (author, (lines, items)) in &authors {
+            writeln!(writer, "| {author} | {lines} | {} |", items.join(", "))?;
+        }
+        writeln!(writer, "\n## Items\n")?;
+        writeln!(writer, "| Item | Authors | Last touched by | Date |")?;
+        writeln!(writer, "| --- | --- | --- | --- |")?;
+        for (item, item_authors) in &items {
+            let mut by_lines: Vec<(&String, &usize)> = item_authors.lines.iter().collect();
+            by_lines.sort_by(|left, right| right.1.cmp(left.1).then(left.0.cmp(right.0)));
+            let names: Vec<&str> = by_lines.iter().map(|(name, _)| name.as_str()).collect();
+            let (last_author, date) = match &item_authors.last {
+                Some((time, author)) => (
+                    author.as_str(),
+                    format_date(UNIX_EPOCH + Duration::from_secs((*time).max(0) as u64)),
+                ),
+                None => ("", String::new()),
+            };
+            writeln!(
+                writer,
+                "| {item} | {} | {last_author} | {date} |",
+                names.join(", ")
+            )?;
+        }
+        let path = contributor_page(doc_root, &epic);
+        write_if_changed(&path, &writer)?;
+        files.push(path);
+    }
+    Ok((files, warnings.into_iter().collect()))
+}
+
+/// Returns the path of the contributor page of an EPIC.
+fn contributor_page(doc_root: &str, epic: &str) -> PathBuf {
+    Path::new(doc_root).join(epic).join("contributors.md")
+}
+
+/// Returns the contributor pages `write_contributor_pages` writes for a history, one per EPIC.
+pub fn contributor_files(history: &HashMap<String, Sequences>, doc_root: &str) -> Vec<PathBuf> {
+    let epics: BTreeSet<&str> = history
+        .keys()
+        .filter_map(|key| key.trim().split(".").nth(1))
+        .collect();
+    epics
+        .into_iter()
+        .map(|epic| contributor_page(doc_root, epic))
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_contributor_pages() {
+    use crate::block::Block;
+    use crate::sequence::Sequence;
+    use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+    use std::process::Command;
+    let root = "target/test-contributors";
+    let _ = remove_dir_all(root);
+    create_dir_all(root).unwrap();
+    let git = |arguments: &[&str], author: &str, date: &str| {
+        assert!(
+            Command::new("git")
+                .arg("-C")
+                .arg(root)
+                .args(["-c", &format!("user.name={author}")])
+                .args(["-c", "user.email=test@example.com"])
+                .args(arguments)
+                .env("GIT_AUTHOR_DATE", date)
+                .env("GIT_COMMITTER_DATE", date)
+                .output()
+                .unwrap()
+                .status
+                .success()
+        )
+    };
+    git(&["init", "-q"], "", "");
+    write(
+        format!("{root}/a.rs"),
+        "//# EPIC A.ITEM B [0]\n//#First\n//#Second\n",
+    )
+    .unwrap();
+    git(&["add", "a.rs"], "", "");
+    git(&["commit", "-q", "-m", "a"], "Ann", "2024-01-01T00:00:00Z");
+    write(
+        format!("{root}/a.rs"),
+        "//# EPIC A.ITEM B [0]\n//#First\n//#Changed\n",
+    )
+    .unwrap();
+    git(
+        &["commit", "-q", "-a", "-m", "b"],
+        "Bob",
+        "2024-02-01T00:00:00Z",
+    );
+    write(format!("{root}/b.rs"), "//# EPIC A.ITEM C [0]\n//#New\n").unwrap();
+
+    let block = |file: &str, end_line| Block {
+        path: format!("{root}/{file}").into(),
+        start_line: 2,
+        end_line,
+        ..Block::default()
+    };
+    let doc_root = format!("{root}/docs");
+    let history: HashMap<String, Sequences> = [
+        (
+            format!("{doc_root}.EPIC A.ITEM B"),
+            Sequences::from([(Sequence::from(0), block("a.rs", 3))]),
+        ),
+        (
+            format!("{doc_root}.EPIC A.ITEM C"),
+            Sequences::from([(Sequence::from(0), block("b.rs", 2))]),
+        ),
+    ]
+    .into_iter()
+    .collect();
+    let (files, warnings) = write_contributor_pages(&history, &doc_root).unwrap();
+    assert_eq!(files, contributor_files(&history, &doc_root));
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].starts_with(&format!("No git history for {root}/b.rs")));
+    let page = read_to_string(format!("{doc_root}/EPIC A/contributors.md")).unwrap();
+    remove_dir_all(root).unwrap();
+    assert_eq!(
+        page,
+        "# Contributors to EPIC A\n\n\
+         | Author | Lines | Items |\n| --- | --- | --- |\n\
+         | Ann | 2 | EPIC A/ITEM B |\n| Bob | 1 | EPIC A/ITEM B |\n\n\
+         ## Items\n\n\
+         | Item | Authors | Last touched by | Date |\n| --- | --- | --- | --- |\n\
+         | EPIC A/ITEM B | Ann, Bob | Bob | 2024-02-01 |\n\
+         | EPIC A/ITEM C |  |  |  |\n"
+    );
+}
@@ -0,0 +1,100 @@
+use crate::density::{FileCount, directory_of};
+use std::collections::BTreeMap;
+
+/// The number of scanned files of a directory, or of the whole run, and how many of them hold
+/// at least one block.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Coverage {
+    pub documented: usize,
+    pub files: usize,
+}
+
+impl Coverage {
+    /// Returns the share of documented files in percent, 100 when there are no files.
+    pub fn percentage(&self) -> f64 {
+        match self.files {
+            0 => 100.0,
+            files => self.documented as f64 * 100.0 / files as f64,
+        }
+    }
+    fn add(&mut self, count: &FileCount) {
+        self.files += 1;
+        if count.blocks > 0 {
+            self.documented += 1;
+        }
+    }
+}
+
+//#EPIC Get Lines.ITEM Documentation coverage [0]
+//#
+//## Documentation coverage
+//#`--coverage` prints, for every source directory, how many of its scanned files hold at least
+//#one block, and the percentage of documented files over the whole run. With
+//#`-coverage-min 80` the run fails with error E015 when fewer than 80 percent of the files are
+//#documented, so a CI pipeline notices when documentation coverage drops.
+/// Counts the documented files of every directory and of all files together.
+///
+/// # Parameters:
+/// - `files`: The line and block count of every scanned file, keyed by its path
+///
+/// # Returns:
+/// - The coverage of every directory, by the path of the directory, and the overall coverage
+pub fn coverage_by_directory(
+    files: &BTreeMap<String, FileCount>,
+) -> (BTreeMap<String, Coverage>, Coverage) {
+    let mut directories: BTreeMap<String, Coverage> = BTreeMap::new();
+    let mut total = Coverage::default();
+    for (file, count) in files {
+        directories
+            .entry(directory_of(file))
+            .or_default()
+            .add(count);
+        total.add(count);
+    }
+    (directories, total)
+}
+
+/// Formats the coverage of every directory followed by the overall coverage.
+pub fn coverage_report(directories: &BTreeMap<String, Coverage>, total: Coverage) -> String {
+    let mut report = String::from("Coverage:\n");
+    for (directory, coverage) in directories {
+        report.push_str(&format!(
+            "  {:>5.1} %  {:>5} of {:<5}  {directory}\n",
+            coverage.percentage(),
+            coverage.documented,
+            coverage.files
+        ));
+    }
+    report.push_str(&format!(
+        "  {:>5.1} %  {:>5} of {:<5}  overall\n",
+        total.percentage(),
+        total.documented,
+        total.files
+    ));
+    report
+}
+
+#[cfg(test)]
+#[test]
+fn test_coverage() {
+    let count = |blocks| FileCount { lines: 10, blocks };
+    let files = BTreeMap::from([
+        ("src/a.rs".to_string(), count(2)),
+        ("src/b.rs".to_string(), count(0)),
+        ("src/io/c.rs".to_string(), count(1)),
+        ("main.rs".to_string(), count(0)),
+    ]);
+    let (directories, total) = coverage_by_directory(&files);
+    assert_eq!(
+        directories["src"],
+        Coverage {
+            documented: 1,
+            files: 2
+        }
+    );
+    assert_eq!(directories["."].percentage(), 0.0);
+    assert_eq!(total.percentage(), 50.0);
+    let report = coverage_report(&directories, total);
+    assert!(report.contains("   50.0 %      1 of 2      src\n"));
+    assert!(report.ends_with("   50.0 %      2 of 4      overall\n"));
+}
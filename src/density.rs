@@ -0,0 +1,143 @@
+use crate::outputs::write_if_changed;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+/// The format in which a report is written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Json,
+}
+
+impl ReportFormat {
+    /// Parses a report format as given on the command line: `md` or `json`.
+    pub fn parse(name: &str) -> Result<ReportFormat, Error> {
+        match name.trim() {
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(Error::other(format!("Unknown report format [{other}]"))),
+        }
+    }
+    /// Returns the file name extension of the format: `md` or `json`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Json => "json",
+        }
+    }
+}
+
+/// The number of lines and blocks found in one scanned source file.
+#[derive(Clone, Debug, Default)]
+pub struct FileCount {
+    pub lines: usize,
+    pub blocks: usize,
+}
+
+/// The totals of one directory in the density report.
+#[derive(Default, Serialize)]
+struct DirectoryCount {
+    files: usize,
+    lines: usize,
+    blocks: usize,
+}
+
+impl DirectoryCount {
+    fn blocks_per_kloc(&self) -> f64 {
+        if self.lines == 0 {
+            0.0
+        } else {
+            self.blocks as f64 * 1000.0 / self.lines as f64
+        }
+    }
+}
+
+/// One directory in the JSON density report.
+#[derive(Serialize)]
+struct DensityRow<'a> {
+    directory: &'a str,
+    #[serde(flatten)]
+    totals: &'a DirectoryCount,
+    blocks_per_kloc: f64,
+}
+
+/// Returns the directory that holds a file, `.` for a file without one.
+pub fn directory_of(file: &str) -> String {
+    Path::new(file)
+        .parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .filter(|parent| !parent.is_empty())
+        .unwrap_or_else(|| ".".to_string())
+}
+
+/// Writes the documentation density, in blocks per thousand lines of code, of every directory.
+///
+/// Each scanned file is counted in the directory that holds it, so the report shows which
+/// subsystems are richly documented through line blocks and which are bare. The report is
+/// written to `density.md` or `density.json` in the document root.
+///
+/// # Parameters:
+/// - `files`: The line and block count of every scanned file, keyed by the file's output path
+/// - `format`: Whether to write a Markdown table or a JSON array
+/// - `doc_root`: The document root the report is written to
+///
+/// # Returns:
+/// - `Ok(PathBuf)` with the path of the report, which is not written again when it already
+///   holds the densities
+pub fn write_density_report(
+    files: &BTreeMap<String, FileCount>,
+    format: ReportFormat,
+    doc_root: &str,
+) -> Result<PathBuf, Error> {
+    let mut directories: BTreeMap<String, DirectoryCount> = BTreeMap::new();
+    for (file, count) in files {
+        let totals = directories.entry(directory_of(file)).or_default();
+        totals.files += 1;
+        totals.lines += count.lines;
+        totals.blocks += count.blocks;
+    }
+
+    let mut writer = Vec::new();
+    match format {
+        ReportFormat::Markdown => {
+            writeln!(writer, "# Documentation density\n")?;
+            writeln!(
+                writer,
+                "| Directory | Files | Lines | Blocks | Blocks per KLOC |"
+            )?;
+            writeln!(writer, "| --- | --- | --- | --- | --- |")?;
+            for (directory, totals) in &directories {
+                writeln!(
+                    writer,
+                    "| {directory} | {} | {} | {} | {:.1} |",
+                    totals.files,
+                    totals.lines,
+                    totals.blocks,
+                    totals.blocks_per_kloc()
+                )?;
+            }
+        }
+        ReportFormat::Json => {
+            let rows: Vec<DensityRow> = directories
+                .iter()
+                .map(|(directory, totals)| DensityRow {
+                    directory,
+                    totals,
+                    blocks_per_kloc: totals.blocks_per_kloc(),
+                })
+                .collect();
+            serde_json::to_writer_pretty(&mut writer, &rows)?;
+            writeln!(writer)?;
+        }
+    }
+    let path = density_file(doc_root, format);
+    write_if_changed(&path, &writer)?;
+    Ok(path)
+}
+
+/// Returns the path of the density report in the given format.
+pub fn density_file(doc_root: &str, format: ReportFormat) -> PathBuf {
+    Path::new(doc_root).join(format!("density.{}", format.extension()))
+}
@@ -0,0 +1,143 @@
+use crate::density::ReportFormat;
+use crate::outputs::write_if_changed;
+use serde::Serialize;
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+/// A block that was rejected, with where it was found and the rule it broke.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// The source file of the block.
+    pub file: String,
+    /// The line of the block header.
+    pub line: usize,
+    /// The block name, with its Sequence number.
+    pub block: String,
+    /// The error code of the rule, such as `E008`; empty for errors without a code.
+    pub code: String,
+    /// What is wrong with the block.
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Builds a diagnostic from an error message, taking the `[E008]` code in front of it
+    /// apart from the rest of the message.
+    pub fn new(file: &str, line: usize, block: &str, error: &str) -> Diagnostic {
+        let (code, message) = error
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once("] "))
+            .filter(|(code, _)| code.starts_with('E'))
+            .unwrap_or(("", error));
+        Diagnostic {
+            file: file.to_string(),
+            line,
+            block: block.trim().to_string(),
+            code: code.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+//#EPIC Get Lines.ITEM Diagnostics summary [0]
+//#
+//## Diagnostics summary
+//#A run goes on after a block is rejected, so its errors scroll by between the other output.
+//#At the end of the run every rejected block is listed once more in a table with its file, the
+//#line of its header, its name and the rule it broke, and `-diagnostics md|json` writes the same
+//#list to the document root for CI.
+/// Returns the table of rejected blocks printed at the end of a run, with aligned columns.
+pub fn summary_table(diagnostics: &[Diagnostic]) -> String {
+    let header = ["FILE", "LINE", "BLOCK", "CODE", "RULE"].map(str::to_string);
+    let rows: Vec<[String; 5]> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            [
+                diagnostic.file.clone(),
+                diagnostic.line.to_string(),
+                diagnostic.block.clone(),
+                diagnostic.code.clone(),
+                diagnostic.message.clone(),
+            ]
+        })
+        .collect();
+    let mut widths = [0; 5];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut table = format!("{} blocks rejected:", diagnostics.len());
+    for row in std::iter::once(&header).chain(&rows) {
+        table.push('\n');
+        for (column, cell) in row.iter().enumerate() {
+            match column {
+                4 => table.push_str(cell),
+                _ => table.push_str(&format!("{cell:<width$}  ", width = widths[column])),
+            }
+        }
+    }
+    table
+}
+
+/// Writes the rejected blocks to `diagnostics.md` or `diagnostics.json` in the document root,
+/// unless the file already holds them, and returns its path.
+pub fn write_diagnostics(
+    diagnostics: &[Diagnostic],
+    format: ReportFormat,
+    doc_root: &str,
+) -> Result<PathBuf, Error> {
+    let mut writer = Vec::new();
+    match format {
+        ReportFormat::Markdown => {
+            writeln!(writer, "# Diagnostics\n")?;
+            writeln!(writer, "| File | Line | Block | Code | Rule |")?;
+            writeln!(writer, "| --- | --- | --- | --- | --- |")?;
+            for diagnostic in diagnostics {
+                writeln!(
+                    writer,
+                    "| {} | {} | {} | {} | {} |",
+                    diagnostic.file.replace('|', "\\|"),
+                    diagnostic.line,
+                    diagnostic.block.replace('|', "\\|"),
+                    diagnostic.code,
+                    diagnostic.message.replace('|', "\\|")
+                )?;
+            }
+        }
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, diagnostics)?;
+            writeln!(writer)?;
+        }
+    }
+    let path = diagnostics_file(doc_root, format);
+    write_if_changed(&path, &writer)?;
+    Ok(path)
+}
+
+/// Returns the path of the diagnostics report in the given format.
+pub fn diagnostics_file(doc_root: &str, format: ReportFormat) -> PathBuf {
+    Path::new(doc_root).join(format!("diagnostics.{}", format.extension()))
+}
+
+#[cfg(test)]
+#[test]
+fn test_summary_table() {
+    let diagnostics = [
+        Diagnostic::new(
+            "src/a.rs",
+            4,
+            "EPIC A.ITEM x [0]",
+            "[E008] Invalid folder prefix [x] [ITEM].",
+        ),
+        Diagnostic::new("b.rs", 12, "EPIC B", "No Sequence number"),
+    ];
+    assert_eq!(diagnostics[0].code, "E008");
+    assert_eq!(diagnostics[1].message, "No Sequence number");
+    assert_eq!(
+        summary_table(&diagnostics),
+        "2 blocks rejected:\n\
+         FILE      LINE  BLOCK              CODE  RULE\n\
+         src/a.rs  4     EPIC A.ITEM x [0]  E008  Invalid folder prefix [x] [ITEM].\n\
+         b.rs      12    EPIC B                   No Sequence number"
+    );
+}
@@ -0,0 +1,144 @@
+use crate::glob::path_matches;
+use encoding_rs::{Encoding, UTF_8};
+use std::fs::read;
+use std::io::Error;
+use std::path::Path;
+
+/// The encodings of source files that are not UTF-8, keyed by a glob on the file path, so a
+/// legacy `*.c` file in Windows-1252 is transcoded on read instead of failing to read.
+#[derive(Clone, Debug, Default)]
+pub struct SourceEncodings {
+    overrides: Vec<(String, &'static Encoding)>,
+    /// The encoding of the files no glob matches and that have no byte order mark, UTF-8 when
+    /// `None`.
+    default: Option<&'static Encoding>,
+}
+
+/// The content of a source file transcoded to UTF-8.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decoded {
+    pub text: String,
+    /// The encoding the file was read in.
+    pub encoding: &'static Encoding,
+    /// Whether bytes that are invalid in the encoding were replaced by U+FFFD.
+    pub lossy: bool,
+}
+
+impl SourceEncodings {
+    /// Parses encoding overrides of the form `glob=label`, for example `*.c=windows-1252`. A
+    /// label without a glob, such as `latin1`, is the encoding of all other files.
+    ///
+    /// The label is any WHATWG encoding label, such as `windows-1252`, `latin1`, `shift_jis`
+    /// or `utf-16le`. A glob without a `/` is matched against the file name, a glob with a `/`
+    /// against the trailing folders and file name of the path. `*` and `?` do not match a `/`,
+    /// `**` matches any number of folders. The first glob that matches a file wins.
+    ///
+    /// # Returns:
+    /// - `Err(Error)` when an override names an unknown encoding
+    pub fn parse(specs: &[String]) -> Result<SourceEncodings, Error> {
+        let mut encodings = SourceEncodings::default();
+        for spec in specs {
+            let Some((glob, label)) = spec.rsplit_once('=') else {
+                let Some(encoding) = Encoding::for_label(spec.trim().as_bytes()) else {
+                    return Err(Error::other(format!(
+                        "Unknown encoding [{spec}]: expected glob=encoding or an encoding"
+                    )));
+                };
+                encodings.default = Some(encoding);
+                continue;
+            };
+            let Some(encoding) = Encoding::for_label(label.trim().as_bytes()) else {
+                return Err(Error::other(format!(
+                    "Unknown encoding [{label}] in [{spec}]"
+                )));
+            };
+            encodings
+                .overrides
+                .push((glob.trim().to_string(), encoding));
+        }
+        Ok(encodings)
+    }
+    /// Returns the encoding the first matching glob gives the source file, or `None` when no
+    /// glob matches.
+    pub fn encoding_for(&self, path: &str) -> Option<&'static Encoding> {
+        self.overrides
+            .iter()
+            .find(|(glob, _)| path_matches(glob, path))
+            .map(|(_, encoding)| *encoding)
+    }
+    /// Reads a source file and transcodes it to UTF-8, see `decode`.
+    pub fn read(&self, path: &Path) -> Result<Decoded, Error> {
+        Ok(self.decode(path, read(path)?))
+    }
+    //#EPIC Get Lines.ITEM Read files in other encodings [0]
+    //#
+    //## Read files in other encodings
+    //#Source files are UTF-8 unless `-encoding` says otherwise, for all files or per glob. A
+    //#file that starts with a byte order mark is read in the encoding the mark stands for, so
+    //#UTF-16 files need no option. Bytes that are invalid in the encoding of a file are
+    //#replaced by U+FFFD with a warning, instead of skipping the whole file.
+    /// Turns the raw content of a source file into UTF-8: with the encoding of the first glob
+    /// that matches its path, else with the encoding of its byte order mark, else with the
+    /// default encoding, which is UTF-8 unless set. Invalid bytes are replaced by U+FFFD.
+    pub fn decode(&self, path: &Path, content: Vec<u8>) -> Decoded {
+        let encoding = self
+            .encoding_for(&path.to_string_lossy())
+            .or_else(|| Encoding::for_bom(&content).map(|(encoding, _)| encoding))
+            .or(self.default);
+        match encoding {
+            Some(encoding) => {
+                let (text, lossy) = encoding.decode_with_bom_removal(&content);
+                Decoded {
+                    text: text.into_owned(),
+                    encoding,
+                    lossy,
+                }
+            }
+            None => match String::from_utf8(content) {
+                Ok(text) => Decoded {
+                    text,
+                    encoding: UTF_8,
+                    lossy: false,
+                },
+                Err(error) => Decoded {
+                    text: String::from_utf8_lossy(error.as_bytes()).into_owned(),
+                    encoding: UTF_8,
+                    lossy: true,
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_source_encodings() {
+    let encodings = SourceEncodings::parse(&[
+        "legacy/**/*.c=windows-1252".to_string(),
+        "*.h = latin1".to_string(),
+    ])
+    .unwrap();
+    assert!(encodings.encoding_for("./src/legacy/io/disk.c").is_some());
+    assert!(encodings.encoding_for("./src/legacy/disk.c").is_some());
+    assert!(encodings.encoding_for("./src/modern/disk.c").is_none());
+    assert!(encodings.encoding_for("src\\disk.h").is_some());
+    assert!(encodings.encoding_for("src/disk.hpp").is_none());
+    let content = encodings.decode(
+        Path::new("a/legacy/x.c"),
+        b"//# caf\xe9 \x93quoted\x94".to_vec(),
+    );
+    assert_eq!(content.text, "//# caf\u{e9} \u{201c}quoted\u{201d}");
+    assert!(!content.lossy);
+    let content = encodings.decode(Path::new("x.rs"), b"//# caf\xe9".to_vec());
+    assert_eq!(content.text, "//# caf\u{fffd}");
+    assert!(content.lossy);
+    let content = encodings.decode(Path::new("x.rs"), b"\xff\xfe/\0/\0#\0".to_vec());
+    assert_eq!(
+        (content.text.as_str(), content.encoding.name()),
+        ("//#", "UTF-16LE")
+    );
+    let latin1 = SourceEncodings::parse(&["latin1".to_string()]).unwrap();
+    assert_eq!(latin1.decode(Path::new("x.rs"), vec![0xe9]).text, "\u{e9}");
+    assert!(SourceEncodings::parse(&["*.c=klingon".to_string()]).is_err());
+    assert!(SourceEncodings::parse(&["*.c".to_string()]).is_err());
+}
@@ -0,0 +1,148 @@
+use crate::block::{Block, Sequences};
+use crate::failure::FailureKind;
+use crate::sequence::Sequence;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Something that happened during a run, in the order it happened.
+///
+/// New kinds of events may be added, so match with a wildcard arm.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum Event<'e> {
+    /// The parser starts on a source file.
+    FileStarted { path: &'e str },
+    /// The parser is done with a source file, which took `duration` to read and parse.
+    FileParsed { path: &'e str, duration: Duration },
+    /// A line block was parsed and added to the history. `name` is the dot delimited block
+    /// name without the document root.
+    BlockFound {
+        name: &'e str,
+        sequence: Sequence,
+        block: &'e Block,
+    },
+    /// A block could not be parsed or stored, such as for a header without a Sequence number
+    /// or a duplicate Sequence number. `header` is the name or header text of the block and
+    /// `line` its header line. The error is also sent as an `Error`.
+    BlockFailed {
+        path: &'e str,
+        line: usize,
+        header: &'e str,
+        message: &'e str,
+    },
+    /// The name of a block matches the path prefixes, so its file can be generated. Sent once
+    /// per name, with all its Sequences, before the write phase.
+    BlockValidated {
+        name: &'e str,
+        sequences: &'e Sequences,
+    },
+    /// The name of a block does not match the path prefixes, so its file is not generated.
+    BlockRejected { name: &'e str, reason: &'e str },
+    /// A generated file was written.
+    FileWritten { path: &'e Path },
+    /// The walk of the source folder left out a file or folder, such as a symbolic link that
+    /// loops back to a folder above it.
+    EntrySkipped { path: &'e Path, reason: &'e str },
+    /// A finding that does not fail the run, such as a dead link or a stale block.
+    Warning { message: &'e str },
+    /// An error the run went on after. It is also part of the `RunError` of the run.
+    Error { kind: FailureKind, message: &'e str },
+}
+
+//#EPIC Get Lines.ITEM Follow a run [0]
+//#
+//## Follow a run
+//#Library callers can subscribe to the events of a run, such as every file that is parsed,
+//#every block that is found and every file that is written, to show progress or to feed other
+//#tools without patching the parser.
+/// Receives the events of a run, see `Comments::with_subscriber`.
+///
+/// Files are written on another thread than they are parsed, so a subscriber must be `Send`
+/// and `Sync`. Any `Fn(&Event)` closure is a subscriber.
+pub trait EventSubscriber: Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+impl<F: Fn(&Event) + Send + Sync> EventSubscriber for F {
+    fn on_event(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Sends the events of a run to the optional subscriber, and prints the messages of the run
+/// unless it is quiet.
+#[derive(Clone, Default)]
+pub(crate) struct Events {
+    subscriber: Option<Arc<dyn EventSubscriber>>,
+    quiet: bool,
+    /// Prints the messages on standard error, as standard output carries the document.
+    stderr: bool,
+}
+
+impl Events {
+    /// Creates the sender of events to the given subscriber.
+    pub(crate) fn new(subscriber: impl EventSubscriber + 'static) -> Events {
+        Events {
+            subscriber: Some(Arc::new(subscriber)),
+            quiet: false,
+            stderr: false,
+        }
+    }
+    /// Keeps warnings and other messages that are not errors off the console. They are still
+    /// sent to the subscriber.
+    pub(crate) fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+    pub(crate) fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+    /// Prints the messages on standard error instead of standard output, for a run that
+    /// writes its document to standard output.
+    pub(crate) fn set_stderr(&mut self, stderr: bool) {
+        self.stderr = stderr;
+    }
+    /// Sends an event to the subscriber.
+    pub(crate) fn emit(&self, event: Event) {
+        if let Some(subscriber) = &self.subscriber {
+            subscriber.on_event(&event);
+        }
+    }
+    /// Prints a warning and sends it to the subscriber.
+    pub(crate) fn warn(&self, message: String) {
+        self.inform(&message);
+        self.emit(Event::Warning { message: &message });
+    }
+    /// Prints a message that is not an error, such as a report at the end of a run.
+    pub(crate) fn inform(&self, message: &str) {
+        if !self.quiet {
+            self.print(message);
+        }
+    }
+    /// Prints an error, also in a quiet run.
+    pub(crate) fn error(&self, message: &str) {
+        self.print(message);
+    }
+    fn print(&self, message: &str) {
+        match self.stderr {
+            true => eprintln!("{message}"),
+            false => println!("{message}"),
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_events() {
+    use std::sync::Mutex;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let events = Events::new(move |event: &Event| {
+        if let Event::Warning { message } = event {
+            log.lock().unwrap().push(message.to_string());
+        }
+    });
+    events.emit(Event::FileStarted { path: "a.rs" });
+    events.warn("Possibly stale".to_string());
+    assert_eq!(*seen.lock().unwrap(), ["Possibly stale"]);
+}
@@ -0,0 +1,238 @@
+use crate::glob::path_matches;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::{HashMap, HashSet};
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+//#EPIC Get Lines.ITEM Skip build output and vendored code [0]
+//#
+//## Skip build output and vendored code
+//#The walk does not descend into folders, and does not read files, that match an `-exclude` glob
+//#or, with `--gitignore`, that git ignores. Folders such as `target/` and `node_modules/` are
+//#then never scanned, so extraction only touches real source files.
+/// The files and folders below the source folder that the walk skips.
+#[derive(Clone, Debug, Default)]
+pub struct Exclusions {
+    root: PathBuf,
+    globs: Vec<String>,
+    /// The rules of the files git ignores, when `--gitignore` is given.
+    ignored: Option<GitIgnores>,
+    /// When set, the only files that are read, as paths the walk finds them at.
+    only: Option<HashSet<PathBuf>>,
+    /// Whether symbolic links are left alone instead of followed.
+    no_follow_links: bool,
+    /// The deepest level the walk descends to, the files of the source folder being level 1.
+    max_depth: Option<usize>,
+}
+
+impl Exclusions {
+    /// Creates the exclusions of a walk of `root`.
+    ///
+    /// # Parameters:
+    /// - `globs`: Globs as for `-encoding`, matched against the path of every file and folder,
+    ///   e.g. `target`, `*.min.js` or `vendor/**/generated`
+    /// - `gitignore`: Whether the paths git ignores are skipped too. When git cannot list them,
+    ///   `warn` is called and only the globs apply
+    pub fn new(
+        root: &str,
+        globs: &[String],
+        gitignore: bool,
+        warn: impl FnOnce(String),
+    ) -> Exclusions {
+        let ignored = match gitignore {
+            true => match GitIgnores::new(Path::new(root)) {
+                Ok(ignored) => Some(ignored),
+                Err(error) => {
+                    warn(format!("gitignore {error}"));
+                    None
+                }
+            },
+            false => None,
+        };
+        Exclusions {
+            root: PathBuf::from(root),
+            globs: globs.to_vec(),
+            ignored,
+            only: None,
+            no_follow_links: false,
+            max_depth: None,
+        }
+    }
+    /// Restricts the walk to the given files, such as the files changed since a git revision.
+    /// Folders are still walked, every other file is skipped.
+    pub fn with_only(mut self, files: Option<HashSet<PathBuf>>) -> Exclusions {
+        self.only = files;
+        self
+    }
+    /// Sets whether the walk follows symbolic links, and the deepest level it descends to.
+    pub fn with_limits(mut self, follow_links: bool, max_depth: Option<usize>) -> Exclusions {
+        self.no_follow_links = !follow_links;
+        self.max_depth = max_depth;
+        self
+    }
+    /// Returns whether the walk follows symbolic links.
+    pub fn follows_links(&self) -> bool {
+        !self.no_follow_links
+    }
+    /// Returns the deepest level the walk descends to, if it is limited.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+    /// Returns whether the walk skips a file or folder, given by its path as the walk found it.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+        self.only
+            .as_ref()
+            .is_some_and(|only| !only.contains(path) && path.is_file())
+            || self
+                .ignored
+                .as_ref()
+                .is_some_and(|ignored| ignored.is_ignored(relative, path.is_dir()))
+            || self
+                .globs
+                .iter()
+                .any(|glob| path_matches(glob, &relative.to_string_lossy()))
+    }
+}
+
+/// The gitignore rules of the git work tree holding the source folder.
+///
+/// The `.gitignore` file of a folder is read the first time a path in it is matched, so the
+/// files of ignored folders, which the walk does not enter, are never read.
+#[derive(Clone, Debug)]
+struct GitIgnores {
+    /// The source folder, as an absolute path.
+    root: PathBuf,
+    /// The top folder of the work tree, holding `.git`.
+    top: PathBuf,
+    /// The rules of `.git/info/exclude` and of the global excludes file, in that order.
+    excludes: Vec<Gitignore>,
+    /// The rules of the `.gitignore` file of every folder matched so far, `None` without one.
+    folders: Arc<Mutex<HashMap<PathBuf, Option<Gitignore>>>>,
+}
+
+impl GitIgnores {
+    /// Reads the excludes of the work tree holding `root`.
+    ///
+    /// # Returns:
+    /// - `Err(Error)` when `root` does not exist or is not in a git work tree
+    fn new(root: &Path) -> Result<GitIgnores, Error> {
+        let root = root.canonicalize()?;
+        let Some(top) = root
+            .ancestors()
+            .find(|folder| folder.join(".git").exists())
+            .map(Path::to_path_buf)
+        else {
+            return Err(Error::other(format!(
+                "{} is not in a git work tree",
+                root.display()
+            )));
+        };
+        let mut exclude = GitignoreBuilder::new(&top);
+        let file = top.join(".git/info/exclude");
+        if file.is_file()
+            && let Some(error) = exclude.add(file)
+        {
+            return Err(Error::other(error));
+        }
+        let (global, _) = GitignoreBuilder::new(&top).build_global();
+        Ok(GitIgnores {
+            excludes: vec![exclude.build().map_err(Error::other)?, global],
+            root,
+            top,
+            folders: Arc::default(),
+        })
+    }
+    /// Returns whether git ignores a file or folder, given by its path relative to the source
+    /// folder.
+    ///
+    /// As in git, the `.gitignore` of the nearest folder that has a rule for the path decides,
+    /// from the folder of the path up to the top of the work tree, then the excludes. A `!`
+    /// rule keeps a path that a rule further away ignores.
+    fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let path = self.root.join(relative);
+        // the rule that decides: `Some(true)` when it ignores the path, `Some(false)` for `!`
+        let decision = |ignore: &Gitignore| {
+            let found = ignore.matched(&path, is_dir);
+            (!found.is_none()).then(|| found.is_ignore())
+        };
+        let mut folders = self.folders.lock().unwrap();
+        path.ancestors()
+            .skip(1)
+            .take_while(|folder| folder.starts_with(&self.top))
+            .find_map(|folder| {
+                folders
+                    .entry(folder.to_path_buf())
+                    .or_insert_with(|| folder_ignores(folder))
+                    .as_ref()
+                    .and_then(decision)
+            })
+            .or_else(|| self.excludes.iter().find_map(decision))
+            .unwrap_or(false)
+    }
+}
+
+/// Returns the rules of the `.gitignore` file of a folder, `None` when it has none or it
+/// cannot be read.
+fn folder_ignores(folder: &Path) -> Option<Gitignore> {
+    let file = folder.join(".gitignore");
+    if !file.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(folder);
+    builder.add(file);
+    builder.build().ok()
+}
+
+#[cfg(test)]
+#[test]
+fn test_exclusions() {
+    let exclusions = Exclusions::new(
+        "./src",
+        &["target".to_string(), "vendor/**/*.js".to_string()],
+        false,
+        |_| {},
+    );
+    assert!(exclusions.is_excluded(Path::new("./src/target")));
+    assert!(exclusions.is_excluded(Path::new("./src/app/target")));
+    assert!(exclusions.is_excluded(Path::new("./src/vendor/lib/a.js")));
+    assert!(!exclusions.is_excluded(Path::new("./src/vendor/lib/a.rs")));
+    assert!(!exclusions.is_excluded(Path::new("./src")));
+    assert!(!exclusions.is_excluded(Path::new("./src/targets.rs")));
+    let only = HashSet::from([PathBuf::from("./src/lib.rs")]);
+    let exclusions = Exclusions::new(".", &[], false, |_| {}).with_only(Some(only));
+    assert!(exclusions.is_excluded(Path::new("./src/main.rs")));
+    assert!(!exclusions.is_excluded(Path::new("./src/lib.rs")));
+    assert!(!exclusions.is_excluded(Path::new("./src")));
+}
+
+#[cfg(test)]
+#[test]
+fn test_gitignore() {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    let root = "target/test-gitignore";
+    let _ = remove_dir_all(root);
+    create_dir_all(format!("{root}/.git/info")).unwrap();
+    create_dir_all(format!("{root}/src/gen")).unwrap();
+    write(format!("{root}/.gitignore"), "*.log\ngen/\n").unwrap();
+    write(format!("{root}/.git/info/exclude"), "local.rs\n").unwrap();
+    write(format!("{root}/src/.gitignore"), "!keep.log\n").unwrap();
+    for file in ["src/a.rs", "src/a.log", "src/keep.log", "src/local.rs"] {
+        write(format!("{root}/{file}"), "").unwrap();
+    }
+    let source = format!("{root}/src");
+    let exclusions = Exclusions::new(&source, &[], true, |message| panic!("{message}"));
+    let excluded = |file: &str| exclusions.is_excluded(&Path::new(&source).join(file));
+    assert!(!excluded("a.rs"));
+    assert!(excluded("a.log"));
+    assert!(!excluded("keep.log"));
+    assert!(excluded("local.rs"));
+    assert!(excluded("gen"));
+    remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,154 @@
+use crate::failure::FailureKind;
+use std::fmt::{Display, Formatter};
+
+/// The cause and fix of an error code, such as the `[E002]` in front of a duplicate Sequence
+/// number error.
+#[derive(Debug, PartialEq)]
+pub struct Explanation {
+    pub code: &'static str,
+    pub kind: FailureKind,
+    pub title: &'static str,
+    pub cause: &'static str,
+    pub fix: &'static str,
+}
+
+/// Every error code, in code order. Codes are never reused for another error.
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E001",
+        kind: FailureKind::Parse,
+        title: "No Sequence number in a block header",
+        cause: "The first line of a block must end with its Sequence number, as in `//#EPIC A [0]`, or match the `-header` grammar. The block header had none, or the number was not a whole number from 0 to 65535.",
+        fix: "Add the Sequence number in square brackets to the end of the header line shown in the error, or fix the `-header` spec.",
+    },
+    Explanation {
+        code: "E002",
+        kind: FailureKind::Parse,
+        title: "Duplicate Sequence number",
+        cause: "Two blocks with the same name have the same Sequence number, so their order in the generated file is undefined. The blocks may be in different source files.",
+        fix: "Give one of the blocks another Sequence number. The error names the file and line of the other block.",
+    },
+    Explanation {
+        code: "E003",
+        kind: FailureKind::Parse,
+        title: "Output path already used",
+        cause: "A block placed with `@path:` is written to a file that another block, with another name or placed by its name, is written to as well.",
+        fix: "Give the `@path:` directive a path that no other block uses, or give both blocks the same name.",
+    },
+    Explanation {
+        code: "E004",
+        kind: FailureKind::Parse,
+        title: "Invalid @path value",
+        cause: "The value of an `@path:` directive is empty, has an empty folder, or has a `.` in a folder or file name, which would be taken for a level separator.",
+        fix: "Write the path as folders and a file name without dots or extension, such as `@path: guides/how-to-deploy`.",
+    },
+    Explanation {
+        code: "E005",
+        kind: FailureKind::Parse,
+        title: "Glossary term defined more than once",
+        cause: "With `-glossary`, two `TERM` blocks define the same term.",
+        fix: "Merge the two definitions into one `TERM` block, or rename one of the terms.",
+    },
+    Explanation {
+        code: "E006",
+        kind: FailureKind::Validation,
+        title: "Block path longer than allowed",
+        cause: "A block name has more dot delimited levels than `-path` allows: one level per prefix plus the file name.",
+        fix: "Remove levels from the block name, or add a prefix to `-path`.",
+    },
+    Explanation {
+        code: "E007",
+        kind: FailureKind::Validation,
+        title: "Block name not unique",
+        cause: "Two blocks use names that differ only in case or white space, such as `EPIC A.ITEM b` and `EPIC A.ITEM B`. They would be written to different files, which overwrite each other on file systems that ignore case. The error names the file and line of both blocks.",
+        fix: "Spell the block name the same in both places, or rename one of the blocks.",
+    },
+    Explanation {
+        code: "E008",
+        kind: FailureKind::Validation,
+        title: "Invalid folder prefix",
+        cause: "A level of a block name does not start with the prefix of its depth in `-path`. The error names the level and the expected prefix, e.g. `[ITEM x] [TASK]`.",
+        fix: "Start the level with the expected prefix, or change `-path`. Blocks with this error are not written; the other blocks are.",
+    },
+    Explanation {
+        code: "E010",
+        kind: FailureKind::Config,
+        title: "Invalid option value",
+        cause: "An option on the command line or in `get-comments.toml` has a value that cannot be used, such as an unknown `-format`, `-filter`, `-marker-position` or `-inline-strings`, or a file given to `-config`, `-fields`, `-glossary`, `-requirements` or `-log` that cannot be read. Nothing was written.",
+        fix: "Correct the value named in the error. The usage, printed for a command line without a valid command, lists the accepted values.",
+    },
+    Explanation {
+        code: "E011",
+        kind: FailureKind::Config,
+        title: "Invalid project configuration",
+        cause: "`get-comments.toml` in the current folder could not be read, or it is not valid TOML, or an option does not have a string, a number, a boolean or an array of strings as value. Nothing was written.",
+        fix: "Correct the line named in the error, or run `--init` in an empty folder to see a valid file.",
+    },
+    Explanation {
+        code: "E012",
+        kind: FailureKind::Parse,
+        title: "Invalid metadata field value",
+        cause: "With `-fields`, a block has an `@name: value` directive whose value does not have the type the field schema declares for `name`: a whole number, a `YYYY-MM-DD` date, an absolute URL or one of the listed values.",
+        fix: "Correct the directive value in the block shown in the error, or change the type of the field in the field schema.",
+    },
+    Explanation {
+        code: "E013",
+        kind: FailureKind::Parse,
+        title: "Continuation of a missing block",
+        cause: "A block header has a `+` after its Sequence number, as in `EPIC A [2+]`, which appends its body to the block with that name and Sequence number, but no file holds that block. The continuation is not written.",
+        fix: "Give the block it continues a header without the `+`, such as `EPIC A [2]`, or correct the name or Sequence number of the continuation.",
+    },
+    Explanation {
+        code: "E014",
+        kind: FailureKind::Validation,
+        title: "Missing required block",
+        cause: "With `-requirements` and `--strict`, an entry of the requirements list is neither the name of a block nor listed in the `@requirement:` directive of a block. The generated files and `requirements.md` were written.",
+        fix: "Document the requirement in a block, or remove it from the requirements list. Without `--strict` missing requirements are only warnings.",
+    },
+    Explanation {
+        code: "E015",
+        kind: FailureKind::Validation,
+        title: "Documentation coverage below the minimum",
+        cause: "With `-coverage-min`, the share of scanned source files holding at least one block is lower than the given percentage. The coverage report printed before the error shows the documented files of every directory.",
+        fix: "Document the files of the directories with the lowest coverage, lower `-coverage-min`, or leave files that need no documentation out of the scan with `-exclude`.",
+    },
+];
+
+/// Returns the explanation of an error code such as `E002`. The code is not case sensitive
+/// and may be given with its square brackets.
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    let code = code.trim().trim_start_matches('[').trim_end_matches(']');
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.code, self.title)?;
+        writeln!(
+            f,
+            "Kind: {} error, exit code {}\n",
+            self.kind,
+            self.kind.exit_code()
+        )?;
+        writeln!(f, "Cause: {}\n", self.cause)?;
+        write!(f, "Fix: {}", self.fix)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_explain() {
+    let explanation = explain("[e002]").unwrap();
+    assert_eq!(explanation.kind, FailureKind::Parse);
+    assert!(
+        explanation
+            .to_string()
+            .starts_with("E002: Duplicate Sequence number\nKind: parse error, exit code 2\n")
+    );
+    assert_eq!(explain("E999"), None);
+    let mut codes: Vec<&str> = EXPLANATIONS.iter().map(|e| e.code).collect();
+    codes.dedup();
+    assert_eq!(codes.len(), EXPLANATIONS.len());
+}
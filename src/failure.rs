@@ -0,0 +1,195 @@
+use crate::events::{Event, Events};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io::{Error, ErrorKind};
+
+/// What went wrong in a run, ordered from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureKind {
+    /// A block name does not match the path prefixes, so its file could not be generated.
+    Validation,
+    /// A block could not be parsed, such as a header without a Sequence number or a
+    /// duplicate Sequence number.
+    Parse,
+    /// A source file could not be read or a generated file could not be written.
+    Io,
+    /// The command line or the project configuration file is invalid, so the run did not
+    /// start.
+    Config,
+}
+
+impl FailureKind {
+    /// Classifies an error of a run. Invalid block paths are reported as `InvalidInput`, the
+    /// parser reports its findings as `Other`, anything else comes from the file system.
+    pub fn of(error: &Error) -> FailureKind {
+        match error.kind() {
+            ErrorKind::InvalidInput => FailureKind::Validation,
+            ErrorKind::Other => FailureKind::Parse,
+            _ => FailureKind::Io,
+        }
+    }
+    /// Returns the process exit code of the kind: 2 for parse, 3 for I/O, 4 for validation
+    /// and 5 for configuration errors.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            FailureKind::Parse => 2,
+            FailureKind::Io => 3,
+            FailureKind::Validation => 4,
+            FailureKind::Config => 5,
+        }
+    }
+}
+
+impl Display for FailureKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FailureKind::Validation => "validation",
+            FailureKind::Parse => "parse",
+            FailureKind::Io => "I/O",
+            FailureKind::Config => "configuration",
+        })
+    }
+}
+
+/// One error that was reported during a run, which went on with the next file or report.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+//#EPIC Get Lines.ITEM Exit codes [0]
+//#
+//## Exit codes
+//#A run goes on after an error, so one bad file does not stop the documentation of the others,
+//#but every error is remembered. When the run is done the process exits with 2 when a block
+//#could not be parsed, 3 when a file could not be read or written and 4 when a block name did
+//#not match the path prefixes. These codes mean partial success: the files of every valid block
+//#were written. With several kinds of errors the most severe one decides: I/O before parse
+//#before validation errors. A command line without a valid command exits with 1, and an option
+//#with an invalid value or an invalid `get-comments.toml` exits with 5 before anything is
+//#written. Every error the parser reports carries a code such as `[E002]`, which
+//#`--explain E002` explains.
+/// All errors of a run, in the order they were reported.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunError {
+    pub failures: Vec<Failure>,
+}
+
+impl RunError {
+    /// Records an error under the kind given by `FailureKind::of`.
+    pub fn push(&mut self, message: String, error: &Error) {
+        self.push_kind(FailureKind::of(error), message);
+    }
+    /// Prints an error the run goes on after, as `context` followed by the error, and
+    /// records it.
+    pub(crate) fn report(&mut self, context: &str, error: Error, events: &Events) {
+        let message = format!("{context} {error}");
+        events.error(&message);
+        self.record(FailureKind::of(&error), message, events);
+    }
+    /// Records an error of the given kind and sends it to the subscriber of the run.
+    pub(crate) fn record(&mut self, kind: FailureKind, message: String, events: &Events) {
+        events.emit(Event::Error {
+            kind,
+            message: &message,
+        });
+        self.push_kind(kind, message);
+    }
+    /// Records an error of the given kind.
+    pub fn push_kind(&mut self, kind: FailureKind, message: String) {
+        self.failures.push(Failure { kind, message });
+    }
+    /// Returns the most severe kind of error, or `None` when the run had no errors.
+    pub fn kind(&self) -> Option<FailureKind> {
+        self.failures.iter().map(|failure| failure.kind).max()
+    }
+    /// Returns the process exit code of the most severe error, 0 when the run had no errors.
+    pub fn exit_code(&self) -> i32 {
+        self.kind().map_or(0, FailureKind::exit_code)
+    }
+    /// Turns the errors of a run into its result.
+    pub fn into_result(self) -> Result<(), RunError> {
+        match self.failures.is_empty() {
+            true => Ok(()),
+            false => Err(self),
+        }
+    }
+}
+
+impl Display for RunError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} errors in run", self.failures.len())?;
+        for failure in &self.failures {
+            write!(f, "\n{} error: {}", failure.kind, failure.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RunError {}
+
+/// The number of source lines shown before and after the offending line of a diagnostic.
+const SNIPPET_CONTEXT: usize = 2;
+
+/// Adds the source location and the offending line, with `SNIPPET_CONTEXT` lines around it, to
+/// an error found in a source file, so it can be fixed without opening the file. The kind of
+/// the error is kept.
+///
+/// # Parameters:
+/// - `line`: The 1-based number of the offending line in `content`
+pub(crate) fn with_snippet(error: Error, file_name: &str, content: &str, line: usize) -> Error {
+    let first = line.saturating_sub(SNIPPET_CONTEXT).max(1);
+    let width = (line + SNIPPET_CONTEXT).to_string().len();
+    let mut message = format!("{error} in {file_name} LINE: {line}");
+    for (number, text) in content
+        .lines()
+        .enumerate()
+        .map(|(index, text)| (index + 1, text))
+        .skip(first - 1)
+        .take(line + SNIPPET_CONTEXT + 1 - first)
+    {
+        let marker = if number == line { '>' } else { ' ' };
+        message.push_str(&format!("\n{marker} {number:>width$} | {text}"));
+    }
+    Error::new(error.kind(), message)
+}
+
+#[cfg(test)]
+#[test]
+fn test_run_error() {
+    let mut errors = RunError::default();
+    assert_eq!(errors.exit_code(), 0);
+    errors.push(
+        "bad path".to_string(),
+        &Error::new(ErrorKind::InvalidInput, "bad path"),
+    );
+    assert_eq!(errors.exit_code(), 4);
+    errors.push("no sequence".to_string(), &Error::other("no sequence"));
+    assert_eq!(errors.exit_code(), 2);
+    errors.push(
+        "missing".to_string(),
+        &Error::new(ErrorKind::NotFound, "missing"),
+    );
+    assert_eq!(errors.kind(), Some(FailureKind::Io));
+    assert_eq!(errors.exit_code(), 3);
+    assert!(errors.to_string().ends_with("\nI/O error: missing"));
+    let json = serde_json::to_string(&errors).unwrap();
+    assert!(json.contains(r#"{"kind":"io","message":"missing"}"#));
+    assert_eq!(serde_json::from_str::<RunError>(&json).unwrap(), errors);
+}
+
+#[cfg(test)]
+#[test]
+fn test_with_snippet() {
+    let content = "fn a() {}\n\n//# EPIC A\n//#text\ncode\nmore\n";
+    let error = with_snippet(Error::other("No Sequence"), "a.rs", content, 3);
+    assert_eq!(error.kind(), ErrorKind::Other);
+    assert_eq!(
+        error.to_string(),
+        "No Sequence in a.rs LINE: 3\n  1 | fn a() {}\n  2 | \n> 3 | //# EPIC A\n  4 | //#text\n  5 | code"
+    );
+    let error = with_snippet(Error::other("Duplicate"), "a.rs", "//# EPIC A [0]\n", 1);
+    assert_eq!(error.to_string(), "Duplicate in a.rs LINE: 1\n> 1 | //# EPIC A [0]");
+}
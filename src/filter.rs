@@ -0,0 +1,151 @@
+use crate::format::OutputFormat;
+use crate::shortcode::expand_line;
+use std::collections::BTreeSet;
+use std::io::Error;
+
+/// Built-in text filters that can be chained and applied to the body of a line block.
+///
+/// The filters are applied in the order in which they are given on the command line,
+/// after the comment marker was stripped and before the block is stored in the history.
+/// The header line that carries the source file link is never filtered.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// Remove whitespace at the end of every line.
+    TrimTrailing,
+    /// Replace runs of blank lines with a single blank line and drop leading and trailing blank lines.
+    CollapseBlankLines,
+    /// Replace every tab with the given number of spaces.
+    ExpandTabs(usize),
+    /// Replace typographic (curly) quotes with their plain ASCII counterparts.
+    PlainQuotes,
+    /// Expand `:emoji:` shortcodes. `@badge[label=message]` directives are kept in the block
+    /// and expanded when a generated file is written, in the markup of its format.
+    Shortcodes,
+}
+
+impl Filter {
+    /// Parses a single filter name as given on the command line.
+    ///
+    /// # Accepted Names:
+    /// - `trim-trailing` → `Filter::TrimTrailing`
+    /// - `collapse-blank` → `Filter::CollapseBlankLines`
+    /// - `tabs` or `tabs=N` → `Filter::ExpandTabs(N)`, four spaces when `N` is omitted
+    /// - `plain-quotes` → `Filter::PlainQuotes`
+    /// - `shortcodes` → `Filter::Shortcodes`
+    ///
+    /// # Returns:
+    /// - `Ok(Filter)` for a known filter name
+    /// - `Err(Error)` for an unknown name or a tab width that is not a number
+    pub fn parse(name: &str) -> Result<Filter, Error> {
+        match name.trim() {
+            "trim-trailing" => Ok(Filter::TrimTrailing),
+            "collapse-blank" => Ok(Filter::CollapseBlankLines),
+            "tabs" => Ok(Filter::ExpandTabs(4)),
+            "plain-quotes" => Ok(Filter::PlainQuotes),
+            "shortcodes" => Ok(Filter::Shortcodes),
+            other => match other.strip_prefix("tabs=") {
+                Some(width) => width
+                    .parse::<usize>()
+                    .map(Filter::ExpandTabs)
+                    .map_err(|_| Error::other(format!("Invalid tab width in filter [{other}]"))),
+                None => Err(Error::other(format!("Unknown filter [{other}]"))),
+            },
+        }
+    }
+    /// Applies this filter to the lines of a block body and returns the filtered lines.
+    pub fn apply(&self, lines: Vec<String>) -> Vec<String> {
+        match self {
+            Filter::TrimTrailing => lines
+                .into_iter()
+                .map(|line| line.trim_end().to_string())
+                .collect(),
+            Filter::CollapseBlankLines => {
+                let mut collapsed: Vec<String> = Vec::with_capacity(lines.len());
+                for line in lines {
+                    let blank = line.trim().is_empty();
+                    let previous_blank = collapsed.last().is_none_or(|l| l.trim().is_empty());
+                    if !(blank && previous_blank) {
+                        collapsed.push(line);
+                    }
+                }
+                while collapsed.last().is_some_and(|l| l.trim().is_empty()) {
+                    collapsed.pop();
+                }
+                collapsed
+            }
+            Filter::ExpandTabs(width) => lines
+                .into_iter()
+                .map(|line| line.replace('\t', &" ".repeat(*width)))
+                .collect(),
+            Filter::PlainQuotes => lines
+                .into_iter()
+                .map(|line| {
+                    line.replace(['\u{201C}', '\u{201D}'], "\"")
+                        .replace(['\u{2018}', '\u{2019}'], "'")
+                })
+                .collect(),
+            Filter::Shortcodes => lines
+                .iter()
+                .map(|line| expand_line(line, OutputFormat::Json))
+                .collect(),
+        }
+    }
+}
+
+/// Runs the lines of a block body through every filter in the chain, in order.
+pub fn apply_filters(filters: &[Filter], lines: Vec<String>) -> Vec<String> {
+    filters
+        .iter()
+        .fold(lines, |lines, filter| filter.apply(lines))
+}
+
+/// Runs the lines of a block body through the filter chain, leaving raw lines untouched.
+///
+/// The body is cut into runs of ordinary lines separated by raw lines. Each run is filtered
+/// on its own and the raw lines are put back in place, so filters never see nor change the
+/// content of a raw region.
+///
+/// # Returns:
+/// - The filtered lines, and the indices of the raw lines in them
+pub fn apply_filters_except_raw(
+    filters: &[Filter],
+    lines: Vec<String>,
+    raw: &BTreeSet<usize>,
+) -> (Vec<String>, BTreeSet<usize>) {
+    let mut filtered = Vec::with_capacity(lines.len());
+    let mut filtered_raw = BTreeSet::new();
+    let mut run = vec![];
+    for (index, line) in lines.into_iter().enumerate() {
+        if raw.contains(&index) {
+            filtered.extend(apply_filters(filters, std::mem::take(&mut run)));
+            filtered_raw.insert(filtered.len());
+            filtered.push(line);
+        } else {
+            run.push(line);
+        }
+    }
+    filtered.extend(apply_filters(filters, run));
+    (filtered, filtered_raw)
+}
+
+#[cfg(test)]
+#[test]
+fn test_filter_chain() {
+    let filters: Vec<Filter> = ["trim-trailing", "collapse-blank", "tabs=2", "plain-quotes"]
+        .iter()
+        .map(|name| Filter::parse(name).unwrap())
+        .collect();
+    let lines = vec![
+        "".to_string(),
+        "\u{201C}quoted\u{201D}   ".to_string(),
+        "".to_string(),
+        "".to_string(),
+        "\tindented".to_string(),
+        "".to_string(),
+    ];
+    assert_eq!(
+        apply_filters(&filters, lines),
+        vec!["\"quoted\"", "", "  indented"]
+    );
+    assert!(Filter::parse("unknown").is_err());
+}
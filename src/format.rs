@@ -0,0 +1,53 @@
+use std::io::Error;
+
+/// A format in which the extracted blocks are written to the document root.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// One Markdown file per block name, the default.
+    Markdown,
+    /// One `comments.json` document holding every block.
+    Json,
+    /// A static HTML site with one page per block name and a navigation sidebar.
+    Html,
+    /// One AsciiDoc file per block name.
+    AsciiDoc,
+    /// An mdBook with one chapter per block name and a `SUMMARY.md` of the hierarchy.
+    MdBook,
+}
+
+impl OutputFormat {
+    /// Parses an output format as given on the command line: `md`, `json`, `html`, `adoc`
+    /// or `mdbook`.
+    pub fn parse(name: &str) -> Result<OutputFormat, Error> {
+        match name.trim() {
+            "md" | "markdown" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            "html" => Ok(OutputFormat::Html),
+            "adoc" | "asciidoc" => Ok(OutputFormat::AsciiDoc),
+            "mdbook" => Ok(OutputFormat::MdBook),
+            other => Err(Error::other(format!("Unknown output format [{other}]"))),
+        }
+    }
+}
+
+/// The Markdown dialect of the generated Markdown files.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MarkdownFlavor {
+    /// Plain CommonMark, the default.
+    #[default]
+    CommonMark,
+    /// MyST, the Markdown of Sphinx: every block gets a target, links between generated files
+    /// use the `{doc}` role and code fences become `{code-block}` directives.
+    Myst,
+}
+
+impl MarkdownFlavor {
+    /// Parses a Markdown flavor as given on the command line: `commonmark` or `myst`.
+    pub fn parse(name: &str) -> Result<MarkdownFlavor, Error> {
+        match name.trim() {
+            "commonmark" | "md" => Ok(MarkdownFlavor::CommonMark),
+            "myst" => Ok(MarkdownFlavor::Myst),
+            other => Err(Error::other(format!("Unknown Markdown flavor [{other}]"))),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::block::Sequences;
+use crate::schema::FieldSchema;
+use crate::sequence::Sequence;
+use std::collections::BTreeSet;
+use std::io::{Error, Write};
+
+/// A value of the front matter of a generated file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FrontMatterValue {
+    /// A number, written as it is.
+    Number(String),
+    /// A string.
+    Text(String),
+    /// A list of strings, one per line in YAML.
+    Texts(Vec<String>),
+    /// The Sequence numbers of the file, on one line in YAML.
+    Sequences(Vec<Sequence>),
+}
+
+/// Returns the front matter of a generated file with the statistics of its blocks, in the
+/// order it is written.
+///
+/// # Fields:
+/// - `title`: The block name of the file, the last component of its path
+/// - `sources`: Every source file contributing a sequence to the file
+/// - `sequences`: The total number of sequences in the file
+/// - `sequence_numbers`: The Sequence numbers of the file, in ascending order
+/// - `first_line` / `last_line`: The lowest and highest source line of the sequences
+/// - `tags`: The comma separated values of the `@tags:` directives of the blocks, sorted and
+///   without duplicates, left out when no block has tags
+/// - The declared metadata fields set by the blocks, when a field schema is given
+/// - `generated`: The UTC time the run started, left out when `generated` is `None`
+/// - `run_id`: The ID of the run that regenerated the file, left out when `run_id` is `None`
+pub fn front_matter(
+    title: &str,
+    blocks: &Sequences,
+    run_id: Option<&str>,
+    generated: Option<&str>,
+    fields: Option<&FieldSchema>,
+) -> Vec<(String, FrontMatterValue)> {
+    let text = |value: &str| FrontMatterValue::Text(value.to_string());
+    let sources: BTreeSet<&str> = blocks.values().map(|block| &*block.source_file).collect();
+    let mut entries = vec![
+        ("title".to_string(), text(title)),
+        (
+            "sources".to_string(),
+            FrontMatterValue::Texts(sources.into_iter().map(str::to_string).collect()),
+        ),
+        (
+            "sequences".to_string(),
+            FrontMatterValue::Number(blocks.len().to_string()),
+        ),
+        (
+            "sequence_numbers".to_string(),
+            FrontMatterValue::Sequences(blocks.keys().copied().collect()),
+        ),
+    ];
+    if let Some(first_line) = blocks.values().map(|block| block.start_line).min() {
+        entries.push((
+            "first_line".to_string(),
+            FrontMatterValue::Number(first_line.to_string()),
+        ));
+    }
+    if let Some(last_line) = blocks.values().map(|block| block.end_line).max() {
+        entries.push((
+            "last_line".to_string(),
+            FrontMatterValue::Number(last_line.to_string()),
+        ));
+    }
+    let tags: BTreeSet<&str> = blocks.values().flat_map(|block| block.tags()).collect();
+    if !tags.is_empty() {
+        entries.push((
+            "tags".to_string(),
+            FrontMatterValue::Texts(tags.into_iter().map(str::to_string).collect()),
+        ));
+    }
+    if let Some(fields) = fields {
+        entries.extend(fields.front_matter(blocks.values().map(|block| &block.metadata)));
+    }
+    if let Some(generated) = generated {
+        entries.push(("generated".to_string(), text(generated)));
+    }
+    if let Some(run_id) = run_id {
+        entries.push(("run_id".to_string(), text(run_id)));
+    }
+    entries
+}
+
+/// Writes front matter as a YAML block between `---` lines, for the Markdown files.
+///
+/// Strings are written as JSON string literals, which are valid YAML scalars, so names with
+/// colons or quotes do not break the front matter.
+pub fn write_yaml<W: Write>(
+    writer: &mut W,
+    entries: &[(String, FrontMatterValue)],
+) -> Result<(), Error> {
+    writeln!(writer, "---")?;
+    for (name, value) in entries {
+        match value {
+            FrontMatterValue::Number(number) => writeln!(writer, "{name}: {number}")?,
+            FrontMatterValue::Text(text) => writeln!(writer, "{name}: {}", quoted(text))?,
+            FrontMatterValue::Texts(texts) => {
+                writeln!(writer, "{name}:")?;
+                for text in texts {
+                    writeln!(writer, "  - {}", quoted(text))?;
+                }
+            }
+            FrontMatterValue::Sequences(sequences) => {
+                // a nested Sequence number such as 2.1 is quoted, so it is not read back as a
+                // float
+                let numbers: Vec<String> = sequences
+                    .iter()
+                    .map(|sequence| match sequence.levels().len() {
+                        1 => sequence.to_string(),
+                        _ => quoted(&sequence.to_string()),
+                    })
+                    .collect();
+                writeln!(writer, "{name}: [{}]", numbers.join(", "))?;
+            }
+        }
+    }
+    writeln!(writer, "---\n")?;
+    Ok(())
+}
+
+/// Returns `text` as a JSON string literal, which is a valid YAML scalar.
+fn quoted(text: &str) -> String {
+    serde_json::to_string(text).unwrap()
+}
+
+/// Writes front matter as the attribute entries of an AsciiDoc document header, such as
+/// `:sources: a.rs, b.rs`, for the AsciiDoc files. Lists are written comma separated.
+pub fn write_attributes<W: Write>(
+    writer: &mut W,
+    entries: &[(String, FrontMatterValue)],
+) -> Result<(), Error> {
+    for (name, value) in entries {
+        let value = match value {
+            FrontMatterValue::Number(text) | FrontMatterValue::Text(text) => text.clone(),
+            FrontMatterValue::Texts(texts) => texts.join(", "),
+            FrontMatterValue::Sequences(sequences) => sequences
+                .iter()
+                .map(Sequence::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        writeln!(writer, ":{name}: {value}")?;
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[test]
+fn test_front_matter() {
+    use crate::block::Block;
+    let block = |start_line: usize, tags: &str| {
+        let lines = vec![format!("@tags: {tags}"), "text".to_string()];
+        let mut block = Block::new("a.rs".into(), start_line, lines, &BTreeSet::new());
+        block.end_line = start_line + 1;
+        block
+    };
+    let blocks = Sequences::from([
+        (Sequence::from(0), block(4, "io, parser")),
+        (Sequence::from(2), block(10, "parser,")),
+    ]);
+    let entries = front_matter("ITEM B", &blocks, None, None, None);
+    let mut yaml = Vec::new();
+    write_yaml(&mut yaml, &entries).unwrap();
+    assert_eq!(
+        String::from_utf8(yaml).unwrap(),
+        "---\ntitle: \"ITEM B\"\nsources:\n  - \"a.rs\"\nsequences: 2\nsequence_numbers: [0, 2]\nfirst_line: 4\nlast_line: 11\ntags:\n  - \"io\"\n  - \"parser\"\n---\n\n"
+    );
+    let mut attributes = Vec::new();
+    write_attributes(&mut attributes, &entries).unwrap();
+    assert_eq!(
+        String::from_utf8(attributes).unwrap(),
+        ":title: ITEM B\n:sources: a.rs\n:sequences: 2\n:sequence_numbers: 0, 2\n:first_line: 4\n:last_line: 11\n:tags: io, parser\n\n"
+    );
+}
@@ -0,0 +1,177 @@
+use crate::block::LastCommit;
+use crate::clock::format_date;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The commit that last changed one line of a source file, as reported by `git blame`.
+#[derive(Clone, Debug)]
+pub struct BlameLine {
+    pub commit: String,
+    pub author: String,
+    /// Author time in seconds since the Unix epoch.
+    pub time: i64,
+}
+
+/// Runs a git command in the folder holding `path` and returns its standard output.
+fn git(path: &str, arguments: &[&str]) -> Result<String, Error> {
+    let folder = Path::new(path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    git_in(folder, arguments)
+}
+
+/// Runs a git command in a folder and returns its standard output.
+fn git_in(folder: &Path, arguments: &[&str]) -> Result<String, Error> {
+    Ok(String::from_utf8_lossy(&git_bytes(folder, arguments)?).to_string())
+}
+
+/// Runs a git command in a folder and returns its standard output as it is.
+fn git_bytes(folder: &Path, arguments: &[&str]) -> Result<Vec<u8>, Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(folder)
+        .args(arguments)
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "git {} failed: {}",
+            arguments.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Returns the files below `root` that changed since the revision `since`: the files
+/// changed, added or removed by the commits after it or in the work tree, and the untracked
+/// files git does not ignore, relative to `root`.
+///
+/// # Returns:
+/// - `Err(Error)` when git is missing, `root` is not in a git work tree or `since` is not a
+///   revision
+pub fn changed_paths(root: &str, since: &str) -> Result<Vec<String>, Error> {
+    let root = Path::new(root);
+    let changed = git_in(root, &["diff", "--name-only", "--relative", since, "--"])?;
+    let untracked = git_in(root, &["ls-files", "--others", "--exclude-standard"])?;
+    Ok(changed
+        .lines()
+        .chain(untracked.lines())
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Returns the files below `root` that are staged for the next commit, added, copied,
+/// modified or renamed, relative to `root`. Staged deletions are left out.
+///
+/// # Returns:
+/// - `Err(Error)` when git is missing or `root` is not in a git work tree
+pub fn staged_paths(root: &str) -> Result<Vec<String>, Error> {
+    let staged = git_in(
+        Path::new(root),
+        &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "--relative",
+            "--diff-filter=ACMR",
+        ],
+    )?;
+    Ok(staged
+        .lines()
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Returns the content of a source file below `root` as it is staged for the next commit,
+/// which can differ from the file in the work tree.
+///
+/// # Returns:
+/// - `Err(Error)` when git is missing, `root` is not in a git work tree or the file is not
+///   in the index
+pub fn staged_content(root: &str, path: &Path) -> Result<Vec<u8>, Error> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let object = format!(":./{}", relative.to_string_lossy().replace('\\', "/"));
+    git_bytes(Path::new(root), &["show", &object])
+}
+
+/// Returns the folder git runs the hooks of the repository holding `root` from, honouring
+/// `core.hooksPath`.
+///
+/// # Returns:
+/// - `Err(Error)` when git is missing or `root` is not in a git work tree
+pub fn hooks_folder(root: &str) -> Result<PathBuf, Error> {
+    let folder = git_in(Path::new(root), &["rev-parse", "--git-path", "hooks"])?;
+    Ok(Path::new(root).join(folder.trim()))
+}
+
+/// Returns the blame information of lines `start` to `end` (inclusive) of a source file.
+///
+/// # Returns:
+/// - `Ok(Vec<BlameLine>)` with one entry per line in the range
+/// - `Err(Error)` when git is missing, the file is not tracked or the range is invalid
+pub fn blame(path: &str, start: usize, end: usize) -> Result<Vec<BlameLine>, Error> {
+    let file_name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let range = format!("{},{}", start, end.max(start));
+    let output = git(
+        path,
+        &["blame", "--line-porcelain", "-L", &range, "--", &file_name],
+    )?;
+
+    let mut lines = vec![];
+    let mut current = BlameLine {
+        commit: String::new(),
+        author: String::new(),
+        time: 0,
+    };
+    for line in output.lines() {
+        if let Some(author) = line.strip_prefix("author ") {
+            current.author = author.to_string();
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            current.time = time.parse().unwrap_or(0);
+        } else if line.starts_with('\t') {
+            lines.push(current.clone());
+        } else if let Some((commit, _)) = line.split_once(' ')
+            && commit.len() == 40
+            && commit.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            current.commit = commit.to_string();
+        }
+    }
+    Ok(lines)
+}
+
+/// Returns the most recent commit among the lines `start` to `end` (inclusive) of a source
+/// file, or `None` when none of the lines is committed yet.
+///
+/// # Returns:
+/// - `Err(Error)` when git is missing, the file is not tracked or the range is invalid
+pub fn last_commit(path: &str, start: usize, end: usize) -> Result<Option<LastCommit>, Error> {
+    let last = blame(path, start, end)?
+        .into_iter()
+        .filter(|line| line.commit.chars().any(|c| c != '0'))
+        .max_by_key(|line| line.time);
+    Ok(last.map(|line| LastCommit {
+        commit: line.commit.chars().take(7).collect(),
+        date: format_date(UNIX_EPOCH + Duration::from_secs(line.time.max(0) as u64)),
+        author: line.author,
+    }))
+}
+
+/// Returns the commit time, in seconds since the Unix epoch, of the last commit that touched
+/// a source file, or `None` when the file has no history.
+pub fn last_commit_time(path: &str) -> Result<Option<i64>, Error> {
+    let file_name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let output = git(path, &["log", "-1", "--format=%ct", "--", &file_name])?;
+    Ok(output.trim().parse::<i64>().ok())
+}
@@ -0,0 +1,44 @@
+/// Matches a path against a glob. A glob without a `/` is matched against the file name, a
+/// glob with a `/` against the trailing folders and file name of the path. `*` and `?` do not
+/// match a `/`, `**` matches any number of folders. `\` in the path is taken for `/`.
+pub fn path_matches(glob: &str, path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let components: Vec<&str> = path.split('/').collect();
+    let glob: Vec<&str> = glob.split('/').collect();
+    (0..components.len()).any(|start| glob_matches(&glob, &components[start..]))
+}
+
+/// Matches a dot delimited block name against a glob with `.` between the levels, as
+/// `path_matches` matches a path against a glob with `/`.
+pub fn block_name_matches(glob: &str, name: &str) -> bool {
+    path_matches(&glob.replace('.', "/"), &name.replace('.', "/"))
+}
+
+/// Matches the folders and file name of a glob against the trailing components of a path.
+fn glob_matches(glob: &[&str], components: &[&str]) -> bool {
+    match (glob.split_first(), components.split_first()) {
+        (None, None) => true,
+        (Some((&"**", rest)), _) => {
+            glob_matches(rest, components)
+                || (!components.is_empty() && glob_matches(glob, &components[1..]))
+        }
+        (Some((pattern, glob_rest)), Some((component, rest))) => {
+            name_matches(pattern.as_bytes(), component.as_bytes()) && glob_matches(glob_rest, rest)
+        }
+        _ => false,
+    }
+}
+
+/// Matches one path component against a pattern in which `*` is any run of characters and `?`
+/// is one character.
+fn name_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            name_matches(rest, name) || (!name.is_empty() && name_matches(pattern, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => name_matches(rest, name_rest),
+        (Some((p, rest)), Some((n, name_rest))) => p == n && name_matches(rest, name_rest),
+        _ => false,
+    }
+}
@@ -0,0 +1,215 @@
+use crate::outputs::write_if_changed;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::read_to_string;
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The prefix of a block header that defines a glossary term, e.g. `//#TERM Line block`.
+pub const TERM_PREFIX: &str = "TERM ";
+
+/// The file in the document root holding the terms defined by `TERM` blocks.
+pub const GLOSSARY_FILE: &str = "glossary.md";
+
+/// A glossary entry: either a definition taken from a `TERM` block, or a link to an
+/// external definition page read from the glossary file.
+#[derive(Clone, Debug)]
+enum Entry {
+    Definition(Vec<String>),
+    Page(String),
+}
+
+/// The glossary terms that are linked in the generated files.
+#[derive(Default)]
+pub struct Glossary {
+    entries: BTreeMap<String, Entry>,
+    /// The pattern matching every term, longest first, compiled when the first file is linked.
+    pattern: OnceLock<Option<Regex>>,
+}
+
+impl Glossary {
+    /// Reads a glossary file holding one `term = definition page` pair per line.
+    ///
+    /// Blank lines and lines starting with `#` are ignored. The definition page is used as
+    /// the link target as is, so it can be a URL or a path relative to the document root.
+    pub fn load(file_name: &str) -> Result<Glossary, Error> {
+        let mut glossary = Glossary::default();
+        for (number, line) in read_to_string(file_name)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((term, page)) if !term.trim().is_empty() => {
+                    glossary.entries.insert(
+                        term.trim().to_string(),
+                        Entry::Page(page.trim().to_string()),
+                    );
+                }
+                _ => {
+                    return Err(Error::other(format!(
+                        "Invalid glossary line {} in {file_name}: expected `term = page`",
+                        number + 1
+                    )));
+                }
+            }
+        }
+        Ok(glossary)
+    }
+    /// Adds the definition of a term found in a `TERM` block.
+    ///
+    /// # Returns:
+    /// - `Err(String)` when the term was already defined by another `TERM` block
+    pub fn add_definition(&mut self, term: &str, lines: Vec<String>) -> Result<(), String> {
+        if let Some(Entry::Definition(_)) = self.entries.get(term) {
+            return Err(format!(
+                "[E005] Glossary term [{term}] is defined more than once."
+            ));
+        }
+        self.entries
+            .insert(term.to_string(), Entry::Definition(lines));
+        self.pattern = OnceLock::new();
+        Ok(())
+    }
+    /// Links the first occurrence of every glossary term in the lines of one generated file.
+    ///
+    /// Terms are matched case sensitively on word boundaries, longest term first, and never
+    /// inside an existing Markdown link. One pattern matches all terms, see `pattern`. Terms already present in
+    /// `linked` were linked in an earlier sequence of the same file and are skipped; newly
+    /// linked terms are added to it. Lines inside fenced code blocks and raw lines are never
+    /// changed.
+    ///
+    /// # Parameters:
+    /// - `lines`: The body lines of one block sequence
+    /// - `raw`: The indices of the raw lines in `lines`
+    /// - `linked`: The terms that were already linked in the file
+    /// - `glossary_page`: The path of the generated glossary page relative to the file
+    /// - `link`: Returns the link to a target with a text in the format of the file
+    pub fn link_terms(
+        &self,
+        lines: &[String],
+        raw: &BTreeSet<usize>,
+        linked: &mut HashSet<String>,
+        glossary_page: &str,
+        link: &dyn Fn(&str, &str) -> String,
+    ) -> Vec<String> {
+        let Some(pattern) = self.pattern() else {
+            return lines.to_vec();
+        };
+        let mut in_fence = false;
+        let mut result = Vec::with_capacity(lines.len());
+        for (index, line) in lines.iter().enumerate() {
+            let mut line = line.clone();
+            if raw.contains(&index) {
+                // raw lines are passed through untouched
+            } else if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            } else if !in_fence {
+                let mut links = Vec::new();
+                for found in pattern.find_iter(&line) {
+                    let term = found.as_str();
+                    if linked.contains(term) || inside_link(&line[..found.start()]) {
+                        continue;
+                    }
+                    let target = match &self.entries[term] {
+                        Entry::Page(page) => page.clone(),
+                        Entry::Definition(_) => format!("{glossary_page}#{}", anchor(term)),
+                    };
+                    links.push((found.range(), link(&target, term)));
+                    linked.insert(term.to_string());
+                }
+                for (range, link) in links.into_iter().rev() {
+                    line.replace_range(range, &link);
+                }
+            }
+            result.push(line);
+        }
+        result
+    }
+    /// Returns the pattern matching any term on word boundaries, longest term first, or `None`
+    /// when there are no terms.
+    fn pattern(&self) -> Option<&Regex> {
+        self.pattern
+            .get_or_init(|| {
+                let mut terms: Vec<&String> = self.entries.keys().collect();
+                terms.sort_by_key(|term| std::cmp::Reverse(term.len()));
+                let alternatives: Vec<String> =
+                    terms.iter().map(|term| regex::escape(term)).collect();
+                (!terms.is_empty())
+                    .then(|| Regex::new(&format!(r"\b(?:{})\b", alternatives.join("|"))).unwrap())
+            })
+            .as_ref()
+    }
+    /// Writes `glossary.md` into the document root with every term defined by a `TERM` block,
+    /// unless it already holds them, and returns its path.
+    pub fn write_page(&self, doc_root: &str) -> Result<PathBuf, Error> {
+        let mut writer = Vec::new();
+        writeln!(writer, "# Glossary\n")?;
+        for (term, entry) in &self.entries {
+            if let Entry::Definition(lines) = entry {
+                writeln!(writer, "## {term}\n")?;
+                for line in lines {
+                    writeln!(writer, "{line}")?;
+                }
+                writeln!(writer)?;
+            }
+        }
+        let path = Path::new(doc_root).join(GLOSSARY_FILE);
+        write_if_changed(&path, &writer)?;
+        Ok(path)
+    }
+    /// Returns `true` when at least one term was defined by a `TERM` block.
+    pub fn has_definitions(&self) -> bool {
+        self.entries
+            .values()
+            .any(|entry| matches!(entry, Entry::Definition(_)))
+    }
+}
+
+/// Returns `true` when the text before a match leaves a Markdown link text or target open.
+fn inside_link(before: &str) -> bool {
+    before.matches('[').count() > before.matches(']').count()
+        || before.matches("](").count() > before.matches(')').count()
+}
+
+/// Returns the heading anchor Markdown renderers derive from a term.
+pub fn anchor(term: &str) -> String {
+    term.to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_link_terms() {
+    let mut glossary = Glossary::default();
+    glossary
+        .add_definition("line block", vec!["Consecutive marked lines.".to_string()])
+        .unwrap();
+    assert!(glossary.add_definition("line block", vec![]).is_err());
+    glossary.add_definition("block", vec![]).unwrap();
+    let mut linked = HashSet::new();
+    let lines = vec![
+        "A line block is found.".to_string(),
+        "Another line block, one block.".to_string(),
+    ];
+    assert_eq!(
+        glossary.link_terms(
+            &lines,
+            &BTreeSet::new(),
+            &mut linked,
+            "../glossary.md",
+            &|target, text| format!("[{text}](<{target}>)")
+        ),
+        vec![
+            "A [line block](<../glossary.md#line-block>) is found.",
+            "Another line block, one [block](<../glossary.md#block>)."
+        ]
+    );
+}
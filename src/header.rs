@@ -0,0 +1,116 @@
+use crate::sequence::Sequence;
+use regex::Regex;
+use std::io::Error;
+
+/// The default header grammar: the block name followed by the sequence number in brackets.
+pub const DEFAULT_HEADER: &str = "{name} [{seq}]";
+
+/// The grammar of the first line of a line block, which carries the block name and the
+/// sequence number.
+///
+/// The grammar is given as a small pattern spec in which `{name}` stands for the block name,
+/// `{seq}` for the sequence number and every other character for itself. Whitespace in the
+/// spec matches any amount of whitespace, including none. Examples:
+/// - `{name} [{seq}]` → `EPIC Get Lines [0]` (the default)
+/// - `{name} @ {seq}` → `EPIC Get Lines @ 0`
+/// - `{name} #{seq}` → `EPIC Get Lines #0`
+/// - `{seq}: {name}` → `0: EPIC Get Lines`
+#[derive(Clone, Debug)]
+pub struct HeaderGrammar {
+    spec: String,
+    pattern: Regex,
+}
+
+impl HeaderGrammar {
+    /// Compiles a header pattern spec.
+    ///
+    /// # Returns:
+    /// - `Err(Error)` when the spec does not hold exactly one `{name}` and one `{seq}`
+    pub fn parse(spec: &str) -> Result<HeaderGrammar, Error> {
+        if spec.matches("{name}").count() != 1 || spec.matches("{seq}").count() != 1 {
+            return Err(Error::other(format!(
+                "Header spec [{spec}] must contain {{name}} and {{seq}} exactly once"
+            )));
+        }
+        let mut pattern = String::from("^");
+        let mut rest = spec.trim();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("{name}") {
+                pattern.push_str("(?P<name>.+?)");
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("{seq}") {
+                pattern.push_str(r"(?P<seq>\d+(?:\.\d+)*)(?P<more>\+)?");
+                rest = after;
+            } else {
+                let c = rest.chars().next().unwrap_or_default();
+                if c.is_whitespace() {
+                    pattern.push_str(r"\s*");
+                } else {
+                    pattern.push_str(&regex::escape(&c.to_string()));
+                }
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        pattern.push('$');
+        let pattern = Regex::new(&pattern).map_err(Error::other)?;
+        Ok(HeaderGrammar {
+            spec: spec.to_string(),
+            pattern,
+        })
+    }
+    /// Splits a block header into its sequence number and its trimmed block name.
+    ///
+    /// # Returns:
+    /// - `Some((sequence, name))` when the header matches the grammar and every level of the
+    ///   sequence number fits in a `u16`, see `Sequence`
+    /// - `None` otherwise
+    pub fn split(&self, header: &str) -> Option<(Sequence, String)> {
+        let capture = self.pattern.captures(header.trim())?;
+        let sequence = capture["seq"].parse::<Sequence>().ok()?;
+        Some((sequence, capture["name"].trim().to_string()))
+    }
+    /// Tells whether a block header continues a block of another file, written with a `+`
+    /// after its sequence number as in `EPIC A [2+]`.
+    pub fn is_continuation(&self, header: &str) -> bool {
+        self.pattern
+            .captures(header.trim())
+            .is_some_and(|capture| capture.name("more").is_some())
+    }
+    /// Returns the pattern spec the grammar was compiled from.
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+}
+
+impl Default for HeaderGrammar {
+    fn default() -> Self {
+        HeaderGrammar::parse(DEFAULT_HEADER).unwrap()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_header_grammar() {
+    let default = HeaderGrammar::default();
+    assert_eq!(
+        default.split("EPIC Get Lines.ITEM Test [12]"),
+        Some((Sequence::from(12), "EPIC Get Lines.ITEM Test".to_string()))
+    );
+    assert_eq!(default.split("No Sequence"), None);
+    let sequence_first = HeaderGrammar::parse("{seq}: {name}").unwrap();
+    assert_eq!(
+        sequence_first.split("3: EPIC A"),
+        Some((Sequence::from(3), "EPIC A".to_string()))
+    );
+    assert_eq!(
+        default.split("EPIC A [2.1.3]"),
+        Some(("2.1.3".parse().unwrap(), "EPIC A".to_string()))
+    );
+    assert_eq!(
+        default.split("EPIC A [2+]"),
+        Some((Sequence::from(2), "EPIC A".to_string()))
+    );
+    assert!(default.is_continuation("EPIC A [2+]"));
+    assert!(!default.is_continuation("EPIC A [2]"));
+    assert!(HeaderGrammar::parse("{name}").is_err());
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeSet;
+
+/// The deepest heading level of Markdown.
+const DEEPEST_LEVEL: usize = 6;
+
+//#EPIC Get Lines.ITEM Heading offset [0]
+//#
+//## Heading offset
+//#Blocks written as stand-alone pages often start at `##`, which clashes with the headings of
+//#the file once several blocks share it. `-heading-offset N` moves every Markdown heading of
+//#the block bodies N levels down when the blocks are written, so `## Setup` becomes
+//#`### Setup` with `-heading-offset 1`. Headings in code fences and raw lines are left alone.
+/// Moves the ATX headings of a block body `offset` levels down, up to level 6. Lines in
+/// fenced code blocks and the `raw` lines are not changed.
+///
+/// # Parameters:
+/// - `lines`: The body lines of a block
+/// - `raw`: The indices of the raw lines in `lines`
+/// - `offset`: The number of levels every heading moves down
+pub fn shift_headings(lines: &[String], raw: &BTreeSet<usize>, offset: usize) -> Vec<String> {
+    let mut fence: Option<&str> = None;
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let text = line.trim_start();
+            let indent = line.len() - text.len();
+            if raw.contains(&index) || indent > 3 {
+                return line.clone();
+            }
+            match fence {
+                Some(marker) => {
+                    if text.starts_with(marker) {
+                        fence = None;
+                    }
+                    return line.clone();
+                }
+                None if text.starts_with("```") || text.starts_with("~~~") => {
+                    fence = Some(&text[..3]);
+                    return line.clone();
+                }
+                None => {}
+            }
+            let level = text.len() - text.trim_start_matches('#').len();
+            let rest = &text[level..];
+            if level == 0
+                || level > DEEPEST_LEVEL
+                || !(rest.is_empty() || rest.starts_with([' ', '\t']))
+            {
+                return line.clone();
+            }
+            format!(
+                "{}{}{rest}",
+                &line[..indent],
+                "#".repeat((level + offset).min(DEEPEST_LEVEL))
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_shift_headings() {
+    let lines = [
+        "## Setup",
+        "#hashtag",
+        "```sh",
+        "# a shell comment",
+        "```",
+        "##### Deep",
+        "# Raw",
+    ]
+    .map(String::from);
+    assert_eq!(
+        shift_headings(&lines, &BTreeSet::from([6]), 2),
+        [
+            "#### Setup",
+            "#hashtag",
+            "```sh",
+            "# a shell comment",
+            "```",
+            "###### Deep",
+            "# Raw"
+        ]
+    );
+}
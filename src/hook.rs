@@ -0,0 +1,76 @@
+use crate::git::hooks_folder;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+/// The line marking a pre-commit hook written by `hook install`, so it can be replaced by a
+/// later install while a hook of another tool is left alone.
+pub const HOOK_MARKER: &str = "# Written by get-comments hook install";
+
+/// Quotes an argument for the POSIX shell.
+fn shell_quote(argument: &str) -> String {
+    format!("'{}'", argument.replace('\'', "'\\''"))
+}
+
+/// Returns the pre-commit hook running `program` with `arguments`, which are expected to hold
+/// `check --staged`.
+pub fn hook_script(program: &str, arguments: &[String]) -> String {
+    let command: Vec<String> = std::iter::once(program)
+        .chain(arguments.iter().map(String::as_str))
+        .map(shell_quote)
+        .collect();
+    format!(
+        "#!/bin/sh\n{HOOK_MARKER}\n# Rejects a commit whose staged source files hold malformed comment blocks.\nexec {}\n",
+        command.join(" ")
+    )
+}
+
+//#EPIC Get Lines.ITEM Pre-commit hook [0]
+//#
+//## Pre-commit hook
+//#`hook install` writes a git pre-commit hook that runs get-comments with `check --staged`
+//#and the marker options of the command line, so a commit adding a malformed comment block is
+//#rejected before it lands instead of failing the next documentation build.
+/// Writes the pre-commit hook `script` into the hooks folder of the git repository holding
+/// `root`, and makes it executable.
+///
+/// An existing pre-commit hook is only replaced when it was written by `hook install`.
+///
+/// # Returns:
+/// - `Ok(PathBuf)` with the path of the hook
+/// - `Err(Error)` when `root` is not in a git work tree, or another pre-commit hook exists
+pub fn install_hook(root: &str, script: &str) -> Result<PathBuf, Error> {
+    let folder = hooks_folder(root)?;
+    let hook = folder.join("pre-commit");
+    if let Ok(existing) = read_to_string(&hook)
+        && !existing.contains(HOOK_MARKER)
+    {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!(
+                "{} is not a get-comments hook, remove it or call get-comments from it",
+                hook.display()
+            ),
+        ));
+    }
+    create_dir_all(&folder)?;
+    write(&hook, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(hook)
+}
+
+#[cfg(test)]
+#[test]
+fn test_hook_script() {
+    let arguments = ["check", "--staged", "-start", "//#", "-path", "EPIC's"].map(String::from);
+    assert_eq!(
+        hook_script("/usr/bin/get-comments", &arguments),
+        format!(
+            "#!/bin/sh\n{HOOK_MARKER}\n# Rejects a commit whose staged source files hold malformed comment blocks.\nexec '/usr/bin/get-comments' 'check' '--staged' '-start' '//#' '-path' 'EPIC'\\''s'\n"
+        )
+    );
+}
@@ -0,0 +1,180 @@
+use crate::outputs::write_if_changed;
+use pulldown_cmark::{Options, Parser, html::push_html};
+use std::collections::BTreeMap;
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+/// The style sheet shared by every page of the site, inlined so the site has no other files.
+const STYLE: &str = "body{margin:0;font-family:sans-serif;line-height:1.5}\
+nav{position:fixed;top:0;bottom:0;left:0;width:18em;overflow:auto;padding:1em;background:#f4f4f4;border-right:1px solid #ddd}\
+nav ul{list-style:none;padding-left:1em;margin:0}nav>ul{padding-left:0}\
+nav .current{font-weight:bold}main{margin-left:20em;padding:1em 2em;max-width:50em}\
+pre{background:#f4f4f4;padding:0.5em;overflow:auto}";
+
+/// The entry page of the site in the document root.
+const INDEX_PAGE: &str = "index.html";
+
+/// One level of the block hierarchy in the sidebar, such as an EPIC with its ITEMs.
+#[derive(Default)]
+struct Node {
+    /// Whether a page exists for this level, which is not so for a level without own blocks.
+    page: bool,
+    children: BTreeMap<String, Node>,
+}
+
+//#EPIC Get Lines.ITEM Export a static site [0]
+//#
+//## Export a static site
+//#With `-format html` every generated Markdown file is also rendered to an HTML page. Every page
+//#has a sidebar with the EPIC/ITEM/TASK tree of all pages, and `index.html` in the document root
+//#is the entry page.
+/// Writes a static HTML site into the document root: one page per block name and an
+/// `index.html` entry page, each with a sidebar holding the tree of all pages. A page that
+/// already holds its content is not written again.
+///
+/// # Parameters:
+/// - `pages`: The dot delimited block name of every page, without the document root, with its
+///   Markdown content
+/// - `doc_root`: The folder the site is written to
+///
+/// # Returns:
+/// - `Ok(Vec<PathBuf>)` with every page of the site, written or not
+pub fn write_site(pages: &BTreeMap<String, String>, doc_root: &str) -> Result<Vec<PathBuf>, Error> {
+    let tree = build_tree(pages.keys().map(String::as_str));
+    let mut files = Vec::new();
+    for (name, markdown) in pages {
+        let path = page_path(doc_root, name);
+        let title = name.rsplit('.').next().unwrap_or(name);
+        let root = "../".repeat(name.split('.').count() - 1);
+        let mut body = String::new();
+        push_html(&mut body, Parser::new_ext(markdown, Options::all()));
+        write_page(&path, title, &root, &tree, name, &body)?;
+        files.push(path);
+    }
+    let index = format!("<h1>Documentation</h1>\n<p>{} pages</p>\n", pages.len());
+    let path = PathBuf::from(doc_root).join(INDEX_PAGE);
+    write_page(&path, "Documentation", "", &tree, "", &index)?;
+    files.push(path);
+    Ok(files)
+}
+
+/// Returns the path of the page of a dot delimited block name, without the document root.
+fn page_path(doc_root: &str, name: &str) -> PathBuf {
+    PathBuf::from(doc_root).join(format!("{}.html", name.replace('.', "/")))
+}
+
+/// Returns the pages `write_site` writes for the given block names, without the document
+/// root: the page of every name and `index.html`.
+pub fn site_files<'n>(names: impl IntoIterator<Item = &'n str>, doc_root: &str) -> Vec<PathBuf> {
+    names
+        .into_iter()
+        .map(|name| page_path(doc_root, name))
+        .chain([PathBuf::from(doc_root).join(INDEX_PAGE)])
+        .collect()
+}
+
+/// Builds the tree of levels from the dot delimited names of the pages.
+fn build_tree<'n>(names: impl IntoIterator<Item = &'n str>) -> Node {
+    let mut tree = Node::default();
+    for name in names {
+        let mut node = &mut tree;
+        for level in name.split('.') {
+            node = node.children.entry(level.to_string()).or_default();
+        }
+        node.page = true;
+    }
+    tree
+}
+
+/// Writes one page with the sidebar and the given HTML body. `root` leads from the page back
+/// to the document root and `current` is the block name of the page.
+fn write_page(
+    path: &Path,
+    title: &str,
+    root: &str,
+    tree: &Node,
+    current: &str,
+    body: &str,
+) -> Result<(), Error> {
+    let mut writer = Vec::new();
+    writeln!(
+        writer,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<nav>\n<a href=\"{root}index.html\">Index</a>",
+        escape(title)
+    )?;
+    write_tree(&mut writer, tree, "", root, current)?;
+    writeln!(writer, "</nav>\n<main>\n{body}</main>\n</body>\n</html>")?;
+    write_if_changed(path, &writer)?;
+    Ok(())
+}
+
+/// Writes the children of a node as a nested list. `prefix` is the block name of the node.
+fn write_tree<W: Write>(
+    writer: &mut W,
+    node: &Node,
+    prefix: &str,
+    root: &str,
+    current: &str,
+) -> Result<(), Error> {
+    writeln!(writer, "<ul>")?;
+    for (level, child) in &node.children {
+        let name = match prefix.is_empty() {
+            true => level.clone(),
+            false => format!("{prefix}.{level}"),
+        };
+        let class = match name == current {
+            true => " class=\"current\"",
+            false => "",
+        };
+        match child.page {
+            true => write!(
+                writer,
+                "<li><a{class} href=\"{root}{}.html\">{}</a>",
+                href(&name.replace('.', "/")),
+                escape(level)
+            )?,
+            false => write!(writer, "<li>{}", escape(level))?,
+        }
+        if !child.children.is_empty() {
+            writeln!(writer)?;
+            write_tree(writer, child, &name, root, current)?;
+        }
+        writeln!(writer, "</li>")?;
+    }
+    writeln!(writer, "</ul>")
+}
+
+/// Escapes the characters that have a meaning in HTML text and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Percent encodes the characters of a relative path that may not appear in a link as is.
+fn href(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' | '"' | '#' | '%' | '?' | '<' | '>' => {
+                encoded.push_str(&format!("%{:02X}", u32::from(c)))
+            }
+            '&' => encoded.push_str("&amp;"),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+#[test]
+fn test_write_tree() {
+    let tree = build_tree(["EPIC A.ITEM B", "EPIC A.ITEM C & D"]);
+    let mut html = Vec::new();
+    write_tree(&mut html, &tree, "", "../", "EPIC A.ITEM B").unwrap();
+    assert_eq!(
+        String::from_utf8(html).unwrap(),
+        "<ul>\n<li>EPIC A\n<ul>\n<li><a class=\"current\" href=\"../EPIC%20A/ITEM%20B.html\">ITEM B</a></li>\n<li><a href=\"../EPIC%20A/ITEM%20C%20&amp;%20D.html\">ITEM C &amp; D</a></li>\n</ul>\n</li>\n</ul>\n"
+    );
+}
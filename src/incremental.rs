@@ -0,0 +1,114 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, read_to_string};
+use std::hash::Hasher;
+use std::io::{BufWriter, Error, Write};
+
+/// The file in the document root in which the source files of the previous run are kept.
+const CACHE_FILE: &str = ".get-comments-cache";
+
+/// What the previous run knew about one source file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceState {
+    /// Fingerprint of the content of the source file.
+    pub hash: String,
+    /// The generated files, as dot delimited block paths, the source file has blocks in.
+    pub outputs: BTreeSet<String>,
+}
+
+/// The state of every source file of a run, keyed by the path of the source file on disk.
+pub type SourceCache = BTreeMap<String, SourceState>;
+
+/// Fingerprints the content of a source file.
+pub fn content_hash(content: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(content.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Reads the source cache of the previous run from the document root.
+///
+/// A missing or unreadable file means there is no previous run, so every generated file is
+/// written.
+pub fn load_cache(doc_root: &str) -> SourceCache {
+    let mut cache = SourceCache::new();
+    let mut current: Option<&mut SourceState> = None;
+    let content = read_to_string(format!("{doc_root}/{CACHE_FILE}")).unwrap_or_default();
+    for line in content.lines() {
+        let fields: Vec<&str> = line.splitn(3, '\t').collect();
+        match fields[..] {
+            ["source", path, hash] => {
+                let state = cache.entry(path.to_string()).or_default();
+                state.hash = hash.to_string();
+                current = Some(state);
+            }
+            ["output", output] => {
+                if let Some(state) = current.as_mut() {
+                    state.outputs.insert(output.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    cache
+}
+
+/// Records the source cache of this run in the document root for the next run.
+pub fn save_cache(cache: &SourceCache, doc_root: &str) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(format!("{doc_root}/{CACHE_FILE}"))?);
+    for (path, state) in cache {
+        writeln!(writer, "source\t{path}\t{}", state.hash)?;
+        for output in &state.outputs {
+            writeln!(writer, "output\t{output}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Finds the generated files that must be written again.
+///
+/// A generated file is dirty when one of the source files it had blocks from in the previous
+/// run, or has blocks from in this run, was added, changed or removed. Generated files that
+/// the previous run wrote and this run no longer produces are dirty too, so they get removed.
+pub fn dirty_outputs(previous: &SourceCache, current: &SourceCache) -> BTreeSet<String> {
+    let mut dirty = BTreeSet::new();
+    for (path, state) in current {
+        match previous.get(path) {
+            Some(before) if before.hash == state.hash => {
+                dirty.extend(before.outputs.symmetric_difference(&state.outputs).cloned());
+            }
+            Some(before) => {
+                dirty.extend(before.outputs.iter().cloned());
+                dirty.extend(state.outputs.iter().cloned());
+            }
+            None => dirty.extend(state.outputs.iter().cloned()),
+        }
+    }
+    for (path, before) in previous {
+        if !current.contains_key(path) {
+            dirty.extend(before.outputs.iter().cloned());
+        }
+    }
+    dirty
+}
+
+#[cfg(test)]
+#[test]
+fn test_dirty_outputs() {
+    let state = |hash: &str, outputs: &[&str]| SourceState {
+        hash: hash.to_string(),
+        outputs: outputs.iter().map(|output| output.to_string()).collect(),
+    };
+    let previous = SourceCache::from([
+        ("a.rs".to_string(), state("1", &["docs.A"])),
+        ("b.rs".to_string(), state("2", &["docs.B", "docs.C"])),
+        ("gone.rs".to_string(), state("3", &["docs.D"])),
+    ]);
+    let current = SourceCache::from([
+        ("a.rs".to_string(), state("1", &["docs.A"])),
+        ("b.rs".to_string(), state("9", &["docs.B"])),
+        ("new.rs".to_string(), state("4", &["docs.E"])),
+    ]);
+    let dirty: Vec<String> = dirty_outputs(&previous, &current).into_iter().collect();
+    assert_eq!(dirty, ["docs.B", "docs.C", "docs.D", "docs.E"]);
+}
@@ -0,0 +1,314 @@
+use crate::block::{Sequences, relative_name};
+use crate::clock::format_date;
+use crate::collation::SortOrder;
+use crate::outputs::write_if_changed;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+/// A column of the per level table of blocks written to a folder's `index.md`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexColumn {
+    /// The block name, linked to the generated file.
+    Name,
+    /// The `@status:` directive of the block.
+    Status,
+    /// The `@owner:` directive of the block.
+    Owner,
+    /// The sequence numbers found for the block.
+    Sequences,
+    /// The date on which a source file contributing to the block last changed.
+    Changed,
+}
+
+impl IndexColumn {
+    /// All columns in their default order.
+    pub fn all() -> Vec<IndexColumn> {
+        vec![
+            IndexColumn::Name,
+            IndexColumn::Status,
+            IndexColumn::Owner,
+            IndexColumn::Sequences,
+            IndexColumn::Changed,
+        ]
+    }
+    /// Parses a column name as given on the command line: `name`, `status`, `owner`,
+    /// `sequences` or `changed`.
+    pub fn parse(name: &str) -> Result<IndexColumn, Error> {
+        match name.trim() {
+            "name" => Ok(IndexColumn::Name),
+            "status" => Ok(IndexColumn::Status),
+            "owner" => Ok(IndexColumn::Owner),
+            "sequences" => Ok(IndexColumn::Sequences),
+            "changed" => Ok(IndexColumn::Changed),
+            other => Err(Error::other(format!("Unknown index column [{other}]"))),
+        }
+    }
+    fn heading(&self) -> &'static str {
+        match self {
+            IndexColumn::Name => "Block",
+            IndexColumn::Status => "Status",
+            IndexColumn::Owner => "Owner",
+            IndexColumn::Sequences => "Sequences",
+            IndexColumn::Changed => "Last change",
+        }
+    }
+    fn cell(&self, file: &str, blocks: &Sequences, reproducible: bool) -> String {
+        let directive = |name: &str| {
+            blocks
+                .values()
+                .find_map(|block| block.metadata.get(name).cloned())
+                .unwrap_or_default()
+        };
+        match self {
+            IndexColumn::Name => format!("[{file}](<{file}.md>)"),
+            IndexColumn::Status => directive("status"),
+            IndexColumn::Owner => directive("owner"),
+            IndexColumn::Sequences => blocks
+                .keys()
+                .map(|sequence| sequence.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            IndexColumn::Changed if reproducible => String::new(),
+            IndexColumn::Changed => blocks
+                .values()
+                .filter_map(|block| block.modified)
+                .max()
+                .map(format_date)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+//#EPIC Get Lines.ITEM Write tables of blocks per folder [0]
+//#
+//## Write tables of blocks per folder
+//#Once the history is written every folder in the document root gets an `index.md` holding a
+//#table with one row per generated file in that folder, so the tree doubles as a status report.
+/// Writes a Markdown table of the generated files to an `index.md` in every output folder.
+///
+/// The history keys are the dot delimited file paths used by `write_history`. Every key is
+/// split into its folder and file name, and the files are grouped per folder. Only files
+/// that were actually written are listed, so blocks that failed validation do not appear.
+/// Files holding only blocks from machine-generated sources are listed after the others.
+/// An index file that already holds its table is not written again.
+///
+/// # Parameters:
+/// - `history`: The comment history, keyed by dot delimited file path and sequence number
+/// - `columns`: The columns to write, in order
+/// - `reproducible`: When `true` the machine specific `Changed` column is left empty
+/// - `order`: The order of the files within a table
+///
+/// # Returns:
+/// - `Ok(Vec<PathBuf>)` with every index file, written or not
+/// - `Err(Error)` when an index file could not be written
+pub fn write_block_tables(
+    history: &HashMap<String, Sequences>,
+    columns: &[IndexColumn],
+    reproducible: bool,
+    order: SortOrder,
+) -> Result<Vec<PathBuf>, Error> {
+    write_index_files(block_tables(history, columns, reproducible, order)?)
+}
+
+/// Renders the tables of `write_block_tables`, keyed by the path of their index file.
+fn block_tables(
+    history: &HashMap<String, Sequences>,
+    columns: &[IndexColumn],
+    reproducible: bool,
+    order: SortOrder,
+) -> Result<BTreeMap<PathBuf, Vec<u8>>, Error> {
+    let mut folders: BTreeMap<String, Vec<(&str, &Sequences)>> = BTreeMap::new();
+    for (key, blocks) in history {
+        let mut path: Vec<&str> = key.trim().split(".").collect();
+        if let Some(file) = path.pop() {
+            let folder = path.join("/");
+            if Path::new(&format!("{folder}/{file}.md")).exists() {
+                folders.entry(folder).or_default().push((file, blocks));
+            }
+        }
+    }
+
+    let mut tables = BTreeMap::new();
+    for (folder, mut files) in folders {
+        let generated = |blocks: &Sequences| blocks.values().all(|block| block.generated);
+        files.sort_by(|(left, left_blocks), (right, right_blocks)| {
+            generated(left_blocks)
+                .cmp(&generated(right_blocks))
+                .then_with(|| order.compare(left, right))
+        });
+        let mut writer = Vec::new();
+        let title = folder.rsplit('/').next().unwrap_or(folder.as_str());
+        writeln!(writer, "# {title}\n")?;
+        let headings: Vec<&str> = columns.iter().map(|column| column.heading()).collect();
+        writeln!(writer, "| {} |", headings.join(" | "))?;
+        writeln!(writer, "|{}", " --- |".repeat(columns.len()))?;
+        for (file, blocks) in files {
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|column| column.cell(file, blocks, reproducible).replace('|', "\\|"))
+                .collect();
+            writeln!(writer, "| {} |", cells.join(" | "))?;
+        }
+        tables.insert(PathBuf::from(format!("{folder}/index.md")), writer);
+    }
+    Ok(tables)
+}
+
+/// Writes the rendered index files, see `write_if_changed`.
+///
+/// # Returns:
+/// - `Ok(Vec<PathBuf>)` with every index file, written or not
+fn write_index_files(files: BTreeMap<PathBuf, Vec<u8>>) -> Result<Vec<PathBuf>, Error> {
+    for (path, content) in &files {
+        write_if_changed(path, content)?;
+    }
+    Ok(files.into_keys().collect())
+}
+
+/// Returns the `index.md` files `write_block_tables` and, with `listings`,
+/// `write_folder_indexes` may write for a history: the one of the folder of every block name
+/// and, for the listings, the ones of the folders above it up to the document root.
+pub fn index_files(
+    history: &HashMap<String, Sequences>,
+    doc_root: &str,
+    listings: bool,
+) -> BTreeSet<PathBuf> {
+    let mut files = BTreeSet::new();
+    for key in history.keys() {
+        let mut levels: Vec<&str> = relative_name(key, doc_root).split('.').collect();
+        levels.pop();
+        loop {
+            files.insert(Path::new(doc_root).join(levels.join("/")).join("index.md"));
+            if !listings || levels.pop().is_none() {
+                break;
+            }
+        }
+    }
+    files
+}
+
+/// The subfolders and generated files of one output folder, by name.
+#[derive(Default)]
+struct FolderEntries {
+    folders: BTreeSet<String>,
+    files: BTreeSet<String>,
+}
+
+//#EPIC Get Lines.ITEM Write a table of contents per folder [0]
+//#
+//## Write a table of contents per folder
+//#Once the history is written every folder in the document root, down from the root itself,
+//#gets an `index.md` listing its subfolders and generated files as Obsidian `[[links]]`, so the
+//#EPIC/ITEM/TASK tree can be browsed without an external tool.
+/// Writes an `index.md` listing the subfolders and generated Markdown files to every folder
+/// of the document root, including the document root itself.
+///
+/// Links are written relative to the document root, as `[[EPIC A/ITEM B|ITEM B]]` for a file
+/// and `[[EPIC A/index|EPIC A]]` for a subfolder. Only files that were actually written are
+/// listed. An index file that already holds its listing is not written again.
+///
+/// # Parameters:
+/// - `history`: The comment history, keyed by dot delimited file path and sequence number
+/// - `doc_root`: The document root the history keys start with
+/// - `tables`: The columns, and whether the run is reproducible, of the tables of
+///   `write_block_tables` the listing is written below; without them the index files start
+///   with a title
+/// - `order`: The order of the subfolders and of the files within a listing
+///
+/// # Returns:
+/// - `Ok(Vec<PathBuf>)` with every index file, written or not
+/// - `Err(Error)` when an index file could not be written
+pub fn write_folder_indexes(
+    history: &HashMap<String, Sequences>,
+    doc_root: &str,
+    tables: Option<(&[IndexColumn], bool)>,
+    order: SortOrder,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut folders: BTreeMap<String, FolderEntries> = BTreeMap::new();
+    for key in history.keys() {
+        let name = relative_name(key, doc_root);
+        let mut levels: Vec<&str> = name.split('.').collect();
+        let Some(file) = levels.pop() else {
+            continue;
+        };
+        if !Path::new(doc_root)
+            .join(format!("{}.md", name.replace('.', "/")))
+            .exists()
+        {
+            continue;
+        }
+        folders
+            .entry(levels.join("/"))
+            .or_default()
+            .files
+            .insert(file.to_string());
+        while let Some(folder) = levels.pop() {
+            folders
+                .entry(levels.join("/"))
+                .or_default()
+                .folders
+                .insert(folder.to_string());
+        }
+    }
+
+    let mut files = match tables {
+        Some((columns, reproducible)) => block_tables(history, columns, reproducible, order)?,
+        None => BTreeMap::new(),
+    };
+    for (folder, entries) in folders {
+        let prefix = match folder.is_empty() {
+            true => String::new(),
+            false => format!("{folder}/"),
+        };
+        let path = Path::new(doc_root).join(&folder).join("index.md");
+        let writer = files.entry(path).or_insert_with(|| {
+            let title = match folder.rsplit('/').next() {
+                Some(title) if !title.is_empty() => title.to_string(),
+                _ => Path::new(doc_root)
+                    .file_name()
+                    .map_or(doc_root.to_string(), |name| name.to_string_lossy().into()),
+            };
+            format!("# {title}\n").into_bytes()
+        });
+        writeln!(writer)?;
+        let sorted = |names: &BTreeSet<String>| {
+            let mut names: Vec<String> = names.iter().cloned().collect();
+            names.sort_by(|left, right| order.compare(left, right));
+            names
+        };
+        for name in sorted(&entries.folders) {
+            writeln!(writer, "- [[{prefix}{name}/index|{name}]]")?;
+        }
+        for name in sorted(&entries.files) {
+            writeln!(writer, "- [[{prefix}{name}|{name}]]")?;
+        }
+    }
+    write_index_files(files)
+}
+
+#[cfg(test)]
+#[test]
+fn test_folder_indexes() {
+    let doc_root = "target/test-folder-indexes";
+    let _ = std::fs::remove_dir_all(doc_root);
+    std::fs::create_dir_all(format!("{doc_root}/EPIC A")).unwrap();
+    std::fs::write(format!("{doc_root}/EPIC A/ITEM B.md"), "").unwrap();
+    std::fs::write(format!("{doc_root}/EPIC C.md"), "").unwrap();
+    let history: HashMap<String, Sequences> = [
+        format!("{doc_root}.EPIC A.ITEM B"),
+        format!("{doc_root}.EPIC C"),
+        format!("{doc_root}.EPIC D"),
+    ]
+    .into_iter()
+    .map(|key| (key, Sequences::new()))
+    .collect();
+    write_folder_indexes(&history, doc_root, None, SortOrder::default()).unwrap();
+    let root = std::fs::read_to_string(format!("{doc_root}/index.md")).unwrap();
+    assert_eq!(
+        root,
+        "# test-folder-indexes\n\n- [[EPIC A/index|EPIC A]]\n- [[EPIC C|EPIC C]]\n"
+    );
+    let epic = std::fs::read_to_string(format!("{doc_root}/EPIC A/index.md")).unwrap();
+    assert_eq!(epic, "# EPIC A\n\n- [[EPIC A/ITEM B|ITEM B]]\n");
+}
@@ -0,0 +1,24 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The flag `interrupt` sets, which stops every run that has no flag of its own, see
+/// `Comments::with_stop_flag`.
+pub(crate) static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the running extraction to stop, typically from a Ctrl-C handler.
+///
+/// The walker stage stops handing out new files. When the request comes in before the write
+/// phase, the document root is left as it was; once the write phase has started it is
+/// finished, so the document root is never left half written. The request is cleared when
+/// the next run begins, so a later run in the same process, such as the next one of watch
+/// mode, runs to the end.
+///
+/// # Returns:
+/// - `true` when the extraction had already been asked to stop
+pub fn interrupt() -> bool {
+    INTERRUPTED.swap(true, Ordering::SeqCst)
+}
+
+/// Returns whether the extraction was asked to stop.
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
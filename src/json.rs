@@ -0,0 +1,120 @@
+use crate::block::{Block, Sequences, relative_name};
+use crate::sequence::Sequence;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{File, create_dir_all, read_to_string};
+use std::io::{BufWriter, Error, Write};
+use std::path::Path;
+
+/// The file in the document root holding every block in the JSON output format.
+pub(crate) const COMMENTS_FILE: &str = "comments.json";
+
+/// Writes every block of the history to `comments.json` in the document root, so other tools
+/// can diff, lint and index the extracted documentation.
+pub fn write_comments(history: &HashMap<String, Sequences>, doc_root: &str) -> Result<(), Error> {
+    create_dir_all(doc_root)?;
+    write_blocks(history, doc_root, &Path::new(doc_root).join(COMMENTS_FILE))
+}
+
+/// One block in a JSON file, with the name and sequence it is kept under in the history.
+#[derive(Serialize, Deserialize)]
+struct Entry<'b> {
+    #[serde(borrow)]
+    name: Cow<'b, str>,
+    sequence: Sequence,
+    #[serde(flatten)]
+    block: Cow<'b, Block>,
+}
+
+/// Writes every block of the history to a JSON file.
+///
+/// The document is an object with a `blocks` array, sorted by block name and sequence. Every
+/// block holds its `name`, without the document root, its `sequence` and the fields of the
+/// serialized `Block`. `read_blocks` reads the file back.
+pub fn write_blocks(
+    history: &HashMap<String, Sequences>,
+    doc_root: &str,
+    file: &Path,
+) -> Result<(), Error> {
+    write_blocks_to(history, doc_root, BufWriter::new(File::create(file)?))
+}
+
+/// Writes every block of the history to `writer` as the document of `write_blocks`.
+pub fn write_blocks_to<W: Write>(
+    history: &HashMap<String, Sequences>,
+    doc_root: &str,
+    mut writer: W,
+) -> Result<(), Error> {
+    let mut names: Vec<&String> = history.keys().collect();
+    names.sort();
+    writeln!(writer, "{{\"blocks\": [")?;
+    let mut separator = "";
+    for name in names {
+        for (sequence, block) in &history[name] {
+            let entry = Entry {
+                name: Cow::Borrowed(relative_name(name, doc_root)),
+                sequence: *sequence,
+                block: Cow::Borrowed(block),
+            };
+            write!(writer, "{separator}  ")?;
+            serde_json::to_writer(&mut writer, &entry)?;
+            separator = ",\n";
+        }
+    }
+    writeln!(writer, "\n]}}")?;
+    writer.flush()
+}
+
+/// The document written by `write_blocks`.
+#[derive(Deserialize)]
+struct Document<'b> {
+    #[serde(borrow)]
+    blocks: Vec<Entry<'b>>,
+}
+
+/// Reads the blocks written by `write_blocks`.
+///
+/// # Returns:
+/// - The blocks keyed by their dot delimited name, without document root
+/// - `Err(Error)` when the file cannot be read or is not a blocks document
+pub fn read_blocks(file: &Path) -> Result<HashMap<String, Sequences>, Error> {
+    let content = read_to_string(file)?;
+    let document: Document = serde_json::from_str(&content)
+        .map_err(|error| Error::other(format!("{error} in {}", file.display())))?;
+    let mut history: HashMap<String, Sequences> = HashMap::new();
+    for entry in document.blocks {
+        history
+            .entry(entry.name.into_owned())
+            .or_default()
+            .insert(entry.sequence, entry.block.into_owned());
+    }
+    Ok(history)
+}
+
+#[cfg(test)]
+#[test]
+fn test_write_and_read_blocks() {
+    let mut block = Block::new(
+        "a.rs".into(),
+        3,
+        vec!["@status: beta".to_string(), "say \"hi\"".to_string()],
+        &Default::default(),
+    );
+    block.end_line = 4;
+    let history = HashMap::from([(
+        "docs.EPIC A.ITEM B".to_string(),
+        Sequences::from([(Sequence::from(2), block)]),
+    )]);
+    let file =
+        std::env::temp_dir().join(format!("get-comments-blocks-{}.json", std::process::id()));
+    write_blocks(&history, "docs", &file).unwrap();
+    let read = read_blocks(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+    let block = &read["EPIC A.ITEM B"][&Sequence::from(2)];
+    assert_eq!(&*block.source_file, "a.rs");
+    assert_eq!((block.start_line, block.end_line), (3, 4));
+    assert_eq!(&*block.lines, ["say \"hi\"".to_string()]);
+    assert_eq!(block.source_text(), "@status: beta\nsay \"hi\"");
+    assert_eq!(block.metadata["status"], "beta");
+}
@@ -0,0 +1,73 @@
+//! Extracts line blocks from source files into a tree of Markdown files.
+//!
+//! A _line block_ is any number of consecutive lines that start with a marker string such as
+//! `//#`. The first line of a block names the Markdown file the block is written to and the
+//! sequence number of the block within that file. See [`Comments`] for the builder API.
+#[cfg(feature = "async")]
+mod async_io;
+mod background;
+mod backlinks;
+pub mod block;
+pub mod clock;
+pub mod collation;
+pub mod confluence;
+mod contributors;
+mod coverage;
+pub mod density;
+pub mod diagnostics;
+pub mod encoding;
+pub mod events;
+pub mod exclude;
+pub mod explain;
+pub mod failure;
+pub mod filter;
+pub mod format;
+mod front_matter;
+mod git;
+mod glob;
+pub mod glossary;
+pub mod header;
+mod headings;
+pub mod hook;
+mod html;
+mod incremental;
+pub mod index;
+mod interrupt;
+mod json;
+pub mod links;
+pub mod logging;
+pub mod markers;
+mod mdbook;
+mod mirror;
+pub mod obsidian;
+mod outputs;
+mod parse;
+mod pipeline;
+mod progress;
+pub mod project;
+mod provenance;
+pub mod query;
+pub mod remote;
+pub mod requirements;
+mod run;
+pub mod schema;
+pub mod separator;
+pub mod sequence;
+mod shortcode;
+mod similarity;
+mod snippet;
+pub mod source_link;
+mod staging;
+mod stale;
+mod stats;
+mod structure;
+pub mod sync_back;
+mod tags;
+pub mod template;
+mod timings;
+pub mod trace;
+pub mod upgrade;
+pub mod watch;
+
+pub use interrupt::{interrupt, is_interrupted};
+pub use parse::Comments;
@@ -0,0 +1,174 @@
+use crate::block::Sequences;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Settings of the external URL check.
+#[derive(Clone, Debug)]
+pub struct UrlCheck {
+    /// Number of URLs that are probed at the same time.
+    pub concurrency: usize,
+    /// When not empty only URLs containing one of these strings are probed.
+    pub allow: Vec<String>,
+    /// URLs containing one of these strings are never probed.
+    pub deny: Vec<String>,
+    /// The command the requests are sent with.
+    pub curl: String,
+}
+
+impl Default for UrlCheck {
+    fn default() -> Self {
+        UrlCheck {
+            concurrency: 8,
+            allow: vec![],
+            deny: vec![],
+            curl: "curl".to_string(),
+        }
+    }
+}
+
+/// An http(s) URL that does not resolve, with every place it was found.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeadLink {
+    pub url: String,
+    /// Why the URL is considered dead, e.g. `HTTP 404` or `no response`.
+    pub reason: String,
+    /// Source file and line of every block in which the URL appears.
+    pub locations: Vec<(Arc<str>, usize)>,
+}
+
+impl UrlCheck {
+    //#EPIC Get Lines.ITEM Check external links [0]
+    //#
+    //## Check external links
+    //#Collect every http(s) URL in the block text, filter them through the allow and deny lists and
+    //#probe each distinct URL once with a HEAD request. Probes run on a small pool of threads.
+    /// Probes every http(s) URL found in the block text and returns the ones that do not resolve.
+    ///
+    /// # Process Flow:
+    /// 1. **Collection**: Finds URLs in the body lines of every block sequence
+    /// 2. **Filtering**: Drops URLs on the deny list, and URLs not on a non-empty allow list
+    /// 3. **Probing**: Sends a HEAD request per distinct URL through `curl`, following redirects,
+    ///    on at most `concurrency` threads. Servers that refuse HEAD are retried with a GET.
+    /// 4. **Reporting**: Returns every URL that gave no response or an HTTP status of 400 or more
+    ///
+    /// # Returns:
+    /// - `Err(String)` with the reason when `curl` could not be run, so no URL was probed
+    ///
+    /// # Note:
+    /// `curl` must be on the `PATH`. It is started once before the probes, so a missing
+    /// `curl` is reported once instead of as a dead link for every URL.
+    pub fn find_dead_links(
+        &self,
+        history: &HashMap<String, Sequences>,
+    ) -> Result<Vec<DeadLink>, String> {
+        let url_pattern = Regex::new(r#"https?://[^\s<>()\[\]"'`]+"#).unwrap();
+        let mut urls: BTreeMap<String, Vec<(Arc<str>, usize)>> = BTreeMap::new();
+        for blocks in history.values() {
+            for block in blocks.values() {
+                for line in block.lines.iter() {
+                    for found in url_pattern.find_iter(line) {
+                        let url = found
+                            .as_str()
+                            .trim_end_matches(['.', ',', ';', ':', '!', '?']);
+                        if self.is_checked(url) {
+                            urls.entry(url.to_string())
+                                .or_default()
+                                .push((block.source_file.clone(), block.start_line));
+                        }
+                    }
+                }
+            }
+        }
+
+        if urls.is_empty() {
+            return Ok(Vec::new());
+        }
+        Command::new(&self.curl)
+            .arg("--version")
+            .output()
+            .map_err(|error| format!("could not run {}: {error}", self.curl))?;
+        let queue = Mutex::new(urls.keys().cloned().collect::<Vec<_>>().into_iter());
+        let failures: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.max(1) {
+                scope.spawn(|| {
+                    loop {
+                        let next = queue.lock().unwrap().next();
+                        let Some(url) = next else { break };
+                        if let Err(reason) = probe(&self.curl, &url) {
+                            failures.lock().unwrap().insert(url, reason);
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(failures
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|(url, reason)| DeadLink {
+                locations: urls.remove(&url).unwrap_or_default(),
+                url,
+                reason,
+            })
+            .collect())
+    }
+    fn is_checked(&self, url: &str) -> bool {
+        let listed = |patterns: &Vec<String>| patterns.iter().any(|p| url.contains(p.as_str()));
+        !listed(&self.deny) && (self.allow.is_empty() || listed(&self.allow))
+    }
+}
+
+/// Requests the URL with the `curl` command and returns the reason when it does not resolve.
+fn probe(curl: &str, url: &str) -> Result<(), String> {
+    let status = |method: &[&str]| -> Result<u16, String> {
+        let output = Command::new(curl)
+            .args(["-s", "-o", "/dev/null", "-L", "--max-time", "10"])
+            .args(method)
+            .args(["-w", "%{http_code}", url])
+            .output()
+            .map_err(|error| format!("could not run curl: {error}"))?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<u16>()
+            .unwrap_or(0))
+    };
+    let mut code = status(&["-I"])?;
+    if code == 405 || code == 501 {
+        code = status(&["-r", "0-0"])?;
+    }
+    match code {
+        0 => Err("no response".to_string()),
+        400.. => Err(format!("HTTP {code}")),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_link_check_without_curl() {
+    use crate::block::Block;
+    use std::collections::BTreeSet;
+    let lines = vec!["See https://example.com/a and https://example.com/b.".to_string()];
+    let block = Block::new("a.rs".into(), 1, lines, &BTreeSet::new());
+    let history = HashMap::from([(
+        "EPIC A".to_string(),
+        Sequences::from([(Default::default(), block)]),
+    )]);
+    let check = UrlCheck {
+        curl: "get-comments-test-no-curl".to_string(),
+        ..Default::default()
+    };
+    let reason = check.find_dead_links(&history).unwrap_err();
+    assert!(reason.starts_with("could not run get-comments-test-no-curl"));
+    let denied = UrlCheck {
+        deny: vec!["example.com".to_string()],
+        ..check
+    };
+    assert!(denied.find_dead_links(&history).unwrap().is_empty());
+}
@@ -0,0 +1,246 @@
+use crate::events::{Event, EventSubscriber};
+use serde_json::{Map, Value, json};
+use std::fs::OpenOptions;
+use std::io::{Error, LineWriter, Write, stdout};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How much a log holds, from least to most.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    /// Errors, warnings and rejected blocks. The level without `-v`.
+    #[default]
+    Warn,
+    /// Also every parsed source file with its parse time, every entry the walk skipped and
+    /// every generated file that was written. The level of `-v`.
+    Info,
+    /// Also every source file as its parse starts, every block found and every valid block
+    /// name. The level of `-vv`.
+    Debug,
+}
+
+impl LogLevel {
+    /// Returns the name of the level as written to the log.
+    fn name(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
+/// The layout of the lines of a log.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LogFormat {
+    /// One readable line per event, such as `[info] file_written path=docs/EPIC A.md`.
+    #[default]
+    Text,
+    /// One JSON object per line with the `level`, the `event`, the `elapsed_ms` since the log
+    /// was opened and the fields of the event.
+    Json,
+}
+
+impl LogFormat {
+    /// Parses the name of a log format.
+    ///
+    /// # Returns:
+    /// - `Err(Error)` when the name is not `text` or `json`
+    pub fn parse(name: &str) -> Result<LogFormat, Error> {
+        match name.trim().to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(Error::other(format!(
+                "Unknown log format [{name}], expected text or json"
+            ))),
+        }
+    }
+}
+
+//#EPIC Get Lines.ITEM Log a run [0]
+//#
+//## Log a run
+//#`-v` logs every parsed source file with its parse time and every generated file that was
+//#written, `-vv` also every block that was found or validated. The log goes to the console, or
+//#with `-log file` to that file, which then also holds the errors and warnings of the run. With
+//#`-log-format json` every line is a JSON object, for tools that read the log.
+/// Writes the events of a run at or below a level to the console or a log file.
+pub struct Logger {
+    level: LogLevel,
+    format: LogFormat,
+    writer: Mutex<Box<dyn Write + Send>>,
+    /// Errors and warnings are printed by the run itself, so they are only logged to a file.
+    to_console: bool,
+    started: Instant,
+}
+
+impl Logger {
+    /// Creates a logger that writes to the console.
+    pub fn console(level: LogLevel, format: LogFormat) -> Logger {
+        Logger::new(level, format, Box::new(stdout()), true)
+    }
+    /// Creates a logger that appends to a log file, so the runs of a watch end up in one log.
+    pub fn file(path: &str, level: LogLevel, format: LogFormat) -> Result<Logger, Error> {
+        let file = LineWriter::new(OpenOptions::new().append(true).create(true).open(path)?);
+        Ok(Logger::new(level, format, Box::new(file), false))
+    }
+    fn new(
+        level: LogLevel,
+        format: LogFormat,
+        writer: Box<dyn Write + Send>,
+        to_console: bool,
+    ) -> Logger {
+        Logger {
+            level,
+            format,
+            writer: Mutex::new(writer),
+            to_console,
+            started: Instant::now(),
+        }
+    }
+    /// Writes one event when its level is logged.
+    fn log(&self, level: LogLevel, event: &str, fields: Map<String, Value>) {
+        if level > self.level {
+            return;
+        }
+        let line = match self.format {
+            LogFormat::Text => {
+                let mut line = format!("[{}] {event}", level.name());
+                for (name, value) in &fields {
+                    match value {
+                        Value::String(text) => line.push_str(&format!(" {name}={text}")),
+                        value => line.push_str(&format!(" {name}={value}")),
+                    }
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut object = Map::new();
+                object.insert("level".to_string(), json!(level.name()));
+                object.insert("event".to_string(), json!(event));
+                object.insert(
+                    "elapsed_ms".to_string(),
+                    json!(self.started.elapsed().as_millis() as u64),
+                );
+                object.extend(fields);
+                Value::Object(object).to_string()
+            }
+        };
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writeln!(writer, "{line}");
+        }
+    }
+}
+
+impl EventSubscriber for Logger {
+    fn on_event(&self, event: &Event) {
+        let fields = |value: Value| match value {
+            Value::Object(fields) => fields,
+            _ => Map::new(),
+        };
+        match event {
+            Event::FileStarted { path } => self.log(
+                LogLevel::Debug,
+                "file_started",
+                fields(json!({ "path": path })),
+            ),
+            Event::FileParsed { path, duration } => self.log(
+                LogLevel::Info,
+                "file_parsed",
+                fields(json!({ "path": path, "ms": duration.as_secs_f64() * 1000.0 })),
+            ),
+            Event::BlockFound {
+                name,
+                sequence,
+                block,
+            } => self.log(
+                LogLevel::Debug,
+                "block_found",
+                fields(json!({
+                    "name": name,
+                    "sequence": sequence,
+                    "source_file": &*block.source_file,
+                    "line": block.start_line,
+                })),
+            ),
+            Event::BlockFailed {
+                path,
+                line,
+                header,
+                message,
+            } => self.log(
+                LogLevel::Error,
+                "block_failed",
+                fields(json!({
+                    "path": path,
+                    "line": line,
+                    "header": header,
+                    "message": message,
+                })),
+            ),
+            Event::BlockValidated { name, sequences } => self.log(
+                LogLevel::Debug,
+                "block_validated",
+                fields(json!({ "name": name, "sequences": sequences.len() })),
+            ),
+            Event::BlockRejected { name, reason } => self.log(
+                LogLevel::Warn,
+                "block_rejected",
+                fields(json!({ "name": name, "reason": reason })),
+            ),
+            Event::FileWritten { path } => self.log(
+                LogLevel::Info,
+                "file_written",
+                fields(json!({ "path": path.to_string_lossy() })),
+            ),
+            Event::EntrySkipped { path, reason } => self.log(
+                LogLevel::Info,
+                "entry_skipped",
+                fields(json!({ "path": path.to_string_lossy(), "reason": reason })),
+            ),
+            Event::Warning { message } if !self.to_console => self.log(
+                LogLevel::Warn,
+                "warning",
+                fields(json!({ "message": message })),
+            ),
+            Event::Error { kind, message } if !self.to_console => self.log(
+                LogLevel::Error,
+                "error",
+                fields(json!({ "kind": kind, "message": message })),
+            ),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_logger() {
+    use std::time::Duration;
+    let path = std::env::temp_dir().join(format!("get-comments-log-{}.jsonl", std::process::id()));
+    let logger = Logger::file(&path.to_string_lossy(), LogLevel::Info, LogFormat::Json).unwrap();
+    logger.on_event(&Event::FileParsed {
+        path: "a.rs",
+        duration: Duration::from_millis(2),
+    });
+    logger.on_event(&Event::FileWritten {
+        path: std::path::Path::new("docs/EPIC A.md"),
+    });
+    logger.on_event(&Event::Warning { message: "stale" });
+    drop(logger);
+    let log = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["event"], "file_parsed");
+    assert_eq!(lines[0]["path"], "a.rs");
+    assert_eq!(lines[1]["event"], "file_written");
+    assert_eq!(lines[1]["path"], "docs/EPIC A.md");
+    assert_eq!(lines[2]["level"], "warn");
+    assert!(LogFormat::parse("xml").is_err());
+}
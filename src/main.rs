@@ -1,5 +1,49 @@
-mod parse;
-use cli_command::parse_command_line;
+use cli_command::{Command, parse_command_line};
+use get_comments::failure::{FailureKind, RunError};
+use get_comments::project::ProjectConfig;
+use get_comments::{
+    Comments, collation, confluence, density, encoding, explain, filter, format, glossary, header,
+    hook, index, links, logging, markers, remote, requirements, schema, separator, source_link,
+    sync_back, template, trace, upgrade, watch,
+};
+use std::io::Error;
+
+/// The exit code when the command line has no valid command.
+const USAGE_ERROR: i32 = 1;
+
+/// The options of a run: those on the command line, and for the others the values in the
+/// project configuration file.
+struct Arguments {
+    name: String,
+    /// The word following the command name, such as `confluence` in `publish confluence`.
+    action: Option<String>,
+    command: Command,
+    project: ProjectConfig,
+}
+
+impl Arguments {
+    fn contains_argument(&self, name: &str) -> bool {
+        self.command.contains_argument(name) || self.project.contains(name)
+    }
+    fn get_argument(&self, name: &str) -> Option<&str> {
+        match self.command.contains_argument(name) {
+            true => self.command.get_argument(name),
+            false => self.project.value(name),
+        }
+    }
+    fn get_argument_all(&self, name: &str) -> Option<&[String]> {
+        match self.command.contains_argument(name) {
+            true => self.command.get_argument_all(name).map(|values| &values[..]),
+            false => self.project.values(name),
+        }
+    }
+    fn get_argument_usize(&self, name: &str) -> Option<usize> {
+        self.get_argument(name).and_then(|value| value.parse().ok())
+    }
+    fn get_argument_f64(&self, name: &str) -> Option<f64> {
+        self.get_argument(name).and_then(|value| value.parse().ok())
+    }
+}
 
 //#EPIC Get Lines [0]
 //## Get lines from text files and put the line blocks into Markdown files.
@@ -20,32 +64,761 @@ use cli_command::parse_command_line;
 //#    Once all of the files is processed then write out the comment one by one to the Markdown files.
 //# 4. [[docs/EPIC Get Lines/ITEM Write the comment lines to the file path and name.md]]
 //#    Take the current comment block and write it out to the Markdown file.
-fn main() {
-    if let Ok(cli) = parse_command_line() {
-        let some_dir = cli.get_argument("dir");
-        let some_work = cli.get_argument("work");
-        let some_start = cli.get_argument("start");
-        let some_path = cli.get_argument("path");
-        let some_extension = cli.get_argument("ext");
-
-        if some_dir.is_some()
-            && some_work.is_some()
-            && some_start.is_some()
-            && some_path.is_some()
-            && some_extension.is_some()
-        {
-            let mut comment_parser = parse::Comments::default();
-            comment_parser.comment_in_files(
-                some_dir.unwrap(),
-                some_work.unwrap(),
-                some_start.unwrap(),
-                some_path.unwrap(),
-                some_extension.unwrap(),
+/// Returns the marker position of `-marker-position`, or after code with `--inline`, with the
+/// string literal handling of `-inline-strings`.
+fn marker_position(cli: &Arguments) -> Result<markers::MarkerPosition, Error> {
+    let position = match cli.contains_argument("inline") {
+        true => markers::MarkerPosition::parse("after-code")?,
+        false => cli
+            .get_argument("marker-position")
+            .map(markers::MarkerPosition::parse)
+            .transpose()?
+            .unwrap_or_default(),
+    };
+    match cli.get_argument("inline-strings") {
+        Some(name) => position.with_string_literals(name),
+        None => Ok(position),
+    }
+}
+
+/// Builds the comment parser from the optional command line arguments.
+///
+/// # Returns:
+/// - `Err(Error)` when an optional argument holds a value that cannot be parsed
+fn configure<'a>(cli: &Arguments) -> Result<Comments<'a>, Error> {
+    let list = |name: &str| {
+        cli.get_argument_all(name)
+            .map(|values| values.to_vec())
+            .unwrap_or_default()
+    };
+    let filters = list("filter")
+        .iter()
+        .map(|name| filter::Filter::parse(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let output_formats = list("format")
+        .iter()
+        .map(|name| format::OutputFormat::parse(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let index_columns = match cli.get_argument_all("index-tables") {
+        None => None,
+        Some([]) => Some(index::IndexColumn::all()),
+        Some(names) => Some(
+            names
+                .iter()
+                .map(|name| index::IndexColumn::parse(name))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+    };
+    let glossary = match cli.get_argument_all("glossary") {
+        None => None,
+        Some(files) => match files.first() {
+            None => Some(glossary::Glossary::default()),
+            Some(file) => Some(glossary::Glossary::load(file)?),
+        },
+    };
+    let field_schema = cli
+        .get_argument("fields")
+        .map(schema::FieldSchema::load)
+        .transpose()?;
+    let density_format = cli
+        .contains_argument("density")
+        .then(|| density::ReportFormat::parse(cli.get_argument("density").unwrap_or("md")))
+        .transpose()?;
+    let diagnostics_format = cli
+        .contains_argument("diagnostics")
+        .then(|| density::ReportFormat::parse(cli.get_argument("diagnostics").unwrap_or("md")))
+        .transpose()?;
+    let trace_format = cli
+        .contains_argument("trace-matrix")
+        .then(|| trace::MatrixFormat::parse(cli.get_argument("trace-matrix").unwrap_or("md")))
+        .transpose()?;
+    let header_grammar = match cli.get_argument("header") {
+        Some(spec) => header::HeaderGrammar::parse(spec)?,
+        None => header::HeaderGrammar::default(),
+    };
+    let log_level = match (cli.contains_argument("vv"), cli.contains_argument("v")) {
+        (true, _) => logging::LogLevel::Debug,
+        (false, true) => logging::LogLevel::Info,
+        (false, false) => logging::LogLevel::Warn,
+    };
+    let log_format = cli
+        .get_argument("log-format")
+        .map(logging::LogFormat::parse)
+        .transpose()?
+        .unwrap_or_default();
+    let logger = match cli.get_argument("log") {
+        Some(file) => Some(logging::Logger::file(file, log_level, log_format)?),
+        None => (log_level > logging::LogLevel::Warn && !cli.contains_argument("quiet"))
+            .then(|| logging::Logger::console(log_level, log_format)),
+    };
+
+    let comments = Comments::new()
+        .with_reproducible(cli.contains_argument("reproducible"))
+        .with_filters(filters)
+        .with_output_formats(output_formats)
+        .with_markdown_flavor(format::MarkdownFlavor::parse(
+            cli.get_argument("flavor").unwrap_or("commonmark"),
+        )?)
+        .with_separators(separator::Separators::parse(&list("separator"))?)
+        .with_sequence_headings(cli.contains_argument("sequence-headings"))
+        .with_heading_offset(cli.get_argument_usize("heading-offset").unwrap_or(0))
+        .with_max_file_size(cli.get_argument_usize("max-file-size"))
+        .with_block_template(
+            cli.get_argument("template")
+                .map(template::BlockTemplate::load)
+                .transpose()?,
+        )
+        .with_link_style(source_link::LinkStyle::parse(
+            cli.get_argument("link-style").unwrap_or("file"),
+            cli.get_argument("repo-url"),
+            cli.get_argument("branch"),
+        )?)
+        .with_index_tables(index_columns)
+        .with_folder_index(cli.contains_argument("folder-index"))
+        .with_structure(cli.contains_argument("structure"))
+        .with_obsidian(cli.contains_argument("obsidian"))
+        .with_clean(cli.contains_argument("clean"))
+        .with_atomic(cli.contains_argument("atomic"))
+        .with_sort_order(collation::SortOrder::parse(&list("sort"))?)
+        .with_cross_references(cli.contains_argument("backlinks"))
+        .with_tag_pages(cli.contains_argument("tag-pages"))
+        .with_coverage(
+            cli.contains_argument("coverage"),
+            cli.get_argument_f64("coverage-min"),
+        )
+        .with_similarity_check(
+            cli.contains_argument("similarity")
+                .then(|| cli.get_argument_f64("similarity").unwrap_or(0.9)),
+        )
+        .with_url_check(
+            cli.contains_argument("check-urls")
+                .then(|| links::UrlCheck {
+                    concurrency: cli.get_argument_usize("url-concurrency").unwrap_or(8),
+                    allow: list("url-allow"),
+                    deny: list("url-deny"),
+                    ..Default::default()
+                }),
+        )
+        .with_glossary(glossary)
+        .with_git_metadata(cli.contains_argument("git-metadata"))
+        .with_contributors(cli.contains_argument("contributors"))
+        .with_stale_check(
+            cli.contains_argument("stale-months")
+                .then(|| cli.get_argument_usize("stale-months").unwrap_or(12) as u32),
+        )
+        .with_density_report(density_format)
+        .with_trace_matrix(trace_format)
+        .with_diagnostics_report(diagnostics_format)
+        .with_requirements(
+            cli.get_argument("requirements")
+                .map(requirements::Requirements::load)
+                .transpose()?,
+        )
+        .with_strict_requirements(cli.contains_argument("strict"))
+        .with_header_grammar(header_grammar)
+        .with_field_schema(field_schema)
+        .with_auto_sequence(cli.contains_argument("auto-sequence"))
+        .with_mirrors(list("mirror"))
+        .with_hard_links(cli.contains_argument("hard-link"))
+        .with_path_inference(cli.contains_argument("infer-path"))
+        .with_front_matter(cli.contains_argument("front-matter"))
+        .with_manifest(
+            cli.contains_argument("manifest")
+                || cli
+                    .get_argument("work")
+                    .and_then(remote::RemoteTarget::parse)
+                    .is_some(),
+        )
+        .with_run_footer(cli.contains_argument("run-footer"))
+        .with_since_run(cli.get_argument("since-run").map(str::to_string))
+        .with_end_marker(cli.get_argument("end").map(str::trim))
+        .with_block_end(cli.get_argument("block-end"))
+        .with_incremental(cli.contains_argument("incremental") || cli.name == "watch")
+        .with_since_revision(cli.get_argument("since").map(str::to_string))
+        .with_background(cli.contains_argument("background"))
+        .with_timings(
+            cli.contains_argument("timings")
+                .then(|| cli.get_argument_usize("timings").unwrap_or(5)),
+        )
+        .with_statistics(
+            cli.contains_argument("stats"),
+            cli.get_argument("stats").map(str::to_string),
+        )
+        .with_progress(cli.contains_argument("progress"))
+        .with_quiet(cli.contains_argument("quiet"))
+        .with_marker_position(marker_position(cli)?)
+        .with_encodings(encoding::SourceEncodings::parse(&list("encoding"))?)
+        .with_exclusions(list("exclude"))
+        .with_name_selection(list("only"), list("skip"))
+        .with_generated_sources(list("generated"))
+        .with_gitignore(cli.contains_argument("gitignore"))
+        .with_walk_limits(
+            !cli.contains_argument("no-follow-links"),
+            cli.get_argument_usize("max-depth"),
+        )
+        .with_marker_config(
+            cli.get_argument("config")
+                .map(markers::MarkerConfig::load)
+                .transpose()?,
+        );
+    Ok(match logger {
+        Some(logger) => comments.with_subscriber(logger),
+        None => comments,
+    })
+}
+
+/// Prints an error that stops a run before it started and returns it as the error of the
+/// run. The error goes to standard error, so it never ends up in a document written to
+/// standard output.
+fn failed(kind: FailureKind, context: &str, error: Error) -> RunError {
+    eprintln!("{context}{error}");
+    let mut errors = RunError::default();
+    errors.push_kind(kind, format!("{context}{error}"));
+    errors
+}
+
+/// Runs one extraction with the parser built from the command line. When the document root
+/// is a URL the run writes into a staging folder, which is then published.
+fn extract(
+    cli: &Arguments,
+    dir: &str,
+    work: &str,
+    start: &str,
+    path: &str,
+    extensions: &[&str],
+) -> Result<(), RunError> {
+    let Some(target) = remote::RemoteTarget::parse(work) else {
+        return extract_into(cli, dir, work, start, path, extensions);
+    };
+    match target.stage() {
+        Ok(staging) => {
+            let mut result = extract_into(cli, dir, &staging, start, path, extensions);
+            match target.publish(&staging) {
+                Ok((uploaded, deleted)) if !cli.contains_argument("quiet") => {
+                    println!("Published to {work}: {uploaded} files uploaded, {deleted} deleted")
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    let publish_error = failed(FailureKind::Io, "publish ", error);
+                    let mut errors = result.err().unwrap_or_default();
+                    errors.failures.extend(publish_error.failures);
+                    result = Err(errors);
+                }
+            }
+            let _ = std::fs::remove_dir_all(staging);
+            result
+        }
+        Err(error) => Err(failed(FailureKind::Io, "stage ", error)),
+    }
+}
+
+/// Runs one extraction into a local document root.
+fn extract_into(
+    cli: &Arguments,
+    dir: &str,
+    work: &str,
+    start: &str,
+    path: &str,
+    extensions: &[&str],
+) -> Result<(), RunError> {
+    match configure(cli) {
+        #[cfg(feature = "async")]
+        Ok(mut comment_parser) if cli.contains_argument("async") => {
+            match tokio::runtime::Runtime::new() {
+                Ok(runtime) => runtime.block_on(
+                    comment_parser.comment_in_files_async(dir, work, start, path, extensions),
+                ),
+                Err(error) => Err(failed(FailureKind::Io, "", error)),
+            }
+        }
+        Ok(mut comment_parser) => {
+            comment_parser.comment_in_files(dir, work, start, path, extensions)
+        }
+        Err(error) => Err(failed(FailureKind::Config, "[E010] ", error)),
+    }
+}
+
+/// The options of `extract` that shape how the source files are parsed, which `check`, `list`
+/// and `hook` take as well.
+const PARSING_OPTIONS: &str = "[-end comment_end] [-block-end text] [-config markers.toml] [-marker-position column0|indented|after-code] [--inline] [-inline-strings keep|auto|skip] [-header spec] [-fields fields.toml] [--auto-sequence] [--infer-path] [-max-file-size bytes] [-encoding glob=encoding...] [-exclude glob...] [--gitignore] [--no-follow-links] [-max-depth n] [-generated glob...] [-v|-vv] [-log file] [-log-format text|json] [--progress] [--quiet]";
+
+/// A mode of get-comments, named by the first word of the command line.
+struct Subcommand {
+    name: &'static str,
+    /// The options of the mode, as shown by its help.
+    usage: &'static str,
+    summary: &'static str,
+    /// Runs the mode, or returns `None` when a mandatory option is missing.
+    run: fn(&Arguments) -> Option<Result<(), RunError>>,
+}
+
+//#EPIC Get Lines.ITEM Subcommands [0]
+//#
+//## Subcommands
+//#The first word of the command line names the mode: `extract`, `check`, `watch`, `list`,
+//#`render`, `publish`, `sync-back`, `upgrade`, `hook` or `init`, each with its own options and its own
+//#`--help`, so a new mode does not add to the options of every other one. A command line
+//#without a mode is taken as `extract`, or as `check` or `watch` with `--check` or `--watch`.
+/// Every mode of get-comments, in the order `help` lists them.
+const SUBCOMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "extract",
+        usage: "(-dir source_folder (-work document_root | -out blocks.json | --stdout) | (-dir - | --stdin) -file-name file) -start comment_start -path legal_folder_prefix -ext file_extension [parsing options] [--clean] [--atomic] [--reproducible] [-format md|json|html|adoc|mdbook...] [-flavor commonmark|myst] [-separator [format=]blank[:n]|rule|text:...] [--sequence-headings] [-heading-offset n] [-template file] [-link-style file|relative|github|none [-repo-url url] [-branch name]] [-filter name...] [-index-tables column...] [--folder-index] [--structure] [--obsidian] [-sort bytes|natural|folded...] [--backlinks] [--tag-pages] [-similarity threshold] [--check-urls [-url-concurrency n] [-url-allow text...] [-url-deny text...]] [-glossary [file]] [--git-metadata] [--contributors] [-stale-months n] [-density md|json] [-diagnostics md|json] [-trace-matrix csv|md] [-requirements file.csv|file.json|file.txt [--strict]] [--front-matter] [--manifest] [--run-footer] [-since-run run_id] [--async] [-timings [slowest_files]] [-stats [file.json]] [--coverage] [-coverage-min percent] [--incremental] [-since revision] [-only glob...] [-skip glob...] [--background] [-mirror folder...] [--hard-link]",
+        summary: "Extracts the blocks of the source files into the document root -work, or with -out into a JSON file for render.",
+        run: run_extract,
+    },
+    Subcommand {
+        name: "check",
+        usage: "[--staged] -dir source_folder -start comment_start -path legal_folder_prefix -ext file_extension [parsing options] [--coverage] [-coverage-min percent]",
+        summary: "Validates the blocks without writing anything; with --staged only the files staged in git are read.",
+        run: run_check,
+    },
+    Subcommand {
+        name: "watch",
+        usage: "-dir source_folder -work document_root -start comment_start -path legal_folder_prefix -ext file_extension [extract options]",
+        summary: "Extracts, then extracts again whenever a source file changes, until Ctrl-C.",
+        run: run_watch,
+    },
+    Subcommand {
+        name: "list",
+        usage: "-dir source_folder -start comment_start -path legal_folder_prefix -ext file_extension [-name glob] [--staged] [parsing options]",
+        summary: "Prints a table of the blocks with their Sequence numbers, source files and lines, without writing anything; -name lists the block names a glob such as 'EPIC A.**' matches.",
+        run: run_list,
+    },
+    Subcommand {
+        name: "render",
+        usage: "-in blocks.json -work document_root -path legal_folder_prefix [extract options]",
+        summary: "Renders the blocks of a JSON file written by extract -out into the document root.",
+        run: run_render,
+    },
+    Subcommand {
+        name: "publish",
+        usage: "confluence -url base_url -space space_key [-parent page_id] -dir source_folder -start comment_start -path legal_folder_prefix -ext file_extension [parsing options]",
+        summary: "Creates or updates a Confluence page for every block name, as a page tree like the document root; set CONFLUENCE_TOKEN, and CONFLUENCE_USER for Confluence Cloud.",
+        run: run_publish,
+    },
+    Subcommand {
+        name: "sync-back",
+        usage: "-file generated.md -start comment_start [-fields schema.toml]",
+        summary: "Writes the blocks edited in a generated Markdown file back into their source comments.",
+        run: run_sync_back,
+    },
+    Subcommand {
+        name: "upgrade",
+        usage: "-work document_root",
+        summary: "Migrates a document root written by an older release.",
+        run: run_upgrade,
+    },
+    Subcommand {
+        name: "hook",
+        usage: "install -dir source_folder -start comment_start -path legal_folder_prefix -ext file_extension [parsing options]",
+        summary: "Writes a git pre-commit hook running check --staged.",
+        run: run_hook,
+    },
+    Subcommand {
+        name: "init",
+        usage: "",
+        summary: "Writes a default get-comments.toml, whose options are used for those left out of the command line.",
+        run: run_init,
+    },
+];
+
+/// Returns the mode of a command line: its first word, or for a command line without one
+/// `check` with `--check`, `watch` with `--watch` and `extract` otherwise.
+fn subcommand_name(command: &Command, project: &ProjectConfig) -> String {
+    let flag = |name: &str| command.contains_argument(name) || project.contains(name);
+    match command.name.as_str() {
+        "" if command.contains_argument("init") => "init".to_string(),
+        "" if command.contains_argument("help") => "help".to_string(),
+        "" if flag("check") => "check".to_string(),
+        "" if flag("watch") => "watch".to_string(),
+        "" => "extract".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// Returns the word following the command name on the command line, such as `confluence` in
+/// `publish confluence`, which the command line parser leaves out of the arguments.
+fn subcommand_action(command: &Command) -> Option<String> {
+    let mut words = std::env::args().skip(1);
+    match words.next() {
+        Some(name) if !command.name.is_empty() && name == command.name => {
+            words.next().filter(|word| !word.starts_with('-'))
+        }
+        _ => None,
+    }
+}
+
+/// Prints the modes, or the options of one mode.
+fn print_help(subcommand: Option<&Subcommand>) {
+    match subcommand {
+        Some(subcommand) => {
+            println!(
+                "get-comments {} {}\n\n{}",
+                subcommand.name, subcommand.usage, subcommand.summary
             );
-        } else {
+            if subcommand.usage.contains("[parsing options]")
+                || subcommand.usage.contains("[extract options]")
+            {
+                println!("\nparsing options: {PARSING_OPTIONS}");
+            }
+            if subcommand.usage.contains("[extract options]") {
+                println!("extract options: see get-comments extract --help");
+            }
+        }
+        None => {
+            println!("get-comments command [options]\n");
+            for subcommand in SUBCOMMANDS {
+                println!("  {:<10} {}", subcommand.name, subcommand.summary);
+            }
             println!(
-                "command line -dir source_folder -work document_root -start comment_start -path legal_folder_prefix -ext file_extension"
-            )
+                "\nget-comments help command or get-comments command --help shows the options of a command; -explain error_code explains an error code such as E002"
+            );
+        }
+    }
+}
+
+/// Returns the comment start and the file extensions of the command line, or `None` when
+/// neither they nor a `-config` file name the markers to look for.
+fn markers(cli: &Arguments) -> Option<(&str, Vec<&str>)> {
+    let start = cli.get_argument("start");
+    let extensions: Vec<&str> = cli.get_argument("ext").into_iter().collect();
+    let has_markers = (start.is_some() || cli.contains_argument("config"))
+        && (!extensions.is_empty() || cli.contains_argument("config"));
+    has_markers.then(|| (start.unwrap_or_default(), extensions))
+}
+
+fn run_extract(cli: &Arguments) -> Option<Result<(), RunError>> {
+    if cli.get_argument("dir") == Some("-") || cli.contains_argument("stdin") {
+        return run_preview(cli);
+    }
+    let (start, extensions) = markers(cli)?;
+    let (dir, path) = (cli.get_argument("dir")?, cli.get_argument("path")?);
+    if cli.contains_argument("stdout") {
+        return Some(match configure(cli) {
+            Ok(mut comment_parser) => comment_parser.extract_to_writer(
+                dir,
+                start,
+                path,
+                &extensions,
+                printed_format(cli),
+                std::io::stdout().lock(),
+            ),
+            Err(error) => Err(failed(FailureKind::Config, "[E010] ", error)),
+        });
+    }
+    match (cli.get_argument("out"), cli.get_argument("work")) {
+        (Some(out), _) => Some(match configure(cli) {
+            Ok(mut comment_parser) => {
+                comment_parser.extract_blocks(dir, out, start, path, &extensions)
+            }
+            Err(error) => Err(failed(FailureKind::Config, "[E010] ", error)),
+        }),
+        (None, Some(work)) => Some(extract(cli, dir, work, start, path, &extensions)),
+        (None, None) => None,
+    }
+}
+
+/// Returns the format blocks are printed in by `--stdout` and `--stdin`: JSON with
+/// `-format json`, Markdown otherwise.
+fn printed_format(cli: &Arguments) -> format::OutputFormat {
+    match cli.get_argument("format") {
+        Some("json") => format::OutputFormat::Json,
+        _ => format::OutputFormat::Markdown,
+    }
+}
+
+/// Extracts the blocks of the source text on standard input and prints them, see
+/// `Comments::preview_source`.
+fn run_preview(cli: &Arguments) -> Option<Result<(), RunError>> {
+    let (file_name, path) = (cli.get_argument("file-name")?, cli.get_argument("path")?);
+    let start = cli.get_argument("start");
+    if start.is_none() && !cli.contains_argument("config") {
+        return None;
+    }
+    Some(
+        match (configure(cli), std::io::read_to_string(std::io::stdin())) {
+            (Ok(mut comment_parser), Ok(content)) => comment_parser.preview_source(
+                file_name,
+                &content,
+                start.unwrap_or_default(),
+                path,
+                printed_format(cli),
+                std::io::stdout().lock(),
+            ),
+            (Err(error), _) => Err(failed(FailureKind::Config, "[E010] ", error)),
+            (_, Err(error)) => Err(failed(FailureKind::Io, "stdin ", error)),
+        },
+    )
+}
+
+fn run_check(cli: &Arguments) -> Option<Result<(), RunError>> {
+    let (start, extensions) = markers(cli)?;
+    let (dir, path) = (cli.get_argument("dir")?, cli.get_argument("path")?);
+    Some(match configure(cli) {
+        Ok(mut comment_parser) => comment_parser.check_files(
+            dir,
+            start,
+            path,
+            &extensions,
+            cli.contains_argument("staged"),
+        ),
+        Err(error) => Err(failed(FailureKind::Config, "[E010] ", error)),
+    })
+}
+
+fn run_watch(cli: &Arguments) -> Option<Result<(), RunError>> {
+    let (start, extensions) = markers(cli)?;
+    let (dir, work, path) = (
+        cli.get_argument("dir")?,
+        cli.get_argument("work")?,
+        cli.get_argument("path")?,
+    );
+    let watcher = configure(cli).and_then(|comment_parser| {
+        watch::SourceWatcher::new(dir, &comment_parser.scanned_extensions(&extensions))
+    });
+    Some(match watcher {
+        Ok(watcher) => {
+            let mut result = extract(cli, dir, work, start, path, &extensions);
+            let quiet = cli.contains_argument("quiet");
+            let warn = |message: String| {
+                if !quiet {
+                    println!("{message}");
+                }
+            };
+            while let Some(changed) = watcher.wait_for_changes(warn) {
+                if !quiet {
+                    println!("{} source files changed, extracting again", changed.len());
+                }
+                result = extract(cli, dir, work, start, path, &extensions);
+            }
+            result
+        }
+        Err(error) => Err(failed(FailureKind::Io, "watch ", error)),
+    })
+}
+
+fn run_list(cli: &Arguments) -> Option<Result<(), RunError>> {
+    let (start, extensions) = markers(cli)?;
+    let (dir, path) = (cli.get_argument("dir")?, cli.get_argument("path")?);
+    Some(match configure(cli) {
+        Ok(mut comment_parser) => {
+            let result = comment_parser.check_files(
+                dir,
+                start,
+                path,
+                &extensions,
+                cli.contains_argument("staged"),
+            );
+            println!("{}", comment_parser.query().table(cli.get_argument("name")));
+            result
+        }
+        Err(error) => Err(failed(FailureKind::Config, "[E010] ", error)),
+    })
+}
+
+fn run_render(cli: &Arguments) -> Option<Result<(), RunError>> {
+    let (blocks, work, path) = (
+        cli.get_argument("in")?,
+        cli.get_argument("work")?,
+        cli.get_argument("path")?,
+    );
+    Some(match configure(cli) {
+        Ok(mut comment_parser) => comment_parser.render_blocks(blocks, work, path),
+        Err(error) => Err(failed(FailureKind::Config, "[E010] ", error)),
+    })
+}
+
+fn run_publish(cli: &Arguments) -> Option<Result<(), RunError>> {
+    if cli.action.as_deref() != Some("confluence") {
+        return None;
+    }
+    let (start, extensions) = markers(cli)?;
+    let (dir, path) = (cli.get_argument("dir")?, cli.get_argument("path")?);
+    let (url, space) = (cli.get_argument("url")?, cli.get_argument("space")?);
+    let target = confluence::ConfluenceTarget::new(url, space, cli.get_argument("parent"));
+    Some(match configure(cli) {
+        Ok(mut comment_parser) => comment_parser
+            .check_files(dir, start, path, &extensions, false)
+            .and_then(|_| {
+                match comment_parser
+                    .rendered_pages("", format::OutputFormat::Markdown)
+                    .and_then(|pages| target.publish(&pages))
+                {
+                    Ok(published) => {
+                        println!(
+                            "Published to Confluence: {} pages created, {} updated",
+                            published.created, published.updated
+                        );
+                        Ok(())
+                    }
+                    Err(error) => Err(failed(FailureKind::of(&error), "publish ", error)),
+                }
+            }),
+        Err(error) => Err(failed(FailureKind::Config, "[E010] ", error)),
+    })
+}
+
+fn run_sync_back(cli: &Arguments) -> Option<Result<(), RunError>> {
+    let (file, start) = (cli.get_argument("file")?, cli.get_argument("start")?);
+    let fields = match cli
+        .get_argument("fields")
+        .map(schema::FieldSchema::load)
+        .transpose()
+    {
+        Ok(fields) => fields,
+        Err(error) => return Some(Err(failed(FailureKind::Config, "[E010] ", error))),
+    };
+    Some(
+        match sync_back::sync_back(file, start.trim(), fields.as_ref()) {
+            Ok(changed) => {
+                println!("Synced {changed} blocks from {file} back to their source files");
+                Ok(())
+            }
+            Err(error) => Err(failed(FailureKind::of(&error), "sync-back ", error)),
+        },
+    )
+}
+
+fn run_upgrade(cli: &Arguments) -> Option<Result<(), RunError>> {
+    let work = cli.get_argument("work")?;
+    Some(match upgrade::upgrade(work) {
+        Ok(applied) if applied.is_empty() => {
+            println!("{work} is up to date");
+            Ok(())
+        }
+        Ok(applied) => {
+            for description in applied {
+                println!("Upgraded {work}: {description}");
+            }
+            Ok(())
+        }
+        Err(error) => Err(failed(FailureKind::of(&error), "upgrade ", error)),
+    })
+}
+
+fn run_hook(cli: &Arguments) -> Option<Result<(), RunError>> {
+    if cli.action.as_deref() != Some("install") {
+        return None;
+    }
+    let mut arguments = vec!["check".to_string(), "--staged".to_string()];
+    for name in [
+        "dir",
+        "start",
+        "end",
+        "block-end",
+        "ext",
+        "path",
+        "config",
+        "marker-position",
+        "inline-strings",
+        "header",
+    ] {
+        if let Some(value) = cli.command.get_argument(name) {
+            arguments.extend([format!("-{name}"), value.to_string()]);
+        }
+    }
+    if cli.command.contains_argument("inline") {
+        arguments.push("--inline".to_string());
+    }
+    let program = std::env::current_exe()
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "get-comments".to_string());
+    let script = hook::hook_script(&program, &arguments);
+    Some(
+        match hook::install_hook(cli.get_argument("dir").unwrap_or("."), &script) {
+            Ok(path) => {
+                println!("Wrote {}", path.display());
+                Ok(())
+            }
+            Err(error) => Err(failed(FailureKind::of(&error), "hook install ", error)),
+        },
+    )
+}
+
+fn run_init(_: &Arguments) -> Option<Result<(), RunError>> {
+    Some(init())
+}
+
+/// Writes the default project configuration file.
+fn init() -> Result<(), RunError> {
+    match ProjectConfig::init() {
+        Ok(()) => {
+            println!("Wrote {}", get_comments::project::PROJECT_FILE);
+            Ok(())
+        }
+        Err(error) => Err(failed(FailureKind::Io, "init ", error)),
+    }
+}
+
+fn main() {
+    // The first Ctrl-C stops the run cleanly, a second one exits at once.
+    let _ = ctrlc::set_handler(|| {
+        if get_comments::interrupt() {
+            std::process::exit(130);
+        }
+    });
+    if let Ok(command) = parse_command_line() {
+        if command.contains_argument("explain") {
+            match command.get_argument("explain").and_then(explain::explain) {
+                Some(explanation) => println!("{explanation}"),
+                None => {
+                    println!("Unknown error code, the codes are:");
+                    for explanation in explain::EXPLANATIONS {
+                        println!("{}: {}", explanation.code, explanation.title);
+                    }
+                    std::process::exit(USAGE_ERROR);
+                }
+            }
+            return;
+        }
+        // init runs before the project configuration is read, as it writes the file.
+        if command.name == "init" || command.contains_argument("init") {
+            if let Err(error) = init() {
+                std::process::exit(error.exit_code());
+            }
+            return;
+        }
+        let cli = match ProjectConfig::find() {
+            Ok(project) => Arguments {
+                name: subcommand_name(&command, &project),
+                action: subcommand_action(&command),
+                command,
+                project,
+            },
+            Err(error) => {
+                let error = failed(FailureKind::Config, "[E011] ", error);
+                std::process::exit(error.exit_code());
+            }
+        };
+        if cli.name == "help" {
+            let topic = std::env::args().nth(2);
+            print_help(
+                SUBCOMMANDS
+                    .iter()
+                    .find(|subcommand| topic.as_deref() == Some(subcommand.name)),
+            );
+            return;
+        }
+        let Some(subcommand) = SUBCOMMANDS
+            .iter()
+            .find(|subcommand| subcommand.name == cli.name)
+        else {
+            println!("Unknown command {}\n", cli.name);
+            print_help(None);
+            std::process::exit(USAGE_ERROR);
+        };
+        if cli.command.contains_argument("help") {
+            print_help(Some(subcommand));
+            return;
+        }
+        match (subcommand.run)(&cli) {
+            Some(Ok(())) => {}
+            Some(Err(error)) => std::process::exit(error.exit_code()),
+            None => {
+                print_help(Some(subcommand));
+                std::process::exit(USAGE_ERROR);
+            }
         }
     }
 }
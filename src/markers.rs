@@ -0,0 +1,561 @@
+use std::fs::read_to_string;
+use std::io::Error;
+use toml::{Table, Value};
+
+/// The comment marker of a language: the start string of a line block, and for block comments
+/// also the end string.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommentMarker {
+    pub start: String,
+    pub end: Option<String>,
+}
+
+/// Where on a line the start marker may be found.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MarkerPosition {
+    /// The marker must start at column 0.
+    ColumnZero,
+    /// The marker may follow whitespace, so indented comments are found.
+    #[default]
+    Indented,
+    /// The marker may follow code, as in `let total = 0; //# ...`. `strings` tells whether a
+    /// marker inside a string literal is found too.
+    AfterCode { strings: StringLiterals },
+}
+
+/// What a marker after code does inside a string literal.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StringLiterals {
+    /// The marker is found.
+    Keep,
+    /// The marker is ignored in a file of a known language, see `Literals`, and found in others.
+    #[default]
+    Auto,
+    /// The marker is ignored, the strings of an unknown language being quoted by `"` or `'`.
+    Skip,
+}
+
+/// The literal syntax of the language of a source file, so a marker inside a string or char
+/// literal is not taken for a comment.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Literals {
+    /// Strings quoted by `"` or `'`, for a language that is not known.
+    #[default]
+    Generic,
+    /// Rust: strings, raw strings such as `r#"..."#` and char literals, which a lifetime such
+    /// as `'a` is not.
+    Rust,
+    /// C, C++, C#, Java, Go, JavaScript and the like: literals quoted by `"`, `'` or a backtick.
+    CLike,
+    /// Python: strings quoted by `"`, `'`, `"""` or `'''`.
+    Python,
+}
+
+impl MarkerPosition {
+    /// Parses a marker position as given on the command line: `column0`, `indented` or
+    /// `after-code`.
+    pub fn parse(name: &str) -> Result<MarkerPosition, Error> {
+        match name.trim() {
+            "column0" => Ok(MarkerPosition::ColumnZero),
+            "indented" => Ok(MarkerPosition::Indented),
+            "after-code" => Ok(MarkerPosition::AfterCode {
+                strings: StringLiterals::default(),
+            }),
+            other => Err(Error::other(format!("Unknown marker position [{other}]"))),
+        }
+    }
+    /// Sets how a position after code treats a marker inside a string literal, as given on
+    /// the command line: `keep` finds it, `skip` ignores it and `auto` ignores it in the files
+    /// of a known language. Other positions are returned unchanged.
+    pub fn with_string_literals(self, name: &str) -> Result<MarkerPosition, Error> {
+        let strings = match name.trim() {
+            "keep" => StringLiterals::Keep,
+            "auto" => StringLiterals::Auto,
+            "skip" => StringLiterals::Skip,
+            other => {
+                return Err(Error::other(format!(
+                    "Unknown string literal handling [{other}]"
+                )));
+            }
+        };
+        Ok(match self {
+            MarkerPosition::AfterCode { .. } => MarkerPosition::AfterCode { strings },
+            position => position,
+        })
+    }
+    /// Returns the text of the line from the start marker on, trimmed, or `None` when the line
+    /// holds no marker at an allowed position. `literals` holds the literal syntax of the file
+    /// the line is from and the literal its lines before left open; a marker inside a literal
+    /// of a known language is not found at any position, unless `-inline-strings keep` is set.
+    pub fn marked_text<'l>(
+        &self,
+        line: &'l str,
+        start: &str,
+        literals: &mut SourceLiterals,
+    ) -> Option<&'l str> {
+        let position = match self {
+            MarkerPosition::ColumnZero | MarkerPosition::Indented => {
+                let column = match self {
+                    MarkerPosition::ColumnZero => 0,
+                    _ => line.len() - line.trim_start().len(),
+                };
+                match literals.syntax {
+                    Literals::Generic => line[column..].starts_with(start).then_some(column),
+                    _ => marker_outside_literals(line, start, literals)
+                        .filter(|position| *position == column),
+                }
+            }
+            MarkerPosition::AfterCode { strings } => match (strings, literals.syntax) {
+                (StringLiterals::Keep, _) | (StringLiterals::Auto, Literals::Generic) => {
+                    line.find(start)
+                }
+                _ => marker_outside_literals(line, start, literals),
+            },
+        };
+        position.map(|position| line[position..].trim())
+    }
+}
+
+/// The literal syntax of a source file and the literal left open at the end of the lines
+/// scanned so far, so a marker on a later line of a multi-line string or docstring is not
+/// taken for a comment.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceLiterals {
+    syntax: Literals,
+    open: Option<OpenLiteral>,
+}
+
+impl SourceLiterals {
+    /// Starts the scan of a file with the given literal syntax.
+    pub fn new(syntax: Literals) -> SourceLiterals {
+        SourceLiterals { syntax, open: None }
+    }
+    /// Starts the scan of a file, with the literal syntax of its extension.
+    pub fn of_file(file_name: &str) -> SourceLiterals {
+        SourceLiterals::new(Literals::of_file(file_name))
+    }
+}
+
+/// A literal, or a block comment, running on past the end of a line.
+#[derive(Clone, Debug, PartialEq)]
+struct OpenLiteral {
+    /// The delimiter closing the literal.
+    end: String,
+    /// Whether `\` escapes the next character, so an escaped delimiter does not close it.
+    escapes: bool,
+}
+
+/// Where a literal a line holds ends.
+enum Literal {
+    /// The literal is closed on the line, after this many bytes.
+    Closed(usize),
+    /// The literal is not closed on the line.
+    Open(OpenLiteral),
+}
+
+impl Literals {
+    /// Returns the literal syntax of a source file by its extension.
+    pub fn of_file(file_name: &str) -> Literals {
+        let extension = file_name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_ascii_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "rs" => Literals::Rust,
+            "py" | "pyi" | "pyw" => Literals::Python,
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "cs" | "java" | "kt" | "kts"
+            | "scala" | "go" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "swift" | "dart"
+            | "php" => Literals::CLike,
+            _ => Literals::Generic,
+        }
+    }
+    /// Returns the literal, or block comment, `text` starts with, or `None` when it starts
+    /// none. `word_start` tells whether `text` follows no identifier character, as the prefix
+    /// of a Rust raw string must. Rust strings, raw strings, backtick quoted literals, text
+    /// blocks and Python triple quoted strings may span lines, other strings of a known language
+    /// only when the line ends in a `\`.
+    fn literal_at(self, text: &str, word_start: bool) -> Option<Literal> {
+        let quote = text.chars().next()?;
+        let literal = |body: &str, end: &str, escapes: bool| {
+            Some(match literal_end(body, end, escapes) {
+                Some(length) => Literal::Closed(text.len() - body.len() + length),
+                None => Literal::Open(OpenLiteral {
+                    end: end.to_string(),
+                    escapes,
+                }),
+            })
+        };
+        match self {
+            Literals::Rust | Literals::CLike if text.starts_with("/*") => {
+                literal(&text[2..], "*/", false)
+            }
+            Literals::Rust if word_start && (quote == 'r' || text.starts_with("br")) => {
+                let raw = text.trim_start_matches('b').strip_prefix('r')?;
+                let hashes = raw.len() - raw.trim_start_matches('#').len();
+                let body = raw[hashes..].strip_prefix('"')?;
+                literal(body, &format!("\"{}", "#".repeat(hashes)), false)
+            }
+            Literals::Rust if quote == '\'' => {
+                let mut chars = text[1..].chars();
+                match chars.next()? {
+                    '\\' => {
+                        string_length(&text[1..], quote).map(|length| Literal::Closed(length + 1))
+                    }
+                    c => (chars.next()? == '\'').then(|| Literal::Closed(c.len_utf8() + 2)),
+                }
+            }
+            Literals::Rust if quote == '"' => literal(&text[1..], "\"", true),
+            Literals::Python | Literals::CLike if text.starts_with("\"\"\"") => {
+                literal(&text[3..], "\"\"\"", true)
+            }
+            Literals::Python if text.starts_with("'''") => literal(&text[3..], "'''", true),
+            Literals::CLike if quote == '`' => literal(&text[1..], "`", true),
+            _ if quote == '"' || quote == '\'' => match string_length(&text[1..], quote) {
+                Some(length) => Some(Literal::Closed(length + 1)),
+                None if self != Literals::Generic && text.ends_with('\\') => {
+                    Some(Literal::Open(OpenLiteral {
+                        end: quote.to_string(),
+                        escapes: true,
+                    }))
+                }
+                None => None,
+            },
+            _ => None,
+        }
+    }
+    /// Returns the start of a line comment of the language, after which a line holds no more
+    /// literals.
+    fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Literals::Generic => None,
+            Literals::Rust | Literals::CLike => Some("//"),
+            Literals::Python => Some("#"),
+        }
+    }
+}
+
+//#EPIC Get Lines.ITEM Markers after code [0]
+//#
+//## Markers after code
+//#With `--inline` a marker anywhere on a line starts or continues a block, as in
+//#`let x = 1; //# EPIC ...`. A marker inside a string or char literal, as in
+//#`let s = "//# not a comment";`, is ignored in Rust, C-like and Python files, which are told
+//#apart by their extension. `-inline-strings skip` ignores markers in the quoted strings of
+//#other files too, and `keep` finds every marker. A literal left open at the end of a line
+//#is followed onto the next lines, so a line inside a multi-line string or a docstring holds
+//#no marker either, also with the default indented position.
+/// Returns the position of the first start marker of a line that is not inside a literal of
+/// the language, scanning on from the literal the lines before left open. The scan ends at
+/// the marker or at a line comment, and the literal the line leaves open is kept in
+/// `literals` for the next line.
+fn marker_outside_literals(
+    line: &str,
+    start: &str,
+    literals: &mut SourceLiterals,
+) -> Option<usize> {
+    let mut position = 0;
+    if let Some(open) = &literals.open {
+        position = literal_end(line, &open.end, open.escapes)?;
+        literals.open = None;
+    }
+    while position < line.len() {
+        let rest = &line[position..];
+        if rest.starts_with(start) {
+            return Some(position);
+        }
+        if literals
+            .syntax
+            .line_comment()
+            .is_some_and(|comment| rest.starts_with(comment))
+        {
+            return None;
+        }
+        let word_start = !line[..position].ends_with(|c: char| c.is_alphanumeric() || c == '_');
+        position += match literals.syntax.literal_at(rest, word_start) {
+            Some(Literal::Closed(length)) => length,
+            Some(Literal::Open(open)) => {
+                literals.open = Some(open);
+                return None;
+            }
+            None => rest.chars().next()?.len_utf8(),
+        };
+    }
+    None
+}
+
+/// Returns the length of a string literal up to and including its closing `quote`, or `None`
+/// when the line does not close it.
+fn string_length(text: &str, quote: char) -> Option<usize> {
+    let mut chars = text.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next()?;
+            }
+            c if c == quote => return Some(index + c.len_utf8()),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the length of the text of a literal up to and including the delimiter `end`, or
+/// `None` when the line does not close it. With `escapes` a `\` escapes the next character.
+fn literal_end(text: &str, end: &str, escapes: bool) -> Option<usize> {
+    let mut chars = text.char_indices();
+    while let Some((index, c)) = chars.next() {
+        if text[index..].starts_with(end) {
+            return Some(index + end.len());
+        }
+        if escapes && c == '\\' {
+            chars.next();
+        }
+    }
+    None
+}
+
+/// The comment markers per file extension, so one run can extract `//#` from Rust, `##` from
+/// Python and `<!--#` from HTML.
+#[derive(Clone, Debug, Default)]
+pub struct MarkerConfig {
+    markers: Vec<(String, CommentMarker)>,
+}
+
+impl MarkerConfig {
+    /// Reads a marker configuration file, see `parse` for the format.
+    pub fn load(file_name: &str) -> Result<MarkerConfig, Error> {
+        MarkerConfig::parse(&read_to_string(file_name)?)
+            .map_err(|error| Error::other(format!("{error} in {file_name}")))
+    }
+    /// Parses a marker configuration: a TOML file with one `extension = marker` pair per
+    /// extension.
+    ///
+    /// The marker is a start string for line blocks, or an array of a start and end string
+    /// for block comments:
+    /// ```toml
+    /// ".rs" = "//#"
+    /// ".py" = "##"
+    /// ".html" = ["<!--#", "-->"]
+    /// ```
+    /// The markers may also be given in a `[markers]` table.
+    pub fn parse(content: &str) -> Result<MarkerConfig, Error> {
+        let table: Table = toml::from_str(content).map_err(Error::other)?;
+        let mut config = MarkerConfig::default();
+        for (extension, value) in table {
+            match (extension.as_str(), value) {
+                ("markers", Value::Table(markers)) => {
+                    for (extension, value) in markers {
+                        config.add(extension, value)?;
+                    }
+                }
+                (_, value) => config.add(extension, value)?,
+            }
+        }
+        Ok(config)
+    }
+    /// Adds the marker of an extension, given by its value in the configuration file.
+    fn add(&mut self, extension: String, value: Value) -> Result<(), Error> {
+        let invalid = || {
+            Error::other(format!(
+                "Invalid marker of [{extension}]: expected `\".ext\" = \"start\"` or `\".ext\" = [\"start\", \"end\"]`"
+            ))
+        };
+        let marker = match value {
+            Value::String(start) => CommentMarker { start, end: None },
+            Value::Array(array) => match <[Value; 2]>::try_from(array) {
+                Ok([Value::String(start), Value::String(end)]) => CommentMarker {
+                    start,
+                    end: Some(end),
+                },
+                _ => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        };
+        if extension.is_empty() || marker.start.is_empty() {
+            return Err(invalid());
+        }
+        self.markers.push((extension, marker));
+        Ok(())
+    }
+    /// Returns the marker of the file, taken from the longest extension the file name ends
+    /// with, or `None` when no extension matches.
+    pub fn marker_for(&self, file_name: &str) -> Option<&CommentMarker> {
+        self.markers
+            .iter()
+            .filter(|(extension, _)| file_name.ends_with(extension.as_str()))
+            .max_by_key(|(extension, _)| extension.len())
+            .map(|(_, marker)| marker)
+    }
+    /// Returns the extensions that have a marker.
+    pub fn extensions(&self) -> impl Iterator<Item = &str> {
+        self.markers.iter().map(|(extension, _)| extension.as_str())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_marker_position() {
+    let indented = "    //# PERSON.A [0] ";
+    let after_code = "let total = 0; //# PERSON.A [0]";
+    let generic = &mut SourceLiterals::new(Literals::Generic);
+    assert_eq!(
+        MarkerPosition::ColumnZero.marked_text(indented, "//#", generic),
+        None
+    );
+    assert_eq!(
+        MarkerPosition::Indented.marked_text(indented, "//#", generic),
+        Some("//# PERSON.A [0]")
+    );
+    assert_eq!(
+        MarkerPosition::Indented.marked_text(after_code, "//#", generic),
+        None
+    );
+    let after_code_position = MarkerPosition::parse("after-code").unwrap();
+    assert_eq!(
+        after_code_position.marked_text(after_code, "//#", generic),
+        Some("//# PERSON.A [0]")
+    );
+    let in_string = r#"let url = "http://#top"; let c = '"'; //# PERSON.B [1]"#;
+    assert_eq!(
+        after_code_position.marked_text(in_string, "//#", generic),
+        Some(r#"//#top"; let c = '"'; //# PERSON.B [1]"#)
+    );
+    let skipping = after_code_position.with_string_literals("skip").unwrap();
+    assert_eq!(
+        skipping.marked_text(in_string, "//#", generic),
+        Some("//# PERSON.B [1]")
+    );
+    assert_eq!(
+        skipping.marked_text(r#"let a = "\"//#";"#, "//#", generic),
+        None
+    );
+    assert!(MarkerPosition::parse("anywhere").is_err());
+    assert!(after_code_position.with_string_literals("drop").is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_literals() {
+    assert_eq!(Literals::of_file("src/main.rs"), Literals::Rust);
+    let rust = &mut SourceLiterals::of_file("src/main.rs");
+    let auto = MarkerPosition::parse("after-code").unwrap();
+    let not_a_comment = r#"let s = "//# not a comment";"#;
+    assert_eq!(auto.marked_text(not_a_comment, "//#", rust), None);
+    assert_eq!(
+        auto.with_string_literals("keep")
+            .unwrap()
+            .marked_text(not_a_comment, "//#", rust),
+        Some(r#"//# not a comment";"#)
+    );
+    assert_eq!(
+        auto.marked_text(
+            r##"let s = r#"a "//#" b"#; let c = '"'; //# PERSON.A [0]"##,
+            "//#",
+            rust
+        ),
+        Some("//# PERSON.A [0]")
+    );
+    assert_eq!(
+        auto.marked_text("fn f<'a>(x: &'a str) {} //# it's PERSON.B [1]", "//#", rust),
+        Some("//# it's PERSON.B [1]")
+    );
+    let python = &mut SourceLiterals::of_file("tool.py");
+    assert_eq!(
+        auto.marked_text(
+            r###"s = "## no"; t = '''##''' ## PERSON.C [2]"###,
+            "##",
+            python
+        ),
+        Some("## PERSON.C [2]")
+    );
+    let javascript = &mut SourceLiterals::of_file("app.js");
+    assert_eq!(
+        auto.marked_text("let s = `//#`; //# PERSON.D [3]", "//#", javascript),
+        Some("//# PERSON.D [3]")
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_multi_line_literals() {
+    let indented = MarkerPosition::default();
+    let python = &mut SourceLiterals::of_file("tool.py");
+    let lines = [
+        "def f():",
+        "    \"\"\"Returns nothing.",
+        "    ## not a block",
+        "    \"\"\"",
+        "    ## PERSON.A [0]",
+    ];
+    let found: Vec<_> = lines
+        .iter()
+        .filter_map(|line| indented.marked_text(line, "##", python))
+        .collect();
+    assert_eq!(found, ["## PERSON.A [0]"]);
+    let after_code = MarkerPosition::parse("after-code").unwrap();
+    let rust = &mut SourceLiterals::of_file("src/main.rs");
+    assert_eq!(
+        after_code.marked_text(r#"let s = "first line"#, "//#", rust),
+        None
+    );
+    assert_eq!(after_code.marked_text("//# not a block", "//#", rust), None);
+    assert_eq!(
+        after_code.marked_text(r#"end"; //# PERSON.B [1]"#, "//#", rust),
+        Some("//# PERSON.B [1]")
+    );
+    assert_eq!(
+        after_code.marked_text("let s = r##\"a \"# b", "//#", rust),
+        None
+    );
+    assert_eq!(indented.marked_text("//# not a block", "//#", rust), None);
+    assert_eq!(indented.marked_text("\"## x", "//#", rust), None);
+    assert_eq!(
+        indented.marked_text("//# PERSON.C [2]", "//#", rust),
+        Some("//# PERSON.C [2]")
+    );
+    assert_eq!(
+        indented.marked_text("// don't \"quote //# here", "//#", rust),
+        None
+    );
+    assert_eq!(
+        indented.marked_text("//# PERSON.D [3]", "//#", rust),
+        Some("//# PERSON.D [3]")
+    );
+    let javascript = &mut SourceLiterals::of_file("app.js");
+    assert_eq!(indented.marked_text("let s = `", "//#", javascript), None);
+    assert_eq!(
+        indented.marked_text("  //# in a template", "//#", javascript),
+        None
+    );
+    assert_eq!(indented.marked_text("`;", "//#", javascript), None);
+    assert_eq!(
+        indented.marked_text("  //# PERSON.E [4]", "//#", javascript),
+        Some("//# PERSON.E [4]")
+    );
+    let generic = &mut SourceLiterals::new(Literals::Generic);
+    assert_eq!(indented.marked_text("s = \"", "//#", generic), None);
+    assert_eq!(
+        indented.marked_text("//# PERSON.F [5]", "//#", generic),
+        Some("//# PERSON.F [5]")
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_marker_config() {
+    let config = MarkerConfig::parse(
+        "# markers\n[markers]\n\".rs\" = \"//#\"\n\".py\" = \"##\"\n\".html\" = [\"<!--#\", \"-->\"]\n\".test.rs\" = \"//!#\"\n",
+    )
+    .unwrap();
+    assert_eq!(config.marker_for("src/main.rs").unwrap().start, "//#");
+    assert_eq!(config.marker_for("a.test.rs").unwrap().start, "//!#");
+    assert_eq!(
+        config.marker_for("index.html"),
+        Some(&CommentMarker {
+            start: "<!--#".to_string(),
+            end: Some("-->".to_string())
+        })
+    );
+    assert_eq!(config.marker_for("a.txt"), None);
+    assert!(MarkerConfig::parse("\".rs\" = //#").is_err());
+}
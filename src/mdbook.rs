@@ -0,0 +1,141 @@
+use crate::outputs::write_if_changed;
+use std::fs::write;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+/// The folder of the book, in the document root, holding the chapters and `SUMMARY.md`.
+const BOOK_SOURCE: &str = "src";
+
+/// The `book.toml` written when the document root has none yet.
+const BOOK_TOML: &str = "[book]\ntitle = \"Documentation\"\nsrc = \"src\"\n";
+
+/// One level of the block hierarchy in the summary, such as an EPIC with its ITEMs. The
+/// children are kept in the order they were met.
+#[derive(Default)]
+struct Chapter {
+    /// Whether a page exists for this level, which is not so for a level without own blocks.
+    page: bool,
+    children: Vec<(String, Chapter)>,
+}
+
+impl Chapter {
+    /// Adds the page of a dot delimited block name, and the levels above it.
+    fn insert(&mut self, name: &str) {
+        let mut chapter = self;
+        for level in name.split('.') {
+            let index = match chapter
+                .children
+                .iter()
+                .position(|(known, _)| known == level)
+            {
+                Some(index) => index,
+                None => {
+                    chapter
+                        .children
+                        .push((level.to_string(), Chapter::default()));
+                    chapter.children.len() - 1
+                }
+            };
+            chapter = &mut chapter.children[index].1;
+        }
+        chapter.page = true;
+    }
+}
+
+/// Returns the path of the chapter of a dot delimited block name given without the document
+/// root.
+pub fn chapter_path(doc_root: &str, name: &str) -> PathBuf {
+    Path::new(doc_root)
+        .join(BOOK_SOURCE)
+        .join(format!("{}.md", name.replace('.', "/")))
+}
+
+/// Returns the files of the book besides the chapters: `SUMMARY.md` and `book.toml`.
+pub fn book_files(doc_root: &str) -> [PathBuf; 2] {
+    [
+        Path::new(doc_root).join(BOOK_SOURCE).join("SUMMARY.md"),
+        Path::new(doc_root).join("book.toml"),
+    ]
+}
+
+//#EPIC Get Lines.ITEM Export an mdBook [0]
+//#
+//## Export an mdBook
+//#With `-format mdbook` the document root becomes an mdBook: every block name is a chapter in
+//#`src`, and `src/SUMMARY.md` nests the chapters along the EPIC/ITEM/TASK hierarchy, siblings
+//#in the order of their first Sequence number. A `book.toml` is written when there is none, so
+//#`mdbook build` works on the document root out of the box.
+/// Writes the chapters, `SUMMARY.md` and, when missing, `book.toml` of an mdBook into the
+/// document root. Files that already hold their content are not written again.
+///
+/// # Parameters:
+/// - `pages`: The dot delimited block name of every chapter, without the document root, with
+///   its Markdown content, in the order of the summary
+/// - `doc_root`: The folder the book is written to
+///
+/// # Returns:
+/// - `Ok(Vec<PathBuf>)` with every file of the book, written or not
+pub fn write_book(pages: &[(String, String)], doc_root: &str) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    let mut tree = Chapter::default();
+    for (name, markdown) in pages {
+        let path = chapter_path(doc_root, name);
+        write_if_changed(&path, markdown.as_bytes())?;
+        files.push(path);
+        tree.insert(name);
+    }
+    let mut summary = String::from("# Summary\n\n");
+    write_chapters(&mut summary, &tree, "", 0);
+    let [summary_file, book] = book_files(doc_root);
+    write_if_changed(&summary_file, summary.as_bytes())?;
+    if !book.exists() {
+        write(&book, BOOK_TOML)?;
+    }
+    files.extend([summary_file, book]);
+    Ok(files)
+}
+
+/// Writes the children of a chapter as a nested list. `prefix` is the path of the chapter.
+/// A level without own blocks becomes a draft chapter, which mdBook lists without a page.
+fn write_chapters(summary: &mut String, chapter: &Chapter, prefix: &str, depth: usize) {
+    for (level, child) in &chapter.children {
+        let path = match prefix.is_empty() {
+            true => level.clone(),
+            false => format!("{prefix}/{level}"),
+        };
+        let indent = "  ".repeat(depth);
+        match child.page {
+            true => summary.push_str(&format!("{indent}- [{level}](<{path}.md>)\n")),
+            false => summary.push_str(&format!("{indent}- [{level}]()\n")),
+        }
+        write_chapters(summary, child, &path, depth + 1);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_write_book() {
+    use std::fs::read_to_string;
+    let doc_root = "target/test-mdbook";
+    let _ = std::fs::remove_dir_all(doc_root);
+    let pages = [
+        ("EPIC B", "# B\n"),
+        ("EPIC A.ITEM C", "# C\n"),
+        ("EPIC B.ITEM D", "# D\n"),
+    ]
+    .map(|(name, markdown)| (name.to_string(), markdown.to_string()));
+    let files = write_book(&pages, doc_root).unwrap();
+    assert_eq!(files.len(), 5);
+    assert_eq!(
+        read_to_string(format!("{doc_root}/src/SUMMARY.md")).unwrap(),
+        "# Summary\n\n- [EPIC B](<EPIC B.md>)\n  - [ITEM D](<EPIC B/ITEM D.md>)\n- [EPIC A]()\n  - [ITEM C](<EPIC A/ITEM C.md>)\n"
+    );
+    assert_eq!(
+        read_to_string(format!("{doc_root}/src/EPIC A/ITEM C.md")).unwrap(),
+        "# C\n"
+    );
+    assert_eq!(
+        read_to_string(format!("{doc_root}/book.toml")).unwrap(),
+        BOOK_TOML
+    );
+}
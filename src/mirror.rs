@@ -0,0 +1,191 @@
+use crate::outputs::{OUTPUTS_FILE, recorded_file};
+use std::fs::{
+    copy, create_dir_all, hard_link, read_dir, read_to_string, remove_dir, remove_file, write,
+};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// The prefix of the files get-comments keeps for itself in the document root, such as the
+/// manifest and the source cache. They are not mirrored.
+const STATE_FILE_PREFIX: &str = ".get-comments";
+
+/// Returns whether `path` is `folder` or lies inside it, comparing the canonical paths so
+/// `.` and `..` and symbolic links do not hide it. A path that does not exist lies nowhere.
+fn lies_in(path: &Path, folder: &Path) -> bool {
+    matches!(
+        (folder.canonicalize(), path.canonicalize()),
+        (Ok(folder), Ok(path)) if path.starts_with(&folder)
+    )
+}
+
+/// Removes the files the previous mirror recorded in `target`, and the folders that are left
+/// empty, leaving every other file alone.
+fn clear_mirror(target: &Path, record: &str) {
+    let files = record
+        .lines()
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| recorded_file(target, line));
+    for file in files {
+        if remove_file(&file).is_err() {
+            continue;
+        }
+        for folder in file
+            .ancestors()
+            .skip(1)
+            .take_while(|folder| *folder != target)
+        {
+            if remove_dir(folder).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+//#EPIC Get Lines.ITEM Mirror the document root [0]
+//#
+//## Mirror the document root
+//#Once a run is written, the document root can be copied, or hard linked, into further folders,
+//#so an internal wiki and the content folder of a web site stay in sync without extracting
+//#twice. The files a mirror receives are listed in its `.get-comments-outputs`, and only those
+//#are removed before the next copy, so files that are no longer generated disappear while
+//#nothing else in the folder is touched. A folder that already holds files without such a list
+//#is refused, as is a folder holding the document root or the source folder.
+/// Replaces the files a previous mirror wrote to `destination` with a copy of the generated
+/// files in `doc_root`, and records them in the manifest of the mirror.
+///
+/// With `hard_link_files` the files are hard linked instead of copied, which takes no extra space;
+/// a file that cannot be linked, for example because the destination is on another file
+/// system, is copied. The state files of get-comments are left out.
+///
+/// # Returns:
+/// - `Ok(usize)` with the number of mirrored files
+/// - `Err(Error)` when the destination is the document root, lies inside it or holds it or
+///   `source_root`, when it holds files but no mirror manifest, or when a file could not be
+///   written
+pub fn mirror_tree(
+    doc_root: &str,
+    source_root: &str,
+    destination: &str,
+    hard_link_files: bool,
+) -> Result<usize, Error> {
+    let root = Path::new(doc_root);
+    let target = Path::new(destination);
+    if target.starts_with(root) || lies_in(target, root) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Mirror {destination} must not be inside the document root {doc_root}"),
+        ));
+    }
+    for (folder, name) in [
+        (root, "document root"),
+        (Path::new(source_root), "source folder"),
+    ] {
+        if !folder.as_os_str().is_empty() && lies_in(folder, target) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Mirror {destination} must not hold the {name} {}",
+                    folder.display()
+                ),
+            ));
+        }
+    }
+    let record = target.join(OUTPUTS_FILE);
+    match read_to_string(&record) {
+        Ok(recorded) => clear_mirror(target, &recorded),
+        Err(_) if read_dir(target).is_ok_and(|mut entries| entries.next().is_some()) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Mirror {destination} holds files but no {OUTPUTS_FILE}, it was not written by get-comments"
+                ),
+            ));
+        }
+        Err(_) => {}
+    }
+    create_dir_all(target)?;
+    let mut mirrored = Vec::new();
+    let entries = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            !entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(STATE_FILE_PREFIX)
+        });
+    for entry in entries {
+        let entry = entry.map_err(Error::other)?;
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let path = target.join(relative);
+        if entry.file_type().is_dir() {
+            create_dir_all(&path)?;
+        } else if entry.file_type().is_file() {
+            let _ = remove_file(&path);
+            if !hard_link_files || hard_link(entry.path(), &path).is_err() {
+                copy(entry.path(), &path)?;
+            }
+            mirrored.push(format!(
+                "{}\n",
+                relative.to_string_lossy().replace('\\', "/")
+            ));
+        }
+    }
+    write(record, mirrored.concat())?;
+    Ok(mirrored.len())
+}
+
+#[cfg(test)]
+#[test]
+fn test_mirror_tree() {
+    let doc_root = "target/test-mirror/docs";
+    let destination = "target/test-mirror/site";
+    let _ = std::fs::remove_dir_all("target/test-mirror");
+    create_dir_all(format!("{doc_root}/EPIC A")).unwrap();
+    std::fs::write(format!("{doc_root}/EPIC A/ITEM B.md"), "text").unwrap();
+    std::fs::write(format!("{doc_root}/.get-comments-cache"), "").unwrap();
+    create_dir_all(format!("{destination}/EPIC Old")).unwrap();
+    std::fs::write(format!("{destination}/EPIC Old/ITEM C.md"), "").unwrap();
+    std::fs::write(format!("{destination}/mine.md"), "by hand").unwrap();
+    assert!(mirror_tree(doc_root, "src", destination, true).is_err());
+    std::fs::write(
+        format!("{destination}/{OUTPUTS_FILE}"),
+        "EPIC Old/ITEM C.md\n../docs/EPIC A/ITEM B.md\n",
+    )
+    .unwrap();
+    assert_eq!(mirror_tree(doc_root, "src", destination, true).unwrap(), 1);
+    let mirrored = std::fs::read_to_string(format!("{destination}/EPIC A/ITEM B.md")).unwrap();
+    assert_eq!(mirrored, "text");
+    assert!(Path::new(&format!("{doc_root}/EPIC A/ITEM B.md")).exists());
+    assert!(!Path::new(&format!("{destination}/EPIC Old")).exists());
+    assert!(Path::new(&format!("{destination}/mine.md")).exists());
+    assert!(!Path::new(&format!("{destination}/.get-comments-cache")).exists());
+    let record = read_to_string(format!("{destination}/{OUTPUTS_FILE}")).unwrap();
+    assert_eq!(record, "EPIC A/ITEM B.md\n");
+    assert!(mirror_tree(doc_root, "src", "target/test-mirror/docs/copy", false).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_mirror_refuses_parent_folders() {
+    let doc_root = "target/test-mirror-parent/docs";
+    let _ = std::fs::remove_dir_all("target/test-mirror-parent");
+    create_dir_all(doc_root).unwrap();
+    std::fs::write(format!("{doc_root}/EPIC A.md"), "text").unwrap();
+    let error = mirror_tree(doc_root, "src", "target/test-mirror-parent", false).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("must not hold the document root")
+    );
+    let error = mirror_tree(doc_root, "src/io", ".", false).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("must not hold the document root")
+    );
+    assert!(Path::new(&format!("{doc_root}/EPIC A.md")).exists());
+}
@@ -0,0 +1,219 @@
+use crate::block::{Sequences, relative_name};
+use crate::outputs::write_if_changed;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{create_dir_all, read_to_string, remove_file, write};
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The folder Obsidian keeps the settings of a vault in, which marks the root of the vault.
+pub const SETTINGS_FOLDER: &str = ".obsidian";
+/// The file in the document root listing the notes written by the last run in Obsidian mode.
+pub const NOTES_FILE: &str = ".get-comments-notes";
+/// The end of the file name of the map of content note of an EPIC, as in `EPIC A MOC.md`.
+const MOC_SUFFIX: &str = " MOC";
+
+/// The Obsidian vault holding the document root, with the notes written into it by a run.
+#[derive(Debug, Default)]
+pub struct Vault {
+    doc_root: String,
+    /// The path of the document root in the vault, ending in `/`; empty when the document root
+    /// is the vault itself.
+    prefix: String,
+    /// The generated notes, as paths below the document root without extension.
+    notes: HashSet<String>,
+    /// The notes written, or found unchanged, by this run.
+    written: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl Vault {
+    /// Finds the vault of a document root: the nearest folder, starting from the document
+    /// root itself, holding an `.obsidian` folder. A document root outside of any vault is
+    /// taken to be a vault of its own.
+    pub fn open(history: &HashMap<String, Sequences>, doc_root: &str) -> Vault {
+        let folder = std::path::absolute(doc_root).unwrap_or_else(|_| PathBuf::from(doc_root));
+        let prefix = folder
+            .ancestors()
+            .find(|ancestor| ancestor.join(SETTINGS_FOLDER).is_dir())
+            .and_then(|vault| folder.strip_prefix(vault).ok())
+            .map(|path| path.to_string_lossy().replace('\\', "/"))
+            .filter(|path| !path.is_empty())
+            .map(|path| format!("{path}/"))
+            .unwrap_or_default();
+        Vault {
+            doc_root: doc_root.to_string(),
+            prefix,
+            notes: history
+                .keys()
+                .map(|key| relative_name(key, doc_root).replace('.', "/"))
+                .collect(),
+            written: Mutex::new(BTreeSet::new()),
+        }
+    }
+    /// Returns the path of the document root in the vault, ending in `/` unless it is empty.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+    /// Rewrites the `[[wiki links]]` to generated notes into links from the root of the vault,
+    /// so Obsidian resolves them wherever the document root is in the vault. Targets are
+    /// matched as `resolve_links` does; other links and raw lines are left alone.
+    pub fn link_notes(&self, lines: &[String], raw: &BTreeSet<usize>) -> Vec<String> {
+        let link_pattern = Regex::new(r"\[\[([^\]|#]+)([^\]]*)\]\]").unwrap();
+        let root = self.doc_root.trim_start_matches("./").trim_end_matches('/');
+        lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| match raw.contains(&index) {
+                true => line.clone(),
+                false => link_pattern
+                    .replace_all(line, |link: &regex::Captures| {
+                        let target = link[1].trim();
+                        let path = target.strip_suffix(".md").unwrap_or(target);
+                        let path = path.trim_start_matches("./");
+                        let path = match self.notes.contains(path) {
+                            true => Some(path),
+                            false => path
+                                .strip_prefix(root)
+                                .and_then(|rest| rest.strip_prefix('/'))
+                                .filter(|rest| self.notes.contains(*rest)),
+                        };
+                        match path {
+                            Some(path) => format!("[[{}{path}{}]]", self.prefix, &link[2]),
+                            None => link[0].to_string(),
+                        }
+                    })
+                    .into_owned(),
+            })
+            .collect()
+    }
+    /// Records a note as written by this run, so it is not removed as stale.
+    pub fn keep(&self, path: &Path) {
+        if let Ok(mut written) = self.written.lock() {
+            written.insert(path.to_path_buf());
+        }
+    }
+    /// Writes a note unless it already holds the content, so Obsidian does not reload notes
+    /// that did not change.
+    ///
+    /// # Returns:
+    /// - `Ok(bool)` telling whether the note was written
+    pub fn write_note(&self, path: &Path, content: &[u8]) -> Result<bool, Error> {
+        self.keep(path);
+        write_if_changed(path, content)
+    }
+    /// Records the notes written by this run in the document root. With `remove_stale` the
+    /// notes written by the last run and not by this one are removed first; without it they
+    /// are kept in the record.
+    ///
+    /// # Returns:
+    /// - `Ok(usize)` with the number of notes removed
+    pub fn finish(&self, remove_stale: bool) -> Result<usize, Error> {
+        let record = Path::new(&self.doc_root).join(NOTES_FILE);
+        let mut notes = match self.written.lock() {
+            Ok(written) => written.clone(),
+            Err(_) => BTreeSet::new(),
+        };
+        let mut removed = 0;
+        for line in read_to_string(&record).unwrap_or_default().lines() {
+            let note = Path::new(&self.doc_root).join(line);
+            if notes.contains(&note) {
+                continue;
+            }
+            match remove_stale {
+                true => removed += remove_file(&note).is_ok() as usize,
+                false => {
+                    notes.insert(note);
+                }
+            }
+        }
+        let lines: Vec<String> = notes
+            .iter()
+            .filter_map(|note| note.strip_prefix(&self.doc_root).ok())
+            .map(|note| format!("{}\n", note.to_string_lossy().replace('\\', "/")))
+            .collect();
+        create_dir_all(&self.doc_root)?;
+        write(record, lines.concat())?;
+        Ok(removed)
+    }
+}
+
+//#EPIC Get Lines.ITEM Obsidian vault mode [0]
+//#
+//## Obsidian vault mode
+//#With `--obsidian` the document root is treated as (a folder in) an Obsidian vault: every EPIC
+//#gets a map of content note linking all its notes, wiki links between blocks are rewritten
+//#to links from the root of the vault, and notes whose content did not change are not written
+//#again, so the file watcher of Obsidian does not reload the whole tree on every run.
+/// Writes a map of content note per EPIC into the document root, such as `EPIC A MOC.md`,
+/// listing the generated notes of the EPIC as a tree of links.
+///
+/// # Returns:
+/// - `Ok(usize)` with the number of notes that were written because they changed
+pub fn write_mocs(history: &HashMap<String, Sequences>, vault: &Vault) -> Result<usize, Error> {
+    let mut epics: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for key in history.keys() {
+        let name = relative_name(key, &vault.doc_root);
+        let epic = name.split('.').next().unwrap_or(name);
+        epics.entry(epic).or_default().push(name);
+    }
+    let mut written = 0;
+    for (epic, mut names) in epics {
+        names.sort_unstable();
+        let mut content = format!("# {epic}\n\n");
+        for name in names {
+            let levels: Vec<&str> = name.split('.').collect();
+            content.push_str(&format!(
+                "{}- [[{}{}|{}]]\n",
+                "  ".repeat(levels.len() - 1),
+                vault.prefix,
+                levels.join("/"),
+                levels[levels.len() - 1]
+            ));
+        }
+        let note = Path::new(&vault.doc_root).join(format!("{epic}{MOC_SUFFIX}.md"));
+        written += vault.write_note(&note, content.as_bytes())? as usize;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+#[test]
+fn test_vault() {
+    use crate::block::Block;
+    use crate::sequence::Sequence;
+    let folder = "target/test-obsidian";
+    let _ = std::fs::remove_dir_all(folder);
+    create_dir_all(format!("{folder}/{SETTINGS_FOLDER}")).unwrap();
+    let doc_root = format!("{folder}/docs");
+    let history: HashMap<String, Sequences> = ["EPIC A", "EPIC A.ITEM B"]
+        .into_iter()
+        .map(|name| {
+            let block = Block::new("a.rs".into(), 1, vec![], &BTreeSet::new());
+            (
+                format!("{doc_root}.{name}"),
+                Sequences::from([(Sequence::from(0), block)]),
+            )
+        })
+        .collect();
+    let vault = Vault::open(&history, &doc_root);
+    assert_eq!(vault.prefix(), "docs/");
+    let lines = vec!["See [[EPIC A/ITEM B#Usage|usage]] and [[EPIC Z]]".to_string()];
+    assert_eq!(
+        vault.link_notes(&lines, &BTreeSet::new()),
+        ["See [[docs/EPIC A/ITEM B#Usage|usage]] and [[EPIC Z]]"]
+    );
+    assert_eq!(write_mocs(&history, &vault).unwrap(), 1);
+    let moc = read_to_string(format!("{doc_root}/EPIC A MOC.md")).unwrap();
+    assert_eq!(
+        moc,
+        "# EPIC A\n\n- [[docs/EPIC A|EPIC A]]\n  - [[docs/EPIC A/ITEM B|ITEM B]]\n"
+    );
+    let stale = Path::new(&doc_root).join("EPIC Z.md");
+    write(&stale, "old").unwrap();
+    write(Path::new(&doc_root).join(NOTES_FILE), "EPIC Z.md\n").unwrap();
+    assert_eq!(vault.finish(true).unwrap(), 1);
+    assert!(!stale.exists());
+    let vault = Vault::open(&history, &doc_root);
+    assert_eq!(write_mocs(&history, &vault).unwrap(), 0);
+}
@@ -0,0 +1,211 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{create_dir_all, read, read_to_string, remove_dir, remove_file, write};
+use std::io::Error;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use walkdir::WalkDir;
+
+/// The file in the document root listing the files the last run created in it.
+pub const OUTPUTS_FILE: &str = ".get-comments-outputs";
+
+/// The files in the document root at the start of the write phase, once the stale files of
+/// the previous run were removed. The files found after the write phase that are not among
+/// them were created by the run, and the generated files it kept are recorded with them.
+#[derive(Debug, Default)]
+pub struct ManagedOutputs {
+    doc_root: PathBuf,
+    existing: HashSet<PathBuf>,
+    /// The generated files the run is expected to write, left in place by `prepare`.
+    generated: HashSet<PathBuf>,
+    /// The generated files the run wrote or found unchanged.
+    kept: Mutex<BTreeSet<PathBuf>>,
+    /// The folders files were removed from, which are removed as well when they end up empty.
+    emptied: BTreeSet<PathBuf>,
+}
+
+/// Returns the path of a file recorded in `.get-comments-outputs` of `folder`, or `None` for a
+/// line that does not lead down from the folder, such as one holding `..` or an absolute path,
+/// so a tampered record cannot remove files elsewhere.
+pub fn recorded_file(folder: &Path, line: &str) -> Option<PathBuf> {
+    let relative = Path::new(line);
+    let file = folder.join(relative);
+    (relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        && file.starts_with(folder))
+    .then_some(file)
+}
+
+/// Returns every file below a folder, a missing folder having none.
+fn files_below(folder: &Path) -> HashSet<PathBuf> {
+    WalkDir::new(folder)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+//#EPIC Get Lines.ITEM Skip unchanged output files [0]
+//#
+//## Skip unchanged output files
+//#A generated file is only written when its content changed; an identical file is left alone
+//#with its modification time, even when every block is rendered again. Build tools watching
+//#the document root, such as mdBook or Hugo, then only rebuild the pages that changed.
+/// Writes `content` to the file at `path` unless the file already holds it, creating the
+/// folders of the file when needed.
+///
+/// # Returns:
+/// - `Ok(bool)` telling whether the file was written
+pub fn write_if_changed(path: &Path, content: &[u8]) -> Result<bool, Error> {
+    if read(path).is_ok_and(|existing| existing == content) {
+        return Ok(false);
+    }
+    if let Some(folder) = path.parent() {
+        create_dir_all(folder)?;
+    }
+    write(path, content)?;
+    Ok(true)
+}
+
+//#EPIC Get Lines.ITEM Keep hand written files in the document root [0]
+//#
+//## Keep hand written files in the document root
+//#A run no longer deletes the whole document root. The files a run creates are listed in
+//#`.get-comments-outputs`; the next run removes those files, except the generated files of
+//#its own blocks, so stale generated files disappear while notes written by hand next to the
+//#generated files are never touched. `--clean` clears the document root as before.
+impl ManagedOutputs {
+    /// Removes the files the previous run recorded in the document root, except the
+    /// `generated` files of this run, which are compared with their new content when written,
+    /// and remembers the files that are left.
+    ///
+    /// # Returns:
+    /// - `Ok((ManagedOutputs, usize))` with the number of files removed
+    pub fn prepare(
+        doc_root: &str,
+        generated: impl IntoIterator<Item = PathBuf>,
+    ) -> Result<(ManagedOutputs, usize), Error> {
+        let doc_root = PathBuf::from(doc_root);
+        let generated: HashSet<PathBuf> = generated.into_iter().collect();
+        let recorded = read_to_string(doc_root.join(OUTPUTS_FILE)).unwrap_or_default();
+        let mut emptied = BTreeSet::new();
+        let mut removed = 0;
+        let files = recorded
+            .lines()
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| recorded_file(&doc_root, line))
+            .filter(|file| !generated.contains(file));
+        for file in files {
+            if remove_file(&file).is_ok() {
+                removed += 1;
+                if let Some(folder) = file.parent() {
+                    emptied.insert(folder.to_path_buf());
+                }
+            }
+        }
+        let existing = files_below(&doc_root);
+        Ok((
+            ManagedOutputs {
+                doc_root,
+                existing,
+                generated,
+                kept: Mutex::new(BTreeSet::new()),
+                emptied,
+            },
+            removed,
+        ))
+    }
+    /// Records a generated file as written by this run, whether or not its content changed.
+    pub fn keep(&self, path: &Path) {
+        if let Ok(mut kept) = self.kept.lock() {
+            kept.insert(path.to_path_buf());
+        }
+    }
+    /// Removes the generated files `prepare` left in place that the run did not write, such
+    /// as the parts a shorter file no longer has. Records the files created since `prepare`
+    /// and the generated files kept in the document root, and removes the folders emptied on
+    /// the way that are still empty.
+    ///
+    /// # Returns:
+    /// - `Ok(usize)` with the number of files recorded
+    pub fn finish(&self) -> Result<usize, Error> {
+        let kept = match self.kept.lock() {
+            Ok(kept) => kept.clone(),
+            Err(_) => BTreeSet::new(),
+        };
+        let mut emptied = self.emptied.clone();
+        for file in self.generated.iter().filter(|file| !kept.contains(*file)) {
+            if remove_file(file).is_ok()
+                && let Some(folder) = file.parent()
+            {
+                emptied.insert(folder.to_path_buf());
+            }
+        }
+        for folder in emptied.iter().rev() {
+            for folder in folder
+                .ancestors()
+                .take_while(|ancestor| ancestor.starts_with(&self.doc_root))
+                .filter(|ancestor| *ancestor != self.doc_root)
+            {
+                if remove_dir(folder).is_err() {
+                    break;
+                }
+            }
+        }
+        let record = self.doc_root.join(OUTPUTS_FILE);
+        let created: BTreeSet<String> = files_below(&self.doc_root)
+            .into_iter()
+            .filter(|file| {
+                *file != record && (!self.existing.contains(file) || kept.contains(file))
+            })
+            .filter_map(|file| {
+                file.strip_prefix(&self.doc_root)
+                    .ok()
+                    .map(|path| path.to_string_lossy().replace('\\', "/"))
+            })
+            .collect();
+        let lines: Vec<String> = created.iter().map(|file| format!("{file}\n")).collect();
+        create_dir_all(&self.doc_root)?;
+        write(record, lines.concat())?;
+        Ok(created.len())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_managed_outputs() {
+    let doc_root = "target/test-outputs";
+    let _ = std::fs::remove_dir_all(doc_root);
+    create_dir_all(format!("{doc_root}/EPIC A/notes")).unwrap();
+    create_dir_all(format!("{doc_root}/EPIC Old")).unwrap();
+    write(format!("{doc_root}/EPIC A/notes/mine.md"), "by hand").unwrap();
+    write(format!("{doc_root}/EPIC Old/ITEM B.md"), "stale").unwrap();
+    write(format!("{doc_root}/EPIC A.md"), "same").unwrap();
+    write(format!("{doc_root}/EPIC A (part 2).md"), "old").unwrap();
+    write("target/test-outputs-outside.md", "not ours").unwrap();
+    write(
+        format!("{doc_root}/{OUTPUTS_FILE}"),
+        "EPIC Old/ITEM B.md\nEPIC A.md\nEPIC A (part 2).md\n../test-outputs-outside.md\n",
+    )
+    .unwrap();
+
+    let generated = ["EPIC A.md", "EPIC A (part 2).md"].map(|file| Path::new(doc_root).join(file));
+    let (outputs, removed) = ManagedOutputs::prepare(doc_root, generated).unwrap();
+    assert_eq!(removed, 1);
+    let unchanged = Path::new(doc_root).join("EPIC A.md");
+    assert!(!write_if_changed(&unchanged, b"same").unwrap());
+    outputs.keep(&unchanged);
+    let created = Path::new(doc_root).join("EPIC A/ITEM C.md");
+    assert!(write_if_changed(&created, b"new").unwrap());
+    outputs.keep(&created);
+    assert_eq!(outputs.finish().unwrap(), 2);
+
+    assert!(!Path::new(&format!("{doc_root}/EPIC Old")).exists());
+    assert!(!Path::new(&format!("{doc_root}/EPIC A (part 2).md")).exists());
+    assert!(Path::new(&format!("{doc_root}/EPIC A/notes/mine.md")).exists());
+    assert!(Path::new("target/test-outputs-outside.md").exists());
+    assert_eq!(recorded_file(Path::new(doc_root), "/etc/passwd"), None);
+    let record = read_to_string(format!("{doc_root}/{OUTPUTS_FILE}")).unwrap();
+    assert_eq!(record, "EPIC A.md\nEPIC A/ITEM C.md\n");
+}
@@ -1,13 +1,82 @@
-use regex::Regex;
-use std::collections::{BTreeMap, HashMap, HashSet};
-use std::fs::{File, OpenOptions, create_dir_all, remove_dir_all};
-use std::io::{self, BufRead, BufWriter, Error, ErrorKind, Write};
-use walkdir::WalkDir;
+#[cfg(feature = "async")]
+use crate::async_io;
+use crate::background::{FILE_PAUSE, lower_priority};
+use crate::backlinks::resolve_links;
+use crate::block::{Block, Sequences, relative_name};
+use crate::clock::{Clock, format_timestamp};
+use crate::collation::SortOrder;
+use crate::contributors::{contributor_files, write_contributor_pages};
+use crate::coverage::{coverage_by_directory, coverage_report};
+use crate::density::{FileCount, ReportFormat, density_file, write_density_report};
+use crate::diagnostics::{Diagnostic, diagnostics_file, summary_table, write_diagnostics};
+use crate::encoding::{Decoded, SourceEncodings};
+use crate::events::{Event, EventSubscriber, Events};
+use crate::exclude::Exclusions;
+use crate::failure::{FailureKind, RunError, with_snippet};
+use crate::filter::{Filter, apply_filters_except_raw};
+use crate::format::{MarkdownFlavor, OutputFormat};
+use crate::front_matter::{FrontMatterValue, front_matter, write_attributes, write_yaml};
+use crate::git::{changed_paths, last_commit, staged_content, staged_paths};
+use crate::glob::{block_name_matches, path_matches};
+use crate::glossary::{GLOSSARY_FILE, Glossary, TERM_PREFIX, anchor};
+use crate::header::HeaderGrammar;
+use crate::headings::shift_headings;
+use crate::html::{site_files, write_site};
+use crate::incremental::{
+    SourceCache, SourceState, content_hash, dirty_outputs, load_cache, save_cache,
+};
+use crate::index::{IndexColumn, index_files, write_block_tables, write_folder_indexes};
+use crate::interrupt::INTERRUPTED;
+use crate::json::{read_blocks, write_blocks, write_blocks_to, write_comments};
+use crate::links::UrlCheck;
+use crate::markers::{CommentMarker, MarkerConfig, MarkerPosition, SourceLiterals};
+use crate::mdbook::{book_files, chapter_path, write_book};
+use crate::mirror::mirror_tree;
+use crate::obsidian::{Vault, write_mocs};
+use crate::outputs::{ManagedOutputs, write_if_changed};
+use crate::pipeline::{CHANNEL_CAPACITY, read_source_files, walk_source_files};
+use crate::progress::Progress;
+use crate::provenance::{
+    FOOTER_PREFIX, RunMetrics, RunRecord, changes_since, load_manifest, save_manifest,
+    write_metrics,
+};
+use crate::query::BlockQuery;
+use crate::requirements::{REQUIREMENTS_FILE, Requirements, write_requirements_report};
+use crate::run::new_run_id;
+use crate::schema::FieldSchema;
+use crate::separator::Separators;
+use crate::sequence::Sequence;
+use crate::shortcode::expand_lines;
+use crate::similarity::find_similar_blocks;
+use crate::snippet::{INCLUDE_CODE, Snippet, append_code, embed_snippets, snippet_reference};
+use crate::source_link::LinkStyle;
+use crate::staging::StagedRoot;
+use crate::stale::{
+    Anchors, STALE_FILE, find_stale_blocks, load_anchors, save_anchors, write_stale_report,
+};
+use crate::stats::{RunStatistics, write_statistics};
+use crate::structure::{STRUCTURE_FILE, write_structure};
+use crate::tags::{tag_files, write_tag_pages};
+use crate::template::BlockTemplate;
+use crate::timings::Timings;
+use crate::trace::{MatrixFormat, trace_matrix_file, write_trace_matrix};
+use crate::upgrade::write_layout_version;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{create_dir_all, remove_dir_all, remove_file};
+use std::io::{BufRead, BufWriter, Error, ErrorKind, IsTerminal, Write, stderr};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 type Value = String;
 type CommentStart = String;
 
 #[derive(Default, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 enum State {
     #[default]
     CODE,
@@ -15,593 +84,4824 @@ enum State {
     ERROR,
 }
 
+/// What the previous run left in the document root, read before the document root is cleared.
+struct PreviousRun {
+    anchors: Anchors,
+    runs: Vec<RunRecord>,
+    sources: SourceCache,
+}
+
+/// Extracts line blocks from source files and writes them to a tree of Markdown files.
+///
+/// A `Comments` is set up with the builder methods and started with `run`, or with
+/// `comment_in_files`, which takes the required settings as arguments.
+///
+/// ```no_run
+/// use get_comments::Comments;
+///
+/// Comments::new()
+///     .with_source_folder("src")
+///     .with_doc_root("docs")
+///     .with_path_prefixes("EPIC.ITEM")
+///     .with_start_marker("//#")
+///     .with_extensions(&[".rs"])
+///     .run()
+///     .expect("comment extraction is not set up");
+/// ```
 #[derive(Default)]
 pub struct Comments<'a> {
     folder_prefixes: Vec<&'a str>,
     current_state: State,
-    comment_history: HashMap<String, BTreeMap<u16, Vec<String>>>,
+    comment_history: HashMap<String, Sequences>,
     comment: Vec<Value>,
     start_of_comment: CommentStart,
-    log_file: Option<io::BufWriter<File>>,
-    comment_block_names: HashSet<String>,
+    comment_block_names: HashMap<String, (String, String, usize)>,
     current_comment_name: String,
-    line_counter: u16,
-    comment_line_start: u16,
+    line_counter: usize,
+    comment_line_start: usize,
+    source_root: String,
+    reproducible: bool,
+    filters: Vec<Filter>,
+    index_columns: Option<Vec<IndexColumn>>,
+    folder_index: bool,
+    structure: bool,
+    cross_references: bool,
+    obsidian: bool,
+    vault: Option<Vault>,
+    clean: bool,
+    atomic: bool,
+    /// The document root of the run, which differs from the one the blocks are written to
+    /// when the run is staged, see `with_atomic`.
+    published_root: String,
+    managed: Option<ManagedOutputs>,
+    backlinks: HashMap<String, BTreeSet<String>>,
+    similarity_threshold: Option<f64>,
+    url_check: Option<UrlCheck>,
+    glossary: Option<Glossary>,
+    contributors: bool,
+    git_metadata: bool,
+    stale_months: Option<u32>,
+    pending_anchor: Option<(String, Sequence)>,
+    /// The block that embeds the source lines after it, with the number of lines, see
+    /// `append_code`.
+    pending_code: Option<(String, Sequence, usize)>,
+    included_code: Vec<String>,
+    /// The snippets of the run by name, and the regions of the file being parsed that are not
+    /// ended yet with their first line.
+    snippets: BTreeMap<String, Snippet>,
+    open_snippets: Vec<(String, usize, Vec<String>)>,
+    /// The errors of the blocks of the file being parsed that could not be stored, see
+    /// `reject_block`.
+    block_errors: Vec<Error>,
+    continuations: Vec<(String, Sequence, Block)>,
+    /// The block path and source file of every continuation merged into a block of another
+    /// file and of every snippet embedded in a block, so the generated file depends on both
+    /// source files.
+    dependencies: Vec<(String, String)>,
+    density_format: Option<ReportFormat>,
+    diagnostics: Vec<Diagnostic>,
+    diagnostics_format: Option<ReportFormat>,
+    trace_format: Option<MatrixFormat>,
+    scanned_files: BTreeMap<String, FileCount>,
+    coverage: bool,
+    coverage_min: Option<f64>,
+    header_grammar: HeaderGrammar,
+    infer_path: bool,
+    raw_lines: BTreeSet<usize>,
+    front_matter: bool,
+    run_id: String,
+    started_at: String,
+    clock: Option<Arc<dyn Clock>>,
+    manifest: bool,
+    run_footer: bool,
+    since_run: Option<String>,
+    doc_root: String,
+    path_prefixes: &'a str,
+    extensions: Vec<String>,
+    end_of_comment: Option<String>,
+    /// The text after the start marker that ends a line block, see `with_block_end`.
+    block_end: Option<String>,
+    comment_indent: String,
+    marker_config: Option<MarkerConfig>,
+    marker_position: MarkerPosition,
+    /// The literal syntax of the file being parsed and the literal its lines so far left open,
+    /// see `MarkerPosition::marked_text`.
+    literals: SourceLiterals,
+    output_formats: Vec<OutputFormat>,
+    markdown_flavor: MarkdownFlavor,
+    separators: Separators,
+    sequence_headings: bool,
+    heading_offset: usize,
+    encodings: SourceEncodings,
+    default_marker: Option<CommentMarker>,
+    slowest_files: Option<usize>,
+    timings: Timings,
+    progress: bool,
+    /// The flag that stops the run, `interrupt` when `None`, see `with_stop_flag`.
+    stop: Option<&'a AtomicBool>,
+    /// Whether the run statistics are printed, and the file they are written to as JSON.
+    statistics: bool,
+    statistics_file: Option<String>,
+    skipped_files: usize,
+    rejected_blocks: Vec<(String, String)>,
+    incremental: bool,
+    since_revision: Option<String>,
+    only_sources: Option<HashSet<PathBuf>>,
+    /// Whether the source files are read as they are staged in git instead of from the work
+    /// tree, see `check_files`.
+    staged_sources: bool,
+    only_names: Vec<String>,
+    skip_names: Vec<String>,
+    source_hashes: BTreeMap<String, String>,
+    background: bool,
+    exclude_globs: Vec<String>,
+    gitignore: bool,
+    no_follow_links: bool,
+    max_depth: Option<usize>,
+    path_overrides: HashMap<String, String>,
+    auto_sequence: bool,
+    auto_numbered: HashSet<(String, Sequence)>,
+    generated_globs: Vec<String>,
+    generated_file: bool,
+    mirrors: Vec<String>,
+    link_style: LinkStyle,
+    sort_order: SortOrder,
+    fields: Option<FieldSchema>,
+    template: Option<BlockTemplate>,
+    max_file_size: Option<usize>,
+    requirements: Option<Requirements>,
+    strict_requirements: bool,
+    tag_pages: bool,
+    hard_links: bool,
+    failures: RunError,
+    events: Events,
 }
 
 impl<'a> Comments<'a> {
-    //#EPIC Get Lines.ITEM Write the comment lines to the file path and name [0]
+    /// Creates a parser with every optional feature off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the folder that is scanned for source files.
+    pub fn with_source_folder(mut self, folder_name: &str) -> Self {
+        self.source_root = folder_name.to_string();
+        self
+    }
+    /// Sets the document root the Markdown files are written to. It is cleared on every run.
+    pub fn with_doc_root(mut self, doc_root: &str) -> Self {
+        self.doc_root = doc_root.to_string();
+        self
+    }
+    /// Sets the dot delimited prefixes every level of a block path must start with, such as
+    /// `EPIC.ITEM`.
+    pub fn with_path_prefixes(mut self, path_prefixes: &'a str) -> Self {
+        self.path_prefixes = path_prefixes;
+        self
+    }
+    /// Sets the string that starts every line of a line block, such as `//#`.
+    pub fn with_start_marker(mut self, start: &str) -> Self {
+        self.start_of_comment = start.to_string();
+        self
+    }
+    /// Sets the endings of the file names that are scanned, such as `.rs`.
+    pub fn with_extensions(mut self, extensions: &[&str]) -> Self {
+        self.extensions = extensions
+            .iter()
+            .map(|extension| extension.to_string())
+            .collect();
+        self
+    }
+    /// Switches from line blocks to block comments, which run from the start marker to the
+    /// given end marker, such as `/*#` ... `*/` or `<!--#` ... `-->`.
+    ///
+    /// The text after the start marker up to the end of the line, or up to the end marker when
+    /// it is on the same line, is the block header. The lines after it are the block body; they
+    /// need no marker and lose the indentation of the line holding the start marker. Text in
+    /// front of the end marker on the closing line still belongs to the body. Raw regions only
+    /// apply to line blocks.
+    pub fn with_end_marker(mut self, end: Option<&str>) -> Self {
+        self.end_of_comment = end.map(str::to_string);
+        self
+    }
+    //#EPIC Get Lines.ITEM End a block explicitly [0]
     //#
-    //## Write Comment Block To File
-    //#Create the file path and write out the comment block to the file having file name.
-    /// Writes comment lines to a markdown file in the specified hierarchical directory structure.
+    //## End a block explicitly
+    //#A line block ends at the first line without the start marker. With `-block-end end` the
+    //#line `//#end` ends it too, so marked lines after it, such as commented-out code that
+    //#follows the documentation, are left out. They are skipped up to the next line without
+    //#the start marker, after which a new block may start, or up to the next block header,
+    //#which starts a new block right away.
+    /// Sets the text that ends a line block when it follows the start marker on a line of its
+    /// own, such as `end` for `//#end`. The marked lines after it are skipped up to the next
+    /// line without the start marker or the next block header. Block comments end at their end
+    /// marker only.
+    pub fn with_block_end(mut self, end: Option<&str>) -> Self {
+        self.block_end = end.map(|end| end.trim().to_string());
+        self
+    }
+    /// Sets the comment markers per file extension.
     ///
-    /// This function takes a dot-delimited file path, creates the necessary directory structure,
-    /// and appends comment lines to a markdown file. It's the core file output operation for
-    /// the documentation generation system.
+    /// Files whose extension is in the configuration are scanned with its marker, every other
+    /// file with the start and end marker of the run. The configured extensions are scanned in
+    /// addition to the extensions of the run.
+    pub fn with_marker_config(mut self, config: Option<MarkerConfig>) -> Self {
+        self.marker_config = config;
+        self
+    }
+    /// Sets where on a line the start marker may be found: at column 0 only, after
+    /// whitespace, which is the default, or after code, where a marker inside a string literal
+    /// may be ignored by the literal syntax of the file's language.
     ///
-    /// # Process Flow:
-    /// 1. **Path Validation**: Calls `is_valid_folder_path` to validate the hierarchical structure
-    /// 2. **Directory Creation**: Creates all necessary directories in the path hierarchy
-    /// 3. **File Preparation**: Opens the markdown file in append mode (creates if doesn't exist)
-    /// 4. **Content Writing**: Writes all comment lines followed by a blank line
-    /// 5. **Buffered Output**: Uses BufWriter for efficient file I/O operations
+    /// Directives and raw regions follow the same rule as block headers and block lines.
+    pub fn with_marker_position(mut self, position: MarkerPosition) -> Self {
+        self.marker_position = position;
+        self
+    }
+    /// Sets the formats in which the blocks are written, Markdown when none are given.
     ///
-    /// # Path Processing:
-    /// - **Input Format**: Dot-delimited path (e.g., "doc_root.EPIC.ITEM.TASK.Description")
-    /// - **Directory Creation**: Converts dots to directory separators and creates folders
-    /// - **File Naming**: The last component becomes the markdown filename
-    /// - **Output Example**: "doc_root/EPIC/ITEM/TASK/Description.md"
+    /// `OutputFormat::Json` writes the whole history to `comments.json` in the document root,
+    /// see `json::write_comments`, and `OutputFormat::MdBook` an mdBook, see
+    /// `mdbook::write_book`. The Markdown files are only written when `OutputFormat::Markdown`
+    /// is one of the formats.
+    pub fn with_output_formats(mut self, formats: Vec<OutputFormat>) -> Self {
+        self.output_formats = formats;
+        self
+    }
+    /// Sets the Markdown dialect of the generated Markdown files, CommonMark by default.
+    pub fn with_markdown_flavor(mut self, flavor: MarkdownFlavor) -> Self {
+        self.markdown_flavor = flavor;
+        self
+    }
+    /// Sets what is written between two blocks of a generated file, per output format. One
+    /// blank line by default.
+    pub fn with_separators(mut self, separators: Separators) -> Self {
+        self.separators = separators;
+        self
+    }
+    //#EPIC Get Lines.ITEM Sequence headings [0]
+    //#
+    //## Sequence headings
+    //#With `--sequence-headings` every block of a generated file gets a heading naming its
+    //#Sequence number and where it comes from, as in `## Sequence 2 — from src/foo.rs:42`, so
+    //#a file gathering blocks from many places shows where each part starts.
+    /// Sets whether every block of a generated file is preceded by a heading with its Sequence
+    /// number, source file and line.
+    pub fn with_sequence_headings(mut self, sequence_headings: bool) -> Self {
+        self.sequence_headings = sequence_headings;
+        self
+    }
+    /// Sets the number of levels the Markdown headings of every block body move down when
+    /// the blocks are written, see `shift_headings`. 0 by default.
+    pub fn with_heading_offset(mut self, offset: usize) -> Self {
+        self.heading_offset = offset;
+        self
+    }
+    /// Sets how the `[SOURCE FILE:]` link of every block points to its source file, see
+    /// `LinkStyle`. A `file:///` link by default.
+    pub fn with_link_style(mut self, link_style: LinkStyle) -> Self {
+        self.link_style = link_style;
+        self
+    }
+    /// Sets the template every block of the Markdown files and HTML pages is written with,
+    /// instead of the `[SOURCE FILE:]` line, the body and a blank line. `None` keeps that
+    /// layout.
+    pub fn with_block_template(mut self, template: Option<BlockTemplate>) -> Self {
+        self.template = template;
+        self
+    }
+    /// Splits a generated file that is larger than the given number of bytes into parts,
+    /// `ITEM B.md`, `ITEM B (part 2).md` and so on, linked to each other. `None` never splits.
+    pub fn with_max_file_size(mut self, max_file_size: Option<usize>) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+    /// Returns the backends of the formats that write one generated file per block name.
+    fn doc_writers(&self) -> Vec<&'static dyn DocWriter> {
+        let mut doc_writers: Vec<&'static dyn DocWriter> = Vec::new();
+        if self.writes_format(OutputFormat::Markdown) {
+            doc_writers.push(match self.markdown_flavor {
+                MarkdownFlavor::CommonMark => &MarkdownWriter,
+                MarkdownFlavor::Myst => &MystWriter,
+            });
+        }
+        if self.writes_format(OutputFormat::AsciiDoc) {
+            doc_writers.push(&AsciiDocWriter);
+        }
+        doc_writers
+    }
+    /// Returns whether the blocks are written in the given format.
+    fn writes_format(&self, format: OutputFormat) -> bool {
+        match self.output_formats.is_empty() {
+            true => format == OutputFormat::Markdown,
+            false => self.output_formats.contains(&format),
+        }
+    }
+    /// Sets the encodings of source files that are not UTF-8.
     ///
-    /// # Parameters:
-    /// - `folder_prefixes`: Expected folder hierarchy for validation
-    /// - `file_path_and_name`: Dot-delimited path where file should be created
-    /// - `lines`: Vector of comment lines to write to the file
+    /// A source file whose path matches one of the globs is transcoded to UTF-8 on read, every
+    /// other source file is read in the encoding of its byte order mark or the default
+    /// encoding. Invalid bytes are replaced with a warning, see `SourceEncodings::decode`.
+    pub fn with_encodings(mut self, encodings: SourceEncodings) -> Self {
+        self.encodings = encodings;
+        self
+    }
+    /// Sets the globs of the files and folders below the source folder that are not scanned,
+    /// such as `target` or `vendor/**`, see `Exclusions::new`.
+    pub fn with_exclusions(mut self, globs: Vec<String>) -> Self {
+        self.exclude_globs = globs;
+        self
+    }
+    /// Skips the files and folders that git ignores, so build output and dependencies listed
+    /// in `.gitignore` are not scanned.
+    pub fn with_gitignore(mut self, gitignore: bool) -> Self {
+        self.gitignore = gitignore;
+        self
+    }
+    /// Sets whether the walk of the source folder follows symbolic links, and the deepest
+    /// level it descends to, the files of the source folder being level 1. Symbolic link
+    /// loops are never followed.
+    pub fn with_walk_limits(mut self, follow_links: bool, max_depth: Option<usize>) -> Self {
+        self.no_follow_links = !follow_links;
+        self.max_depth = max_depth;
+        self
+    }
+    /// Returns the exclusions and limits of the walk of the source folder.
+    fn exclusions(&self, folder_name: &str) -> Exclusions {
+        Exclusions::new(
+            folder_name,
+            &self.exclude_globs,
+            self.gitignore,
+            |message| self.events.warn(message),
+        )
+        .with_only(self.only_sources.clone())
+        .with_limits(!self.no_follow_links, self.max_depth)
+    }
+    /// Sets the globs of the machine-generated source files, such as `*.pb.rs` or
+    /// `generated/**`, matched like the globs of `with_exclusions`.
     ///
-    /// # Returns:
-    /// - `Ok(())` on successful file creation and writing
-    /// - `Err(std::io::Error)` if directory creation, file opening, or writing fails
+    /// The blocks of a generated file, or of a file holding the `get-comments:generated`
+    /// directive, are written after the hand-written blocks of the same generated file, below
+    /// a `Generated` heading, and the files holding only generated blocks are listed last in
+    /// the index tables, so generated stubs do not drown hand-written documentation.
+    pub fn with_generated_sources(mut self, globs: Vec<String>) -> Self {
+        self.generated_globs = globs;
+        self
+    }
+    /// Returns whether a source file is machine-generated by one of its globs.
+    fn is_generated_source(&self, file_name: &str) -> bool {
+        let relative = Path::new(file_name)
+            .strip_prefix(self.source_root.as_str())
+            .unwrap_or(Path::new(file_name))
+            .to_string_lossy();
+        self.generated_globs
+            .iter()
+            .any(|glob| path_matches(glob, &relative))
+    }
+    /// Enables the timing report, printed at the end of the run, with the wall time and item
+    /// count of the walk, parse, validate and write phases and the `slowest` files that took
+    /// the longest to read and parse.
+    pub fn with_timings(mut self, slowest: Option<usize>) -> Self {
+        self.slowest_files = slowest;
+        self
+    }
+    /// Records the wall time of the validate and write phases, and prints the timing report
+    /// and the run statistics when they are enabled.
+    fn finish_timings(&mut self, doc_root: &str, validate: Duration, write: Duration) {
+        let blocks = self.comment_history.values().map(Sequences::len).sum();
+        let files = self.comment_history.len();
+        self.timings
+            .add_phase("validate", validate, blocks, "blocks");
+        self.timings.add_phase("write", write, files, "files");
+        if let Some(slowest) = self.slowest_files {
+            self.events.inform(self.timings.report(slowest).trim_end());
+        }
+        self.finish_statistics(doc_root);
+    }
+    /// Prints the summary of the run and writes it as JSON to `json_file`: the files scanned
+    /// and skipped, the Sequences of every block name, the comment lines, the rejected blocks
+    /// with their reasons and the time of every phase.
+    pub fn with_statistics(mut self, print: bool, json_file: Option<String>) -> Self {
+        self.statistics = print || json_file.is_some();
+        self.statistics_file = json_file;
+        self
+    }
+    /// Prints the run statistics and writes them to the statistics file, when enabled.
+    fn finish_statistics(&mut self, doc_root: &str) {
+        if !self.statistics {
+            return;
+        }
+        let statistics = RunStatistics {
+            files_scanned: self.scanned_files.len(),
+            files_skipped: self.skipped_files,
+            sequences: self
+                .comment_history
+                .iter()
+                .map(|(name, blocks)| (relative_name(name, doc_root).to_string(), blocks.len()))
+                .collect(),
+            comment_lines: self
+                .comment_history
+                .values()
+                .flat_map(|blocks| blocks.values())
+                .map(|block| block.source_lines.len().max(block.lines.len()))
+                .sum(),
+            rejected: self.rejected_blocks.clone(),
+            phases: self.timings.phases(),
+        };
+        self.events.inform(statistics.report().trim_end());
+        if let Some(file) = &self.statistics_file
+            && let Err(error) = write_statistics(&statistics, file)
+        {
+            self.failures
+                .report("write statistics", error, &self.events);
+        }
+    }
+    /// Enables incremental runs, which only write the generated files whose source files
+    /// changed.
     ///
-    /// # File Operations:
-    /// - **Append Mode**: Files are opened in append mode to support multiple comment blocks
-    /// - **Create Flag**: Files are created if they don't exist
-    /// - **Buffered Writing**: Uses BufWriter for performance with multiple write operations
-    /// - **Blank Line**: Adds a trailing blank line to separate comment blocks
+    /// Every source file is still parsed, so blocks are validated across the whole tree, but
+    /// the document root is not cleared. A generated file is written again only when one of
+    /// its source files was added, changed or removed since the previous run, and generated
+    /// files that are no longer produced are removed. The fingerprint of every source file,
+    /// with the generated files it has blocks in, is kept in `.get-comments-cache` in the
+    /// document root. Changing other settings between runs needs a full run.
+    pub fn with_incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+    /// Restricts the run to the source files changed since a git revision, such as `HEAD` in
+    /// a pre-commit hook, see `git::changed_paths`.
     ///
-    /// # Note:
-    /// - This function is called by `write_history` for each comment block Sequence
-    /// - Multiple Sequences of the same comment block are appended to the same file
-    /// - The directory structure mirrors the hierarchical organization of comment blocks
-    /// - File operations are atomic within this function call
-    fn write_out_to_file(
+    /// The run is incremental, see `with_incremental`, and only the changed source files and
+    /// the other source files that had blocks in the same generated files in the previous run
+    /// are read, so those generated files are complete when they are written again. The other
+    /// generated files are left as they are. Without a previous incremental run, or when git
+    /// cannot list the changes, every source file is read. Reports built from the whole
+    /// history, such as the indexes, only hold the blocks of the files that were read.
+    pub fn with_since_revision(mut self, revision: Option<String>) -> Self {
+        self.since_revision = revision;
+        self
+    }
+    /// Returns whether the run only writes the generated files whose source files changed.
+    fn is_incremental(&self) -> bool {
+        self.incremental || self.since_revision.is_some()
+    }
+    //#EPIC Get Lines.ITEM Extract some of the blocks [0]
+    //#
+    //## Extract some of the blocks
+    //#`-only` and `-skip` take globs on the block names, such as `-only "EPIC Get Lines.*"`, and
+    //#only the generated files of the selected blocks are written. The rest of the document root
+    //#is left as it is, so the documentation of one EPIC can be generated again on its own.
+    /// Restricts the generated files written to those of the block names selected by globs,
+    /// see `glob::block_name_matches`.
+    ///
+    /// A block name is selected when it or a level above it matches one of the `only` globs,
+    /// or `only` is empty, and neither it nor a level above it matches one of the `skip` globs.
+    /// The document root is not cleared: the generated files of the selected block names are
+    /// written again and every other file is left alone. The reports are still built from all
+    /// blocks.
+    pub fn with_name_selection(mut self, only: Vec<String>, skip: Vec<String>) -> Self {
+        self.only_names = only;
+        self.skip_names = skip;
+        self
+    }
+    /// Returns whether only the generated files of some block names are written.
+    fn selects_names(&self) -> bool {
+        !self.only_names.is_empty() || !self.skip_names.is_empty()
+    }
+    /// Returns whether the generated file of a history key is written, see
+    /// `with_name_selection`.
+    fn is_selected(&self, key: &str, doc_root: &str) -> bool {
+        let name = relative_name(key, doc_root);
+        let levels: Vec<&str> = name.split('.').collect();
+        let matches = |globs: &[String]| {
+            (1..=levels.len()).any(|depth| {
+                let name = levels[..depth].join(".");
+                globs.iter().any(|glob| block_name_matches(glob, &name))
+            })
+        };
+        (self.only_names.is_empty() || matches(&self.only_names)) && !matches(&self.skip_names)
+    }
+    /// Returns the source files a `-since` run reads: the files changed since the revision,
+    /// and the files sharing a generated file with one of them in the previous run.
+    ///
+    /// # Returns:
+    /// - `None` when every source file is read
+    fn changed_sources(
         &self,
-        folder_prefixes: &Vec<&str>,
-        file_path_and_name: &str,
-        lines: &Vec<String>,
-    ) -> Result<(), std::io::Error> {
-        // file_name is a '.' delimited slice. Each slice is a folder starting
-        // from the current `working folder
-        let mut path: Vec<&str> = file_path_and_name.split(".").collect();
-        if let Err(message) = self.is_valid_folder_path(folder_prefixes, file_path_and_name) {
-            return Err(Error::new(ErrorKind::Other, message));
+        folder_name: &str,
+        previous: &SourceCache,
+    ) -> Option<HashSet<PathBuf>> {
+        let revision = self.since_revision.as_ref()?;
+        if previous.is_empty() {
+            self.events
+                .inform("No previous incremental run in the document root: reading every file");
+            return None;
         }
-
-        if let Some(file) = path.pop() {
-            create_dir_all(path.join("/"))?;
-            path.push(file);
-            let path_and_file_name = format!("{}.md", path.join("/"));
-            let file = OpenOptions::new()
-                .append(true)
-                .create(true)
-                .open(path_and_file_name)?;
-            let mut writer = BufWriter::new(file);
-            for line in lines {
-                writeln!(writer, "{}", line)?;
+        let changed: HashSet<PathBuf> = match changed_paths(folder_name, revision) {
+            Ok(paths) => paths
+                .iter()
+                .map(|path| Path::new(folder_name).join(path))
+                .collect(),
+            Err(error) => {
+                self.events
+                    .warn(format!("Reading every file, since {revision} {error}"));
+                return None;
+            }
+        };
+        let outputs: HashSet<&String> = previous
+            .iter()
+            .filter(|(path, _)| changed.contains(Path::new(path)))
+            .flat_map(|(_, state)| &state.outputs)
+            .collect();
+        let sharing = previous
+            .iter()
+            .filter(|(_, state)| state.outputs.iter().any(|output| outputs.contains(output)))
+            .map(|(path, _)| PathBuf::from(path));
+        let files: HashSet<PathBuf> = changed.iter().cloned().chain(sharing).collect();
+        self.events.inform(&format!(
+            "Since {revision}: reading {} changed and related files",
+            files.len()
+        ));
+        Some(files)
+    }
+    /// Sets the clock the start of a run and the age of source files in the stale check are
+    /// read from, the system clock by default.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+    /// Enables background runs, which keep out of the way of builds and editors.
+    ///
+    /// A background run lowers the CPU and I/O priority of the process, see `lower_priority`,
+    /// and waits a few milliseconds after every file in the walker and writer stages.
+    pub fn with_background(mut self, background: bool) -> Self {
+        self.background = background;
+        self
+    }
+    /// Sets the subscriber that receives the events of every run, such as every parsed file,
+    /// every block found and every generated file written, see `Event`.
+    pub fn with_subscriber(mut self, subscriber: impl EventSubscriber + 'static) -> Self {
+        let quiet = self.events.is_quiet();
+        self.events = Events::new(subscriber);
+        self.events.set_quiet(quiet);
+        self
+    }
+    /// Keeps everything but errors off the console: warnings, reports such as the timings,
+    /// and the progress bar. The subscriber still receives every event.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.events.set_quiet(quiet);
+        self
+    }
+    /// Draws a progress bar of the parsed files on standard error, when it is a terminal.
+    /// The bar starts at once, and shows the total once the walker stage found every file.
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+    /// Stops the run when `flag` is set instead of when `interrupt` is called, so a service
+    /// embedding the extractor can cancel one run without touching the others. The flag is
+    /// cleared when the run begins.
+    pub fn with_stop_flag(mut self, flag: &'a AtomicBool) -> Self {
+        self.stop = Some(flag);
+        self
+    }
+    /// Returns the clock of the run, see `with_clock`.
+    fn clock(&self) -> &dyn Clock {
+        match &self.clock {
+            Some(clock) => clock.as_ref(),
+            None => &SystemTime::now,
+        }
+    }
+    /// Returns the flag that stops the run, see `with_stop_flag`.
+    fn stop_flag(&self) -> &'a AtomicBool {
+        self.stop.unwrap_or(&INTERRUPTED)
+    }
+    /// Returns whether the run was asked to stop, see `interrupt`.
+    fn is_interrupted(&self) -> bool {
+        self.stop_flag().load(Ordering::SeqCst)
+    }
+    /// Returns the progress bar of a run that parses `total` files, when it is shown.
+    fn start_progress(&self, total: Option<usize>) -> Option<Progress> {
+        (self.progress && !self.events.is_quiet() && stderr().is_terminal())
+            .then(|| Progress::new(total))
+    }
+    /// Returns the pause between two files in the walker and writer stages.
+    fn file_pause(&self) -> Duration {
+        match self.background {
+            true => FILE_PAUSE,
+            false => Duration::ZERO,
+        }
+    }
+    /// Returns the fingerprint of every scanned source file with the generated files it has
+    /// blocks in, and, in a `-since` run, that of the previous run for the files not read.
+    fn source_cache(&self, previous: &SourceCache) -> SourceCache {
+        let mut cache: SourceCache = self
+            .source_hashes
+            .iter()
+            .map(|(path, hash)| {
+                let state = SourceState {
+                    hash: hash.clone(),
+                    ..Default::default()
+                };
+                (path.clone(), state)
+            })
+            .collect();
+        for (name, blocks) in &self.comment_history {
+            for block in blocks.values() {
+                if let Some(state) = cache.get_mut(&*block.path) {
+                    state.outputs.insert(name.trim().to_string());
+                }
             }
-            writeln!(writer, "")?;
         }
-        Ok(())
+        for (name, path) in &self.dependencies {
+            if let Some(state) = cache.get_mut(path) {
+                state.outputs.insert(name.trim().to_string());
+            }
+        }
+        // the files a `-since` run did not read are as the previous run found them
+        if let Some(only) = &self.only_sources {
+            for (path, state) in previous {
+                if !only.contains(Path::new(path)) {
+                    cache.entry(path.clone()).or_insert_with(|| state.clone());
+                }
+            }
+        }
+        cache
     }
-    /// Extracts Sequence number from comment block names and returns the sanitized name.
+    /// Returns the report files the write phase may write to the document root besides the
+    /// generated files of the blocks. `clear_outputs` leaves them in place, so a report that
+    /// did not change is not written again.
+    fn report_files(&self, doc_root: &str) -> Vec<PathBuf> {
+        let history = &self.comment_history;
+        let root = Path::new(doc_root);
+        let mut files = Vec::new();
+        if self.writes_format(OutputFormat::Html) {
+            let names = history.keys().map(|name| relative_name(name, doc_root));
+            files.extend(site_files(names, doc_root));
+        }
+        if self
+            .glossary
+            .as_ref()
+            .is_some_and(Glossary::has_definitions)
+        {
+            files.push(root.join(GLOSSARY_FILE));
+        }
+        if self.stale_months.is_some() {
+            files.push(root.join(STALE_FILE));
+        }
+        if self.tag_pages {
+            files.extend(tag_files(history, doc_root));
+        }
+        if self.requirements.is_some() {
+            files.push(root.join(REQUIREMENTS_FILE));
+        }
+        if let Some(format) = self.density_format {
+            files.push(density_file(doc_root, format));
+        }
+        if let Some(format) = self.trace_format {
+            files.push(trace_matrix_file(doc_root, format));
+        }
+        if let Some(format) = self.diagnostics_format {
+            files.push(diagnostics_file(doc_root, format));
+        }
+        if self.contributors {
+            files.extend(contributor_files(history, doc_root));
+        }
+        if self.index_columns.is_some() || self.folder_index {
+            files.extend(index_files(history, doc_root, self.folder_index));
+        }
+        if self.structure {
+            files.push(root.join(STRUCTURE_FILE));
+        }
+        files
+    }
+    /// Records the files of a report as written by this run, so the document root keeps
+    /// them, see `ManagedOutputs::keep`.
+    fn keep_outputs(&self, files: impl IntoIterator<Item = PathBuf>) {
+        if let Some(managed) = &self.managed {
+            files.into_iter().for_each(|file| managed.keep(&file));
+        }
+    }
+    /// Prepares the document root for the write phase.
     ///
-    /// This function parses comment block names that follow the pattern "BlockName [N]"
-    /// where N is a Sequence number in brackets at the end of the string. It extracts
-    /// both the Sequence number and the base block name for separate handling.
+    /// # Returns:
+    /// - `None` when every generated file is written, after removing the files the previous
+    ///   run created, see `ManagedOutputs`, or with `clean` the whole document root
+    /// - `Some(dirty)` in incremental mode, after removing the generated files of removed
+    ///   blocks and the parts of the files that must be written again; only these are written
+    fn clear_outputs(
+        &mut self,
+        doc_root: &str,
+        previous: &PreviousRun,
+    ) -> Option<BTreeSet<String>> {
+        if self.obsidian {
+            self.vault = Some(Vault::open(&self.comment_history, doc_root));
+        }
+        if !self.is_incremental() && !self.selects_names() {
+            if self.vault.is_some() {
+                return None;
+            }
+            if self.clean {
+                let _ = remove_dir_all(doc_root);
+                return None;
+            }
+            let mut generated = Vec::new();
+            for name in self.comment_history.keys() {
+                for doc_writer in self.doc_writers() {
+                    let path = generated_path(name, doc_writer.extension());
+                    generated.extend(
+                        (2..)
+                            .map(|part| part_path(&path, part))
+                            .take_while(|part| part.exists()),
+                    );
+                    generated.push(path);
+                }
+            }
+            if self.writes_format(OutputFormat::MdBook) {
+                generated.extend(
+                    self.comment_history
+                        .keys()
+                        .map(|name| chapter_path(doc_root, relative_name(name, doc_root))),
+                );
+                generated.extend(book_files(doc_root));
+            }
+            generated.extend(self.report_files(doc_root));
+            match ManagedOutputs::prepare(doc_root, generated) {
+                Ok((outputs, _)) => self.managed = Some(outputs),
+                Err(error) => self.failures.report("clear outputs", error, &self.events),
+            }
+            return None;
+        }
+        let mut dirty = match self.is_incremental() {
+            true => dirty_outputs(&previous.sources, &self.source_cache(&previous.sources)),
+            false => self.comment_history.keys().cloned().collect(),
+        };
+        dirty.retain(|name| self.is_selected(name, doc_root));
+        for name in &dirty {
+            for doc_writer in self.doc_writers() {
+                let path = generated_path(name, doc_writer.extension());
+                // A file written again is compared with its new content instead.
+                if !self.comment_history.contains_key(name) {
+                    let _ = remove_file(&path);
+                }
+                for part in 2.. {
+                    if remove_file(part_path(&path, part)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        self.events.inform(&format!(
+            "{}: writing {} of {} generated files",
+            match self.is_incremental() {
+                true => "Incremental",
+                false => "Selected",
+            },
+            dirty
+                .iter()
+                .filter(|name| self.comment_history.contains_key(*name))
+                .count(),
+            self.comment_history.len()
+        ));
+        Some(dirty)
+    }
+    /// Returns the extensions to scan: the given ones followed by the configured ones.
+    pub fn scanned_extensions(&self, file_extensions: &[&str]) -> Vec<String> {
+        let configured = self
+            .marker_config
+            .iter()
+            .flat_map(|config| config.extensions());
+        file_extensions
+            .iter()
+            .copied()
+            .chain(configured)
+            .map(str::to_string)
+            .collect()
+    }
+    /// Selects the comment marker for the file from the marker configuration, falling back on
+    /// the marker of the run.
     ///
-    /// # Pattern Matching:
-    /// - **Regex Pattern**: `r"\[\d+\]$"` - matches numbers in brackets at string end
-    /// - **Examples**:
-    ///   - "EPIC.Get Lines.ITEM Test Block [1]" → Sequence=1, name="EPIC.Get Lines.ITEM Test Block"
-    ///   - "Simple Comment [42]" → Sequence=42, name="Simple Comment"
-    ///   - "No Sequence" → Error: "No Sequence number exist in name of block"
+    /// # Returns:
+    /// - `false` when the marker configuration has no marker for the file and the run has
+    ///   none either, so the file cannot be parsed
+    fn select_marker(&mut self, file_name: &str) -> bool {
+        let Some(config) = &self.marker_config else {
+            return true;
+        };
+        match config
+            .marker_for(file_name)
+            .or(self.default_marker.as_ref())
+            .cloned()
+        {
+            Some(marker) => {
+                self.start_of_comment = marker.start;
+                self.end_of_comment = marker.end;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Returns the blocks found by the last run, keyed by the dot delimited block path
+    /// prefixed with the document root, with the Sequences of every path in Sequence order.
     ///
-    /// # Extraction Process:
-    /// 1. **Regex Matching**: Finds Sequence number pattern at end of string
-    /// 2. **Sequence Parsing**: Extracts number from brackets and converts to u16
-    /// 3. **Validation**: Ensures Sequence number exists and is valid
-    /// 4. **Name Sanitized**: Removes Sequence suffix to get clean block name
+    /// Every `Block` holds both the marker stripped source lines and the rendered lines, so
+    /// embedding applications can choose the representation they consume.
+    pub fn history(&self) -> &HashMap<String, Sequences> {
+        &self.comment_history
+    }
+    /// Returns a query view on the blocks found by the last run, with names relative to the
+    /// document root, see `BlockQuery`.
+    pub fn query(&self) -> BlockQuery<'_> {
+        BlockQuery::new(&self.comment_history, &self.doc_root)
+    }
+    //#EPIC Get Lines.ITEM Parse sources held in memory [0]
+    //#
+    //## Parse sources held in memory
+    //#`parse_str` and `parse_reader` run the extraction state machine over text that does not
+    //#come from the source folder, so the parser can be tested and fuzzed without files on disk.
+    /// Parses the source text of one file into the history, with the start marker, end marker
+    /// and path prefixes of the builder methods. The blocks are added to those parsed before,
+    /// so several files can be parsed one after the other and then looked at with `query`.
+    /// Names are not validated against the path prefixes, as `check_files` does.
     ///
     /// # Parameters:
-    /// - `a_string`: Comment block name string that may contain Sequence suffix
+    /// - `name`: The file name of the text, which selects the marker of a marker configuration
+    ///   and is the source file of its blocks
+    /// - `content`: The source text
     ///
     /// # Returns:
-    /// - `Ok((u16, String))` - Tuple containing (sequence_number, sanitized_block_name)
-    /// - `Err(Error)` - If no Sequence number is found in the string
-    ///
-    /// # Error Conditions:
-    /// - No Sequence number pattern found at the end of the string
-    /// - Sequence number cannot be parsed as u16 (though regex ensures it's numeric)
-    ///
-    /// # Use Cases:
-    /// - Used by `write_out_all_history` to separate Sequence from block name for storage
-    /// - Enables multiple Sequences of the same comment block to be tracked and organized
-    /// - Supports versioned documentation where blocks can be updated over time
-    ///
-    /// # Note:
-    /// - The Sequence number must be at the very end of the string in brackets
-    /// - The regex ensures only numeric values are accepted as Sequence numbers
-    /// - This enables the system to maintain Sequence history for comment blocks
-    /// - Sequence numbers are used to order comment blocks chronologically in output
-    fn strip_number_in_str(&self, a_string: &String) -> Result<(u16, String), Error> {
-        let version_of_block = Regex::new(r"\[\d+\]$").unwrap();
-        let mut sequence_number: Option<u16> = None;
-        if let Some(capture) = version_of_block.captures(a_string) {
-            if let Some(matched) = capture.get(0) {
-                if let Ok(version_num) = matched
-                    .as_str()
-                    .replace("[", "")
-                    .replace("]", "")
-                    .parse::<u16>()
-                {
-                    sequence_number = Some(version_num);
-                }
-            }
-        }
-
-        if sequence_number.is_none() {
+    /// - `Err(Error)` with kind `InvalidInput` when the start marker or path prefixes are not
+    ///   set, or the error of a malformed block, see `parse_file`
+    pub fn parse_str(&mut self, name: &str, content: &str) -> Result<(), Error> {
+        if self.path_prefixes.is_empty()
+            || (self.start_of_comment.is_empty() && self.marker_config.is_none())
+        {
             return Err(Error::new(
-                ErrorKind::Other,
-                "No Sequence number exist in name of block",
+                ErrorKind::InvalidInput,
+                "Start marker and path prefixes not set",
             ));
         }
-        let block = version_of_block.replace_all(a_string, "");
-        Ok((sequence_number.unwrap(), block.as_ref().to_string()))
+        if self.default_marker.is_none() && !self.start_of_comment.is_empty() {
+            self.default_marker = Some(CommentMarker {
+                start: self.start_of_comment.clone(),
+                end: self.end_of_comment.clone(),
+            });
+        }
+        let doc_root = self.doc_root.clone();
+        self.parse_file(name, content, &doc_root, self.path_prefixes)
     }
-    /// Writes all accumulated comment blocks from history to their respective documentation files.
-    ///
-    /// This function serves as the final output phase of the documentation generation process,
-    /// iterating through all comment blocks stored in `comment_history` and writing them to
-    /// their designated markdown files in the documentation hierarchy.
+    /// Parses the source text read from `reader` into the history, see `parse_str`.
+    pub fn parse_reader<R: BufRead>(&mut self, name: &str, mut reader: R) -> Result<(), Error> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        self.parse_str(name, &content)
+    }
+    /// Runs the extraction with the settings of the builder methods, see `comment_in_files`.
     ///
-    /// # Process Flow:
-    /// 1. **Iteration**: Loops through all comment blocks organized by file path and Sequence
-    /// 2. **File Writing**: For each comment block, calls `write_out_to_file` to create/append
-    ///    to the corresponding markdown file
-    /// 3. **Error Collection**: Accumulates any file writing errors without stopping the process
-    /// 4. **Final Error Check**: Returns a single error if any file operations failed
+    /// # Returns:
+    /// - `Err(Error)` when the source folder, document root or path prefixes are not set, or
+    ///   when the start marker or extensions are not set and there is no marker configuration.
+    ///   When the run itself had errors the `Error` wraps the `RunError`.
+    pub fn run(&mut self) -> Result<(), Error> {
+        let required = [
+            ("Source folder", self.source_root.is_empty()),
+            ("Document root", self.doc_root.is_empty()),
+            ("Path prefixes", self.path_prefixes.is_empty()),
+            (
+                "Start marker",
+                self.start_of_comment.is_empty() && self.marker_config.is_none(),
+            ),
+            (
+                "Extensions",
+                self.extensions.is_empty() && self.marker_config.is_none(),
+            ),
+        ];
+        if let Some((name, _)) = required.iter().find(|(_, missing)| *missing) {
+            return Err(Error::other(format!("{name} not set")));
+        }
+        let folder_name = self.source_root.clone();
+        let doc_root = self.doc_root.clone();
+        let start = self.start_of_comment.clone();
+        let extensions = self.extensions.clone();
+        let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+        self.comment_in_files(
+            &folder_name,
+            &doc_root,
+            &start,
+            self.path_prefixes,
+            &extensions,
+        )
+        .map_err(Error::other)
+    }
+    /// Enables or disables run-to-run reproducible output.
     ///
-    /// # Data Structure Navigation:
-    /// - **Outer HashMap**: Keyed by file path (e.g., "doc_root.EPIC.ITEM")
-    /// - **Inner BTreeMap**: Keyed by Sequence number, maintains comment blocks in Sequence order
-    /// - **Value**: Vector of comment lines for each Sequence of a comment block
+    /// In reproducible mode nothing machine specific may leak into the generated files:
+    /// source links are written relative to the scanned folder instead of the path as it
+    /// was given on the command line, and any timestamp that an output would otherwise carry
+    /// is suppressed. The processing order never depends on the file system, see
+    /// `write_history`.
     ///
-    /// # Error Handling Strategy:
-    /// - **Non-blocking**: Continues processing all files even if some fail
-    /// - **Aggregated Errors**: Collects all error messages into a single string
-    /// - **Single Return**: Returns one comprehensive error if any failures occurred
+    /// # Parameters:
+    /// - `reproducible`: `true` to strip timestamps, absolute paths and host specific content
     ///
     /// # Returns:
-    /// - `Ok(())` if all comment blocks were successfully written to files
-    /// - `Err(Error)` containing concatenated error messages if any file operations failed
-    ///
-    /// # Note:
-    /// - This function is typically called at the end of `comment_in_files` after all
-    ///   source files have been processed
-    /// - The use of BTreeMap ensures comment blocks are written in Sequence order
-    /// - File paths are constructed from the hierarchical comment block names
-    /// - Multiple Sequences of the same comment block are written to the same file
-    ///   in Sequence order
-    fn write_history(&self) -> Result<(), Error> {
-        let mut error_string = String::new();
-        self.comment_history.iter().for_each(
-            |blocks_to_write: (&String, &BTreeMap<u16, Vec<String>>)| {
-                let file_name = blocks_to_write.0.as_str().trim();
-
-                for (_, value) in blocks_to_write.1 {
-                    if let Err(error) =
-                        self.write_out_to_file(&self.folder_prefixes, file_name, value)
-                    {
-                        error_string = error.to_string()
-                    }
-                }
-            },
-        );
-        if !error_string.is_empty() {
-            Err(Error::new(ErrorKind::Other, error_string))
-        } else {
-            Ok(())
-        }
+    /// - The updated `Comments` so calls can be chained after `Comments::default()`
+    pub fn with_reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
     }
-    /// Validates the folder path structure and naming conventions for comment blocks.
+    /// Sets the chain of built-in text filters applied to every block body.
     ///
-    /// This function performs comprehensive validation on comment block names to ensure
-    /// they follow the required hierarchical structure and naming conventions before
-    /// being processed for documentation generation.
+    /// The filters run in the given order on the marker stripped lines of a block when the
+    /// block is finalised, so the output does not depend on how carefully the comments were
+    /// formatted. The generated `[SOURCE FILE:]` header line is not passed through the filters.
     ///
-    /// # Validation Rules:
-    /// 1. **Path Structure**: The comment block name must be a dot-separated path
-    ///    (e.g., "EPIC.ITEM.TASK.Description")
-    /// 2. **Path Length**: The path cannot exceed the folder prefix hierarchy length + 1
-    ///    (e.g., if folder_prefixes has 3 elements, path can have up to 4 elements)
-    /// 3. **Uniqueness**: Comment block names must be unique across the entire codebase
-    /// 4. **Prefix Matching**: Each path component (except the first) must start with
-    ///    the corresponding folder prefix
+    /// # Parameters:
+    /// - `filters`: The filters to apply, see `Filter::parse` for the names accepted on the command line
+    pub fn with_filters(mut self, filters: Vec<Filter>) -> Self {
+        self.filters = filters;
+        self
+    }
+    /// Enables the per folder `index.md` table of blocks with the given columns.
     ///
-    /// # Validation Process:
-    /// 1. **Path Parsing**: Splits the dot-separated path into components
-    /// 2. **Empty Check**: Ensures at least one path component exists
-    /// 3. **Length Check**: Validates path doesn't exceed maximum allowed depth
-    /// 4. **Uniqueness Check**: Verifies comment block name hasn't been used before
-    /// 5. **Prefix Validation**: Ensures each path component matches folder prefix hierarchy
+    /// After the history is written every output folder gets a table with one row per
+    /// generated file, showing the columns in the given order. `None` disables the tables.
     ///
     /// # Parameters:
-    /// - `folder_prefixes`: Expected folder hierarchy prefixes (e.g., ["EPIC", "ITEM", "TASK"])
-    /// - `file_path_and_name`: Dot-separated comment block path to validate
+    /// - `columns`: The columns of the table, see `IndexColumn::parse` for the accepted names
+    pub fn with_index_tables(mut self, columns: Option<Vec<IndexColumn>>) -> Self {
+        self.index_columns = columns;
+        self
+    }
+    /// Enables the per folder `index.md` table of contents.
     ///
-    /// # Returns:
-    /// - `Ok(())` if the path is valid
-    /// - `Err(String)` with descriptive error message if validation fails
+    /// After the history is written every output folder, down from the document root, gets
+    /// an `index.md` listing its subfolders and Markdown files as `[[links]]`. With the index
+    /// tables the listing is written below the table.
+    pub fn with_folder_index(mut self, folder_index: bool) -> Self {
+        self.folder_index = folder_index;
+        self
+    }
+    /// Enables the diagram of the block hierarchy.
     ///
-    /// # Error Messages:
-    /// - "There is no file path in the first line of the comment block." - Empty path
-    /// - "Path is longer than what is allowed." - Path exceeds maximum depth
-    /// - "Comment block name must be unique in code base." - Duplicate block name
-    /// - "Invalid folder prefix [actual] [expected]." - Path component doesn't match prefix
+    /// After the history is written the document root gets a `structure.md` holding a
+    /// Mermaid graph of the EPIC/ITEM/TASK tree, each node linked to its generated file.
+    pub fn with_structure(mut self, structure: bool) -> Self {
+        self.structure = structure;
+        self
+    }
+    /// Sets the order of the block names in the index tables and folder indexes, see
+    /// `SortOrder`. Byte order by default.
+    pub fn with_sort_order(mut self, sort_order: SortOrder) -> Self {
+        self.sort_order = sort_order;
+        self
+    }
+    /// Enables cross reference resolution.
     ///
-    /// # Example:
-    /// For folder_prefixes = ["EPIC", "ITEM", "TASK"]:
-    /// - Valid: "EPIC epic.ITEM item.TASK task.Description"
-    /// - Invalid: "EPIC epic.ITEM wrong.TASK task" (second component doesn't start with "ITEM")
-    /// - Invalid: "EPIC epic.ITEM item.TASK task.Too.Long" (exceeds maximum depth)
-    fn is_valid_folder_path(
-        &self,
-        folder_prefixes: &Vec<&str>,
-        file_path_and_name: &str,
-    ) -> Result<(), String> {
-        let path: Vec<&str> = file_path_and_name.split(".").collect();
-        if path.is_empty() {
-            return Err(
-                "There is no file path in the first line of the comment block.".to_string(),
+    /// Every `[[wiki link]]` in a block body is looked up among the generated files, see
+    /// `resolve_links`. A link that names no generated file is reported as a warning, and
+    /// every generated Markdown file ends with a `Referenced by` section listing the files
+    /// that link to it.
+    pub fn with_cross_references(mut self, cross_references: bool) -> Self {
+        self.cross_references = cross_references;
+        self
+    }
+    /// Clears the whole document root before writing, as releases before the managed output
+    /// did, instead of only removing the files the previous run created, see
+    /// `ManagedOutputs`.
+    pub fn with_clean(mut self, clean: bool) -> Self {
+        self.clean = clean;
+        self
+    }
+    /// Enables atomic runs, which write into a staging copy of the document root that
+    /// replaces the document root once the run is done, see `StagedRoot`.
+    ///
+    /// The document root is left as it was when a file could not be read or written, or the
+    /// run was interrupted. Obsidian vault mode writes in place, as replacing the folder would
+    /// make Obsidian reload every note.
+    pub fn with_atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+    /// Stages the run when it is atomic, see `with_atomic`.
+    ///
+    /// # Returns:
+    /// - `Ok(None)` when the run writes into the document root itself
+    fn stage(&self, doc_root: &str) -> Result<Option<StagedRoot>, Error> {
+        match self.atomic && !self.obsidian && !doc_root.is_empty() {
+            true => StagedRoot::stage(doc_root, &self.run_id).map(Some),
+            false => Ok(None),
+        }
+    }
+    /// Puts the staging folder of an atomic run in the place of the document root, unless a
+    /// file could not be read or written, or the run was interrupted.
+    fn publish(&mut self, staged: StagedRoot) {
+        let failed = self
+            .failures
+            .failures
+            .iter()
+            .any(|failure| failure.kind == FailureKind::Io);
+        if failed || self.is_interrupted() {
+            self.events.warn(format!(
+                "The run did not complete: {} was left as it was",
+                self.published_root
+            ));
+        } else if let Err(error) = staged.publish() {
+            self.failures.report("publish", error, &self.events);
+        }
+    }
+    /// Enables the Obsidian vault mode, see `Vault`.
+    ///
+    /// The document root is not cleared: a generated note is only written when its content
+    /// changed, and the notes the last run wrote but this run did not are removed. Wiki links
+    /// to generated notes are rewritten to links from the root of the vault, and every EPIC
+    /// gets a map of content note such as `EPIC A MOC.md`.
+    pub fn with_obsidian(mut self, obsidian: bool) -> Self {
+        self.obsidian = obsidian;
+        self
+    }
+    /// Enables the near duplicate check with the given similarity threshold.
+    ///
+    /// After all files are parsed every pair of block sequences whose text is at least
+    /// `threshold` similar (0.0 to 1.0) is reported as a warning, so copy pasted
+    /// requirements that drifted apart can be consolidated or cross referenced.
+    pub fn with_similarity_check(mut self, threshold: Option<f64>) -> Self {
+        self.similarity_threshold = threshold;
+        self
+    }
+    /// Enables the check of the http(s) URLs appearing in block text.
+    ///
+    /// After all files are parsed every distinct URL that passes the allow and deny lists of
+    /// `check` is probed, and every URL that does not resolve is reported as a warning with
+    /// the source file and line of the blocks it appears in.
+    pub fn with_url_check(mut self, check: Option<UrlCheck>) -> Self {
+        self.url_check = check;
+        self
+    }
+    /// Enables glossary term linking.
+    ///
+    /// With a glossary, blocks whose header starts with `TERM ` (e.g. `//#TERM Line block`)
+    /// define a term instead of a document. A sequence number after the term, as in
+    /// `//#TERM Line block [0]`, is not part of the term. The definitions are written to `glossary.md` in
+    /// the document root, and the first occurrence of every term in each generated file is
+    /// linked to its definition. Terms read from a glossary file link to their own page.
+    pub fn with_glossary(mut self, glossary: Option<Glossary>) -> Self {
+        self.glossary = glossary;
+        self
+    }
+    /// Adds the last commit that changed the lines of every block, with its author and
+    /// date, to the `[SOURCE FILE:]` line of the block, as found by `git blame`.
+    pub fn with_git_metadata(mut self, git_metadata: bool) -> Self {
+        self.git_metadata = git_metadata;
+        self
+    }
+    /// Enables the contributor page per EPIC, built from `git blame` of the block lines.
+    pub fn with_contributors(mut self, contributors: bool) -> Self {
+        self.contributors = contributors;
+        self
+    }
+    /// Enables the possibly stale documentation report.
+    ///
+    /// Blocks whose source file git shows untouched for more than `months` months, or whose
+    /// documented code line disappeared since the previous run, are written to `stale.md`.
+    pub fn with_stale_check(mut self, months: Option<u32>) -> Self {
+        self.stale_months = months;
+        self
+    }
+    /// Writes a page per `@tags:` tag to the `tags` folder of the document root, listing the
+    /// blocks carrying the tag, and `tags/index.md` listing all tags.
+    pub fn with_tag_pages(mut self, tag_pages: bool) -> Self {
+        self.tag_pages = tag_pages;
+        self
+    }
+    /// Compares the blocks with a list of expected requirement IDs or block names. The
+    /// present, missing and unexpected entries are written to `requirements.md`, and every
+    /// missing requirement is printed as a warning.
+    pub fn with_requirements(mut self, requirements: Option<Requirements>) -> Self {
+        self.requirements = requirements;
+        self
+    }
+    /// Fails the run, with a validation error, when a requirement of `with_requirements` is
+    /// not documented by any block, instead of only warning about it.
+    pub fn with_strict_requirements(mut self, strict: bool) -> Self {
+        self.strict_requirements = strict;
+        self
+    }
+    /// Enables the documentation density report, in blocks per thousand lines, per directory.
+    pub fn with_density_report(mut self, format: Option<ReportFormat>) -> Self {
+        self.density_format = format;
+        self
+    }
+    /// Writes the rejected blocks of a run to `diagnostics.md` or `diagnostics.json` in the
+    /// document root, besides the summary table printed at the end of every run with rejected
+    /// blocks.
+    pub fn with_diagnostics_report(mut self, format: Option<ReportFormat>) -> Self {
+        self.diagnostics_format = format;
+        self
+    }
+    /// Writes the traceability matrix, with a row per block name, a column per source file
+    /// and the lines of the blocks in the cells, to `trace-matrix.csv` or `trace-matrix.md`
+    /// in the document root.
+    pub fn with_trace_matrix(mut self, format: Option<MatrixFormat>) -> Self {
+        self.trace_format = format;
+        self
+    }
+    /// Writes the static HTML site, see `html::write_site`. The pages hold the blocks as the
+    /// Markdown files do, without front matter and run footer.
+    fn write_html(&self, doc_root: &str) -> Result<Vec<PathBuf>, Error> {
+        write_site(
+            &self.rendered_pages(doc_root, OutputFormat::Html)?,
+            doc_root,
+        )
+    }
+    /// Renders the Markdown page of every block name, as the HTML site shows it, keyed by the
+    /// dot delimited block name without the document root. The blocks are separated by the
+    /// separator of `format`, and source links are relative to the generated file.
+    pub fn rendered_pages(
+        &self,
+        doc_root: &str,
+        format: OutputFormat,
+    ) -> Result<BTreeMap<String, String>, Error> {
+        let separator = self.separators.text(format);
+        let mut pages = BTreeMap::new();
+        for (name, blocks) in &self.comment_history {
+            let page = generated_path(name, "html");
+            let folder = page.parent().unwrap_or(Path::new(""));
+            pages.insert(
+                relative_name(name, doc_root).to_string(),
+                self.render_page(name, blocks, folder, &separator, doc_root)?,
             );
         }
-        if path.len() > folder_prefixes.len() + 1 {
-            return Err("Path is longer than what is allowed.".to_string());
+        Ok(pages)
+    }
+    /// Writes the blocks as an mdBook, see `write_book`, the chapters in the order of their
+    /// first Sequence number and then their name.
+    fn write_mdbook(&self, doc_root: &str) -> Result<Vec<PathBuf>, Error> {
+        let separator = self.separators.text(OutputFormat::MdBook);
+        let mut chapters = Vec::new();
+        for (name, blocks) in &self.comment_history {
+            let relative = relative_name(name, doc_root);
+            let chapter = chapter_path(doc_root, relative);
+            let folder = chapter.parent().unwrap_or(Path::new(""));
+            let markdown = self.render_page(name, blocks, folder, &separator, doc_root)?;
+            let first = blocks.keys().next().copied().unwrap_or_default();
+            chapters.push((first, relative.to_string(), markdown));
         }
-
-        let comment_name = file_path_and_name;
-        if self.comment_block_names.contains(comment_name) {
-            return Err("Comment block name must be unique in code base.".to_string());
+        chapters.sort_by(|left, right| (left.0, &left.1).cmp(&(right.0, &right.1)));
+        let pages: Vec<(String, String)> = chapters
+            .into_iter()
+            .map(|(_, name, markdown)| (name, markdown))
+            .collect();
+        write_book(&pages, doc_root)
+    }
+    /// Writes the heading of a block with `with_sequence_headings`, and returns the block with
+    /// its headings shifted by the heading offset and its shortcodes expanded for the format of
+    /// `doc_writer`. The block is only copied when it changes.
+    fn prepared_block<'b>(
+        &self,
+        writer: &mut BufWriter<Vec<u8>>,
+        doc_writer: &dyn DocWriter,
+        sequence: Sequence,
+        block: &'b Block,
+    ) -> Result<Cow<'b, Block>, Error> {
+        if self.sequence_headings {
+            doc_writer.write_heading(writer, &sequence_heading(sequence, block))?;
         }
-
-        let prefixes: Vec<_> = path[1..].iter().zip(folder_prefixes).collect();
-        for item in prefixes {
-            if !item.0.starts_with(item.1) {
-                return Err(format!("Invalid folder prefix [{}] [{}].", item.0, item.1));
+        let mut block = Cow::Borrowed(block);
+        if self.heading_offset != 0 {
+            let lines = shift_headings(&block.lines, &block.raw, self.heading_offset);
+            block.to_mut().lines = lines.into();
+        }
+        if self.filters.contains(&Filter::Shortcodes) {
+            let lines = expand_lines(&block.lines, &block.raw, doc_writer.format());
+            block.to_mut().lines = lines.into();
+        }
+        Ok(block)
+    }
+    /// Renders the blocks of a block name to the Markdown of a page in `folder`, which source
+    /// links are relative to, the blocks separated by `separator`.
+    fn render_page(
+        &self,
+        name: &str,
+        blocks: &Sequences,
+        folder: &Path,
+        separator: &str,
+        doc_root: &str,
+    ) -> Result<String, Error> {
+        let mut writer = BufWriter::new(Vec::new());
+        for (index, (sequence, block)) in blocks.iter().enumerate() {
+            if index > 0 {
+                writer.write_all(separator.as_bytes())?;
+            }
+            let prepared = self.prepared_block(&mut writer, &MarkdownWriter, *sequence, block)?;
+            let block = prepared.as_ref();
+            let link = self.source_link(block, folder);
+            match &self.template {
+                Some(template) => writer.write_all(
+                    template
+                        .render(
+                            relative_name(name, doc_root),
+                            *sequence,
+                            block,
+                            link.as_deref(),
+                        )
+                        .as_bytes(),
+                )?,
+                None => block.write_with_link(&mut writer, link.as_deref())?,
             }
         }
-        Ok(())
+        let markdown = writer.into_inner().map_err(|error| error.into_error())?;
+        Ok(String::from_utf8_lossy(&markdown).into_owned())
     }
-    /// Initializes a new comment block by extracting metadata from the first comment line.
+    /// Writes the density report from the line count of every scanned file and the blocks
+    /// found in it.
+    fn write_density(&self, doc_root: &str, format: ReportFormat) -> Result<PathBuf, Error> {
+        write_density_report(&self.counted_files(), format, doc_root)
+    }
+    /// Returns the line and block count of every scanned file, keyed by its path as it
+    /// appears in the generated output.
+    fn counted_files(&self) -> BTreeMap<String, FileCount> {
+        let mut files = self.scanned_files.clone();
+        for block in self
+            .comment_history
+            .values()
+            .flat_map(|blocks| blocks.values())
+        {
+            if let Some(count) = files.get_mut(&*block.path) {
+                count.blocks += 1;
+            }
+        }
+        files
+            .into_iter()
+            .map(|(path, count)| (self.source_link_path(&path), count))
+            .collect()
+    }
+    /// Prints the documentation coverage, the share of scanned files with at least one block,
+    /// per directory. With `minimum`, in percent, the run fails when the overall coverage is
+    /// lower.
+    pub fn with_coverage(mut self, report: bool, minimum: Option<f64>) -> Self {
+        self.coverage = report || minimum.is_some();
+        self.coverage_min = minimum;
+        self
+    }
+    /// Prints the coverage report and fails the run when the coverage is below the minimum.
+    fn check_coverage(&mut self) {
+        if !self.coverage {
+            return;
+        }
+        let (directories, total) = coverage_by_directory(&self.counted_files());
+        self.events
+            .inform(coverage_report(&directories, total).trim_end());
+        if let Some(minimum) = self.coverage_min
+            && total.percentage() < minimum
+        {
+            self.failures.report(
+                "check coverage",
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "[E015] Documentation coverage of {:.1} % is below the minimum of {minimum} %",
+                        total.percentage()
+                    ),
+                ),
+                &self.events,
+            );
+        }
+    }
+    /// Numbers the blocks whose header has no Sequence number automatically.
     ///
-    /// This function is called when transitioning from CODE to COMMENT state to process
-    /// the first line of a comment block, which contains the comment block name and
-    /// serves as the metadata header for the entire comment block.
+    /// Such a block gets the Sequence number after the highest one of its name so far, so
+    /// blocks are numbered in walk order. A block that is given that number explicitly later
+    /// on takes precedence, and the automatically numbered block moves on to the next free
+    /// number.
+    pub fn with_auto_sequence(mut self, auto_sequence: bool) -> Self {
+        self.auto_sequence = auto_sequence;
+        self
+    }
+    /// Sets the folders the document root is mirrored into once a run is written. The files
+    /// a previous mirror wrote are replaced, so it holds the same generated files as the
+    /// document root afterwards; other files in the folder are left alone.
+    pub fn with_mirrors(mut self, mirrors: Vec<String>) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+    /// Hard links the files of the mirrors to the files in the document root instead of
+    /// copying them.
+    pub fn with_hard_links(mut self, hard_links: bool) -> Self {
+        self.hard_links = hard_links;
+        self
+    }
+    /// Sets the declared metadata fields. The `@name: value` directives of every block are
+    /// checked against their declared type while parsing, and the declared fields are written
+    /// to the front matter. `None` accepts every directive.
+    pub fn with_field_schema(mut self, fields: Option<FieldSchema>) -> Self {
+        self.fields = fields;
+        self
+    }
+    /// Sets the grammar of the first line of a block, see `HeaderGrammar` for the pattern spec.
+    pub fn with_header_grammar(mut self, grammar: HeaderGrammar) -> Self {
+        self.header_grammar = grammar;
+        self
+    }
+    /// Prints a warning, with the source locations, for every pair of nearly identical blocks.
+    fn report_similar_blocks(&self, threshold: f64) {
+        let location = |(name, sequence): &(String, Sequence)| {
+            self.comment_history
+                .get(name)
+                .and_then(|blocks| blocks.get(sequence))
+                .map(|block| format!("{} LINE: {}", block.source_file, block.start_line))
+                .unwrap_or_default()
+        };
+        for pair in find_similar_blocks(&self.comment_history, threshold) {
+            self.events.warn(format!(
+                "Similar blocks ({:.0}%): {} [{}] ({}) and {} [{}] ({})",
+                pair.similarity * 100.0,
+                pair.first.0,
+                pair.first.1,
+                location(&pair.first),
+                pair.second.0,
+                pair.second.1,
+                location(&pair.second)
+            ));
+        }
+    }
+    /// Enables hierarchy inference from the source file's directory structure.
     ///
-    /// # Key Operations:
-    /// - **Name Extraction**: Strips the comment marker prefix and trims whitespace to get
-    ///   the comment block name (e.g., "//# EPIC.Get Lines.ITEM Test Block [1]" → "EPIC.Get Lines.ITEM Test Block [1]")
-    /// - **Line Number Recording**: Sets `comment_line_start` to track where the comment block begins
-    /// - **Name Storage**: Stores the extracted comment block name for later processing
+    /// In this mode the header of a block only supplies the leaf name. The folders between
+    /// the scanned root and the source file supply the levels above it, each level prefixed
+    /// with the folder prefix for its depth, e.g. with `-path EPIC.ITEM.TEST` the header
+    /// `//#Login [0]` in `auth/session/login.rs` becomes `EPIC auth.ITEM session.TEST Login`.
+    pub fn with_path_inference(mut self, infer_path: bool) -> Self {
+        self.infer_path = infer_path;
+        self
+    }
+    /// Derives the full block path from the directories of the source file and the leaf name.
     ///
-    /// # Parameters:
-    /// - `line`: The first line of a comment block including the comment marker prefix
+    /// Every directory below the scanned root becomes one level of the path. A level or leaf
+    /// that does not yet start with the folder prefix of its depth is given that prefix, and
+    /// dots in directory names are replaced because a dot separates the levels of a path.
+    fn infer_block_path(&self, file_name: &str, leaf: &str) -> String {
+        let relative = Path::new(file_name)
+            .strip_prefix(self.source_root.as_str())
+            .unwrap_or(Path::new(file_name));
+        let mut levels: Vec<String> = relative
+            .parent()
+            .map(|parent| {
+                parent
+                    .components()
+                    .filter(|component| matches!(component, Component::Normal(_)))
+                    .map(|component| component.as_os_str().to_string_lossy().replace('.', "_"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        levels.push(leaf.to_string());
+        levels
+            .iter()
+            .enumerate()
+            .map(|(depth, level)| match self.folder_prefixes.get(depth) {
+                Some(prefix) if !level.starts_with(prefix) => format!("{prefix} {level}"),
+                _ => level.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+    /// Enables the YAML front matter at the top of every generated file.
     ///
-    /// # Returns:
-    /// - `Ok(())` on successful initialization
-    /// - `Err(String)` if the comment block name is invalid (though currently no validation occurs)
+    /// The front matter holds the block statistics of the file: its title, the contributing
+    /// source files, the number and the list of sequences, the first and last source line,
+    /// the `@tags:` of its blocks, and the start time and ID of the run that regenerated it.
+    /// The start time and run ID are left out in reproducible mode.
+    pub fn with_front_matter(mut self, front_matter: bool) -> Self {
+        self.front_matter = front_matter;
+        self
+    }
+    /// Enables the run manifest and metrics file in the document root.
     ///
-    /// # Note:
-    /// - The comment block name typically follows a hierarchical naming convention with
-    ///   dot-separated components (e.g., "EPIC.ITEM.TASK")
-    /// - The line number is recorded as `line_counter + 1` because `line_counter` tracks
-    ///   the line that was just processed, and we want the starting line of the comment
-    /// - This function is called exclusively by `parse_comment` during state transitions
-    /// - The extracted comment block name will later be processed by `strip_number_in_str`
-    ///   to separate Sequence numbers from the actual block name
-    fn parse_comment_start(&mut self, line: &str) -> Result<(), String> {
-        let comment_name = line[self.start_of_comment.len()..].trim();
-        self.comment_line_start = self.line_counter + 1;
-        self.current_comment_name = comment_name.to_string();
+    /// Every run gets a ULID as run ID. The manifest `.get-comments-manifest` records, per run
+    /// ID, the generated files with a fingerprint of their content, and `metrics.json` holds
+    /// the counts of the last run with its run ID, so documentation changes can be correlated
+    /// with CI pipeline runs.
+    pub fn with_manifest(mut self, manifest: bool) -> Self {
+        self.manifest = manifest;
+        self
+    }
+    /// Ends every generated file with a footer naming the run that generated it. The footer
+    /// is left out in reproducible mode.
+    pub fn with_run_footer(mut self, run_footer: bool) -> Self {
+        self.run_footer = run_footer;
+        self
+    }
+    /// Reports the generated files that were added, changed or removed since the given run.
+    /// This implies the manifest, as the files of earlier runs are looked up in it.
+    pub fn with_since_run(mut self, run_id: Option<String>) -> Self {
+        self.manifest |= run_id.is_some();
+        self.since_run = run_id;
+        self
+    }
+    /// Records the files of this run in the manifest, writes the metrics file and reports the
+    /// changes since the run asked for with `with_since_run`.
+    ///
+    /// In reproducible mode the run is recorded under an ID derived from the generated files
+    /// instead of the ULID of the run.
+    fn record_run(&self, previous_runs: Vec<RunRecord>, doc_root: &str) -> Result<(), Error> {
+        create_dir_all(doc_root)?;
+        let run_id = (!self.reproducible).then_some(self.run_id.as_str());
+        let current = save_manifest(previous_runs.clone(), run_id, doc_root)?;
+        let metrics = RunMetrics {
+            files_scanned: self.scanned_files.len(),
+            lines_scanned: self.scanned_files.values().map(|count| count.lines).sum(),
+            blocks: self
+                .comment_history
+                .values()
+                .map(|blocks| blocks.len())
+                .sum(),
+        };
+        write_metrics(&metrics, &current.run_id, current.files.len(), doc_root)?;
+        if let Some(since_run) = &self.since_run {
+            for (path, change) in changes_since(&previous_runs, since_run, &current)? {
+                self.events
+                    .inform(&format!("{change:?} since run {since_run}: {path}"));
+            }
+        }
         Ok(())
     }
-    /// Processes individual comment lines and manages comment block state transitions.
+    /// Returns the path of the source file as it must appear in the generated output.
     ///
-    /// This function handles the core logic of parsing comment lines and managing the state
-    /// machine transitions between CODE and COMMENT states. It distinguishes between the
-    /// first line of a comment block (which contains metadata) and subsequent comment lines.
+    /// Outside reproducible mode the path is returned unchanged. In reproducible mode the
+    /// scanned root folder is stripped from the front of the path and path separators are
+    /// normalised to `/` so the same tree produces the same text on every machine.
+    fn source_link_path(&self, file_name: &str) -> String {
+        if !self.reproducible {
+            return file_name.to_string();
+        }
+        let relative = file_name
+            .strip_prefix(self.source_root.as_str())
+            .unwrap_or(file_name)
+            .replace('\\', "/");
+        relative.trim_start_matches('/').to_string()
+    }
+    /// Returns the target of the source link of a block written to a generated file in
+    /// `folder`, see `LinkStyle::target`.
     ///
-    /// # State Machine Logic:
-    /// - **CODE → COMMENT**: When encountering the first comment line, transitions to COMMENT state
-    ///   and calls `parse_comment_start` to extract block metadata (name, line number)
-    /// - **COMMENT → COMMENT**: When already in COMMENT state, adds the line content to the
-    ///   current comment block buffer
+    /// In reproducible mode the source file of a block is stored relative to the scanned
+    /// folder, so a `file:///` link would not point to it, and an absolute path differs from
+    /// machine to machine. The link is written relative to the generated file instead.
+    fn source_link(&self, block: &Block, folder: &Path) -> Option<String> {
+        match self.link_style {
+            LinkStyle::File if self.reproducible => {
+                let source_file = Path::new(&self.source_root).join(&*block.source_file);
+                let located = Block {
+                    source_file: source_file.to_string_lossy().into(),
+                    ..block.clone()
+                };
+                LinkStyle::Relative.target(&located, folder)
+            }
+            _ => self.link_style.target(block, folder),
+        }
+    }
+    //#EPIC Get Lines.ITEM Write the comment lines to the file path and name [0]
+    //#
+    //## Write Comment Block To File
+    //#Create the file path and write out the comment block to the file having file name.
+    /// Writes comment lines to a markdown file in the specified hierarchical directory structure.
     ///
-    /// # Line Processing:
-    /// - **First Comment Line**: Contains the comment block name and triggers state transition
-    /// - **Subsequent Lines**: Contain actual comment content, stripped of the comment marker
+    /// This function takes a dot-delimited file path, creates the necessary directory structure,
+    /// and writes the rendered comment blocks to a markdown file. It's the core file output
+    /// operation for the documentation generation system.
+    ///
+    /// # Process Flow:
+    /// 1. **Path Validation**: Calls `is_valid_folder_path` to validate the hierarchical structure
+    /// 2. **Directory Creation**: Creates all necessary directories in the path hierarchy
+    /// 3. **Content Writing**: Writes the content rendered by `render_file` in one write, unless
+    ///    the file already holds it, see `write_if_changed`
+    ///
+    /// # Path Processing:
+    /// - **Input Format**: Dot-delimited path (e.g., "doc_root.EPIC.ITEM.TASK.Description")
+    /// - **Directory Creation**: Converts dots to directory separators and creates folders
+    /// - **File Naming**: The last component becomes the markdown filename
+    /// - **Output Example**: "doc_root/EPIC/ITEM/TASK/Description.md"
     ///
     /// # Parameters:
-    /// - `line`: The raw comment line including the comment marker prefix
+    /// - `folder_prefixes`: Expected folder hierarchy for validation
+    /// - `file_path_and_name`: Dot-delimited path where file should be created
+    /// - `part`: The part of a split file, see `render_parts`; 1 for a file that is not split
+    /// - `content`: The rendered file, see `render_file`
     ///
     /// # Returns:
-    /// - `Ok(())` on successful parsing
-    /// - `Err(String)` if `parse_comment_start` fails (e.g., invalid comment block name)
+    /// - `Ok(())` on successful file creation and writing
+    /// - `Err(std::io::Error)` if directory creation, file opening, or writing fails
     ///
-    /// # Error Conditions:
-    /// - Failure in `parse_comment_start` when processing the first comment line
-    /// - Invalid comment block name format
+    /// # File Operations:
+    /// - **Unchanged Files**: A file already holding the content keeps its modification time
+    /// - **Create Flag**: Files are created if they don't exist
     ///
     /// # Note:
-    /// This function is called by `parse_file` for every line that starts with the
-    /// comment marker string. It's responsible for the state transitions that define
-    /// comment block boundaries.
-    fn parse_comment(&mut self, line: &str) -> Result<(), String> {
-        if self.current_state == State::CODE {
-            self.current_state = State::COMMENT;
-            self.parse_comment_start(line)?;
-        } else {
-            let comment_line = line[self.start_of_comment.len()..].to_string();
-            self.comment.push(comment_line.to_string());
+    /// - This function is called by `write_history` once per file, with all of its Sequences
+    /// - Multiple Sequences of the same comment block end up in the same file
+    /// - The directory structure mirrors the hierarchical organization of comment blocks
+    /// - File operations are atomic within this function call
+    fn write_out_to_file(
+        &self,
+        folder_prefixes: &Vec<&str>,
+        file_path_and_name: &str,
+        extension: &str,
+        part: usize,
+        content: &[u8],
+    ) -> Result<(), std::io::Error> {
+        let path_and_file_name = part_path(
+            &self.output_file_path(folder_prefixes, file_path_and_name, extension)?,
+            part,
+        );
+        if let Some(vault) = &self.vault {
+            if vault.write_note(&path_and_file_name, content)? {
+                self.events.emit(Event::FileWritten {
+                    path: &path_and_file_name,
+                });
+            }
+            return Ok(());
+        }
+        if let Some(managed) = &self.managed {
+            managed.keep(&path_and_file_name);
+        }
+        if write_if_changed(&path_and_file_name, content)? {
+            self.events.emit(Event::FileWritten {
+                path: &path_and_file_name,
+            });
         }
         Ok(())
     }
-    //#EPIC Get Lines.ITEM Write out all of the history [0]
-    //#
-    //##Write out all blocks encountered in the past after the last file was processed
-    /// Finalizes and stores a completed comment block into the comment history.
+    /// Validates the dot delimited file path and returns the path of its generated file with
+    /// the given extension.
+    fn output_file_path(
+        &self,
+        folder_prefixes: &Vec<&str>,
+        file_path_and_name: &str,
+        extension: &str,
+    ) -> Result<PathBuf, std::io::Error> {
+        // file_name is a '.' delimited slice. Each slice is a folder starting
+        // from the current `working folder
+        if let Err(message) = self.is_valid_output_name(folder_prefixes, file_path_and_name) {
+            return Err(Error::new(ErrorKind::InvalidInput, message));
+        }
+        Ok(generated_path(file_path_and_name, extension))
+    }
+    /// Renders a generated file with the given backend: the optional front matter, every
+    /// Sequence in Sequence order with its glossary terms linked, and the optional run footer.
+    fn render_file(
+        &self,
+        file_name: &str,
+        blocks: &Sequences,
+        doc_writer: &dyn DocWriter,
+    ) -> Result<Vec<u8>, Error> {
+        let mut writer = BufWriter::new(Vec::new());
+        if self.front_matter {
+            let title = file_name.rsplit(".").next().unwrap_or(file_name);
+            let run_id = (!self.reproducible).then_some(self.run_id.as_str());
+            let generated = (!self.reproducible).then_some(self.started_at.as_str());
+            doc_writer.write_front_matter(
+                &mut writer,
+                &front_matter(title, blocks, run_id, generated, self.fields.as_ref()),
+            )?;
+        }
+        let mut linked_terms = HashSet::new();
+        let glossary_page = format!(
+            "{}glossary.md",
+            "../".repeat(file_name.split(".").count().saturating_sub(2))
+        );
+        let mut generated_heading = blocks.values().any(|block| !block.generated);
+        let ordered = blocks
+            .iter()
+            .filter(|(_, block)| !block.generated)
+            .chain(blocks.iter().filter(|(_, block)| block.generated));
+        let separator = self.separators.text(doc_writer.format());
+        let generated_file = generated_path(file_name, doc_writer.extension());
+        let folder = generated_file.parent().unwrap_or(Path::new(""));
+        let name = relative_name(file_name, &self.doc_root);
+        for (index, (sequence, block)) in ordered.enumerate() {
+            let link = self.source_link(block, folder);
+            if index > 0 {
+                writer.write_all(separator.as_bytes())?;
+            }
+            if block.generated && std::mem::take(&mut generated_heading) {
+                doc_writer.write_heading(&mut writer, GENERATED_HEADING)?;
+            }
+            doc_writer.write_target(
+                &mut writer,
+                &anchor(&format!(
+                    "{} {}",
+                    name.replace('.', " "),
+                    sequence.to_string().replace('.', "-")
+                )),
+            )?;
+            let prepared = self.prepared_block(&mut writer, doc_writer, *sequence, block)?;
+            let block = prepared.as_ref();
+            let linked;
+            let block = match &self.glossary {
+                Some(glossary) => {
+                    linked = Block {
+                        lines: glossary
+                            .link_terms(
+                                &block.lines,
+                                &block.raw,
+                                &mut linked_terms,
+                                &glossary_page,
+                                &|target, text| doc_writer.link(target, text),
+                            )
+                            .into(),
+                        ..block.clone()
+                    };
+                    &linked
+                }
+                None => block,
+            };
+            let in_vault;
+            let block = match &self.vault {
+                Some(vault) => {
+                    in_vault = Block {
+                        lines: vault.link_notes(&block.lines, &block.raw).into(),
+                        ..block.clone()
+                    };
+                    &in_vault
+                }
+                None => block,
+            };
+            match &self.template {
+                Some(template) if doc_writer.format() == OutputFormat::Markdown => writer
+                    .write_all(
+                        template
+                            .render(name, *sequence, block, link.as_deref())
+                            .as_bytes(),
+                    )?,
+                _ => doc_writer.write_block(&mut writer, block, link.as_deref())?,
+            }
+        }
+        if let Some(sources) = self.backlinks.get(file_name)
+            && doc_writer.format() == OutputFormat::Markdown
+        {
+            doc_writer.write_heading(&mut writer, BACKLINKS_HEADING)?;
+            for source in sources {
+                let title = source.rsplit('.').next().unwrap_or(source);
+                writeln!(
+                    writer,
+                    "- [[{}{}|{title}]]",
+                    self.vault.as_ref().map_or("", Vault::prefix),
+                    source.replace('.', "/")
+                )?;
+            }
+            writeln!(writer)?;
+        }
+        if self.run_footer && !self.reproducible {
+            doc_writer.write_footer(&mut writer, &self.run_id)?;
+        }
+        writer.into_inner().map_err(|error| error.into_error())
+    }
+    /// Renders a generated file as `render_file` does, split into parts when it is larger than
+    /// the maximum file size.
     ///
-    /// This function is called when a comment block ends (either by encountering non-comment lines
-    /// or reaching end of file) to process the accumulated comment lines and store them in the
-    /// comment history for later output.
+    /// The Sequences are divided over the parts in the order they are written, each part
+    /// holding at least one Sequence, and every part ends with links to the other parts. Part
+    /// 1 keeps the name of the file, see `part_path` for the names of the others.
+    fn render_parts(
+        &self,
+        file_name: &str,
+        blocks: &Sequences,
+        doc_writer: &dyn DocWriter,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let content = self.render_file(file_name, blocks, doc_writer)?;
+        let Some(max_size) = self
+            .max_file_size
+            .filter(|max_size| content.len() > *max_size)
+        else {
+            return Ok(vec![content]);
+        };
+        let mut parts = vec![Sequences::new()];
+        let mut size = 0;
+        let ordered = blocks
+            .iter()
+            .filter(|(_, block)| !block.generated)
+            .chain(blocks.iter().filter(|(_, block)| block.generated));
+        for (sequence, block) in ordered {
+            let block_size = block.source_file.len()
+                + block.lines.iter().map(|line| line.len() + 1).sum::<usize>()
+                + PART_BLOCK_OVERHEAD;
+            if size + block_size > max_size && parts.last().is_some_and(|part| !part.is_empty()) {
+                parts.push(Sequences::new());
+                size = 0;
+            }
+            size += block_size;
+            if let Some(part) = parts.last_mut() {
+                part.insert(*sequence, block.clone());
+            }
+        }
+        if parts.len() == 1 {
+            return Ok(vec![content]);
+        }
+        let title = file_name.rsplit('.').next().unwrap_or(file_name);
+        let file = PathBuf::from(format!("{title}.{}", doc_writer.extension()));
+        let count = parts.len();
+        let links: Vec<String> = (1..=count)
+            .map(|part| {
+                doc_writer.link(
+                    &part_path(&file, part).to_string_lossy(),
+                    &format!("part {part}"),
+                )
+            })
+            .collect();
+        parts
+            .iter()
+            .enumerate()
+            .map(|(index, part)| {
+                let mut content = self.render_file(file_name, part, doc_writer)?;
+                let others: Vec<&str> = links
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(_, link)| link.as_str())
+                    .collect();
+                writeln!(
+                    content,
+                    "Part {} of {count}: {}\n",
+                    index + 1,
+                    others.join(" | ")
+                )?;
+                Ok(content)
+            })
+            .collect()
+    }
+    /// Extracts Sequence number from comment block names and returns the sanitized name.
     ///
-    /// # Process Flow:
-    /// 1. **State Transition**: Returns parser state from COMMENT to CODE
-    /// 2. **Block Preparation**: Adds source file metadata and line number to comment block
-    /// 3. **Sequence Extraction**: Parses Sequence number from comment block name using regex
-    /// 4. **History Storage**: Stores the comment block in the hierarchical comment history
-    /// 5. **Duplicate Prevention**: Checks for duplicate Sequence numbers in the same block name
-    /// 6. **Cleanup**: Clears the current comment buffer for the next block
+    /// This function parses comment block names with the configured header grammar, which by
+    /// default is "BlockName [N]" where N is a Sequence number in brackets at the end of the
+    /// string. It extracts both the Sequence number and the base block name for separate handling.
     ///
-    /// # Key Operations:
-    /// - **Metadata Addition**: Prepends source file path and line number to comment block
-    /// - **Sequence Management**: Extracts and validates Sequence numbers from block names
-    /// - **Hierarchical Storage**: Organizes comments by documentation path and Sequence
-    /// - **Duplicate Detection**: Ensures unique Sequence numbers per comment block name
+    /// # Pattern Matching:
+    /// - **Grammar**: The `HeaderGrammar` set with `with_header_grammar`, `{name} [{seq}]` by default
+    /// - **Examples** for the default grammar:
+    ///   - "EPIC.Get Lines.ITEM Test Block [1]" → Sequence=1, name="EPIC.Get Lines.ITEM Test Block"
+    ///   - "Simple Comment [42]" → Sequence=42, name="Simple Comment"
+    ///   - "EPIC.Spec [2.1]" → Sequence=2.1, name="EPIC.Spec", see `Sequence`
+    ///   - "No Sequence" → Error: "No Sequence number exist in name of block"
+    ///
+    /// # Extraction Process:
+    /// 1. **Grammar Matching**: Matches the header against the header grammar
+    /// 2. **Sequence Parsing**: Extracts the Sequence number and converts every level to u16
+    /// 3. **Validation**: Ensures Sequence number exists and is valid
+    /// 4. **Name Sanitized**: Removes Sequence suffix to get clean block name
     ///
     /// # Parameters:
-    /// - `file_name`: Source file path where the comment block was found
-    /// - `doc_root`: Base documentation path for organizing output
+    /// - `a_string`: Comment block name string that may contain Sequence suffix
     ///
     /// # Returns:
-    /// - `Ok(())` on successful storage
-    /// - `Err(std::io::Error)` if duplicate Sequence numbers are detected
+    /// - `Ok((Sequence, String))` - Tuple containing (sequence_number, sanitized_block_name)
+    /// - `Err(Error)` - If the header does not match the grammar or a level of the Sequence
+    ///   number is not a u16
     ///
     /// # Error Conditions:
-    /// - Duplicate Sequence numbers in the same comment block name
-    /// - Invalid Sequence number format in comment block name
+    /// - The header does not match the grammar, such as a header without a Sequence number
+    /// - A level of the Sequence number cannot be parsed as u16
+    ///
+    /// # Use Cases:
+    /// - Used by `write_out_all_history` to separate Sequence from block name for storage
+    /// - Enables multiple Sequences of the same comment block to be tracked and organized
+    /// - Supports versioned documentation where blocks can be updated over time
     ///
     /// # Note:
-    /// The function uses BTreeMap to maintain comment blocks in Sequence order and
-    /// HashSet to ensure unique comment block names across the entire codebase.
-    fn write_out_all_history(
-        &mut self,
-        file_name: &str,
-        doc_root: &str,
-    ) -> Result<(), std::io::Error> {
-        self.current_state = State::CODE;
-        if self.comment.len() > 0 {
-            let mut all_block_lines = vec![format!(
-                "[SOURCE FILE:](file:///{file_name}) LINE: {}\n",
-                self.comment_line_start
-            )];
-            // keep history of comments
-            all_block_lines.append(&mut self.comment);
-            let comment_name = self.strip_number_in_str(&self.current_comment_name)?;
-
-            let check_insert = self
-                .comment_history
-                .entry(format!("{doc_root}.{}", comment_name.1))
-                .or_insert_with(|| BTreeMap::new())
-                .insert(comment_name.0, all_block_lines);
-
-            if check_insert.is_some() {
-                return Err(Error::new(
-                    ErrorKind::Other,
-                    format!(
-                        "Duplicate Sequence number exist in name of block {}",
-                        comment_name.0
-                    ),
-                ));
-            }
-
-            self.comment_block_names
-                .insert(self.current_comment_name.clone());
-            self.comment.clear();
-        }
-        Ok(())
+    /// - With the default grammar the Sequence number must be at the very end of the string in
+    ///   brackets
+    /// - The returned block name is trimmed, so `Name [0]` and `Name[0]` address the same file
+    /// - This enables the system to maintain Sequence history for comment blocks
+    /// - Sequence numbers are used to order comment blocks in output
+    fn strip_number_in_str(&self, a_string: &str) -> Result<(Sequence, String), Error> {
+        self.header_grammar.split(a_string).ok_or_else(|| {
+            Error::other(format!(
+                "[E001] No Sequence number exist in name of block, expected [{}]",
+                self.header_grammar.spec()
+            ))
+        })
     }
-    //#EPIC Get Lines.ITEM Parse file for line blocks [0]
+    //#EPIC Get Lines.ITEM Deterministic output [0]
     //#
-    //## Parse file for line blocks
-    //#Open the file iff it exist. Read the file line by line and check if the line starts with the _start_
-    //#string. If the line does start with the _start_ string then keep the line in the current _comment_
-    /// Parses a source file to extract specially formatted comment blocks and organize them into documentation.
+    //## Deterministic output
+    //#The generated files do not depend on the file system or on hash order: the source files are
+    //#walked in file name order, folder by folder, the generated files are written in block path
+    //#order and the blocks of a file in Sequence order. Two runs over the same sources give the
+    //#same files, written in the same order, so the generated tree can be committed to git.
+    /// Returns the blocks of the history sorted by their dot delimited block path, the order
+    /// the generated files are written in.
+    fn sorted_history(&self) -> Vec<(&String, &Sequences)> {
+        let mut history: Vec<(&String, &Sequences)> = self.comment_history.iter().collect();
+        history.sort_unstable_by_key(|(name, _)| *name);
+        history
+    }
+    /// Writes all accumulated comment blocks from history to their respective documentation files.
     ///
-    /// This function implements a state machine that processes files line by line, looking for comment blocks
-    /// that start with a specific marker string. It handles the complete life cycle of comment extraction:
+    /// This function serves as the final output phase of the documentation generation process,
+    /// iterating through all comment blocks stored in `comment_history` and writing them to
+    /// their designated markdown files in the documentation hierarchy.
     ///
     /// # Process Flow:
-    /// 1. **File Setup**: Opens the file and sets up folder prefix hierarchy from the dot-delimited prefix string
-    /// 2. **Line Processing**: Reads each line and checks for comment markers
-    /// 3. **State Management**: Tracks whether currently in CODE or COMMENT state
-    /// 4. **Comment Extraction**: When in COMMENT state, collects lines into comment blocks
-    /// 5. **Block Finalization**: Writes out completed comment blocks when returning to CODE state or EOF
-    ///
-    /// # State Transitions:
-    /// - **CODE → COMMENT**: When encountering a line starting with `start_of_comment`
-    /// - **COMMENT → CODE**: When encountering a non-comment line while in COMMENT state
-    /// - **Any → ERROR**: When parsing errors occur
+    /// 1. **Iteration**: Loops through all comment blocks in block path order, see
+    ///    `sorted_history`, and within a file in Sequence order
+    /// 2. **File Writing**: For each file, renders its comment blocks with `render_file` and
+    ///    sends them to the writer stage, a thread that calls `write_out_to_file` to
+    ///    create/append to the corresponding markdown file
+    /// 3. **Error Collection**: Accumulates any file writing errors without stopping the process
+    /// 4. **Final Error Check**: Returns a single error if any file operations failed
     ///
-    /// # Error Handling:
-    /// - I/O errors are propagated via Result
-    /// - Parsing errors set ERROR state and log to file/stdout
-    /// - Line counter tracks position for error reporting
+    /// # Data Structure Navigation:
+    /// - **Outer HashMap**: Keyed by file path (e.g., "doc_root.EPIC.ITEM")
+    /// - **Inner BTreeMap**: Keyed by Sequence number, maintains comment blocks in Sequence order
+    /// - **Value**: Vector of comment lines for each Sequence of a comment block
     ///
-    /// # Parameters:
-    /// - `file_name`: Path to source file to parse
-    /// - `doc_root`: Root directory for generated documentation
-    /// - `folder_prefix`: Dot-delimited string defining folder hierarchy for output
+    /// # Error Handling Strategy:
+    /// - **Non-blocking**: Continues processing all files even if some fail
+    /// - **Aggregated Errors**: Collects all error messages into a single string
+    /// - **Single Return**: Returns one comprehensive error if any failures occurred
     ///
     /// # Returns:
-    /// - `Ok(())` on successful parsing
-    /// - `Err(std::io::Error)` on I/O or parsing failures
-    fn parse_file(
-        &mut self,
-        file_name: &str,
-        doc_root: &str,
-        folder_prefix: &'a str,
-    ) -> Result<(), std::io::Error> {
-        let file = File::open(file_name)?;
-        let buf_reader = io::BufReader::new(file);
-        let folder_prefixes: Vec<&'a str> = folder_prefix.split(".").collect();
-        self.folder_prefixes = folder_prefixes;
-        for line in buf_reader.lines() {
-            let line = line?;
-            let potential_comment_line = line.trim();
-            if potential_comment_line.starts_with(self.start_of_comment.as_str()) {
-                if let Err(message) = self.parse_comment(potential_comment_line) {
-                    self.current_state = State::ERROR;
-                    if self.log_file.is_some() {
-                        let log = self.log_file.as_mut().unwrap();
-                        log.write_all(message.as_bytes())?;
-                    } else {
-                        println!("parse file {message}");
+    /// - `Ok(())` if all comment blocks were successfully written to files
+    /// - `Err(Error)` containing concatenated error messages if any file operations failed
+    ///
+    /// # Note:
+    /// - This function is typically called at the end of `comment_in_files` after all
+    ///   source files have been processed
+    /// - The use of BTreeMap ensures comment blocks are written in Sequence order
+    /// - File paths are constructed from the hierarchical comment block names
+    /// - Multiple Sequences of the same comment block are written to the same file
+    ///   in Sequence order
+    fn write_history(
+        &self,
+        only: Option<&BTreeSet<String>>,
+        doc_writer: &dyn DocWriter,
+    ) -> Result<(), Error> {
+        let mut last_error = None;
+        let (sender, rendered) = sync_channel::<(&str, usize, Vec<u8>)>(CHANNEL_CAPACITY);
+        thread::scope(|scope| {
+            let writer = scope.spawn(|| {
+                let mut last_error = None;
+                for (file_name, part, content) in rendered {
+                    if let Err(error) = self.write_out_to_file(
+                        &self.folder_prefixes,
+                        file_name,
+                        doc_writer.extension(),
+                        part,
+                        &content,
+                    ) {
+                        last_error = Some(error)
+                    }
+                    if self.background {
+                        thread::sleep(FILE_PAUSE);
                     }
                 }
-            } else {
-                if self.current_state == State::COMMENT {
-                    self.write_out_all_history(file_name, doc_root)?;
+                last_error
+            });
+            for (name, blocks) in self.sorted_history() {
+                let file_name = name.trim();
+                if only.is_some_and(|only| !only.contains(file_name)) {
+                    continue;
+                }
+                match self.render_parts(file_name, blocks, doc_writer) {
+                    Ok(parts) => {
+                        let sent = parts.into_iter().enumerate().all(|(index, content)| {
+                            sender.send((file_name, index + 1, content)).is_ok()
+                        });
+                        if !sent {
+                            break;
+                        }
+                    }
+                    Err(error) => last_error = Some(error),
                 }
             }
-            self.line_counter += 1u16;
-        }
-        if self.current_state == State::COMMENT {
-            self.write_out_all_history(file_name, doc_root)?;
+            drop(sender);
+            if let Ok(Some(writer_error)) = writer.join() {
+                last_error = Some(writer_error)
+            }
+        });
+        match last_error {
+            Some(error) => Err(error),
+            None => Ok(()),
         }
-        Ok(())
     }
-    //#EPIC Get Lines.ITEM Get Line Blocks in all files [0]
-    //#
-    //## Get all the line blocks by looking at all the files in the folder having the file name extension
-    //#Get all the files and filter by file type and file extension, then parse the filtered files. Write
-    //#out any errors encountered to the console.
-    /// Orchestrates the extraction of comment blocks from all files in a directory tree.
+    /// Validates the folder path structure and naming conventions for comment blocks.
     ///
-    /// This is the main entry point for the documentation generation system. It recursively scans
-    /// a directory structure, processes all files with the specified extension, extracts comment
-    /// blocks, and generates organized documentation output.
+    /// This function performs comprehensive validation on comment block names to ensure
+    /// they follow the required hierarchical structure and naming conventions before
+    /// being processed for documentation generation.
     ///
-    /// # Process Flow:
-    /// 1. **Setup**: Clears existing documentation directory and initializes parser state
-    /// 2. **Directory Traversal**: Recursively walks through the folder structure using WalkDir
-    /// 3. **File Filtering**: Processes only files with the specified extension
-    /// 4. **File Processing**: Calls `parse_file` on each matching file to extract comments
-    /// 5. **Error Handling**: Logs parsing errors but continues processing other files
-    /// 6. **Finalization**: Writes out all accumulated comment history to documentation files
+    /// # Validation Rules:
+    /// 1. **Path Structure**: The comment block name must be a dot-separated path
+    ///    (e.g., "EPIC.ITEM.TASK.Description")
+    /// 2. **Path Length**: The path cannot exceed the folder prefix hierarchy length + 1
+    ///    (e.g., if folder_prefixes has 3 elements, path can have up to 4 elements)
+    /// 3. **Prefix Matching**: Each path component (except the first) must start with
+    ///    the corresponding folder prefix
     ///
-    /// # Key Features:
-    /// - **Recursive Scanning**: Follows symbolic links and processes subdirectories
-    /// - **File Type Filtering**: Only processes files with specified extension (e.g., ".rs", ".py")
-    /// - **Error Resilience**: Continues processing even when individual files fail
-    /// - **Clean Output**: Removes existing documentation before generating new content
+    /// # Validation Process:
+    /// 1. **Path Parsing**: Splits the dot-separated path into components
+    /// 2. **Empty Check**: Ensures at least one path component exists
+    /// 3. **Length Check**: Validates path doesn't exceed maximum allowed depth
+    /// 4. **Prefix Validation**: Ensures each path component matches folder prefix hierarchy
     ///
     /// # Parameters:
-    /// - `folder_name`: Root directory to scan for source files
-    /// - `doc_root`: Output directory for generated documentation
-    /// - `start`: String that marks the beginning of comment blocks (e.g., "//#")
-    /// - `folder_prefixes`: Dot-delimited hierarchy for organizing output documentation
-    /// - `file_extension`: File extension filter (e.g., "rs" for Rust files)
+    /// - `folder_prefixes`: Expected folder hierarchy prefixes (e.g., ["EPIC", "ITEM", "TASK"])
+    /// - `file_path_and_name`: Dot-separated comment block path to validate
     ///
-    /// # Side Effects:
-    /// - Removes and recreates the `doc_root` directory
-    /// - Creates markdown files in the documentation hierarchy
-    /// - Prints error messages to console for failed file processing
+    /// # Returns:
+    /// - `Ok(())` if the path is valid
+    /// - `Err(String)` with descriptive error message if validation fails
     ///
-    /// # Note:
-    /// This function doesn't return a Result but handles errors internally by logging them,
-    /// allowing the process to continue even when individual files fail to parse.
-    pub fn comment_in_files(
-        &mut self,
-        folder_name: &str,
-        doc_root: &str,
-        start: &str,
-        folder_prefixes: &'a str,
-        file_extension: &str,
-    ) {
-        let _ = remove_dir_all(doc_root);
-        self.start_of_comment = start.to_string();
-        self.current_state = State::CODE;
+    /// # Error Messages:
+    /// - "There is no file path in the first line of the comment block." - Empty path
+    /// - "Path is longer than what is allowed." - Path exceeds maximum depth
+    /// - "Invalid folder prefix [actual] [expected]." - Path component doesn't match prefix
+    ///
+    /// # Example:
+    /// For folder_prefixes = ["EPIC", "ITEM", "TASK"]:
+    /// - Valid: "EPIC epic.ITEM item.TASK task.Description"
+    /// - Invalid: "EPIC epic.ITEM wrong.TASK task" (second component doesn't start with "ITEM")
+    /// - Invalid: "EPIC epic.ITEM item.TASK task.Too.Long" (exceeds maximum depth)
+    fn is_valid_folder_path(
+        &self,
+        folder_prefixes: &Vec<&str>,
+        file_path_and_name: &str,
+    ) -> Result<(), String> {
+        let path: Vec<&str> = file_path_and_name.split(".").collect();
+        if path.is_empty() {
+            return Err(
+                "There is no file path in the first line of the comment block.".to_string(),
+            );
+        }
+        if path.len() > folder_prefixes.len() + 1 {
+            return Err("[E006] Path is longer than what is allowed.".to_string());
+        }
 
-        for entry in WalkDir::new(folder_name)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let file_name = entry.file_name().to_string_lossy();
-            if entry.file_type().is_file() && file_name.ends_with(file_extension) {
-                if let Some(name) = entry.path().to_str() {
-                    self.line_counter = 1u16;
-                    if let Err(error) = self.parse_file(name, doc_root, folder_prefixes) {
-                        println!("comment in file {error:?}");
-                    } else {
-                        if self.current_state == State::ERROR {
-                            println!("Error occurred while parsing file: {}", name);
-                        }
-                        // to do log None case as file is deleted while getting scanned
-                    }
-                }
+        let prefixes: Vec<_> = path[1..].iter().zip(folder_prefixes).collect();
+        for item in prefixes {
+            if !item.0.starts_with(item.1) {
+                return Err(format!(
+                    "[E008] Invalid folder prefix [{}] [{}].",
+                    item.0, item.1
+                ));
             }
         }
-        // all files is processed to print out the history of self lines
-        if let Err(error) = self.write_history() {
-            println!("write history {error:?}");
-        };
+        Ok(())
     }
-}
-
-#[cfg(test)]
-#[test]
-fn test_if_file_path_is_valid() {
-    let mut comments = Comments::default();
-    let path = &vec!["EPIC", "ITEM", "TEST"];
-    if let Err(error) = comments.is_valid_folder_path(path, "EPIC epic.ITEM item.TEST test") {
-        println!("test {error}");
+    /// Validates the name of a generated file like `is_valid_folder_path`, except for the
+    /// names that blocks were moved to with an `@path` directive, which may lie outside the
+    /// path prefixes.
+    fn is_valid_output_name(
+        &self,
+        folder_prefixes: &Vec<&str>,
+        file_path_and_name: &str,
+    ) -> Result<(), String> {
+        match self.comment_history.get(file_path_and_name) {
+            Some(blocks) if has_path_override(blocks) => Ok(()),
+            _ => self.is_valid_folder_path(folder_prefixes, file_path_and_name),
+        }
     }
-    comments.current_comment_name = "EPIC epic.ITEM item.TEST test".to_string();
-    comments
-        .comment_block_names
-        .insert(comments.current_comment_name.clone());
-    if let Err(error) = comments.is_valid_folder_path(path, "EPIC epic.ITEM item.TEST test") {
-        println!("{error}");
+    /// Initializes a new comment block by extracting metadata from the first comment line.
+    ///
+    /// This function is called when transitioning from CODE to COMMENT state to process
+    /// the first line of a comment block, which contains the comment block name and
+    /// serves as the metadata header for the entire comment block.
+    ///
+    /// # Key Operations:
+    /// - **Name Extraction**: Strips the comment marker prefix and trims whitespace to get
+    ///   the comment block name (e.g., "//# EPIC.Get Lines.ITEM Test Block [1]" → "EPIC.Get Lines.ITEM Test Block [1]")
+    /// - **Line Number Recording**: Sets `comment_line_start` to track where the comment block begins
+    /// - **Name Storage**: Stores the extracted comment block name for later processing
+    ///
+    /// # Parameters:
+    /// - `line`: The first line of a comment block including the comment marker prefix
+    ///
+    /// # Returns:
+    /// - `Ok(())` on successful initialization
+    /// - `Err(String)` if the comment block name is invalid (though currently no validation occurs)
+    ///
+    /// # Note:
+    /// - The comment block name typically follows a hierarchical naming convention with
+    ///   dot-separated components (e.g., "EPIC.ITEM.TASK")
+    /// - The line number is recorded as `line_counter + 1` because `line_counter` tracks
+    ///   the line that was just processed, and we want the starting line of the comment
+    /// - This function is called exclusively by `parse_comment` during state transitions
+    /// - The extracted comment block name will later be processed by `strip_number_in_str`
+    ///   to separate Sequence numbers from the actual block name
+    fn parse_comment_start(&mut self, line: &str) -> Result<(), String> {
+        let comment_name = line[self.start_of_comment.len()..].trim();
+        self.comment_line_start = self.line_counter + 1;
+        self.current_comment_name = comment_name.to_string();
+        Ok(())
     }
+    /// Processes individual comment lines and manages comment block state transitions.
+    ///
+    /// This function handles the core logic of parsing comment lines and managing the state
+    /// machine transitions between CODE and COMMENT states. It distinguishes between the
+    /// first line of a comment block (which contains metadata) and subsequent comment lines.
+    ///
+    /// # State Machine Logic:
+    /// - **CODE → COMMENT**: When encountering the first comment line, transitions to COMMENT state
+    ///   and calls `parse_comment_start` to extract block metadata (name, line number)
+    /// - **COMMENT → COMMENT**: When already in COMMENT state, adds the line content to the
+    ///   current comment block buffer
+    ///
+    /// # Line Processing:
+    /// - **First Comment Line**: Contains the comment block name and triggers state transition
+    /// - **Subsequent Lines**: Contain actual comment content, stripped of the comment marker
+    ///   and of one space after it, so deeper indentation such as in code fences is kept
+    ///
+    /// # Parameters:
+    /// - `line`: The raw comment line including the comment marker prefix
+    ///
+    /// # Returns:
+    /// - `Ok(())` on successful parsing
+    /// - `Err(String)` if `parse_comment_start` fails (e.g., invalid comment block name)
+    ///
+    /// # Error Conditions:
+    /// - Failure in `parse_comment_start` when processing the first comment line
+    /// - Invalid comment block name format
+    ///
+    /// # Note:
+    /// This function is called by `parse_file` for every line that starts with the
+    /// comment marker string. It's responsible for the state transitions that define
+    /// comment block boundaries.
+    fn parse_comment(&mut self, line: &str) -> Result<(), String> {
+        if self.current_state == State::CODE {
+            self.current_state = State::COMMENT;
+            self.parse_comment_start(line)?;
+        } else {
+            // one space after the marker separates it from the text, further indentation is kept
+            let text = &line[self.start_of_comment.len()..];
+            let comment_line = text.strip_prefix(' ').unwrap_or(text);
+            self.comment.push(comment_line.to_string());
+        }
+        Ok(())
+    }
+    //#EPIC Get Lines.ITEM Write out all of the history [0]
+    //#
+    //##Write out all blocks encountered in the past after the last file was processed
+    /// Finalizes and stores a completed comment block into the comment history.
+    ///
+    /// This function is called when a comment block ends (either by encountering non-comment lines
+    /// or reaching end of file) to process the accumulated comment lines and store them in the
+    /// comment history for later output.
+    ///
+    /// # Process Flow:
+    /// 1. **State Transition**: Returns parser state from COMMENT to CODE
+    /// 2. **Block Preparation**: Adds source file metadata and line number to comment block
+    ///    and runs the block body through the configured text filters
+    /// 3. **Sequence Extraction**: Parses Sequence number from comment block name using regex
+    /// 4. **History Storage**: Stores the comment block in the hierarchical comment history
+    /// 5. **Duplicate Prevention**: Checks for duplicate Sequence numbers in the same block name
+    /// 6. **Cleanup**: Clears the current comment buffer for the next block
+    ///
+    /// # Key Operations:
+    /// - **Metadata Addition**: Prepends source file path and line number to comment block
+    /// - **Sequence Management**: Extracts and validates Sequence numbers from block names
+    /// - **Hierarchical Storage**: Organizes comments by documentation path and Sequence
+    /// - **Duplicate Detection**: Ensures unique Sequence numbers per comment block name
+    ///
+    /// # Parameters:
+    /// - `file_name`: Source file path where the comment block was found
+    /// - `doc_root`: Base documentation path for organizing output
+    ///
+    /// # Returns:
+    /// - `Ok(())` on successful storage
+    /// - `Err(std::io::Error)` if duplicate Sequence numbers are detected
+    ///
+    /// # Error Conditions:
+    /// - Duplicate Sequence numbers in the same comment block name
+    /// - A block name spelled differently elsewhere, see `check_unique_name`
+    /// - Invalid Sequence number format in comment block name
+    ///
+    /// # Note:
+    /// The function uses BTreeMap to maintain comment blocks in Sequence order.
+    fn write_out_all_history(
+        &mut self,
+        file_name: &str,
+        doc_root: &str,
+    ) -> Result<(), std::io::Error> {
+        self.current_state = State::CODE;
+        if self.comment.is_empty() {
+            let header_line = self.comment_line_start.saturating_sub(1);
+            let message = "Block has no lines and is not written";
+            self.events.warn(format!(
+                "{message}: {} in {file_name} LINE: {header_line}",
+                self.current_comment_name.trim()
+            ));
+            self.diagnostics.push(Diagnostic::new(
+                file_name,
+                header_line,
+                &self.current_comment_name,
+                message,
+            ));
+        } else {
+            // keep history of comments
+            let mut block = Block::with_fields(
+                self.source_link_path(file_name).into(),
+                self.comment_line_start,
+                std::mem::take(&mut self.comment),
+                &std::mem::take(&mut self.raw_lines),
+                self.fields.as_ref(),
+            );
+            let (lines, raw) =
+                apply_filters_except_raw(&self.filters, block.lines.to_vec(), &block.raw);
+            (block.lines, block.raw) = (lines.into(), raw);
+            block.path = file_name.into();
+            block.end_line = self.line_counter.saturating_sub(1);
+            block.modified = std::fs::metadata(file_name)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            block.generated = self.generated_file;
+            if let Some(fields) = &self.fields {
+                fields.validate(&block.metadata).map_err(Error::other)?;
+            }
+            if let Some(glossary) = self.glossary.as_mut()
+                && let Some(term) = self.current_comment_name.strip_prefix(TERM_PREFIX)
+            {
+                // `//#TERM Line block [0]` defines the term `Line block`
+                let term = match self.header_grammar.split(term) {
+                    Some((_, term)) => term,
+                    None => term.trim().to_string(),
+                };
+                glossary
+                    .add_definition(&term, block.lines.to_vec())
+                    .map_err(Error::other)?;
+                return Ok(());
+            }
+            let mut auto = false;
+            let mut comment_name = match self.strip_number_in_str(&self.current_comment_name) {
+                Ok(comment_name) => comment_name,
+                Err(_) if self.auto_sequence => {
+                    auto = true;
+                    (
+                        Sequence::default(),
+                        self.current_comment_name.trim().to_string(),
+                    )
+                }
+                Err(error) => return Err(error),
+            };
+            if self.infer_path {
+                comment_name.1 = self.infer_block_path(file_name, &comment_name.1);
+            }
+            self.check_unique_name(&comment_name.1, file_name)?;
+
+            let moved = block.path_override().is_some();
+            if let Some(path) = block.path_override() {
+                let original = std::mem::replace(&mut comment_name.1, override_name(path)?);
+                let owner = self
+                    .path_overrides
+                    .entry(comment_name.1.clone())
+                    .or_insert_with(|| original.clone());
+                if *owner != original {
+                    return Err(Error::other(format!(
+                        "[E003] Output path {path} of block {original} is already used by block {owner}"
+                    )));
+                }
+            }
+            let key = format!("{doc_root}.{}", comment_name.1);
+            if !auto
+                && self
+                    .header_grammar
+                    .is_continuation(&self.current_comment_name)
+            {
+                // appended to the block it continues once all files are parsed
+                self.continuations.push((key, comment_name.0, block));
+                return Ok(());
+            }
+            if let Some(blocks) = self.comment_history.get(&key)
+                && has_path_override(blocks) != moved
+            {
+                return Err(Error::other(format!(
+                    "[E003] Output path of block {} is already used by another block",
+                    comment_name.1
+                )));
+            }
+            match auto {
+                true => {
+                    comment_name.0 =
+                        next_sequence(self.comment_history.get(&key), Sequence::default())?;
+                    self.auto_numbered.insert((key.clone(), comment_name.0));
+                }
+                false => self.renumber_auto_sequence(&key, comment_name.0)?,
+            }
+            let blocks = self.comment_history.entry(key.clone()).or_default();
+            // the first block with the Sequence number is kept, the duplicate is reported
+            if let Some(first) = blocks.get(&comment_name.0) {
+                return Err(Error::other(format!(
+                    "[E002] Duplicate Sequence number exist in name of block {} (first defined in {} LINE: {})",
+                    comment_name.0,
+                    first.path,
+                    first.start_line.saturating_sub(1)
+                )));
+            }
+            blocks.insert(comment_name.0, block);
+            self.events.emit(Event::BlockFound {
+                name: &comment_name.1,
+                sequence: comment_name.0,
+                block: &self.comment_history[&key][&comment_name.0],
+            });
+            self.comment.clear();
+            if let Some(count) = self.comment_history[&key][&comment_name.0]
+                .metadata
+                .get(INCLUDE_CODE)
+            {
+                match count.trim().parse::<usize>() {
+                    Ok(count) => self.pending_code = Some((key.clone(), comment_name.0, count)),
+                    Err(_) => self.events.warn(format!(
+                        "Invalid @{INCLUDE_CODE} value {count} in {file_name} LINE: {}",
+                        self.comment_line_start.saturating_sub(1)
+                    )),
+                }
+            }
+            self.pending_anchor = Some((key, comment_name.0));
+        }
+        Ok(())
+    }
+    /// Checks that a block name is spelled the same wherever it is used. Names are compared
+    /// without their Sequence numbers, in lower case and with runs of white space as one
+    /// space, so `EPIC A.ITEM b` and `EPIC A.ITEM  B` are taken for the same block; they
+    /// would otherwise be written to separate files, which overwrite each other on case
+    /// insensitive file systems.
+    ///
+    /// # Returns:
+    /// - `Err(Error)` naming the file and header line where the other spelling was found
+    fn check_unique_name(&mut self, name: &str, file_name: &str) -> Result<(), Error> {
+        let folded = name
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        let header_line = self.comment_line_start.saturating_sub(1);
+        let (known, known_file, known_line) = self
+            .comment_block_names
+            .entry(folded)
+            .or_insert_with(|| (name.to_string(), file_name.to_string(), header_line));
+        if known.as_str() != name {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "[E007] Block name {known} in {known_file} LINE: {known_line} is spelled {name}"
+                ),
+            ));
+        }
+        Ok(())
+    }
+    //#EPIC Get Lines.ITEM Continue a block in another file [0]
+    //#
+    //## Continue a block in another file
+    //#A block that documents code spread over several files can be continued in another file
+    //#with a `+` after the Sequence number of the block it continues, as in `[2+]`. The body of
+    //#the continuation is appended to the block instead of being rejected as a duplicate
+    //#Sequence number; several continuations are appended in the order of their files and lines.
+    /// Appends every continuation block to the block it continues, ordered by source file and
+    /// line, so the result does not depend on the order the files were parsed in. A
+    /// continuation without a block to continue is reported and left out.
+    fn merge_continuations(&mut self, doc_root: &str) {
+        let mut continuations = std::mem::take(&mut self.continuations);
+        continuations.sort_by(|(_, _, left), (_, _, right)| {
+            (&left.path, left.start_line).cmp(&(&right.path, right.start_line))
+        });
+        for (key, sequence, continuation) in continuations {
+            match self
+                .comment_history
+                .get_mut(&key)
+                .and_then(|blocks| blocks.get_mut(&sequence))
+            {
+                Some(block) => {
+                    self.dependencies
+                        .push((key.clone(), continuation.path.to_string()));
+                    block.append(continuation);
+                }
+                None => {
+                    let name = relative_name(&key, doc_root);
+                    let header_line = continuation.start_line.saturating_sub(1);
+                    let message = format!("[E013] Block {name} [{sequence}+] continues no block");
+                    self.diagnostics.push(Diagnostic::new(
+                        &continuation.path,
+                        header_line,
+                        &format!("{name} [{sequence}+]"),
+                        &message,
+                    ));
+                    let error = Error::other(format!(
+                        "{message} in {} LINE: {header_line}",
+                        continuation.path
+                    ));
+                    self.failures.report("comment in file", error, &self.events);
+                }
+            }
+        }
+    }
+    /// Looks up the last commit of the lines of every block, see `with_git_metadata`. A
+    /// source file without git history is warned about once, and its blocks are written
+    /// without commit.
+    fn add_git_metadata(&mut self) {
+        let mut warned = BTreeSet::new();
+        for block in self
+            .comment_history
+            .values_mut()
+            .flat_map(|blocks| blocks.values_mut())
+        {
+            let start = block.start_line.saturating_sub(1).max(1);
+            match last_commit(&block.path, start, block.end_line) {
+                Ok(commit) => block.last_commit = commit,
+                Err(error) => {
+                    if warned.insert(block.path.clone()) {
+                        self.events
+                            .warn(format!("No git history for {}: {error}", block.path));
+                    }
+                }
+            }
+        }
+    }
+    /// Moves an automatically numbered block out of the way of a block that was given the same
+    /// Sequence number explicitly, to the next free Sequence number.
+    fn renumber_auto_sequence(&mut self, key: &str, sequence: Sequence) -> Result<(), Error> {
+        if !self.auto_numbered.remove(&(key.to_string(), sequence)) {
+            return Ok(());
+        }
+        let blocks = self.comment_history.entry(key.to_string()).or_default();
+        if let Some(block) = blocks.remove(&sequence) {
+            let next = next_sequence(Some(blocks), sequence)?;
+            blocks.insert(next, block);
+            self.auto_numbered.insert((key.to_string(), next));
+        }
+        Ok(())
+    }
+    /// Records the first line of code after the most recent block as that block's code anchor.
+    fn anchor_pending_block(&mut self, code_line: &str) {
+        if let Some((key, sequence)) = self.pending_anchor.take()
+            && let Some(block) = self
+                .comment_history
+                .get_mut(&key)
+                .and_then(|blocks| blocks.get_mut(&sequence))
+        {
+            block.code_anchor = Some(code_line.into());
+        }
+    }
+    //#EPIC Get Lines.ITEM Parse file for line blocks [0]
+    //#
+    //## Parse file for line blocks
+    //#Take the content of the file as read by the walker stage. Go through the file line by line and check if the line starts with the _start_
+    //#string. If the line does start with the _start_ string then keep the line in the current _comment_
+    /// Parses a source file to extract specially formatted comment blocks and organize them into documentation.
+    ///
+    /// This function implements a state machine that processes files line by line, looking for comment blocks
+    /// that start with a specific marker string. It handles the complete life cycle of comment extraction:
+    ///
+    /// # Process Flow:
+    /// 1. **File Setup**: Sets up folder prefix hierarchy from the dot-delimited prefix string,
+    ///    resets the line counter and state, and selects the comment marker for the file's
+    ///    extension
+    /// 2. **Line Processing**: Takes each line of the content and checks for comment markers
+    /// 3. **State Management**: Tracks whether currently in CODE or COMMENT state
+    /// 4. **Comment Extraction**: When in COMMENT state, collects lines into comment blocks
+    /// 5. **Block Finalization**: Writes out completed comment blocks when returning to CODE state or EOF
+    ///
+    /// # State Transitions:
+    /// - **CODE → COMMENT**: When encountering a line starting with `start_of_comment`
+    /// - **COMMENT → CODE**: When encountering a non-comment line while in COMMENT state
+    /// - **Any → ERROR**: When parsing errors occur
+    ///
+    /// # Error Handling:
+    /// - I/O errors are propagated via Result
+    /// - Parsing errors set ERROR state and log to file/stdout
+    /// - Line counter tracks position for error reporting
+    ///
+    /// # Parameters:
+    /// - `file_name`: Path to source file to parse
+    /// - `content`: Content of the source file, as read by the walker stage
+    /// - `doc_root`: Root directory for generated documentation
+    /// - `folder_prefix`: Dot-delimited string defining folder hierarchy for output
+    ///
+    /// # Returns:
+    /// - `Ok(())` on successful parsing
+    /// - `Err(std::io::Error)` on I/O or parsing failures. The parser goes on after a block that
+    ///   could not be stored, and the error names every such block with its header line and
+    ///   the lines around it, see `reject_block`
+    fn parse_file(
+        &mut self,
+        file_name: &str,
+        content: &str,
+        doc_root: &str,
+        folder_prefix: &'a str,
+    ) -> Result<(), std::io::Error> {
+        let folder_prefixes: Vec<&'a str> = folder_prefix.split(".").collect();
+        self.folder_prefixes = folder_prefixes;
+        self.line_counter = 1;
+        self.current_state = State::CODE;
+        self.pending_code = None;
+        self.included_code.clear();
+        self.open_snippets.clear();
+        self.block_errors.clear();
+        if !self.select_marker(file_name) {
+            self.events.warn(format!(
+                "No comment marker configured for {file_name}, the file is skipped"
+            ));
+            return Ok(());
+        }
+        self.literals = SourceLiterals::of_file(file_name);
+        self.generated_file = self.is_generated_source(file_name);
+        if self.is_incremental() {
+            self.source_hashes
+                .insert(file_name.to_string(), content_hash(content));
+        }
+        self.parse_lines(file_name, content, doc_root);
+        let mut block_errors = std::mem::take(&mut self.block_errors).into_iter();
+        match block_errors.next() {
+            None => Ok(()),
+            Some(first) => {
+                let message = block_errors.fold(first.to_string(), |message, error| {
+                    format!("{message}\n{error}")
+                });
+                Err(Error::new(first.kind(), message))
+            }
+        }
+    }
+    /// Records a block that could not be stored, such as for a header without a Sequence
+    /// number or a duplicate Sequence number. The block is sent to the subscriber and added to
+    /// the diagnostics, its error is kept with the lines around the header, see
+    /// `with_snippet`, and the parser goes back to the CODE state to parse the rest of the file.
+    fn reject_block(&mut self, error: Error, file_name: &str, content: &str) {
+        let header_line = self.comment_line_start.saturating_sub(1);
+        let message = error.to_string();
+        self.events.emit(Event::BlockFailed {
+            path: file_name,
+            line: header_line,
+            header: &self.current_comment_name,
+            message: &message,
+        });
+        self.diagnostics.push(Diagnostic::new(
+            file_name,
+            header_line,
+            &self.current_comment_name,
+            &message,
+        ));
+        self.block_errors
+            .push(with_snippet(error, file_name, content, header_line));
+        self.current_state = State::CODE;
+        self.comment.clear();
+        self.raw_lines.clear();
+    }
+    /// Stores the block of the current comment, see `write_out_all_history`, or rejects it,
+    /// see `reject_block`.
+    fn finish_block(&mut self, file_name: &str, content: &str, doc_root: &str) {
+        if let Err(error) = self.write_out_all_history(file_name, doc_root) {
+            self.reject_block(error, file_name, content);
+        }
+    }
+    /// Runs the state machine of `parse_file` over the lines of a source file. A block that
+    /// could not be stored does not stop the parser, see `reject_block`.
+    fn parse_lines(&mut self, file_name: &str, content: &str, doc_root: &str) {
+        let mut extracting = true;
+        let mut raw = false;
+        let mut ended = false;
+        for line in content.lines() {
+            let was_code = self.current_state == State::CODE;
+            let marked =
+                self.marker_position
+                    .marked_text(line, &self.start_of_comment, &mut self.literals);
+            if marked.is_some() {
+                self.finish_included_code(file_name);
+            }
+            if let Some(directive) = marked.and_then(|text| self.parser_directive(text)) {
+                if self.current_state == State::COMMENT && !directive.ends_with("raw") {
+                    self.finish_block(file_name, content, doc_root);
+                }
+                match directive {
+                    "raw" | "end-raw" if self.current_state == State::COMMENT => {
+                        raw = directive == "raw";
+                    }
+                    "off" => extracting = false,
+                    "on" => extracting = true,
+                    "generated" => self.mark_generated(file_name),
+                    "ignore-file" => {
+                        self.skipped_files += 1;
+                        self.forget_blocks_of(file_name);
+                        self.pending_anchor = None;
+                        return;
+                    }
+                    other => self.events.warn(format!(
+                        "Unknown directive get-comments:{other} in {file_name} LINE: {}",
+                        self.line_counter
+                    )),
+                }
+            } else if let Some((begin, name)) = marked
+                .filter(|_| self.end_of_comment.is_none() || was_code)
+                .and_then(|text| self.snippet_marker(text))
+            {
+                if self.current_state == State::COMMENT {
+                    self.finish_block(file_name, content, doc_root);
+                }
+                self.mark_snippet(begin, name, file_name);
+            } else if !extracting {
+                // lines between get-comments:off and get-comments:on are not extracted
+            } else if self.end_of_comment.is_some() {
+                if let Err(error) = self.parse_block_comment_line(line, file_name, doc_root) {
+                    self.reject_block(error, file_name, content);
+                }
+            } else if raw
+                && self.current_state == State::COMMENT
+                && marked.is_some()
+                && let Some(position) = line.find(self.start_of_comment.as_str())
+            {
+                self.raw_lines.insert(self.comment.len());
+                self.comment
+                    .push(line[position + self.start_of_comment.len()..].to_string());
+            } else if let Some(text) = marked
+                && self.is_block_end(text)
+            {
+                if self.current_state == State::COMMENT {
+                    self.finish_block(file_name, content, doc_root);
+                }
+                ended = true;
+            } else if ended && marked.is_some_and(|text| !self.is_block_header(text)) {
+                // marked lines after the end of a block are not extracted, up to a new header
+            } else if let Some(text) = marked {
+                ended = false;
+                if self.current_state == State::CODE {
+                    self.pending_anchor = None;
+                }
+                if let Err(message) = self.parse_comment(text) {
+                    self.current_state = State::ERROR;
+                    self.events.error(&format!("parse file {message}"));
+                    self.failures.record(
+                        FailureKind::Parse,
+                        format!(
+                            "parse file {message} in {file_name} LINE: {}",
+                            self.line_counter
+                        ),
+                        &self.events,
+                    );
+                }
+            } else {
+                if self.current_state == State::COMMENT {
+                    self.finish_block(file_name, content, doc_root);
+                }
+                if !line.trim().is_empty() {
+                    self.anchor_pending_block(line.trim());
+                }
+                self.include_code_line(line, file_name);
+            }
+            if self.current_state != State::COMMENT {
+                raw = false;
+            }
+            if marked.is_none() {
+                ended = false;
+                // the closing line of a block comment is no code
+                if self.end_of_comment.is_none() || (was_code && self.current_state == State::CODE)
+                {
+                    for (_, _, lines) in &mut self.open_snippets {
+                        lines.push(line.to_string());
+                    }
+                }
+            }
+            self.line_counter += 1;
+        }
+        for (name, start_line, _) in std::mem::take(&mut self.open_snippets) {
+            self.events.warn(format!(
+                "Snippet not ended: {name} in {file_name} LINE: {}",
+                start_line.saturating_sub(1)
+            ));
+        }
+        self.pending_anchor = None;
+        if self.current_state == State::COMMENT {
+            if let Some(end) = &self.end_of_comment {
+                self.events.warn(format!(
+                    "Block comment not closed with {end} in {file_name} LINE: {}",
+                    self.comment_line_start.saturating_sub(1)
+                ));
+            }
+            self.finish_block(file_name, content, doc_root);
+        }
+        self.finish_included_code(file_name);
+    }
+    /// Handles one line in block comment mode, see `with_end_marker`.
+    ///
+    /// # State Transitions:
+    /// - **CODE → COMMENT**: On a line starting with `start_of_comment`; the rest of the line,
+    ///   up to the end marker, is the block header
+    /// - **COMMENT → CODE**: On the line holding the end marker, which finishes the block
+    fn parse_block_comment_line(
+        &mut self,
+        line: &str,
+        file_name: &str,
+        doc_root: &str,
+    ) -> Result<(), std::io::Error> {
+        let end = self.end_of_comment.clone().unwrap_or_default();
+        let trimmed = line.trim();
+        if self.current_state == State::COMMENT {
+            let (text, closed) = match line.find(end.as_str()) {
+                Some(position) => (line[..position].trim_end(), true),
+                None => (line, false),
+            };
+            let text = text
+                .strip_prefix(self.comment_indent.as_str())
+                .unwrap_or_else(|| text.trim_start());
+            if !closed || !text.trim().is_empty() {
+                self.comment.push(text.to_string());
+            }
+            if closed {
+                self.write_out_all_history(file_name, doc_root)?;
+            }
+        } else if let Some(marked) =
+            self.marker_position
+                .marked_text(line, &self.start_of_comment, &mut self.literals)
+        {
+            self.pending_anchor = None;
+            let header = &marked[self.start_of_comment.len()..];
+            let closed = header.find(end.as_str());
+            let header = &marked[..self.start_of_comment.len() + closed.unwrap_or(header.len())];
+            self.current_state = State::COMMENT;
+            self.parse_comment_start(header).map_err(Error::other)?;
+            self.comment_indent = line[..line.len() - line.trim_start().len()].to_string();
+            if closed.is_some() {
+                self.write_out_all_history(file_name, doc_root)?;
+            }
+        } else {
+            if !trimmed.is_empty() {
+                self.anchor_pending_block(trimmed);
+            }
+            self.include_code_line(line, file_name);
+        }
+        Ok(())
+    }
+    /// Collects a source line after a block with `@include-code`, and embeds the collected
+    /// lines once there are as many as the directive asks for.
+    fn include_code_line(&mut self, line: &str, file_name: &str) {
+        let Some((_, _, count)) = self.pending_code else {
+            return;
+        };
+        self.included_code.push(line.to_string());
+        if self.included_code.len() >= count {
+            self.finish_included_code(file_name);
+        }
+    }
+    /// Embeds the source lines collected for a block with `@include-code` in its body, see
+    /// `append_code`.
+    fn finish_included_code(&mut self, file_name: &str) {
+        let Some((key, sequence, _)) = self.pending_code.take() else {
+            return;
+        };
+        let code = std::mem::take(&mut self.included_code);
+        if let Some(block) = self
+            .comment_history
+            .get_mut(&key)
+            .and_then(|blocks| blocks.get_mut(&sequence))
+        {
+            append_code(block, file_name, code);
+        }
+    }
+    /// Returns whether a marked line begins or ends a snippet, with the name of the snippet,
+    /// as in `//#snippet-begin parse` or `/*#snippet-end parse */`.
+    fn snippet_marker<'l>(&self, text: &'l str) -> Option<(bool, &'l str)> {
+        let text = text[self.start_of_comment.len()..].trim();
+        let text = match &self.end_of_comment {
+            Some(end) => text.strip_suffix(end.as_str()).unwrap_or(text).trim_end(),
+            None => text,
+        };
+        match text.strip_prefix("snippet-begin ") {
+            Some(name) => Some((true, name.trim())),
+            None => text
+                .strip_prefix("snippet-end ")
+                .map(|name| (false, name.trim())),
+        }
+    }
+    /// Opens a snippet region on the line after the current one, or stores the open region
+    /// of the same name as a snippet.
+    fn mark_snippet(&mut self, begin: bool, name: &str, file_name: &str) {
+        if begin {
+            self.open_snippets
+                .push((name.to_string(), self.line_counter + 1, Vec::new()));
+            return;
+        }
+        let Some(index) = self
+            .open_snippets
+            .iter()
+            .position(|(open, _, _)| open == name)
+        else {
+            self.events.warn(format!(
+                "Snippet ended without a begin: {name} in {file_name} LINE: {}",
+                self.line_counter
+            ));
+            return;
+        };
+        let (name, start_line, lines) = self.open_snippets.remove(index);
+        let snippet = Snippet {
+            source_file: file_name.to_string(),
+            start_line,
+            lines,
+        };
+        if let Some(previous) = self.snippets.insert(name.clone(), snippet) {
+            self.events.warn(format!(
+                "Snippet {name} in {file_name} LINE: {} replaces the one in {} LINE: {}",
+                start_line.saturating_sub(1),
+                previous.source_file,
+                previous.start_line.saturating_sub(1)
+            ));
+        }
+    }
+    /// Replaces the references to snippets in the block bodies by the code of the snippets,
+    /// see `embed_snippets`, and warns about references to unknown snippets. The generated
+    /// file of a block depends on the source files of the snippets it embeds.
+    fn embed_snippets(&mut self) {
+        for (key, block) in self
+            .comment_history
+            .iter_mut()
+            .flat_map(|(key, blocks)| blocks.values_mut().map(move |block| (key, block)))
+        {
+            let embedded = block
+                .lines
+                .iter()
+                .filter_map(|line| self.snippets.get(snippet_reference(line)?))
+                .map(|snippet| (key.clone(), snippet.source_file.clone()));
+            self.dependencies.extend(embedded);
+            for name in embed_snippets(block, &self.snippets) {
+                self.events.warn(format!(
+                    "Unknown snippet {name} in {} LINE: {}",
+                    block.source_file, block.start_line
+                ));
+            }
+        }
+    }
+    /// Tells whether a marked line ends the block, see `with_block_end`.
+    fn is_block_end(&self, text: &str) -> bool {
+        self.block_end
+            .as_deref()
+            .is_some_and(|end| text[self.start_of_comment.len()..].trim() == end)
+    }
+    /// Tells whether the text of a marked line, marker included, is a block header matching
+    /// the header grammar, which starts a new block after the end of one.
+    fn is_block_header(&self, text: &str) -> bool {
+        self.header_grammar
+            .split(&text[self.start_of_comment.len()..])
+            .is_some()
+    }
+    /// Returns the name of a parser directive such as `//#get-comments:off`, or `None` when
+    /// the line is not a directive.
+    ///
+    /// # Directives:
+    /// - `get-comments:off` stops extraction until the next `get-comments:on`
+    /// - `get-comments:on` resumes extraction
+    /// - `get-comments:ignore-file` excludes the whole file, including blocks found before it
+    /// - `get-comments:generated` marks the whole file as machine-generated, see
+    ///   `with_generated_sources`
+    ///
+    /// In block comment mode the directive may be closed on the same line, as in
+    /// `/*# get-comments:off */`.
+    fn parser_directive<'l>(&self, line: &'l str) -> Option<&'l str> {
+        let directive = line
+            .strip_prefix(self.start_of_comment.as_str())?
+            .trim()
+            .strip_prefix("get-comments:")?;
+        let directive = match &self.end_of_comment {
+            Some(end) => directive
+                .trim_end()
+                .strip_suffix(end.as_str())
+                .unwrap_or(directive),
+            None => directive,
+        };
+        Some(directive.trim())
+    }
+    /// Marks the given source file as machine-generated, including the blocks found in it
+    /// before the directive.
+    fn mark_generated(&mut self, file_name: &str) {
+        self.generated_file = true;
+        for block in self
+            .comment_history
+            .values_mut()
+            .flat_map(|blocks| blocks.values_mut())
+            .filter(|block| &*block.path == file_name)
+        {
+            block.generated = true;
+        }
+    }
+    /// Removes every block that was found in the given source file from the history, and the
+    /// blocks of the file that were rejected from the diagnostics. Files are parsed one at a
+    /// time, so the block names first used in the file are used by no other file yet and are
+    /// forgotten as well.
+    fn forget_blocks_of(&mut self, file_name: &str) {
+        self.block_errors.clear();
+        self.diagnostics
+            .retain(|diagnostic| diagnostic.file != file_name);
+        self.comment_block_names
+            .retain(|_, (_, known_file, _)| known_file != file_name);
+        self.continuations
+            .retain(|(_, _, block)| &*block.path != file_name);
+        self.comment_history.retain(|_, blocks| {
+            blocks.retain(|_, block| &*block.path != file_name);
+            !blocks.is_empty()
+        });
+    }
+    //#EPIC Get Lines.ITEM Get Line Blocks in all files [0]
+    //#
+    //## Get all the line blocks by looking at all the files in the folder having the file name extension
+    //#Get all the files and filter by file type and file extension, then parse the filtered files. Write
+    //#out any errors encountered to the console.
+    /// Orchestrates the extraction of comment blocks from all files in a directory tree.
+    ///
+    /// This is the main entry point for the documentation generation system. It recursively scans
+    /// a directory structure, processes all files with the specified extension, extracts comment
+    /// blocks, and generates organized documentation output.
+    ///
+    /// # Process Flow:
+    /// 1. **Setup**: Initializes parser state
+    /// 2. **Directory Traversal**: The walker stage, on its own thread, recursively walks through
+    ///    the folder structure using WalkDir and reads the matching files
+    /// 3. **File Filtering**: Processes only files with one of the specified extensions
+    /// 4. **File Processing**: Calls `parse_file` on each file received from the walker stage
+    /// 5. **Error Handling**: Logs parsing errors but continues processing other files
+    /// 6. **Finalization**: Clears the existing documentation directory and writes out all
+    ///    accumulated comment history to documentation files through the writer stage
+    ///
+    /// The stages are connected by channels that hold at most `CHANNEL_CAPACITY` files, so
+    /// reading overlaps with parsing and writing overlaps with rendering while memory use stays
+    /// bounded.
+    ///
+    /// # Key Features:
+    /// - **Recursive Scanning**: Follows symbolic links and processes subdirectories
+    /// - **File Type Filtering**: Only processes files with one of the specified extensions (e.g., ".rs", ".py")
+    /// - **Error Resilience**: Continues processing even when individual files fail
+    /// - **Clean Output**: Removes existing documentation before generating new content
+    ///
+    /// # Parameters:
+    /// - `folder_name`: Root directory to scan for source files
+    /// - `doc_root`: Output directory for generated documentation
+    /// - `start`: String that marks the beginning of comment blocks (e.g., "//#")
+    /// - `folder_prefixes`: Dot-delimited hierarchy for organizing output documentation
+    /// - `file_extensions`: File name endings to scan (e.g., ".rs" for Rust files)
+    ///
+    /// # Side Effects:
+    /// - Removes and recreates the `doc_root` directory
+    /// - Creates markdown files in the documentation hierarchy
+    /// - Prints error messages to console for failed file processing
+    ///
+    /// # Returns:
+    /// - `Err(RunError)` with every error that was logged during the run. Errors are handled
+    ///   as they occur, allowing the process to continue even when individual files fail to
+    ///   parse, and are returned once the run is done
+    ///
+    /// When the run is interrupted with `interrupt` no new files are parsed. Before the write
+    /// phase the documentation directory is left as it was; once the write phase has started
+    /// it is finished.
+    pub fn comment_in_files(
+        &mut self,
+        folder_name: &str,
+        doc_root: &str,
+        start: &str,
+        folder_prefixes: &'a str,
+        file_extensions: &[&str],
+    ) -> Result<(), RunError> {
+        let previous = self.begin_run(folder_name, doc_root, start);
+        let staged = match self.stage(doc_root) {
+            Ok(staged) => staged,
+            Err(error) => {
+                self.failures.report("stage", error, &self.events);
+                return self.finish_run();
+            }
+        };
+        let root = staged.as_ref().map_or(doc_root, StagedRoot::path);
+        self.parse_files(folder_name, root, folder_prefixes, file_extensions);
+        if !self.stop_if_interrupted(folder_name, doc_root) {
+            self.write_phase(root, previous);
+        }
+        if let Some(staged) = staged {
+            self.publish(staged);
+        }
+        self.finish_run()
+    }
+    /// Parses the source files like `comment_in_files` and writes the blocks to the JSON file
+    /// `blocks_file` instead of a document root, see `json::write_blocks`.
+    ///
+    /// This is the first stage of a split run: the expensive parse is done once, for example in
+    /// CI, and `render_blocks` renders the file into any number of formats and document roots
+    /// without walking the source tree again.
+    pub fn extract_blocks(
+        &mut self,
+        folder_name: &str,
+        blocks_file: &str,
+        start: &str,
+        folder_prefixes: &'a str,
+        file_extensions: &[&str],
+    ) -> Result<(), RunError> {
+        self.begin_run(folder_name, "", start);
+        self.parse_files(folder_name, "", folder_prefixes, file_extensions);
+        if self.stop_if_interrupted(folder_name, blocks_file) {
+            return self.finish_run();
+        }
+        let started = Instant::now();
+        self.check_history("");
+        let validate_time = started.elapsed();
+        let started = Instant::now();
+        if let Err(error) = write_blocks(&self.comment_history, "", Path::new(blocks_file)) {
+            self.failures.report("write blocks", error, &self.events);
+        }
+        self.finish_timings("", validate_time, started.elapsed());
+        self.finish_run()
+    }
+    //#EPIC Get Lines.ITEM Check blocks before committing [0]
+    //#
+    //## Check blocks before committing
+    //#`check` parses and validates the blocks without writing anything, so malformed blocks
+    //#(bad folder prefixes, duplicate names, missing Sequence numbers) fail the run. With
+    //#`--staged` only the files staged in git are read, which is what the pre-commit hook
+    //#written by `hook install` runs.
+    /// Parses the source files like `comment_in_files` and validates the blocks, without
+    /// writing anything.
+    ///
+    /// A block name rejected by `folder_prefixes` is reported as a failure, next to the errors
+    /// found while parsing. With `staged_only` only the source files staged in git are read,
+    /// with their content as it is staged, see `git::staged_paths` and `git::staged_content`,
+    /// so changes that are not staged do not hide a malformed block from the commit; blocks in
+    /// other files are not seen, so a name is only checked against the staged files.
+    pub fn check_files(
+        &mut self,
+        folder_name: &str,
+        start: &str,
+        folder_prefixes: &'a str,
+        file_extensions: &[&str],
+        staged_only: bool,
+    ) -> Result<(), RunError> {
+        self.begin_run(folder_name, "", start);
+        if staged_only {
+            match staged_paths(folder_name) {
+                Ok(paths) => {
+                    self.only_sources = Some(
+                        paths
+                            .iter()
+                            .map(|path| Path::new(folder_name).join(path))
+                            .collect(),
+                    )
+                }
+                Err(error) => self.failures.report("staged files", error, &self.events),
+            }
+            self.staged_sources = true;
+        }
+        self.parse_files(folder_name, "", folder_prefixes, file_extensions);
+        if self.stop_if_interrupted(folder_name, "") {
+            return self.finish_run();
+        }
+        self.check_history("");
+        let mut names: Vec<&String> = self.comment_history.keys().collect();
+        names.sort();
+        for name in names {
+            if let Err(reason) = self.is_valid_output_name(&self.folder_prefixes, name) {
+                self.failures.report(
+                    "check",
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("{}: {reason}", relative_name(name, "")),
+                    ),
+                    &self.events,
+                );
+            }
+        }
+        self.events
+            .inform(&format!("Checked {} files", self.scanned_files.len()));
+        self.finish_run()
+    }
+    //#EPIC Get Lines.ITEM Preview a file from standard input [0]
+    //#
+    //## Preview a file from standard input
+    //#With `-dir -`, or `--stdin`, the source text is read from standard input and its blocks are
+    //#printed instead of written to a document root, so an editor can preview the documentation
+    //#of the file being edited without saving it. `-file-name` tells which file the text is,
+    //#for the comment markers of its extension and the source links.
+    /// Parses one source file given as text, as `comment_in_files` parses the files of a
+    /// folder, and writes its blocks to `writer` instead of a document root: the page of every
+    /// block name under a heading with its name, or with `OutputFormat::Json` the document of
+    /// `extract_blocks`. The messages of the run are printed on standard error, so `writer`
+    /// may be standard output.
+    pub fn preview_source<W: Write>(
+        &mut self,
+        file_name: &str,
+        content: &str,
+        start: &str,
+        folder_prefixes: &'a str,
+        format: OutputFormat,
+        writer: W,
+    ) -> Result<(), RunError> {
+        self.events.set_stderr(true);
+        self.begin_run("", "", start);
+        let result = self.parse_file(file_name, content, "", folder_prefixes);
+        self.finish_file(file_name, result);
+        self.check_history("");
+        self.print_blocks(format, writer);
+        self.finish_run()
+    }
+    //#EPIC Get Lines.ITEM Print the blocks to standard output [0]
+    //#
+    //## Print the blocks to standard output
+    //#With `--stdout` the pages of all block names are printed one after the other instead of
+    //#written to a document root, each under a heading with its block name, so the output can
+    //#be piped into a pager, `grep` or another tool.
+    /// Parses the source files like `comment_in_files` and writes the blocks to `writer`
+    /// instead of a document root, as `preview_source` writes them.
+    pub fn extract_to_writer<W: Write>(
+        &mut self,
+        folder_name: &str,
+        start: &str,
+        folder_prefixes: &'a str,
+        file_extensions: &[&str],
+        format: OutputFormat,
+        writer: W,
+    ) -> Result<(), RunError> {
+        self.events.set_stderr(true);
+        self.begin_run(folder_name, "", start);
+        self.parse_files(folder_name, "", folder_prefixes, file_extensions);
+        if self.stop_if_interrupted(folder_name, "") {
+            return self.finish_run();
+        }
+        self.check_history("");
+        self.print_blocks(format, writer);
+        self.finish_run()
+    }
+    /// Writes the page of every block name under a heading with its name, or with
+    /// `OutputFormat::Json` the document of `extract_blocks`, reporting a failed write.
+    fn print_blocks<W: Write>(&mut self, format: OutputFormat, mut writer: W) {
+        let written = match format {
+            OutputFormat::Json => write_blocks_to(&self.comment_history, "", &mut writer),
+            _ => self
+                .rendered_pages("", OutputFormat::Markdown)
+                .and_then(|pages| {
+                    for (name, page) in pages {
+                        write!(writer, "# {name}\n\n{page}")?;
+                    }
+                    writer.flush()
+                }),
+        };
+        if let Err(error) = written {
+            self.failures.report("print blocks", error, &self.events);
+        }
+    }
+    /// Renders the blocks of a JSON file written by `extract_blocks` into the document root,
+    /// as `comment_in_files` would have rendered them.
+    ///
+    /// The blocks are validated against `folder_prefixes` again. Incremental runs are not
+    /// supported, as no source files are scanned.
+    pub fn render_blocks(
+        &mut self,
+        blocks_file: &str,
+        doc_root: &str,
+        folder_prefixes: &'a str,
+    ) -> Result<(), RunError> {
+        let previous = self.begin_run("", doc_root, "");
+        self.folder_prefixes = folder_prefixes.split(".").collect();
+        let staged = match self.stage(doc_root) {
+            Ok(staged) => staged,
+            Err(error) => {
+                self.failures.report("stage", error, &self.events);
+                return self.finish_run();
+            }
+        };
+        let root = staged.as_ref().map_or(doc_root, StagedRoot::path);
+        match read_blocks(Path::new(blocks_file)) {
+            Ok(history) => {
+                for (name, blocks) in history {
+                    self.comment_history
+                        .insert(format!("{root}.{name}"), blocks);
+                }
+            }
+            Err(error) => {
+                self.failures.report("read blocks", error, &self.events);
+                return self.finish_run();
+            }
+        }
+        self.write_phase(root, previous);
+        if let Some(staged) = staged {
+            self.publish(staged);
+        }
+        self.finish_run()
+    }
+    /// Walks the source folder and parses every source file with one of the extensions, in
+    /// the walk order, while the walker stage reads ahead.
+    fn parse_files(
+        &mut self,
+        folder_name: &str,
+        doc_root: &str,
+        folder_prefixes: &'a str,
+        file_extensions: &[&str],
+    ) {
+        let file_extensions = self.scanned_extensions(file_extensions);
+        let pause = self.file_pause();
+        let encodings = self.encodings.clone();
+        let exclusions = self.exclusions(folder_name);
+        let mut progress = self.start_progress(None);
+        let total = OnceLock::new();
+        let (sender, files) = sync_channel(CHANNEL_CAPACITY);
+        let mut parse_time = Duration::ZERO;
+        let events = self.events.clone();
+        let stop = self.stop_flag();
+        let staged_root = self.staged_sources.then_some(folder_name);
+        let read = |path: &Path| match staged_root {
+            Some(root) => staged_content(root, path).map(|content| encodings.decode(path, content)),
+            None => encodings.read(path),
+        };
+        let walk_time = thread::scope(|scope| {
+            let walker = scope.spawn(|| {
+                let started = Instant::now();
+                let entries =
+                    walk_source_files(folder_name, &file_extensions, &exclusions, &events, stop);
+                let _ = total.set(entries.len());
+                read_source_files(entries, &read, sender, pause, stop);
+                started.elapsed()
+            });
+            for file in files {
+                let started = Instant::now();
+                self.events.emit(Event::FileStarted { path: &file.name });
+                let result = file.content.and_then(|content| {
+                    self.warn_if_lossy(&file.name, &content);
+                    self.parse_file(&file.name, &content.text, doc_root, folder_prefixes)
+                });
+                self.finish_file(&file.name, result);
+                parse_time += started.elapsed();
+                let duration = file.read_time + started.elapsed();
+                self.timings.add_file(&file.name, duration);
+                self.events.emit(Event::FileParsed {
+                    path: &file.name,
+                    duration,
+                });
+                if let Some(progress) = &mut progress {
+                    if let Some(total) = total.get() {
+                        progress.set_total(*total);
+                    }
+                    progress.advance(&file.name);
+                }
+                // to do log None case as file is deleted while getting scanned
+            }
+            walker.join().unwrap_or_default()
+        });
+        if let Some(progress) = &mut progress {
+            progress.finish();
+        }
+        let scanned = self.scanned_files.len();
+        self.timings.add_phase("walk", walk_time, scanned, "files");
+        self.timings
+            .add_phase("parse", parse_time, scanned, "files");
+    }
+    /// Validates the history and writes the generated files and reports into the document
+    /// root.
+    fn write_phase(&mut self, doc_root: &str, previous: PreviousRun) {
+        let started = Instant::now();
+        self.check_history(doc_root);
+        let validate_time = started.elapsed();
+        let started = Instant::now();
+        let only = self.clear_outputs(doc_root, &previous);
+        // all files is processed to print out the history of self lines
+        for doc_writer in self.doc_writers() {
+            if let Err(error) = self.write_history(only.as_ref(), doc_writer) {
+                self.failures.report("write history", error, &self.events);
+            };
+        }
+        self.write_reports(doc_root, previous);
+        self.finish_timings(doc_root, validate_time, started.elapsed());
+    }
+    /// The tokio based counterpart of `comment_in_files`, for embedding the extractor into
+    /// async services.
+    ///
+    /// Walking the source folder, reading the source files and writing the generated block
+    /// files is done with non-blocking I/O, and up to 16 source files are read at the same
+    /// time. The files are still parsed one by one in walk order, so the output is the same as
+    /// that of `comment_in_files`. The reports and pages built from the complete history, such
+    /// as the index tables or the glossary page, are written with blocking I/O.
+    #[cfg(feature = "async")]
+    pub async fn comment_in_files_async(
+        &mut self,
+        folder_name: &str,
+        doc_root: &str,
+        start: &str,
+        folder_prefixes: &'a str,
+        file_extensions: &[&str],
+    ) -> Result<(), RunError> {
+        let previous = self.begin_run(folder_name, doc_root, start);
+        let staged = match self.stage(doc_root) {
+            Ok(staged) => staged,
+            Err(error) => {
+                self.failures.report("stage", error, &self.events);
+                return self.finish_run();
+            }
+        };
+        let doc_root = staged.as_ref().map_or(doc_root, StagedRoot::path);
+        let file_extensions = self.scanned_extensions(file_extensions);
+        let started = Instant::now();
+        let exclusions = self.exclusions(folder_name);
+        let paths = async_io::find_files(
+            folder_name,
+            &file_extensions,
+            &exclusions,
+            self.file_pause(),
+            &self.events,
+        )
+        .await;
+        self.timings
+            .add_phase("walk", started.elapsed(), paths.len(), "files");
+        let mut parse_time = Duration::ZERO;
+        let mut progress = self.start_progress(Some(paths.len()));
+        let mut reads = async_io::FileReads::new(paths, &self.encodings);
+        while let Some((path, content)) = reads.next().await {
+            if self.is_interrupted() {
+                break;
+            }
+            if let Some(name) = path.to_str() {
+                let started = Instant::now();
+                self.events.emit(Event::FileStarted { path: name });
+                let result = content.and_then(|content| {
+                    self.warn_if_lossy(name, &content);
+                    self.parse_file(name, &content.text, doc_root, folder_prefixes)
+                });
+                self.finish_file(name, result);
+                parse_time += started.elapsed();
+                self.timings.add_file(name, started.elapsed());
+                self.events.emit(Event::FileParsed {
+                    path: name,
+                    duration: started.elapsed(),
+                });
+                if let Some(progress) = &mut progress {
+                    progress.advance(name);
+                }
+            }
+        }
+        if let Some(progress) = &mut progress {
+            progress.finish();
+        }
+        let scanned = self.scanned_files.len();
+        self.timings
+            .add_phase("parse", parse_time, scanned, "files");
+        if self.stop_if_interrupted(folder_name, doc_root) {
+            return self.finish_run();
+        }
+        let started = Instant::now();
+        self.check_history(doc_root);
+        let validate_time = started.elapsed();
+        let started = Instant::now();
+        let only = self.clear_outputs(doc_root, &previous);
+        let mut last_error = None;
+        let history = self.sorted_history();
+        let files = self.doc_writers().into_iter().flat_map(|doc_writer| {
+            history
+                .iter()
+                .map(move |(name, blocks)| (doc_writer, (*name, *blocks)))
+        });
+        for (doc_writer, (name, blocks)) in files {
+            let file_name = name.trim();
+            if only.as_ref().is_some_and(|only| !only.contains(file_name)) {
+                continue;
+            }
+            let rendered = self.render_parts(file_name, blocks, doc_writer);
+            let (path, parts) = match rendered.and_then(|parts| {
+                Ok((
+                    self.output_file_path(
+                        &self.folder_prefixes,
+                        file_name,
+                        doc_writer.extension(),
+                    )?,
+                    parts,
+                ))
+            }) {
+                Ok(written) => written,
+                Err(error) => {
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+            for (index, content) in parts.into_iter().enumerate() {
+                let path = part_path(&path, index + 1);
+                if self.background {
+                    tokio::time::sleep(FILE_PAUSE).await;
+                }
+                let written = match &self.vault {
+                    Some(vault) => vault.write_note(&path, &content),
+                    None => {
+                        if let Some(managed) = &self.managed {
+                            managed.keep(&path);
+                        }
+                        async_io::write_file_if_changed(&path, &content).await
+                    }
+                }
+                .map(|written| {
+                    if written {
+                        self.events.emit(Event::FileWritten { path: &path })
+                    }
+                });
+                if let Err(error) = written {
+                    last_error = Some(error)
+                }
+            }
+        }
+        if let Some(error) = last_error {
+            self.failures.report("write history", error, &self.events);
+        }
+        self.write_reports(doc_root, previous);
+        self.finish_timings(doc_root, validate_time, started.elapsed());
+        if let Some(staged) = staged {
+            self.publish(staged);
+        }
+        self.finish_run()
+    }
+    /// Reads what the previous run left in the document root and resets the parser for a new
+    /// run. The document root is only cleared when the write phase starts.
+    fn begin_run(&mut self, folder_name: &str, doc_root: &str, start: &str) -> PreviousRun {
+        let previous = PreviousRun {
+            anchors: match self.stale_months {
+                Some(_) => load_anchors(doc_root),
+                None => Default::default(),
+            },
+            runs: match self.manifest {
+                true => load_manifest(doc_root),
+                false => Vec::new(),
+            },
+            sources: match self.is_incremental() {
+                true => load_cache(doc_root),
+                false => SourceCache::new(),
+            },
+        };
+        self.start_of_comment = start.to_string();
+        self.default_marker = (!start.is_empty()).then(|| CommentMarker {
+            start: start.to_string(),
+            end: self.end_of_comment.clone(),
+        });
+        self.only_sources = self.changed_sources(folder_name, &previous.sources);
+        self.staged_sources = false;
+        self.source_root = folder_name.to_string();
+        self.published_root = doc_root.to_string();
+        self.run_id = new_run_id();
+        self.started_at = format_timestamp(self.clock().now());
+        self.current_state = State::CODE;
+        self.stop_flag().store(false, Ordering::SeqCst);
+        self.failures = RunError::default();
+        self.comment_history.clear();
+        self.comment_block_names.clear();
+        self.diagnostics.clear();
+        self.snippets.clear();
+        self.dependencies.clear();
+        self.skipped_files = 0;
+        self.rejected_blocks.clear();
+        if self.background
+            && let Err(error) = lower_priority()
+        {
+            self.events.warn(format!("lower priority {error}"));
+        }
+        previous
+    }
+    /// Hands out the errors that were logged during the run.
+    fn finish_run(&mut self) -> Result<(), RunError> {
+        let mut diagnostics = std::mem::take(&mut self.diagnostics);
+        if !diagnostics.is_empty() {
+            diagnostics
+                .sort_by(|left, right| (&left.file, left.line).cmp(&(&right.file, right.line)));
+            self.events.inform(&summary_table(&diagnostics));
+        }
+        self.events.set_stderr(false);
+        std::mem::take(&mut self.failures).into_result()
+    }
+    /// Reports what was processed when the run was interrupted before the write phase.
+    ///
+    /// # Returns:
+    /// - `true` when the run was interrupted and must stop without touching the document root
+    fn stop_if_interrupted(&self, folder_name: &str, doc_root: &str) -> bool {
+        if self.is_interrupted() {
+            self.events.inform(&format!(
+                "Interrupted: parsed {} files, the rest of {folder_name} was not scanned and {doc_root} was left as it was",
+                self.scanned_files.len()
+            ));
+        }
+        self.is_interrupted()
+    }
+    /// Warns when bytes of a source file that are invalid in its encoding were replaced.
+    fn warn_if_lossy(&self, file_name: &str, content: &Decoded) {
+        if content.lossy {
+            self.events.warn(format!(
+                "Invalid {} replaced by U+FFFD in {file_name}, set its encoding with -encoding",
+                content.encoding.name()
+            ));
+        }
+    }
+    /// Reports the outcome of parsing one source file and records its line count.
+    fn finish_file(&mut self, name: &str, result: Result<(), std::io::Error>) {
+        if let Err(error) = result {
+            self.skipped_files += 1;
+            self.failures.report("comment in file", error, &self.events);
+        } else if self.current_state == State::ERROR {
+            let message = format!("Error occurred while parsing file: {name}");
+            self.events.error(&message);
+            self.failures
+                .record(FailureKind::Parse, message, &self.events);
+        }
+        self.scanned_files.insert(
+            name.to_string(),
+            FileCount {
+                lines: self.line_counter.saturating_sub(1),
+                blocks: 0,
+            },
+        );
+    }
+    /// Runs the checks on the complete history that only print warnings, tells the
+    /// subscriber which block names are valid and which are rejected, and resolves the cross
+    /// references.
+    fn check_history(&mut self, doc_root: &str) {
+        self.merge_continuations(doc_root);
+        self.embed_snippets();
+        if self.git_metadata {
+            self.add_git_metadata();
+        }
+        let mut names: Vec<&String> = self.comment_history.keys().collect();
+        names.sort();
+        for name in names {
+            let relative = relative_name(name, doc_root);
+            match self.is_valid_output_name(&self.folder_prefixes, name) {
+                Ok(()) => self.events.emit(Event::BlockValidated {
+                    name: relative,
+                    sequences: &self.comment_history[name],
+                }),
+                Err(reason) => {
+                    self.events.emit(Event::BlockRejected {
+                        name: relative,
+                        reason: &reason,
+                    });
+                    self.rejected_blocks
+                        .push((relative.to_string(), reason.clone()));
+                    for (sequence, block) in &self.comment_history[name] {
+                        self.diagnostics.push(Diagnostic::new(
+                            &block.source_file,
+                            block.start_line.saturating_sub(1),
+                            &format!("{relative} [{sequence}]"),
+                            &reason,
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(threshold) = self.similarity_threshold {
+            self.report_similar_blocks(threshold);
+        }
+        if self.cross_references {
+            let references = resolve_links(&self.comment_history, doc_root, &self.published_root);
+            for link in &references.dangling {
+                self.events.warn(format!(
+                    "Dangling link: [[{}]] in {} LINE: {}",
+                    link.target, link.source_file, link.line
+                ));
+            }
+            self.backlinks = references.backlinks;
+        }
+        match self
+            .url_check
+            .as_ref()
+            .map(|check| check.find_dead_links(&self.comment_history))
+        {
+            Some(Ok(dead_links)) => {
+                for dead_link in dead_links {
+                    for (source_file, line) in &dead_link.locations {
+                        self.events.warn(format!(
+                            "Dead link ({}): {} in {} LINE: {}",
+                            dead_link.reason, dead_link.url, source_file, line
+                        ));
+                    }
+                }
+            }
+            Some(Err(reason)) => self
+                .events
+                .warn(format!("Link check unavailable: {reason}")),
+            None => {}
+        }
+        self.check_coverage();
+    }
+    /// Writes the reports and pages that are built from the complete history.
+    fn write_reports(&mut self, doc_root: &str, previous: PreviousRun) {
+        // the blocks of the files a `-since` run did not read are missing from the history
+        let whole = self.only_sources.is_none();
+        if !whole {
+            self.events.inform(
+                "Only changed files were read: reports built from all blocks are left as they were",
+            );
+        }
+        if whole
+            && self.writes_format(OutputFormat::Json)
+            && let Err(error) = write_comments(&self.comment_history, doc_root)
+        {
+            self.failures.report("write json", error, &self.events);
+        }
+        if whole && self.writes_format(OutputFormat::Html) {
+            match self.write_html(doc_root) {
+                Ok(files) => self.keep_outputs(files),
+                Err(error) => self.failures.report("write html", error, &self.events),
+            }
+        }
+        if whole && self.writes_format(OutputFormat::MdBook) {
+            match self.write_mdbook(doc_root) {
+                Ok(files) => self.keep_outputs(files),
+                Err(error) => self.failures.report("write mdbook", error, &self.events),
+            }
+        }
+        if whole
+            && let Some(glossary) = &self.glossary
+            && glossary.has_definitions()
+        {
+            match glossary.write_page(doc_root) {
+                Ok(file) => self.keep_outputs([file]),
+                Err(error) => self.failures.report("write glossary", error, &self.events),
+            }
+        }
+        if let Some(months) = self.stale_months.filter(|_| whole) {
+            let stale = find_stale_blocks(
+                &self.comment_history,
+                &previous.anchors,
+                months,
+                self.clock(),
+            );
+            for block in &stale {
+                self.events.warn(format!(
+                    "Possibly stale: {} [{}] ({} LINE: {}): {}",
+                    block.name, block.sequence, block.source_file, block.line, block.reason
+                ));
+            }
+            match write_stale_report(&stale, doc_root)
+                .and_then(|file| save_anchors(&self.comment_history, doc_root).map(|_| file))
+            {
+                Ok(file) => self.keep_outputs([file]),
+                Err(error) => self
+                    .failures
+                    .report("write stale report", error, &self.events),
+            }
+        }
+        if whole && self.tag_pages {
+            match write_tag_pages(&self.comment_history, doc_root) {
+                Ok(files) => self.keep_outputs(files),
+                Err(error) => self.failures.report("write tag pages", error, &self.events),
+            }
+        }
+        if whole && let Some(requirements) = &self.requirements {
+            let reconciliation = requirements.reconcile(&self.comment_history, doc_root);
+            self.events.inform(&format!(
+                "Requirements: {} present, {} missing, {} unexpected blocks, see {doc_root}/requirements.md",
+                reconciliation.present.len(),
+                reconciliation.missing.len(),
+                reconciliation.unexpected.len()
+            ));
+            for id in &reconciliation.missing {
+                let message = format!("Requirement {id} is not documented by any block");
+                match self.strict_requirements {
+                    true => self.failures.report(
+                        "check requirements",
+                        Error::new(ErrorKind::InvalidInput, format!("[E014] {message}")),
+                        &self.events,
+                    ),
+                    false => self.events.warn(message),
+                }
+            }
+            match write_requirements_report(&reconciliation, doc_root) {
+                Ok(file) => self.keep_outputs([file]),
+                Err(error) => {
+                    self.failures
+                        .report("write requirements report", error, &self.events)
+                }
+            }
+        }
+        if let Some(format) = self.density_format.filter(|_| whole) {
+            match self.write_density(doc_root, format) {
+                Ok(file) => self.keep_outputs([file]),
+                Err(error) => self
+                    .failures
+                    .report("write density report", error, &self.events),
+            }
+        }
+        if let Some(format) = self.trace_format.filter(|_| whole) {
+            match write_trace_matrix(&self.comment_history, doc_root, format) {
+                Ok(file) => self.keep_outputs([file]),
+                Err(error) => self
+                    .failures
+                    .report("write trace matrix", error, &self.events),
+            }
+        }
+        if let Some(format) = self.diagnostics_format {
+            match write_diagnostics(&self.diagnostics, format, doc_root) {
+                Ok(file) => self.keep_outputs([file]),
+                Err(error) => self
+                    .failures
+                    .report("write diagnostics", error, &self.events),
+            }
+        }
+        if whole && self.contributors {
+            match write_contributor_pages(&self.comment_history, doc_root) {
+                Ok((files, warnings)) => {
+                    self.keep_outputs(files);
+                    warnings
+                        .into_iter()
+                        .for_each(|warning| self.events.warn(warning));
+                }
+                Err(error) => self
+                    .failures
+                    .report("write contributors", error, &self.events),
+            }
+        }
+        // with folder indexes the tables are written above the listings
+        if whole
+            && !self.folder_index
+            && let Some(columns) = &self.index_columns
+        {
+            match write_block_tables(
+                &self.comment_history,
+                columns,
+                self.reproducible,
+                self.sort_order,
+            ) {
+                Ok(files) => self.keep_outputs(files),
+                Err(error) => self.failures.report("write index", error, &self.events),
+            }
+        }
+        if whole && self.folder_index {
+            let tables = self
+                .index_columns
+                .as_deref()
+                .map(|columns| (columns, self.reproducible));
+            match write_folder_indexes(&self.comment_history, doc_root, tables, self.sort_order) {
+                Ok(files) => self.keep_outputs(files),
+                Err(error) => self
+                    .failures
+                    .report("write folder index", error, &self.events),
+            }
+        }
+        if whole
+            && let Some(vault) = &self.vault
+            && let Err(error) = write_mocs(&self.comment_history, vault)
+                .and_then(|_| vault.finish(!self.is_incremental() && !self.selects_names()))
+        {
+            self.failures.report("write vault", error, &self.events);
+        }
+        if whole && self.structure {
+            match write_structure(&self.comment_history, doc_root, self.sort_order) {
+                Ok(file) => self.keep_outputs([file]),
+                Err(error) => self.failures.report("write structure", error, &self.events),
+            }
+        }
+        if self.manifest
+            && let Err(error) = write_layout_version(doc_root)
+                .and_then(|_| self.record_run(previous.runs, doc_root))
+        {
+            self.failures.report("record run", error, &self.events);
+        }
+        // A run writing some of the block names keeps the cache, so the files it did not
+        // write are still found to be changed by the next incremental run.
+        if self.is_incremental()
+            && !self.selects_names()
+            && let Err(error) = create_dir_all(doc_root)
+                .and_then(|_| save_cache(&self.source_cache(&previous.sources), doc_root))
+        {
+            self.failures
+                .report("save source cache", error, &self.events);
+        }
+        if let Some(outputs) = self.managed.take()
+            && let Err(error) = outputs.finish()
+        {
+            self.failures.report("record outputs", error, &self.events);
+        }
+        for mirror in &self.mirrors {
+            if let Err(error) = mirror_tree(doc_root, &self.source_root, mirror, self.hard_links) {
+                self.failures.report("mirror", error, &self.events);
+            }
+        }
+        if self.is_interrupted() {
+            self.events.inform(&format!(
+                "Interrupted during the write phase: {doc_root} was written completely"
+            ));
+        }
+    }
+}
+
+/// Returns the path of the generated file of a dot delimited block path, with the given file
+/// name extension.
+fn generated_path(file_path_and_name: &str, extension: &str) -> PathBuf {
+    let path: Vec<&str> = file_path_and_name.split(".").collect();
+    PathBuf::from(format!("{}.{extension}", path.join("/")))
+}
+
+/// Returns the path of a part of a split generated file: the path itself for part 1, and the
+/// file name followed by ` (part N)` for the others, as in `ITEM B (part 2).md`.
+fn part_path(path: &Path, part: usize) -> PathBuf {
+    if part < 2 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(extension) => path.with_file_name(format!(
+            "{stem} (part {part}).{}",
+            extension.to_string_lossy()
+        )),
+        None => path.with_file_name(format!("{stem} (part {part})")),
+    }
+}
+
+/// Returns the first top level Sequence number after the highest one of the blocks and
+/// `at_least`, 0 when there are no blocks. Nested Sequence numbers count by their top level,
+/// so the number after `2.1` is `3`.
+///
+/// # Returns:
+/// - `Err(Error)` when the highest Sequence number is already the largest one
+fn next_sequence(blocks: Option<&Sequences>, at_least: Sequence) -> Result<Sequence, Error> {
+    match blocks.and_then(|blocks| blocks.keys().next_back()) {
+        None if at_least == Sequence::default() => Ok(Sequence::default()),
+        highest => highest
+            .map_or(0, Sequence::top)
+            .max(at_least.top())
+            .checked_add(1)
+            .map(Sequence::from)
+            .ok_or_else(|| Error::other("[E001] No free Sequence number left for the block")),
+    }
+}
+
+/// Returns whether the blocks of a name are placed by an `@path` directive instead of by
+/// their name, so the name is not checked against the path prefixes.
+fn has_path_override(blocks: &Sequences) -> bool {
+    blocks.values().any(|block| block.path_override().is_some())
+}
+
+/// Turns the value of an `@path` directive, such as `guides/how-to-deploy`, into the dot
+/// delimited block name of its generated file.
+///
+/// # Returns:
+/// - `Err(Error)` when the path is empty, has an empty or `..` folder, or a `.` in a folder
+///   or file name, which would be taken for a level separator
+fn override_name(path: &str) -> Result<String, Error> {
+    let levels: Vec<&str> = path
+        .trim()
+        .trim_matches('/')
+        .split('/')
+        .map(str::trim)
+        .collect();
+    if levels
+        .iter()
+        .any(|level| level.is_empty() || level.contains('.'))
+    {
+        return Err(Error::other(format!(
+            "[E004] Invalid output path [{path}]: expected folders and a file name without dots, such as guides/how-to-deploy"
+        )));
+    }
+    Ok(levels.join("."))
+}
+
+/// The heading of the list of files linking to a generated file.
+const BACKLINKS_HEADING: &str = "Referenced by";
+
+/// The heading above the blocks from machine-generated source files in a generated file that
+/// also holds hand-written blocks.
+const GENERATED_HEADING: &str = "Generated";
+
+/// Returns the heading written before a block with `with_sequence_headings`.
+fn sequence_heading(sequence: Sequence, block: &Block) -> String {
+    format!(
+        "Sequence {sequence} — from {}:{}",
+        block.source_file, block.start_line
+    )
+}
+
+/// The estimated size of the `[SOURCE FILE:]` line and blank lines of a block, on top of its
+/// source path and body, used to divide the blocks of a split file over its parts.
+const PART_BLOCK_OVERHEAD: usize = 40;
+
+/// A backend that writes the generated files, one per block name, in one documentation
+/// format.
+trait DocWriter: Sync {
+    /// The output format of the generated files.
+    fn format(&self) -> OutputFormat;
+    /// The file name extension of the generated files, without the dot.
+    fn extension(&self) -> &'static str;
+    /// Writes a second level section heading followed by a blank line.
+    fn write_heading(&self, writer: &mut BufWriter<Vec<u8>>, title: &str) -> Result<(), Error>;
+    /// Returns a link to another generated file, by its path relative to the file linking it.
+    fn link(&self, target: &str, text: &str) -> String;
+    /// Writes the front matter of a generated file, as a YAML block by default.
+    fn write_front_matter(
+        &self,
+        writer: &mut BufWriter<Vec<u8>>,
+        entries: &[(String, FrontMatterValue)],
+    ) -> Result<(), Error> {
+        write_yaml(writer, entries)
+    }
+    /// Writes the footer naming the run that generated the file. The emphasis of the footer
+    /// is the same in Markdown and AsciiDoc, and `provenance` finds it by its prefix.
+    fn write_footer(&self, writer: &mut BufWriter<Vec<u8>>, run_id: &str) -> Result<(), Error> {
+        writeln!(writer, "{FOOTER_PREFIX}{run_id}_")
+    }
+    /// Writes the target a block can be referenced by, before the block. Only MyST has them.
+    fn write_target(&self, _writer: &mut BufWriter<Vec<u8>>, _label: &str) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Writes the link to the source file of a block, with the given target, followed by its
+    /// body lines and a trailing blank line.
+    fn write_block(
+        &self,
+        writer: &mut BufWriter<Vec<u8>>,
+        block: &Block,
+        link: Option<&str>,
+    ) -> Result<(), Error>;
+}
+
+/// Writes Markdown files, the default format.
+struct MarkdownWriter;
+
+impl DocWriter for MarkdownWriter {
+    fn format(&self) -> OutputFormat {
+        OutputFormat::Markdown
+    }
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+    fn write_heading(&self, writer: &mut BufWriter<Vec<u8>>, title: &str) -> Result<(), Error> {
+        writeln!(writer, "## {title}\n")
+    }
+    fn link(&self, target: &str, text: &str) -> String {
+        format!("[{text}](<{target}>)")
+    }
+    fn write_block(
+        &self,
+        writer: &mut BufWriter<Vec<u8>>,
+        block: &Block,
+        link: Option<&str>,
+    ) -> Result<(), Error> {
+        block.write_with_link(writer, link)
+    }
+}
+
+//#EPIC Get Lines.ITEM MyST flavor [0]
+//#
+//## MyST flavor
+//#With `-flavor myst` the Markdown files follow the conventions of MyST, so Sphinx can include
+//#them directly: every block is preceded by a target such as `(epic-a-item-b-1)=`, links
+//#between generated files use the `{doc}` role, and code fences with a language become
+//#`{code-block}` directives.
+/// Writes Markdown files in the MyST flavor of Sphinx. Raw lines are written as they are.
+struct MystWriter;
+
+impl DocWriter for MystWriter {
+    fn format(&self) -> OutputFormat {
+        OutputFormat::Markdown
+    }
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+    fn write_heading(&self, writer: &mut BufWriter<Vec<u8>>, title: &str) -> Result<(), Error> {
+        writeln!(writer, "## {title}\n")
+    }
+    fn link(&self, target: &str, text: &str) -> String {
+        let target = target.strip_suffix(".md").unwrap_or(target);
+        format!("{{doc}}`{text} <{target}>`")
+    }
+    fn write_target(&self, writer: &mut BufWriter<Vec<u8>>, label: &str) -> Result<(), Error> {
+        writeln!(writer, "({label})=\n")
+    }
+    fn write_block(
+        &self,
+        writer: &mut BufWriter<Vec<u8>>,
+        block: &Block,
+        link: Option<&str>,
+    ) -> Result<(), Error> {
+        block.write_source_line(writer, link)?;
+        // the character and length of the fence of the open code block
+        let mut fence: Option<(u8, usize)> = None;
+        for (index, line) in block.lines.iter().enumerate() {
+            let code = line.trim_start();
+            let marker = code
+                .bytes()
+                .next()
+                .filter(|byte| *byte == b'`' || *byte == b'~');
+            let length = marker.map_or(0, |marker| {
+                code.bytes().take_while(|byte| *byte == marker).count()
+            });
+            if block.raw.contains(&index) || length < 3 {
+                writeln!(writer, "{line}")?;
+                continue;
+            }
+            let info = code[length..].trim();
+            match fence {
+                Some((open, open_length))
+                    if marker == Some(open) && length >= open_length && info.is_empty() =>
+                {
+                    fence = None;
+                    writeln!(writer, "{line}")?;
+                }
+                Some(_) => writeln!(writer, "{line}")?,
+                None => {
+                    fence = marker.map(|marker| (marker, length));
+                    match !info.is_empty() && !info.starts_with('{') {
+                        true => writeln!(
+                            writer,
+                            "{}{}{{code-block}} {info}",
+                            &line[..line.len() - code.len()],
+                            &code[..length]
+                        )?,
+                        false => writeln!(writer, "{line}")?,
+                    }
+                }
+            }
+        }
+        writeln!(writer)
+    }
+}
+
+/// Writes AsciiDoc files. Markdown headings in the body become AsciiDoc section titles, so
+/// `## Title` is written as `== Title`. Raw lines are written as they are.
+struct AsciiDocWriter;
+
+impl DocWriter for AsciiDocWriter {
+    fn format(&self) -> OutputFormat {
+        OutputFormat::AsciiDoc
+    }
+    fn extension(&self) -> &'static str {
+        "adoc"
+    }
+    fn write_heading(&self, writer: &mut BufWriter<Vec<u8>>, title: &str) -> Result<(), Error> {
+        writeln!(writer, "== {title}\n")
+    }
+    fn link(&self, target: &str, text: &str) -> String {
+        format!("link:{}[{text}]", target.replace(' ', "%20"))
+    }
+    fn write_front_matter(
+        &self,
+        writer: &mut BufWriter<Vec<u8>>,
+        entries: &[(String, FrontMatterValue)],
+    ) -> Result<(), Error> {
+        write_attributes(writer, entries)
+    }
+    fn write_block(
+        &self,
+        writer: &mut BufWriter<Vec<u8>>,
+        block: &Block,
+        link: Option<&str>,
+    ) -> Result<(), Error> {
+        match link {
+            Some(link) => writeln!(
+                writer,
+                "link:{link}[SOURCE FILE:] LINE: {}{}\n",
+                block.line_range(),
+                block.commit_note()
+            )?,
+            None => writeln!(
+                writer,
+                "SOURCE FILE: {} LINE: {}{}\n",
+                block.source_file,
+                block.line_range(),
+                block.commit_note()
+            )?,
+        }
+        for (index, line) in block.lines.iter().enumerate() {
+            let level = line.bytes().take_while(|byte| *byte == b'#').count();
+            match line[level..].strip_prefix(' ') {
+                Some(title) if (1..=6).contains(&level) && !block.raw.contains(&index) => {
+                    writeln!(writer, "{} {title}", "=".repeat(level))?
+                }
+                _ => writeln!(writer, "{line}")?,
+            }
+        }
+        writeln!(writer)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_if_file_path_is_valid() {
+    let mut comments = Comments::default();
+    let path = &vec!["EPIC", "ITEM", "TEST"];
+    if let Err(error) = comments.is_valid_folder_path(path, "EPIC epic.ITEM item.TEST test") {
+        println!("test {error}");
+    }
+    assert!(
+        comments
+            .is_valid_folder_path(path, "EPIC epic.ITEM item.TASK test")
+            .is_err()
+    );
+    comments.comment_line_start = 4;
+    assert!(comments.check_unique_name("EPIC A.ITEM b", "a.rs").is_ok());
+    assert!(comments.check_unique_name("EPIC A.ITEM b", "b.rs").is_ok());
+    comments.comment_line_start = 8;
+    assert_eq!(
+        comments
+            .check_unique_name("EPIC A.ITEM  B", "b.rs")
+            .unwrap_err()
+            .to_string(),
+        "[E007] Block name EPIC A.ITEM b in a.rs LINE: 3 is spelled EPIC A.ITEM  B"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_run_requires_settings() {
+    let error = Comments::new()
+        .with_source_folder("testdata")
+        .with_doc_root("target/test-run")
+        .with_path_prefixes("PERSON.INVOICE.ITEM")
+        .with_start_marker(".")
+        .run()
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Extensions not set");
+}
+
+#[cfg(test)]
+#[test]
+fn test_block_comment_mode() {
+    let mut comments = Comments::new()
+        .with_start_marker("/*#")
+        .with_end_marker(Some("*/"));
+    let source = "  /*# EPIC A [0]\n  # Title\n\n    indented\n  last */\n  fn a() {}\n/*# EPIC A [1]\nonly */\n";
+    comments.parse_file("a.rs", source, "docs", "EPIC").unwrap();
+    let blocks = &comments.comment_history["docs.EPIC A"];
+    assert_eq!(
+        &*blocks[&Sequence::from(0)].lines,
+        ["# Title", "", "  indented", "last"]
+    );
+    assert_eq!(
+        blocks[&Sequence::from(0)].code_anchor.as_deref(),
+        Some("fn a() {}")
+    );
+    assert_eq!(&*blocks[&Sequence::from(1)].lines, ["only"]);
+}
+
+#[cfg(test)]
+#[test]
+fn test_asciidoc_writer() {
+    let block = Block::new(
+        "src/main.rs".into(),
+        4,
+        vec!["## Title".to_string(), "#hashtag and text".to_string()],
+        &BTreeSet::new(),
+    );
+    let mut writer = BufWriter::new(Vec::new());
+    AsciiDocWriter
+        .write_block(&mut writer, &block, Some("file:///src/main.rs"))
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(writer.into_inner().unwrap()).unwrap(),
+        "link:file:///src/main.rs[SOURCE FILE:] LINE: 4\n\n== Title\n#hashtag and text\n\n"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_asciidoc_file() {
+    let mut glossary = Glossary::default();
+    glossary.add_definition("block", vec![]).unwrap();
+    let mut comments = Comments::new()
+        .with_front_matter(true)
+        .with_run_footer(true)
+        .with_filters(vec![Filter::Shortcodes])
+        .with_glossary(Some(glossary));
+    comments.begin_run("", "docs", "//#");
+    let blocks = Sequences::from([(
+        Sequence::from(0),
+        Block::new(
+            "a.rs".into(),
+            1,
+            vec!["One block. @badge[beta]".to_string()],
+            &BTreeSet::new(),
+        ),
+    )]);
+    let content = comments
+        .render_file("docs.EPIC A", &blocks, &AsciiDocWriter)
+        .unwrap();
+    let content = String::from_utf8(content).unwrap();
+    assert!(content.starts_with(":title: EPIC A\n:sources: a.rs\n:sequences: 1\n"));
+    assert!(!content.contains("---"), "{content}");
+    assert!(content.contains(
+        "One link:glossary.md#block[block]. image:https://img.shields.io/badge/badge-beta-orange[badge: beta]"
+    ));
+    assert!(content.ends_with(&format!("{FOOTER_PREFIX}{}_\n", comments.run_id)));
+}
+
+#[cfg(test)]
+#[test]
+fn test_myst_writer() {
+    let lines = [
+        "Run it:",
+        "```rust",
+        "main();",
+        "```",
+        "```",
+        "plain",
+        "```",
+        "````markdown",
+        "```rust",
+        "```",
+        "````",
+        "~~~python",
+        "```",
+        "~~~",
+    ];
+    let block = Block::new(
+        "src/main.rs".into(),
+        4,
+        lines.map(String::from).to_vec(),
+        &BTreeSet::new(),
+    );
+    let mut writer = BufWriter::new(Vec::new());
+    MystWriter
+        .write_target(&mut writer, "epic-a-item-b-1")
+        .unwrap();
+    MystWriter.write_block(&mut writer, &block, None).unwrap();
+    assert_eq!(
+        String::from_utf8(writer.into_inner().unwrap()).unwrap(),
+        "(epic-a-item-b-1)=\n\nSOURCE FILE: src/main.rs LINE: 4\n\nRun it:\n```{code-block} rust\nmain();\n```\n```\nplain\n```\n````{code-block} markdown\n```rust\n```\n````\n~~~{code-block} python\n```\n~~~\n\n"
+    );
+    assert_eq!(
+        MystWriter.link("ITEM B (part 2).md", "part 2"),
+        "{doc}`part 2 <ITEM B (part 2)>`"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_path_override() {
+    let mut comments = Comments::default();
+    comments.begin_run("", "docs", "//#");
+    let content = "//# EPIC A.ITEM B [0]\n//#@path: /guides/how-to-deploy\n//#Deploy\ncode\n\
+        //# EPIC A.ITEM C [0]\n//#@path: guides/how-to-deploy\n//#Other\ncode\n";
+    let result = comments.parse_file("a.rs", content, "docs", "EPIC.ITEM");
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("already used by block EPIC A.ITEM B")
+    );
+    let name = "docs.guides.how-to-deploy";
+    assert_eq!(
+        &*comments.comment_history[name][&Sequence::from(0)].lines,
+        ["Deploy"]
+    );
+    assert!(
+        comments
+            .is_valid_output_name(&comments.folder_prefixes, name)
+            .is_ok()
+    );
+    assert!(override_name("guides/v1.2/deploy").is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_generated_blocks_last() {
+    let mut comments = Comments::new().with_generated_sources(vec!["*.pb.rs".to_string()]);
+    comments.begin_run("src", "docs", "//#");
+    comments
+        .parse_file("src/a.pb.rs", "//# EPIC A [0]\n//#Stub\n", "docs", "EPIC")
+        .unwrap();
+    let content = "//# EPIC A [1]\n//#Early\ncode\n//#get-comments:generated\n//# EPIC B [0]\n//#Late\n";
+    comments
+        .parse_file("src/b.rs", content, "docs", "EPIC")
+        .unwrap();
+    comments
+        .parse_file("src/c.rs", "//# EPIC A [2]\n//#Written\n", "docs", "EPIC")
+        .unwrap();
+    assert!(comments.comment_history["docs.EPIC A"][&Sequence::from(1)].generated);
+    assert!(comments.comment_history["docs.EPIC B"][&Sequence::from(0)].generated);
+    let content = comments
+        .render_file("docs.EPIC A", &comments.comment_history["docs.EPIC A"], &MarkdownWriter)
+        .unwrap();
+    let content = String::from_utf8(content).unwrap();
+    let written = content.find("Written").unwrap();
+    let heading = content.find("## Generated").unwrap();
+    assert!(written < heading && heading < content.find("Stub").unwrap());
+}
+
+#[cfg(test)]
+#[test]
+fn test_duplicate_sequence_snippet() {
+    let mut comments = Comments::default();
+    comments.begin_run("", "docs", "//#");
+    let content = "//# EPIC A [0]\n//#First\ncode\n//# EPIC A [0]\n//#Second\n";
+    let error = comments
+        .parse_file("a.rs", content, "docs", "EPIC")
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[E002] Duplicate Sequence number exist in name of block 0 (first defined in a.rs LINE: 1) in a.rs LINE: 4\n  2 | //#First\n  3 | code\n> 4 | //# EPIC A [0]\n  5 | //#Second"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_after_rejected_block() {
+    let mut comments = Comments::default();
+    comments.begin_run("", "docs", "//#");
+    let content = "//# EPIC A [0]\n//#First\ncode\n//# EPIC A [0]\n//#Second\ncode\n\
+        //# EPIC B\n//#Text\ncode\n//# EPIC A [1]\n//#Third\n";
+    let error = comments
+        .parse_file("a.rs", content, "docs", "EPIC")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("[E002] Duplicate Sequence number exist in name of block 0"));
+    assert!(error.contains("in a.rs LINE: 7"));
+    let lines: Vec<usize> = comments
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.line)
+        .collect();
+    assert_eq!(lines, [4, 7]);
+    let blocks = &comments.comment_history["docs.EPIC A"];
+    assert_eq!(&*blocks[&Sequence::from(0)].lines, ["First"]);
+    assert_eq!(&*blocks[&Sequence::from(1)].lines, ["Third"]);
+}
+
+#[cfg(test)]
+#[test]
+fn test_reproducible_source_link() {
+    let mut comments = Comments::new().with_reproducible(true);
+    comments.begin_run("src", "docs", "//#");
+    comments
+        .parse_file("src/io/a.rs", "//# EPIC A [0]\n//#Text\n", "docs", "EPIC")
+        .unwrap();
+    let content = comments
+        .render_file(
+            "docs.EPIC A",
+            &comments.comment_history["docs.EPIC A"],
+            &MarkdownWriter,
+        )
+        .unwrap();
+    assert!(
+        String::from_utf8(content)
+            .unwrap()
+            .starts_with("[SOURCE FILE:](../src/io/a.rs) LINE: 2\n")
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_check_staged_content() {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::process::Command;
+    let root = "target/test-check-staged";
+    let _ = remove_dir_all(root);
+    create_dir_all(root).unwrap();
+    let git = |arguments: &[&str]| {
+        assert!(
+            Command::new("git")
+                .arg("-C")
+                .arg(root)
+                .args(arguments)
+                .output()
+                .unwrap()
+                .status
+                .success()
+        )
+    };
+    git(&["init", "-q"]);
+    write(
+        format!("{root}/a.rs"),
+        "//# EPIC A\n//#No Sequence number\n",
+    )
+    .unwrap();
+    git(&["add", "a.rs"]);
+    write(
+        format!("{root}/a.rs"),
+        "//# EPIC A [0]\n//#Fixed, not staged\n",
+    )
+    .unwrap();
+    let mut comments = Comments::new();
+    assert!(
+        comments
+            .check_files(root, "//#", "EPIC", &["rs"], false)
+            .is_ok()
+    );
+    assert!(
+        comments
+            .check_files(root, "//#", "EPIC", &["rs"], true)
+            .is_err()
+    );
+    git(&["add", "a.rs"]);
+    assert!(
+        comments
+            .check_files(root, "//#", "EPIC", &["rs"], true)
+            .is_ok()
+    );
+    remove_dir_all(root).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+fn test_block_separator() {
+    let separators = Separators::parse(&["rule".to_string()]).unwrap();
+    let comments = Comments::new().with_separators(separators);
+    let block = |line: &str| Block::new("a.rs".into(), 1, vec![line.to_string()], &BTreeSet::new());
+    let blocks = Sequences::from([
+        (Sequence::from(0), block("first")),
+        (Sequence::from(1), block("second")),
+    ]);
+    let content = comments
+        .render_file("docs.EPIC A", &blocks, &MarkdownWriter)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(content).unwrap(),
+        "[SOURCE FILE:](file:///a.rs) LINE: 1\n\nfirst\n\n---\n\n[SOURCE FILE:](file:///a.rs) LINE: 1\n\nsecond\n\n"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_sequence_headings() {
+    let comments = Comments::new().with_sequence_headings(true);
+    let blocks = Sequences::from([(
+        Sequence::from(2),
+        Block::new(
+            "src/foo.rs".into(),
+            42,
+            vec!["text".to_string()],
+            &BTreeSet::new(),
+        ),
+    )]);
+    let content = comments
+        .render_file("docs.EPIC A", &blocks, &MarkdownWriter)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(content).unwrap(),
+        "## Sequence 2 — from src/foo.rs:42\n\n[SOURCE FILE:](file:///src/foo.rs) LINE: 42\n\ntext\n\n"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_split_large_file() {
+    let comments = Comments::new().with_max_file_size(Some(150));
+    let block = |line: &str| Block::new("a.rs".into(), 1, vec![line.repeat(100)], &BTreeSet::new());
+    let blocks = Sequences::from([
+        (Sequence::from(0), block("a")),
+        (Sequence::from(1), block("b")),
+        (Sequence::from(2), block("c")),
+    ]);
+    let parts = comments
+        .render_parts("docs.EPIC A.ITEM B", &blocks, &MarkdownWriter)
+        .unwrap();
+    assert_eq!(parts.len(), 3);
+    let first = String::from_utf8(parts[0].clone()).unwrap();
+    assert!(first.contains(&"a".repeat(100)) && !first.contains('b'));
+    assert!(first.ends_with(
+        "Part 1 of 3: [part 2](<ITEM B (part 2).md>) | [part 3](<ITEM B (part 3).md>)\n\n"
+    ));
+    assert_eq!(
+        part_path(Path::new("docs/EPIC A/ITEM B.md"), 2),
+        Path::new("docs/EPIC A/ITEM B (part 2).md")
+    );
+    let small = Comments::new().with_max_file_size(Some(10_000));
+    assert_eq!(
+        small
+            .render_parts("docs.EPIC A.ITEM B", &blocks, &MarkdownWriter)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_auto_sequence() {
+    let mut comments = Comments::new().with_auto_sequence(true);
+    comments.begin_run("", "docs", "//#");
+    let content =
+        "//# EPIC A\n//#First\ncode\n//# EPIC A\n//#Second\ncode\n//# EPIC A [1]\n//#Explicit\n";
+    comments
+        .parse_file("a.rs", content, "docs", "EPIC")
+        .unwrap();
+    let blocks = &comments.comment_history["docs.EPIC A"];
+    let text = |sequence: u16| blocks[&Sequence::from(sequence)].lines.join("");
+    assert_eq!(
+        blocks.keys().map(Sequence::top).collect::<Vec<_>>(),
+        [0, 1, 2]
+    );
+    assert_eq!(
+        (text(0), text(1), text(2)),
+        ("First".into(), "Explicit".into(), "Second".into())
+    );
+    assert!(Comments::new().strip_number_in_str("EPIC A").is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_continued_block() {
+    let mut comments = Comments::default();
+    comments.begin_run("", "docs", "//#");
+    comments
+        .parse_file("c.rs", "//# EPIC A [0+]\n//#Third\n", "docs", "EPIC")
+        .unwrap();
+    comments
+        .parse_file(
+            "b.rs",
+            "//# EPIC A [0+]\n//#Second\ncode\n//# EPIC B [4+]\n//#Lost\n",
+            "docs",
+            "EPIC",
+        )
+        .unwrap();
+    comments
+        .parse_file("a.rs", "//# EPIC A [0]\n//#First\n", "docs", "EPIC")
+        .unwrap();
+    comments.merge_continuations("docs");
+    let block = &comments.comment_history["docs.EPIC A"][&Sequence::from(0)];
+    assert_eq!(block.lines.join(" "), "First Second Third");
+    assert!(!comments.comment_history.contains_key("docs.EPIC B"));
+    assert_eq!(comments.diagnostics[0].code, "E013");
+    assert_eq!(comments.diagnostics[0].block, "EPIC B [4+]");
+    for file in ["a.rs", "b.rs", "c.rs"] {
+        comments
+            .source_hashes
+            .insert(file.to_string(), String::new());
+    }
+    let cache = comments.source_cache(&SourceCache::new());
+    assert!(cache["c.rs"].outputs.contains("docs.EPIC A"));
+    assert!(cache["b.rs"].outputs.contains("docs.EPIC A"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_name_selection() {
+    let comments = Comments::default().with_name_selection(
+        vec!["EPIC Get Lines.*".to_string()],
+        vec!["ITEM Old*".to_string()],
+    );
+    assert!(!comments.is_selected("docs.EPIC Get Lines", "docs"));
+    assert!(comments.is_selected("docs.EPIC Get Lines.ITEM Parse", "docs"));
+    assert!(comments.is_selected("docs.EPIC Get Lines.ITEM Parse.TASK Split", "docs"));
+    assert!(!comments.is_selected("docs.EPIC Get Lines.ITEM Old parser", "docs"));
+    assert!(!comments.is_selected("docs.EPIC Get Lines.ITEM Old parser.TASK A", "docs"));
+    assert!(!comments.is_selected("docs.EPIC Other.ITEM Parse", "docs"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_preview_source() {
+    let content = "//# EPIC A.ITEM B [1]\n//#Text\ncode\n";
+    let mut comments = Comments::default();
+    let mut markdown = Vec::new();
+    comments
+        .preview_source(
+            "a.rs",
+            content,
+            "//#",
+            "EPIC.ITEM",
+            OutputFormat::Markdown,
+            &mut markdown,
+        )
+        .unwrap();
+    let markdown = String::from_utf8(markdown).unwrap();
+    assert!(markdown.starts_with("# EPIC A.ITEM B\n\n[SOURCE FILE:](file:///a.rs) LINE: "));
+    assert!(markdown.ends_with("\n\nText\n\n"));
+    let mut json = Vec::new();
+    comments
+        .preview_source(
+            "a.rs",
+            content,
+            "//#",
+            "EPIC.ITEM",
+            OutputFormat::Json,
+            &mut json,
+        )
+        .unwrap();
+    assert!(
+        String::from_utf8(json)
+            .unwrap()
+            .contains("\"name\":\"EPIC A.ITEM B\"")
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_stop_flag_cleared_by_next_run() {
+    let folder = "target/test-stop-flag";
+    let _ = std::fs::remove_dir_all(folder);
+    std::fs::create_dir_all(folder).unwrap();
+    std::fs::write(format!("{folder}/a.rs"), "//# EPIC A [0]\n//#Text\ncode\n").unwrap();
+    // a stop request left over from an earlier run
+    let stop = AtomicBool::new(true);
+    let mut comments = Comments::default().with_stop_flag(&stop);
+    let mut markdown = Vec::new();
+    comments
+        .extract_to_writer(
+            folder,
+            "//#",
+            "EPIC",
+            &[".rs"],
+            OutputFormat::Markdown,
+            &mut markdown,
+        )
+        .unwrap();
+    assert!(!stop.load(Ordering::SeqCst));
+    assert!(String::from_utf8(markdown).unwrap().contains("Text"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_glossary_term_sequence() {
+    let mut comments = Comments::new()
+        .with_start_marker("//#")
+        .with_path_prefixes("EPIC")
+        .with_glossary(Some(Glossary::default()));
+    comments
+        .parse_str("a.rs", "//#TERM Line block [0]\n//#Marked lines.\ncode\n")
+        .unwrap();
+    let linked = comments.glossary.as_ref().unwrap().link_terms(
+        &["One Line block.".to_string()],
+        &BTreeSet::new(),
+        &mut HashSet::new(),
+        "glossary.md",
+        &|target, text| MarkdownWriter.link(target, text),
+    );
+    assert_eq!(linked, ["One [Line block](<glossary.md#line-block>)."]);
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_str() {
+    let mut comments = Comments::new();
+    assert!(comments.parse_str("a.rs", "//# EPIC A [0]\n").is_err());
+    let mut comments = Comments::new()
+        .with_start_marker("//#")
+        .with_path_prefixes("EPIC.ITEM");
+    comments
+        .parse_str("a.rs", "//# EPIC A.ITEM B [1]\n//#Text\ncode\n")
+        .unwrap();
+    let source = std::io::Cursor::new("//# EPIC A.ITEM B [2]\n//#More\n");
+    comments.parse_reader("b.rs", source).unwrap();
+    let query = comments.query();
+    let blocks = query.block_by_name("EPIC A.ITEM B").unwrap();
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(&*blocks[&Sequence::from(2)].source_file, "b.rs");
+    assert!(
+        comments
+            .parse_str("c.rs", "//# EPIC A.ITEM B [1]\n//#Again\n")
+            .is_err()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_file_without_marker() {
+    let config = MarkerConfig::parse("[markers]\n\".rs\" = \"//#\"\n").unwrap();
+    let mut comments = Comments::new()
+        .with_marker_config(Some(config))
+        .with_path_prefixes("EPIC.ITEM");
+    comments
+        .parse_str("a.rs", "//# EPIC A.ITEM B [1]\n//#Text\ncode\n")
+        .unwrap();
+    comments
+        .parse_str("notes.txt", "any line\nEPIC A.ITEM C [1]\n")
+        .unwrap();
+    let query = comments.query();
+    assert!(query.block_by_name("EPIC A.ITEM B").is_some());
+    assert_eq!(comments.history().len(), 1);
+}
+
+#[cfg(test)]
+#[test]
+fn test_block_end() {
+    let mut comments = Comments::new()
+        .with_start_marker("//#")
+        .with_path_prefixes("EPIC.ITEM")
+        .with_block_end(Some("end"));
+    comments
+        .parse_str(
+            "a.rs",
+            "//# EPIC A.ITEM B [1]\n//#Text\n//#end\n//#let old = 1;\n\n//# EPIC A.ITEM B [2]\n//#More\n//#end\n//# EPIC A.ITEM B [3]\n//#Right after\n",
+        )
+        .unwrap();
+    let query = comments.query();
+    let blocks = query.block_by_name("EPIC A.ITEM B").unwrap();
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(&*blocks[&Sequence::from(1)].lines, ["Text"]);
+    assert_eq!(&*blocks[&Sequence::from(3)].lines, ["Right after"]);
+}
+
+#[cfg(test)]
+#[test]
+fn test_fenced_code_in_block() {
+    let mut comments = Comments::new()
+        .with_start_marker("//#")
+        .with_path_prefixes("EPIC");
+    comments
+        .parse_str(
+            "a.rs",
+            "//# EPIC A [0]\n//# Example:\n//# ```rust\n//# fn main() {\n//#     let x = 1;\n//# }\n//# ```\n",
+        )
+        .unwrap();
+    let query = comments.query();
+    let blocks = query.block_by_name("EPIC A").unwrap();
+    assert_eq!(
+        &*blocks[&Sequence::from(0)].lines,
+        [
+            "Example:",
+            "```rust",
+            "fn main() {",
+            "    let x = 1;",
+            "}",
+            "```"
+        ]
+    );
+    let content = comments
+        .render_file("docs.EPIC A", blocks, &MarkdownWriter)
+        .unwrap();
+    assert!(
+        String::from_utf8(content)
+            .unwrap()
+            .contains("\n\nExample:\n```rust\nfn main() {\n    let x = 1;\n}\n```\n\n")
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_include_code() {
+    let mut comments = Comments::new()
+        .with_start_marker("//#")
+        .with_path_prefixes("EPIC");
+    comments
+        .parse_str(
+            "a.rs",
+            "//# EPIC A [0]\n//# The entry point:\n//# @include-code: 3\nfn main() {\n    run();\n}\nfn run() {}\n//# EPIC A [1]\n//# @include-code: 5\nlet a = 1;\n//# EPIC B [0]\n//# b\n",
+        )
+        .unwrap();
+    let query = comments.query();
+    let blocks = query.block_by_name("EPIC A").unwrap();
+    assert_eq!(
+        &*blocks[&Sequence::from(0)].lines,
+        [
+            "The entry point:",
+            "",
+            "```rust",
+            "fn main() {",
+            "    run();",
+            "}",
+            "```"
+        ]
+    );
+    assert_eq!(
+        &*blocks[&Sequence::from(1)].lines,
+        ["```rust", "let a = 1;", "```"]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_snippets() {
+    let mut comments = Comments::new()
+        .with_start_marker("//#")
+        .with_path_prefixes("EPIC");
+    comments
+        .parse_str(
+            "a.rs",
+            "//# EPIC A [0]\n//# The parser:\n//# {{snippet parse}}\n//#snippet-begin parse\nfn parse() {\n//#snippet-begin body\n    read();\n//#snippet-end body\n}\n//#snippet-end parse\n",
+        )
+        .unwrap();
+    comments.embed_snippets();
+    let query = comments.query();
+    let blocks = query.block_by_name("EPIC A").unwrap();
+    assert_eq!(
+        &*blocks[&Sequence::from(0)].lines,
+        [
+            "The parser:",
+            "```rust",
+            "fn parse() {",
+            "    read();",
+            "}",
+            "```"
+        ]
+    );
+    assert_eq!(comments.snippets["body"].lines, ["    read();"]);
+    assert_eq!(comments.snippets["parse"].start_line, 5);
+    comments
+        .parse_str("b.rs", "//# EPIC B [0]\n//# {{snippet body}}\n")
+        .unwrap();
+    comments.embed_snippets();
+    for file in ["a.rs", "b.rs"] {
+        comments
+            .source_hashes
+            .insert(file.to_string(), String::new());
+    }
+    let cache = comments.source_cache(&SourceCache::new());
+    assert!(
+        cache["a.rs"]
+            .outputs
+            .iter()
+            .any(|output| output.ends_with("EPIC B"))
+    );
+}
+
+/// Returns a line of a generated source file: code, a block header, a body line, a marker in
+/// a string, a marker with trailing white space only or an empty line.
+#[cfg(test)]
+fn source_line() -> impl proptest::strategy::Strategy<Value = String> {
+    use proptest::prelude::*;
+    prop_oneof![
+        "[a-z(){};= ]{0,12}",
+        (0u8..4).prop_map(|sequence| format!("//# EPIC A.ITEM B [{sequence}]")),
+        "//#[a-zA-Z .@:+\\[\\]0-9]{0,16}",
+        Just("let marker = \"//# EPIC A.ITEM C [1]\";".to_string()),
+        Just("//#   ".to_string()),
+        Just(String::new()),
+    ]
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_parser_stores_or_reports(lines in proptest::collection::vec(source_line(), 0..30)) {
+        let mut comments = Comments::new()
+            .with_start_marker("//#")
+            .with_path_prefixes("EPIC.ITEM");
+        let _ = comments.parse_str("a.rs", &lines.join("\n"));
+        for (index, line) in lines.iter().enumerate() {
+            let header_line = index + 1;
+            // a header right after a marked line is a body line of the block before it
+            let starts_block = line.starts_with("//# EPIC")
+                && (index == 0 || !lines[index - 1].starts_with("//#"));
+            if !starts_block {
+                continue;
+            }
+            let stored = comments
+                .comment_history
+                .values()
+                .flat_map(|blocks| blocks.values())
+                .any(|block| &*block.source_file == "a.rs" && block.start_line == header_line + 1);
+            let reported = comments
+                .diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.line == header_line);
+            proptest::prop_assert!(
+                stored || reported,
+                "header {line} at line {header_line} was neither stored nor reported"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_block_failed_event() {
+    use std::sync::Mutex;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let mut comments = Comments::new().with_subscriber(move |event: &Event| {
+        if let Event::BlockFailed {
+            path, line, header, ..
+        } = event
+        {
+            log.lock().unwrap().push(format!("{path}:{line} {header}"));
+        }
+    });
+    comments.begin_run("", "docs", "//#");
+    let content = "//# EPIC A [0]\n//#First\ncode\n//# EPIC A [0]\n//#Second\n";
+    assert!(
+        comments
+            .parse_file("a.rs", content, "docs", "EPIC")
+            .is_err()
+    );
+    comments.begin_run("", "docs", "//#");
+    let content = "//# EPIC B\n//#Text\n";
+    assert!(
+        comments
+            .parse_file("b.rs", content, "docs", "EPIC")
+            .is_err()
+    );
+    assert_eq!(
+        *seen.lock().unwrap(),
+        ["a.rs:4 EPIC A [0]", "b.rs:1 EPIC B"]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_forget_blocks_of() {
+    let mut comments = Comments::default();
+    comments.begin_run("", "docs", "//#");
+    let ignored = "//# EPIC A [0]\n//#First\ncode\n//#get-comments:ignore-file\n";
+    comments
+        .parse_file("a.rs", ignored, "docs", "EPIC")
+        .unwrap();
+    assert!(comments.comment_history.is_empty());
+    // the spelling of the ignored file no longer counts
+    comments
+        .parse_file("b.rs", "//# EPIC a [1]\n//#Second\n", "docs", "EPIC")
+        .unwrap();
+    // a name first used in another file stays known when a file using it is ignored
+    let ignored = "//# EPIC a [2]\n//#Third\ncode\n//#get-comments:ignore-file\n";
+    comments
+        .parse_file("c.rs", ignored, "docs", "EPIC")
+        .unwrap();
+    let error = comments
+        .parse_file("d.rs", "//# EPIC A [3]\n//#Fourth\n", "docs", "EPIC")
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("[E007] Block name EPIC a in b.rs LINE: 1 is spelled EPIC A")
+    );
+    assert_eq!(comments.comment_history["docs.EPIC a"].len(), 1);
+}
+
+#[cfg(test)]
+#[test]
+fn test_raw_region() {
+    let mut comments =
+        Comments::new().with_filters(vec![Filter::TrimTrailing, Filter::ExpandTabs(2)]);
+    comments.begin_run("", "docs", "//#");
+    let content = "//# EPIC A [0]\n//# text  \n//#get-comments:raw\n//#  +--+  \n//#@status: draft\n//#\tTab\n//#get-comments:end-raw\n//#\tafter\n";
+    comments
+        .parse_file("a.rs", content, "docs", "EPIC")
+        .unwrap();
+    let block = &comments.comment_history["docs.EPIC A"][&Sequence::from(0)];
+    assert_eq!(
+        *block.lines,
+        ["text", "  +--+  ", "@status: draft", "\tTab", "  after"]
+    );
+    assert_eq!(block.raw, BTreeSet::from([1, 2, 3]));
+    assert!(block.metadata.is_empty());
 }
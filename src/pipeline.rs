@@ -0,0 +1,272 @@
+use crate::encoding::Decoded;
+use crate::events::{Event, Events};
+use crate::exclude::Exclusions;
+use std::io::Error;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::{DirEntry, WalkDir};
+
+/// The number of items that may wait in a channel between two stages of the pipeline. This
+/// bounds the memory a run uses, whatever the size of the scanned tree.
+pub const CHANNEL_CAPACITY: usize = 16;
+
+/// A source file as read by the walker stage.
+pub struct SourceFile {
+    /// Path of the source file on disk.
+    pub name: String,
+    /// Content of the source file, or the error that kept it from being read.
+    pub content: Result<Decoded, Error>,
+    /// Time spent reading the source file.
+    pub read_time: Duration,
+}
+
+//#EPIC Get Lines.ITEM Run as a pipeline [0]
+//#
+//## Run as a pipeline
+//#A run is three stages connected by bounded channels: the walker finds and reads the source files,
+//#the parser takes them one by one in walk order, and the writer writes the rendered Markdown files.
+//#Reading overlaps with parsing and a full channel makes the stage before it wait.
+/// Finds every file below `root` whose name ends with one of `extensions`, for the walker
+/// stage, see `read_source_files`.
+///
+/// Symbolic links are followed, and the entries of every folder are visited in file name order,
+/// so the files are parsed in the same order on every file system. The folder is walked before
+/// the first file is read, so the number of files is known and the progress of the run can be
+/// shown against it. The walk stops early when `stop` is set.
+pub fn walk_source_files(
+    root: &str,
+    extensions: &[String],
+    exclusions: &Exclusions,
+    events: &Events,
+    stop: &AtomicBool,
+) -> Vec<DirEntry> {
+    source_entries(root, extensions, exclusions, Some(events))
+        .take_while(|_| !stop.load(Ordering::SeqCst))
+        .collect()
+}
+
+/// The walker stage: reads the files found by `walk_source_files` with `read`, such as
+/// `SourceEncodings::read`, and sends their content to the parser stage.
+///
+/// The stage stops early when the parser stage hangs up or `stop` is set. After every
+/// file the stage waits for `pause`, which throttles background runs.
+pub fn read_source_files(
+    entries: Vec<DirEntry>,
+    read: &dyn Fn(&Path) -> Result<Decoded, Error>,
+    files: SyncSender<SourceFile>,
+    pause: Duration,
+    stop: &AtomicBool,
+) {
+    for entry in entries {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        if let Some(name) = entry.path().to_str() {
+            let read_started = Instant::now();
+            let content = read(entry.path());
+            let file = SourceFile {
+                name: name.to_string(),
+                content,
+                read_time: read_started.elapsed(),
+            };
+            if files.send(file).is_err() {
+                break;
+            }
+            if !pause.is_zero() {
+                thread::sleep(pause);
+            }
+        }
+    }
+}
+
+//#EPIC Get Lines.ITEM Limit the walk [0]
+//#
+//## Limit the walk
+//#The walk follows symbolic links, but never around a loop: a link back to a folder it is in is
+//#skipped. `--no-follow-links` leaves every symbolic link alone, and `-max-depth N` keeps the
+//#walk within N levels of the source folder, its own files being level 1. With `-v` every
+//#entry the walk skips is logged with the reason.
+/// Returns the files below `root` whose name ends with one of `extensions`, in walk order.
+///
+/// The entries left out by the limits of `exclusions`, symbolic link loops and entries that
+/// cannot be read are sent to `events` as `Event::EntrySkipped`.
+fn source_entries<'w>(
+    root: &str,
+    extensions: &'w [String],
+    exclusions: &'w Exclusions,
+    events: Option<&'w Events>,
+) -> impl Iterator<Item = DirEntry> + 'w {
+    let skipped = move |path: &Path, reason: &str| {
+        if let Some(events) = events {
+            events.emit(Event::EntrySkipped { path, reason });
+        }
+    };
+    let mut walk = WalkDir::new(root)
+        .follow_links(exclusions.follows_links())
+        .sort_by_file_name();
+    if let Some(max_depth) = exclusions.max_depth() {
+        walk = walk.max_depth(max_depth);
+    }
+    walk.into_iter()
+        .filter_entry(move |entry| !exclusions.is_excluded(entry.path()))
+        .filter_map(move |entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(error) => {
+                let reason = match error.loop_ancestor() {
+                    Some(ancestor) => {
+                        format!("symbolic link loop back to {}", ancestor.display())
+                    }
+                    None => error.to_string(),
+                };
+                if let Some(path) = error.path() {
+                    skipped(path, &reason);
+                }
+                None
+            }
+        })
+        .filter(move |entry| {
+            if entry.path_is_symlink() && !exclusions.follows_links() {
+                skipped(entry.path(), "symbolic link not followed");
+                return false;
+            }
+            if entry.file_type().is_dir() && exclusions.max_depth() == Some(entry.depth()) {
+                skipped(entry.path(), "folder below -max-depth");
+                return false;
+            }
+            let file_name = entry.file_name().to_string_lossy();
+            entry.file_type().is_file()
+                && extensions
+                    .iter()
+                    .any(|extension| file_name.ends_with(extension))
+        })
+}
+
+#[cfg(all(test, unix))]
+#[test]
+fn test_walk_limits() {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    let root = "target/test-walk-limits";
+    let other = "target/test-walk-limits-other";
+    let _ = remove_dir_all(root);
+    let _ = remove_dir_all(other);
+    create_dir_all(format!("{root}/a/b")).unwrap();
+    create_dir_all(other).unwrap();
+    for file in ["top.rs", "a/x.rs", "a/b/c.rs"] {
+        write(format!("{root}/{file}"), "").unwrap();
+    }
+    write(format!("{other}/o.rs"), "").unwrap();
+    // a loop back to an ancestor, and a link to a folder outside the walked tree
+    std::os::unix::fs::symlink("..", format!("{root}/a/b/up")).unwrap();
+    std::os::unix::fs::symlink("../../test-walk-limits-other", format!("{root}/a/other")).unwrap();
+    let extensions = [".rs".to_string()];
+    let count = |follow_links, max_depth| {
+        let exclusions =
+            Exclusions::new(root, &[], false, |_| {}).with_limits(follow_links, max_depth);
+        let stop = AtomicBool::new(false);
+        walk_source_files(root, &extensions, &exclusions, &Events::default(), &stop).len()
+    };
+    assert_eq!(count(true, None), 4);
+    assert_eq!(count(false, None), 3);
+    assert_eq!(count(true, Some(2)), 2);
+    remove_dir_all(root).unwrap();
+    remove_dir_all(other).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+fn test_pipeline_stages() {
+    use crate::encoding::SourceEncodings;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::mpsc::sync_channel;
+    let root = "target/test-pipeline-stages";
+    let _ = remove_dir_all(root);
+    create_dir_all(format!("{root}/sub")).unwrap();
+    for file in ["b.rs", "a.rs", "c.txt", "sub/d.rs"] {
+        write(format!("{root}/{file}"), file).unwrap();
+    }
+    let extensions = [".rs".to_string()];
+    let exclusions = Exclusions::new(root, &[], false, |_| {});
+    let stop = AtomicBool::new(false);
+    let entries = || walk_source_files(root, &extensions, &exclusions, &Events::default(), &stop);
+    let encodings = SourceEncodings::default();
+    let reads = AtomicUsize::new(0);
+    let read = |path: &Path| {
+        reads.fetch_add(1, Ordering::SeqCst);
+        encodings.read(path)
+    };
+
+    // the files arrive in walk order, with their content
+    let (sender, receiver) = sync_channel(CHANNEL_CAPACITY);
+    read_source_files(entries(), &read, sender, Duration::ZERO, &stop);
+    let files: Vec<(String, String)> = receiver
+        .iter()
+        .map(|file| (file.name, file.content.unwrap().text))
+        .collect();
+    assert_eq!(
+        files,
+        [
+            (format!("{root}/a.rs"), "a.rs".to_string()),
+            (format!("{root}/b.rs"), "b.rs".to_string()),
+            (format!("{root}/sub/d.rs"), "sub/d.rs".to_string()),
+        ]
+    );
+
+    // a full channel makes the walker wait, and it stops when the parser hangs up
+    reads.store(0, Ordering::SeqCst);
+    let (sender, receiver) = sync_channel(0);
+    thread::scope(|scope| {
+        let walker =
+            scope.spawn(|| read_source_files(entries(), &read, sender, Duration::ZERO, &stop));
+        assert!(receiver.recv().unwrap().name.ends_with("a.rs"));
+        drop(receiver);
+        walker.join().unwrap();
+    });
+    assert_eq!(reads.load(Ordering::SeqCst), 2);
+
+    // nothing is read once the run is stopped
+    reads.store(0, Ordering::SeqCst);
+    let found = entries();
+    stop.store(true, Ordering::SeqCst);
+    let (sender, receiver) = sync_channel(CHANNEL_CAPACITY);
+    read_source_files(found, &read, sender, Duration::ZERO, &stop);
+    assert!(receiver.iter().next().is_none());
+    assert_eq!(reads.load(Ordering::SeqCst), 0);
+    remove_dir_all(root).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+fn test_background_pause() {
+    use crate::background::FILE_PAUSE;
+    use crate::encoding::SourceEncodings;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::sync::mpsc::sync_channel;
+    let root = "target/test-background-pause";
+    let _ = remove_dir_all(root);
+    create_dir_all(root).unwrap();
+    for file in ["a.rs", "b.rs", "c.rs"] {
+        write(format!("{root}/{file}"), "").unwrap();
+    }
+    let extensions = [".rs".to_string()];
+    let exclusions = Exclusions::new(root, &[], false, |_| {});
+    let stop = AtomicBool::new(false);
+    let entries = walk_source_files(root, &extensions, &exclusions, &Events::default(), &stop);
+    let encodings = SourceEncodings::default();
+    let (sender, receiver) = sync_channel(CHANNEL_CAPACITY);
+    let started = Instant::now();
+    read_source_files(
+        entries,
+        &|path| encodings.read(path),
+        sender,
+        FILE_PAUSE,
+        &stop,
+    );
+    // the walker waits after every file it sends
+    assert!(started.elapsed() >= FILE_PAUSE * 3);
+    assert_eq!(receiver.iter().count(), 3);
+    remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,108 @@
+use std::io::{Write, stderr};
+use std::time::{Duration, Instant};
+
+/// The shortest time between two redraws of the bar, so a tree of small files is not slowed
+/// down by the terminal.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The number of characters of the bar itself.
+const BAR_WIDTH: usize = 30;
+
+/// The number of characters of the file name shown after the bar, counted from its end.
+const NAME_WIDTH: usize = 40;
+
+//#EPIC Get Lines.ITEM Show progress [0]
+//#
+//## Show progress
+//#On a large tree a run can parse for minutes without printing anything. `--progress` draws a
+//#bar of the parsed files out of all files to scan on standard error, with the file being
+//#parsed, and removes it when the parse phase ends. The bar appears at once; until the walk
+//#has found every file the total is shown as `?`. The bar is only drawn on a terminal, so
+//#logs and CI output stay clean.
+/// A progress bar of the parsed source files, drawn on one line of standard error.
+pub struct Progress {
+    total: Option<usize>,
+    done: usize,
+    drawn: Option<Instant>,
+}
+
+impl Progress {
+    /// Creates the bar of a run that parses `total` files, an unknown number when `None`.
+    pub fn new(total: Option<usize>) -> Progress {
+        Progress {
+            total,
+            done: 0,
+            drawn: None,
+        }
+    }
+    /// Sets the number of files of the run once it is known.
+    pub fn set_total(&mut self, total: usize) {
+        self.total = Some(total);
+    }
+    /// Counts a parsed file, and draws the bar again when it was not drawn for a while or all
+    /// files are parsed.
+    pub fn advance(&mut self, file_name: &str) {
+        self.done += 1;
+        if self.total.is_none_or(|total| self.done < total)
+            && self
+                .drawn
+                .is_some_and(|drawn| drawn.elapsed() < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.drawn = Some(Instant::now());
+        let mut stderr = stderr();
+        let _ = write!(
+            stderr,
+            "\r{}\x1b[K",
+            progress_line(self.done, self.total, file_name)
+        );
+        let _ = stderr.flush();
+    }
+    /// Removes the bar from the terminal.
+    pub fn finish(&mut self) {
+        if self.drawn.take().is_some() {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+/// Formats the bar of `done` out of `total` files followed by the end of the file name. The
+/// bar stays empty while the total is not known.
+fn progress_line(done: usize, total: Option<usize>, file_name: &str) -> String {
+    let filled = match total {
+        None => 0,
+        Some(0) => BAR_WIDTH,
+        Some(total) => BAR_WIDTH * done.min(total) / total,
+    };
+    let total = total.map_or("?".to_string(), |total| total.to_string());
+    let skipped = file_name.chars().count().saturating_sub(NAME_WIDTH);
+    let name: String = file_name.chars().skip(skipped).collect();
+    format!(
+        "[{}{}] {done}/{total} files  {}{name}",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        match skipped {
+            0 => "",
+            _ => "...",
+        }
+    )
+}
+
+#[cfg(test)]
+#[test]
+fn test_progress_line() {
+    assert_eq!(
+        progress_line(1, Some(3), "src/a.rs"),
+        "[##########--------------------] 1/3 files  src/a.rs"
+    );
+    assert_eq!(
+        progress_line(2, None, "b.rs"),
+        "[------------------------------] 2/? files  b.rs"
+    );
+    let long = format!("src/{}/b.rs", "x".repeat(50));
+    assert!(
+        progress_line(3, Some(3), &long)
+            .ends_with(&format!("3/3 files  ...{}/b.rs", "x".repeat(35)))
+    );
+}
@@ -0,0 +1,163 @@
+use std::fs::{OpenOptions, read_to_string};
+use std::io::{Error, ErrorKind, Write};
+use std::path::Path;
+use toml::{Table, Value};
+
+/// The name of the project configuration file, looked up in the current folder.
+pub const PROJECT_FILE: &str = "get-comments.toml";
+
+/// The configuration `--init` writes: the mandatory options with example values and the
+/// optional ones commented out.
+const DEFAULT_PROJECT: &str = r#"# get-comments project configuration. Every key is a command line option without its
+# leading dashes; an option given on the command line overrides the value here.
+dir = "src"
+work = "docs"
+start = "//#"
+path = "EPIC.ITEM"
+ext = ".rs"
+
+# end = "*/"
+# block-end = "end"
+# config = "markers.toml"
+# marker-position = "indented"
+# inline = true
+# inline-strings = "auto"
+# format = ["md"]
+# link-style = "relative"
+# filter = ["trim-trailing"]
+# exclude = ["target", "node_modules"]
+# gitignore = true
+# max-depth = 8
+# reproducible = true
+# front-matter = true
+# manifest = true
+# timings = 5
+# stats = "stats.json"
+# coverage-min = 80
+# similarity = 0.9
+# log-format = "text"
+"#;
+
+//#EPIC Get Lines.ITEM Project configuration [0]
+//#
+//## Project configuration
+//#The options of a run may be kept in `get-comments.toml` in the folder get-comments is started
+//#from, so `-dir`, `-work`, `-start`, `-path` and `-ext` need not be typed every run. Every key
+//#is an option name, its value a string, a number, `true` for a flag or an array of strings for
+//#an option taking several values. An option given on the command line overrides the file.
+//#`--init` writes a default file to start from.
+/// The option values of a project configuration file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProjectConfig {
+    values: Vec<(String, Vec<String>)>,
+}
+
+impl ProjectConfig {
+    /// Reads `get-comments.toml` from the current folder, or returns an empty configuration
+    /// when there is none.
+    pub fn find() -> Result<ProjectConfig, Error> {
+        match Path::new(PROJECT_FILE).is_file() {
+            true => ProjectConfig::load(PROJECT_FILE),
+            false => Ok(ProjectConfig::default()),
+        }
+    }
+    /// Reads a project configuration file, see `parse` for the format.
+    pub fn load(file_name: &str) -> Result<ProjectConfig, Error> {
+        ProjectConfig::parse(&read_to_string(file_name)?)
+            .map_err(|error| Error::new(error.kind(), format!("{error} in {file_name}")))
+    }
+    /// Parses a project configuration: a TOML file with one `option = value` pair per option.
+    ///
+    /// The option is a command line option without its leading dashes. The value is a
+    /// string, a number, a boolean or an array of strings:
+    /// ```toml
+    /// dir = "src"
+    /// ext = ".rs"
+    /// timings = 5
+    /// gitignore = true
+    /// exclude = ["target", "vendor/**"]
+    /// ```
+    /// A flag set to `false` is left out.
+    pub fn parse(content: &str) -> Result<ProjectConfig, Error> {
+        let table: Table = toml::from_str(content)
+            .map_err(|error| Error::new(ErrorKind::InvalidInput, error.to_string()))?;
+        let mut config = ProjectConfig::default();
+        for (name, value) in table {
+            let invalid = || {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid option [{name}]: expected a string, a number, a boolean or an array of strings"
+                    ),
+                )
+            };
+            let values = match value {
+                Value::String(value) => vec![value],
+                Value::Integer(number) => vec![number.to_string()],
+                Value::Float(number) => vec![number.to_string()],
+                Value::Boolean(true) => Vec::new(),
+                Value::Boolean(false) => continue,
+                Value::Array(values) => values
+                    .into_iter()
+                    .map(|value| match value {
+                        Value::String(value) => Ok(value),
+                        _ => Err(invalid()),
+                    })
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(invalid()),
+            };
+            config.values.push((name, values));
+        }
+        Ok(config)
+    }
+    /// Writes the default configuration to `get-comments.toml` in the current folder. An
+    /// existing file is not overwritten.
+    pub fn init() -> Result<(), Error> {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(PROJECT_FILE)?
+            .write_all(DEFAULT_PROJECT.as_bytes())
+    }
+    /// Returns whether the option is set.
+    pub fn contains(&self, name: &str) -> bool {
+        self.values(name).is_some()
+    }
+    /// Returns the first value of the option, or `None` when it is not set or is a flag.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values(name)
+            .and_then(|values| values.first())
+            .map(String::as_str)
+    }
+    /// Returns all values of the option, empty for a flag, or `None` when it is not set.
+    pub fn values(&self, name: &str) -> Option<&[String]> {
+        self.values
+            .iter()
+            .find(|(known, _)| known == name)
+            .map(|(_, values)| values.as_slice())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_project_config() {
+    let config = ProjectConfig::parse(
+        "# project\ndir = \"src\" # sources\ntimings = 5\nsimilarity = 0.8\ngitignore = true\nmanifest = false\nexclude = [\"target\", \"vendor/**\"]\nformat = []\n",
+    )
+    .unwrap();
+    assert_eq!(config.value("dir"), Some("src"));
+    assert_eq!(config.value("timings"), Some("5"));
+    assert_eq!(config.value("similarity"), Some("0.8"));
+    assert!(config.contains("gitignore"));
+    assert_eq!(config.value("gitignore"), None);
+    assert!(!config.contains("manifest"));
+    assert_eq!(
+        config.values("exclude"),
+        Some(&["target".to_string(), "vendor/**".to_string()][..])
+    );
+    assert_eq!(config.values("format"), Some(&[][..]));
+    assert!(ProjectConfig::parse("dir = src").is_err());
+    assert!(ProjectConfig::parse("dir \"src\"").is_err());
+    assert!(ProjectConfig::parse("exclude = [1]").is_err());
+    assert!(ProjectConfig::parse(DEFAULT_PROJECT).unwrap().contains("ext"));
+}
@@ -0,0 +1,264 @@
+use crate::obsidian::NOTES_FILE;
+use crate::outputs::OUTPUTS_FILE;
+use crate::upgrade::VERSION_FILE;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{File, read, read_to_string};
+use std::io::{BufWriter, Error, Write};
+use walkdir::WalkDir;
+
+/// The file in the document root in which the generated files of every run are recorded.
+pub const MANIFEST_FILE: &str = ".get-comments-manifest";
+/// The file in the document root in which the metrics of the last run are written.
+const METRICS_FILE: &str = "metrics.json";
+/// The number of runs kept in the manifest, the oldest runs are dropped first.
+const KEPT_RUNS: usize = 100;
+/// The start of the footer line naming the run that generated a file.
+pub const FOOTER_PREFIX: &str = "_Generated by run ";
+/// The FNV-1a offset basis and prime, for fingerprints that stay the same across builds.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The files generated by one run, keyed by their path relative to the document root, with
+/// a fingerprint of their content.
+#[derive(Clone, Debug, Default)]
+pub struct RunRecord {
+    pub run_id: String,
+    pub files: BTreeMap<String, String>,
+}
+
+/// The counts written to the metrics file of a run.
+#[derive(Debug, Default, Serialize)]
+pub struct RunMetrics {
+    pub files_scanned: usize,
+    pub lines_scanned: usize,
+    pub blocks: usize,
+}
+
+/// How a generated file changed between two runs.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// Reads the runs recorded in the manifest, oldest first.
+///
+/// This must be called before the document root is cleared. A missing or unreadable file
+/// simply means no run was recorded yet.
+pub fn load_manifest(doc_root: &str) -> Vec<RunRecord> {
+    let mut runs: Vec<RunRecord> = Vec::new();
+    if let Ok(content) = read_to_string(format!("{doc_root}/{MANIFEST_FILE}")) {
+        for line in content.lines() {
+            let fields: Vec<&str> = line.splitn(3, '\t').collect();
+            match fields[..] {
+                ["run", run_id] => runs.push(RunRecord {
+                    run_id: run_id.to_string(),
+                    ..Default::default()
+                }),
+                ["file", path, fingerprint] => {
+                    if let Some(run) = runs.last_mut() {
+                        run.files.insert(path.to_string(), fingerprint.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    runs
+}
+
+/// Records the files now in the document root as the run `run_id` and appends the run to
+/// the manifest.
+///
+/// Without a run ID, as in reproducible mode, the ID is derived from the fingerprints of the
+/// files, and a run that generates the same files as the last recorded run is not appended
+/// again, so an unchanged document root gets the same manifest on every run.
+///
+/// # Returns:
+/// - The record of the run, to compare with earlier runs
+pub fn save_manifest(
+    mut runs: Vec<RunRecord>,
+    run_id: Option<&str>,
+    doc_root: &str,
+) -> Result<RunRecord, Error> {
+    let files = fingerprint_files(doc_root);
+    let current = RunRecord {
+        run_id: run_id.map_or_else(|| content_run_id(&files), str::to_string),
+        files,
+    };
+    if runs.last().is_none_or(|last| last.run_id != current.run_id) {
+        runs.push(current.clone());
+    }
+    let first_kept = runs.len().saturating_sub(KEPT_RUNS);
+    let mut writer = BufWriter::new(File::create(format!("{doc_root}/{MANIFEST_FILE}"))?);
+    for run in &runs[first_kept..] {
+        writeln!(writer, "run\t{}", run.run_id)?;
+        for (path, fingerprint) in &run.files {
+            writeln!(writer, "file\t{path}\t{fingerprint}")?;
+        }
+    }
+    Ok(current)
+}
+
+/// Fingerprints the content of every file in the document root, except the manifest, the
+/// metrics file, the layout version file and the list of Obsidian notes. Run footers are skipped, so a file only counts as changed when its
+/// documentation changed.
+fn fingerprint_files(doc_root: &str) -> BTreeMap<String, String> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(doc_root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        if let Ok(relative) = entry.path().strip_prefix(doc_root)
+            && let Ok(content) = read(entry.path())
+        {
+            let path = relative.to_string_lossy().replace('\\', "/");
+            if ![
+                MANIFEST_FILE,
+                METRICS_FILE,
+                VERSION_FILE,
+                NOTES_FILE,
+                OUTPUTS_FILE,
+            ]
+            .contains(&path.as_str())
+            {
+                let lines = content
+                    .split(|byte| *byte == b'\n')
+                    .filter(|line| !line.starts_with(FOOTER_PREFIX.as_bytes()));
+                files.insert(path, fingerprint(lines));
+            }
+        }
+    }
+    files
+}
+
+/// Hashes the given lines, each followed by a line feed, with 64 bit FNV-1a.
+///
+/// Unlike `DefaultHasher` the result never depends on the Rust release, so the fingerprints
+/// in the manifest stay comparable across builds.
+fn fingerprint<'a>(lines: impl Iterator<Item = &'a [u8]>) -> String {
+    let mut hash = FNV_OFFSET;
+    for byte in lines.flat_map(|line| line.iter().chain(b"\n")) {
+        hash = (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
+    }
+    format!("{hash:016x}")
+}
+
+/// Derives a run ID from the fingerprints of the generated files, so runs that generate the
+/// same files get the same ID.
+fn content_run_id(files: &BTreeMap<String, String>) -> String {
+    let lines: Vec<String> = files
+        .iter()
+        .map(|(path, fingerprint)| format!("{path}\t{fingerprint}"))
+        .collect();
+    fingerprint(lines.iter().map(String::as_bytes)).to_uppercase()
+}
+
+/// Lists the generated files that were added, changed or removed since the run `run_id`.
+///
+/// # Returns:
+/// - `Err(Error)` when the manifest holds no run with the given ID
+pub fn changes_since(
+    runs: &[RunRecord],
+    run_id: &str,
+    current: &RunRecord,
+) -> Result<Vec<(String, Change)>, Error> {
+    let Some(since) = runs.iter().find(|run| run.run_id == run_id) else {
+        return Err(Error::other(format!(
+            "Run [{run_id}] is not in the manifest"
+        )));
+    };
+    let mut changes = Vec::new();
+    for (path, fingerprint) in &current.files {
+        match since.files.get(path) {
+            None => changes.push((path.clone(), Change::Added)),
+            Some(previous) if previous != fingerprint => {
+                changes.push((path.clone(), Change::Changed))
+            }
+            Some(_) => {}
+        }
+    }
+    for path in since.files.keys() {
+        if !current.files.contains_key(path) {
+            changes.push((path.clone(), Change::Removed));
+        }
+    }
+    changes.sort();
+    Ok(changes)
+}
+
+/// Writes the metrics of the run `run_id` to `metrics.json` in the document root.
+pub fn write_metrics(
+    metrics: &RunMetrics,
+    run_id: &str,
+    files_written: usize,
+    doc_root: &str,
+) -> Result<(), Error> {
+    /// The document of the metrics file.
+    #[derive(Serialize)]
+    struct Metrics<'a> {
+        run_id: &'a str,
+        #[serde(flatten)]
+        metrics: &'a RunMetrics,
+        files_written: usize,
+    }
+    let mut writer = BufWriter::new(File::create(format!("{doc_root}/{METRICS_FILE}"))?);
+    serde_json::to_writer(
+        &mut writer,
+        &Metrics {
+            run_id,
+            metrics,
+            files_written,
+        },
+    )?;
+    writeln!(writer)
+}
+
+#[cfg(test)]
+#[test]
+fn test_changes_since() {
+    let record = |run_id: &str, files: &[(&str, &str)]| RunRecord {
+        run_id: run_id.to_string(),
+        files: files
+            .iter()
+            .map(|(path, fingerprint)| (path.to_string(), fingerprint.to_string()))
+            .collect(),
+    };
+    let runs = vec![record("A", &[("a.md", "1"), ("b.md", "2"), ("c.md", "3")])];
+    let current = record("B", &[("a.md", "1"), ("b.md", "9"), ("d.md", "4")]);
+    assert_eq!(
+        changes_since(&runs, "A", &current).unwrap(),
+        vec![
+            ("b.md".to_string(), Change::Changed),
+            ("c.md".to_string(), Change::Removed),
+            ("d.md".to_string(), Change::Added),
+        ]
+    );
+    assert!(changes_since(&runs, "X", &current).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_reproducible_manifest() {
+    let doc_root = "target/test-manifest";
+    let _ = std::fs::remove_dir_all(doc_root);
+    std::fs::create_dir_all(doc_root).unwrap();
+    std::fs::write(format!("{doc_root}/EPIC A.md"), "a\n").unwrap();
+    let first = save_manifest(Vec::new(), None, doc_root).unwrap();
+    assert_eq!(first.files["EPIC A.md"], "e5be99190415bda8");
+    let manifest = read_to_string(format!("{doc_root}/{MANIFEST_FILE}")).unwrap();
+    let second = save_manifest(load_manifest(doc_root), None, doc_root).unwrap();
+    assert_eq!(second.run_id, first.run_id);
+    assert_eq!(
+        read_to_string(format!("{doc_root}/{MANIFEST_FILE}")).unwrap(),
+        manifest
+    );
+    std::fs::write(format!("{doc_root}/EPIC A.md"), "b\n").unwrap();
+    let third = save_manifest(load_manifest(doc_root), None, doc_root).unwrap();
+    assert_ne!(third.run_id, first.run_id);
+    assert_eq!(load_manifest(doc_root).len(), 2);
+}
@@ -0,0 +1,249 @@
+use crate::block::{Block, Sequences, relative_name};
+use crate::glob::block_name_matches;
+use crate::sequence::Sequence;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A read only view on the blocks of a run, keyed by the dot delimited block name without the
+/// document root, such as `EPIC Get Lines.ITEM Parse`.
+///
+/// The names are kept in hierarchy order: a level comes before the levels below it, and the
+/// levels of one parent are ordered by name.
+pub struct BlockQuery<'h> {
+    names: BTreeMap<Vec<&'h str>, (&'h str, &'h Sequences)>,
+}
+
+impl<'h> BlockQuery<'h> {
+    /// Creates the view on a comment history whose keys are prefixed with `doc_root`.
+    pub fn new(history: &'h HashMap<String, Sequences>, doc_root: &str) -> BlockQuery<'h> {
+        let names = history
+            .iter()
+            .map(|(key, sequences)| {
+                let name = relative_name(key, doc_root);
+                (name.split('.').collect(), (name, sequences))
+            })
+            .collect();
+        BlockQuery { names }
+    }
+    /// Returns every block name with its Sequences in hierarchy order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'h str, &'h Sequences)> + '_ {
+        self.names.values().copied()
+    }
+    /// Returns the block name itself and every block name below it in hierarchy order, for
+    /// example `blocks_under("EPIC Get Lines")` returns all ITEMs and TASKs of that EPIC.
+    pub fn blocks_under(&self, name: &str) -> impl Iterator<Item = (&'h str, &'h Sequences)> + '_ {
+        let levels: Vec<String> = name.split('.').map(str::to_string).collect();
+        self.names
+            .iter()
+            .filter(move |(key, _)| {
+                key.len() >= levels.len() && levels.iter().zip(key.iter()).all(|(a, b)| a == b)
+            })
+            .map(|(_, entry)| *entry)
+    }
+    /// Returns all Sequences of a block name.
+    ///
+    /// # Returns:
+    /// - `None` when no block has that name
+    pub fn block_by_name(&self, name: &str) -> Option<&'h Sequences> {
+        let levels: Vec<&str> = name.split('.').collect();
+        self.names.get(&levels).map(|(_, sequences)| *sequences)
+    }
+    /// Returns one Sequence of a block name.
+    pub fn block(&self, name: &str, sequence: Sequence) -> Option<&'h Block> {
+        self.block_by_name(name)?.get(&sequence)
+    }
+    /// Returns the Sequence numbers of a block name in ascending order, empty when no block
+    /// has that name.
+    pub fn sequences_of(&self, name: &str) -> Vec<Sequence> {
+        self.block_by_name(name)
+            .map(|sequences| sequences.keys().copied().collect())
+            .unwrap_or_default()
+    }
+    /// Returns every Sequence whose `@field: value` directive has the given value, with its
+    /// block name, in hierarchy and Sequence order. The field name is not case sensitive.
+    pub fn blocks_where(
+        &self,
+        field: &str,
+        value: &str,
+    ) -> impl Iterator<Item = (&'h str, &'h Block)> + '_ {
+        let field = field.to_lowercase();
+        let value = value.to_string();
+        self.iter().flat_map(move |(name, sequences)| {
+            let field = field.clone();
+            let value = value.clone();
+            sequences
+                .values()
+                .filter(move |block| block.metadata.get(&field) == Some(&value))
+                .map(move |block| (name, block))
+        })
+    }
+    /// Returns the paths of the source files that hold the Sequences of a block name.
+    pub fn sources_of(&self, name: &str) -> BTreeSet<&'h str> {
+        self.block_by_name(name)
+            .into_iter()
+            .flat_map(|sequences| sequences.values())
+            .map(|block| &*block.path)
+            .collect()
+    }
+    /// Returns the block names a glob matches, in hierarchy order. The glob has `.` between
+    /// the levels and is matched as `-exclude` matches a path: a glob without a `.` is matched
+    /// against the last level, `*` and `?` stay within a level and `**` matches any number of
+    /// levels, so `EPIC Get Lines.**` matches every block of that EPIC.
+    pub fn blocks_matching(
+        &self,
+        glob: &str,
+    ) -> impl Iterator<Item = (&'h str, &'h Sequences)> + '_ {
+        let glob = glob.to_string();
+        self.iter()
+            .filter(move |(name, _)| block_name_matches(&glob, name))
+    }
+    /// Returns a table with a row per Sequence: the block name, the Sequence number, the source
+    /// file and the line range, in hierarchy and Sequence order. With `glob` only the block
+    /// names it matches are listed, see `blocks_matching`.
+    pub fn table(&self, glob: Option<&str>) -> String {
+        let header = ["BLOCK", "SEQUENCE", "FILE", "LINES"].map(str::to_string);
+        let blocks: Vec<(&str, &Sequences)> = match glob {
+            Some(glob) => self.blocks_matching(glob).collect(),
+            None => self.iter().collect(),
+        };
+        let rows: Vec<[String; 4]> = blocks
+            .into_iter()
+            .flat_map(|(name, sequences)| {
+                sequences.iter().map(move |(sequence, block)| {
+                    [
+                        name.to_string(),
+                        sequence.to_string(),
+                        block.source_file.to_string(),
+                        match block.end_line > block.start_line {
+                            true => format!("{}-{}", block.start_line, block.end_line),
+                            false => block.start_line.to_string(),
+                        },
+                    ]
+                })
+            })
+            .collect();
+        let mut widths = [0; 4];
+        for row in std::iter::once(&header).chain(&rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let lines: Vec<String> = std::iter::once(&header)
+            .chain(&rows)
+            .map(|row| {
+                format!(
+                    "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+                    row[0],
+                    row[1],
+                    row[2],
+                    row[3],
+                    w0 = widths[0],
+                    w1 = widths[1],
+                    w2 = widths[2]
+                )
+            })
+            .collect();
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_block_query() {
+    let block = |path: &str| Block {
+        path: path.into(),
+        ..Block::default()
+    };
+    let mut history = HashMap::new();
+    for (key, sequence, path) in [
+        ("docs.EPIC B", 0, "b.rs"),
+        ("docs.EPIC A.ITEM Z", 0, "z.rs"),
+        ("docs.EPIC A", 0, "a.rs"),
+        ("docs.EPIC A.ITEM Y", 1, "y.rs"),
+        ("docs.EPIC A.ITEM Y", 0, "x.rs"),
+        ("docs.EPIC A B", 0, "ab.rs"),
+    ] {
+        history
+            .entry(key.to_string())
+            .or_insert_with(Sequences::new)
+            .insert(Sequence::from(sequence), block(path));
+    }
+    let query = BlockQuery::new(&history, "docs");
+    let names: Vec<&str> = query.iter().map(|(name, _)| name).collect();
+    assert_eq!(
+        names,
+        [
+            "EPIC A",
+            "EPIC A.ITEM Y",
+            "EPIC A.ITEM Z",
+            "EPIC A B",
+            "EPIC B"
+        ]
+    );
+    let under: Vec<&str> = query.blocks_under("EPIC A").map(|(name, _)| name).collect();
+    assert_eq!(under, ["EPIC A", "EPIC A.ITEM Y", "EPIC A.ITEM Z"]);
+    assert_eq!(
+        query.sequences_of("EPIC A.ITEM Y"),
+        [Sequence::from(0), Sequence::from(1)]
+    );
+    assert_eq!(
+        query.sources_of("EPIC A.ITEM Y"),
+        BTreeSet::from(["x.rs", "y.rs"])
+    );
+    let mut tagged = block("t.rs");
+    tagged
+        .metadata
+        .insert("status".to_string(), "beta".to_string());
+    history.insert(
+        "docs.EPIC C".to_string(),
+        Sequences::from([
+            (Sequence::from(0), block("c.rs")),
+            (Sequence::from(1), tagged),
+        ]),
+    );
+    let query = BlockQuery::new(&history, "docs");
+    let beta: Vec<(&str, &str)> = query
+        .blocks_where("Status", "beta")
+        .map(|(name, block)| (name, &*block.path))
+        .collect();
+    assert_eq!(beta, [("EPIC C", "t.rs")]);
+    assert!(query.block_by_name("EPIC D").is_none());
+    assert_eq!(&*query.block("EPIC B", 0.into()).unwrap().path, "b.rs");
+}
+
+#[cfg(test)]
+#[test]
+fn test_block_table() {
+    let block = |source_file: &str, start_line: usize, end_line: usize| Block {
+        source_file: source_file.into(),
+        start_line,
+        end_line,
+        ..Block::default()
+    };
+    let history: HashMap<String, Sequences> = [
+        (
+            "docs.EPIC A.ITEM Parse".to_string(),
+            Sequences::from([
+                (Sequence::from(0), block("src/parse.rs", 12, 18)),
+                (Sequence::from(1), block("src/a.rs", 4, 4)),
+            ]),
+        ),
+        (
+            "docs.EPIC B".to_string(),
+            Sequences::from([(Sequence::from(0), block("src/b.rs", 1, 3))]),
+        ),
+    ]
+    .into_iter()
+    .collect();
+    let query = BlockQuery::new(&history, "docs");
+    assert_eq!(
+        query.table(None),
+        "BLOCK              SEQUENCE  FILE          LINES\nEPIC A.ITEM Parse  0         src/parse.rs  12-18\nEPIC A.ITEM Parse  1         src/a.rs      4\nEPIC B             0         src/b.rs      1-3"
+    );
+    let names: Vec<&str> = query
+        .blocks_matching("EPIC A.**")
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(names, ["EPIC A.ITEM Parse"]);
+    assert_eq!(query.blocks_matching("*Pars?").count(), 1);
+    assert_eq!(query.blocks_matching("EPIC *").count(), 1);
+}
@@ -0,0 +1,232 @@
+use crate::provenance::{MANIFEST_FILE, load_manifest};
+use std::collections::BTreeSet;
+use std::env;
+use std::fs::{create_dir_all, remove_file};
+use std::io::Error;
+use std::path::Path;
+use std::process::Command;
+
+/// A document root on a remote server. The run writes into a local staging folder, and
+/// `publish` uploads what changed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemoteTarget {
+    /// An S3 bucket, given as `s3://bucket/prefix`.
+    S3 { bucket: String, prefix: String },
+    /// A WebDAV collection, given as an `http://` or `https://` URL.
+    WebDav { url: String },
+}
+
+impl RemoteTarget {
+    /// Parses a document root given as a URL.
+    ///
+    /// # Returns:
+    /// - `None` when the document root is a local folder
+    pub fn parse(work: &str) -> Option<RemoteTarget> {
+        if let Some(location) = work.strip_prefix("s3://") {
+            let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+            return Some(RemoteTarget::S3 {
+                bucket: bucket.to_string(),
+                prefix: prefix.trim_matches('/').to_string(),
+            });
+        }
+        (work.starts_with("http://") || work.starts_with("https://")).then(|| {
+            RemoteTarget::WebDav {
+                url: work.trim_end_matches('/').to_string(),
+            }
+        })
+    }
+    /// Creates the local staging folder of a run and downloads the manifest of the previous
+    /// run into it, so the run can tell which remote files disappeared.
+    ///
+    /// # Returns:
+    /// - The path of the staging folder
+    pub fn stage(&self) -> Result<String, Error> {
+        let staging = env::temp_dir().join(format!("get-comments-stage-{}", std::process::id()));
+        create_dir_all(&staging)?;
+        self.download(MANIFEST_FILE, &staging.join(MANIFEST_FILE))?;
+        Ok(staging.to_string_lossy().into_owned())
+    }
+    //#EPIC Get Lines.ITEM Publish to a remote document root [0]
+    //#
+    //## Publish to a remote document root
+    //#When `-work` is an `s3://` or WebDAV URL the run writes into a local staging folder. The files
+    //#whose fingerprint in the manifest changed since the previous run are uploaded, and the files
+    //#the previous run recorded but this run did not write are deleted from the server.
+    /// Uploads the files of the staging folder that were added or changed since the previous
+    /// run, deletes the remote files that this run no longer wrote, and uploads the manifest.
+    ///
+    /// # Returns:
+    /// - The number of uploaded and deleted files
+    pub fn publish(&self, staging: &str) -> Result<(usize, usize), Error> {
+        let runs = load_manifest(staging);
+        let Some(current) = runs.last() else {
+            return Err(Error::other(format!("No run recorded in {staging}")));
+        };
+        let previous = runs.len().checked_sub(2).map(|index| &runs[index]);
+        let mut collections = BTreeSet::new();
+        let mut uploaded = 0;
+        for (path, fingerprint) in &current.files {
+            if previous.and_then(|run| run.files.get(path)) != Some(fingerprint) {
+                self.create_collections(path, &mut collections);
+                self.upload(path, &Path::new(staging).join(path))?;
+                uploaded += 1;
+            }
+        }
+        let mut deleted = 0;
+        for path in previous.iter().flat_map(|run| run.files.keys()) {
+            if !current.files.contains_key(path) {
+                self.delete(path)?;
+                deleted += 1;
+            }
+        }
+        self.upload(MANIFEST_FILE, &Path::new(staging).join(MANIFEST_FILE))?;
+        Ok((uploaded, deleted))
+    }
+    /// Returns the URL of a file given by its path relative to the document root.
+    fn url(&self, relative: &str) -> String {
+        let relative = encode_path(relative);
+        match self {
+            RemoteTarget::S3 { bucket, prefix } => {
+                let key = match prefix.is_empty() {
+                    true => relative,
+                    false => format!("{}/{relative}", encode_path(prefix)),
+                };
+                match env::var("AWS_ENDPOINT_URL") {
+                    Ok(endpoint) => format!("{}/{bucket}/{key}", endpoint.trim_end_matches('/')),
+                    Err(_) => format!("https://{bucket}.s3.{}.amazonaws.com/{key}", region()),
+                }
+            }
+            RemoteTarget::WebDav { url } => format!("{url}/{relative}"),
+        }
+    }
+    /// Returns the `curl` arguments that authenticate a request.
+    ///
+    /// S3 requests are signed with the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// optional `AWS_SESSION_TOKEN` from the environment. WebDAV requests use
+    /// `WEBDAV_USER` and `WEBDAV_PASSWORD` when set, and `~/.netrc` otherwise.
+    fn authentication(&self) -> Vec<String> {
+        let variable = |name: &str| env::var(name).unwrap_or_default();
+        match self {
+            RemoteTarget::S3 { .. } => {
+                let mut arguments = vec![
+                    "--aws-sigv4".to_string(),
+                    format!("aws:amz:{}:s3", region()),
+                    "--user".to_string(),
+                    format!(
+                        "{}:{}",
+                        variable("AWS_ACCESS_KEY_ID"),
+                        variable("AWS_SECRET_ACCESS_KEY")
+                    ),
+                ];
+                if let Ok(token) = env::var("AWS_SESSION_TOKEN") {
+                    arguments.push("-H".to_string());
+                    arguments.push(format!("x-amz-security-token: {token}"));
+                }
+                arguments
+            }
+            RemoteTarget::WebDav { .. } => match env::var("WEBDAV_USER") {
+                Ok(user) => vec![
+                    "--user".to_string(),
+                    format!("{user}:{}", variable("WEBDAV_PASSWORD")),
+                ],
+                Err(_) => vec!["--netrc-optional".to_string()],
+            },
+        }
+    }
+    /// Downloads a file of the document root.
+    ///
+    /// # Returns:
+    /// - `false` when the file does not exist on the server. S3 answers 403 instead of 404
+    ///   when the credentials may not list the bucket, so both mean missing.
+    fn download(&self, relative: &str, file: &Path) -> Result<bool, Error> {
+        match self.curl(&["-o", &file.to_string_lossy()], relative) {
+            Ok(()) => Ok(true),
+            Err(error)
+                if error.to_string().contains("404") || error.to_string().contains("403") =>
+            {
+                let _ = remove_file(file);
+                Ok(false)
+            }
+            Err(error) => Err(error),
+        }
+    }
+    /// Uploads a file to the document root.
+    fn upload(&self, relative: &str, file: &Path) -> Result<(), Error> {
+        self.curl(&["-T", &file.to_string_lossy()], relative)
+    }
+    /// Deletes a file from the document root.
+    fn delete(&self, relative: &str) -> Result<(), Error> {
+        self.curl(&["-X", "DELETE"], relative)
+    }
+    /// Creates the WebDAV collections a file is put in, each folder once per run. S3 has no
+    /// folders. A collection that already exists is not an error.
+    fn create_collections(&self, relative: &str, created: &mut BTreeSet<String>) {
+        if let RemoteTarget::WebDav { .. } = self {
+            let levels: Vec<&str> = relative.split('/').collect();
+            for end in 1..levels.len() {
+                let folder = levels[..end].join("/");
+                if created.insert(folder.clone()) {
+                    let _ = self.curl(&["-X", "MKCOL"], &folder);
+                }
+            }
+        }
+    }
+    /// Runs `curl` on the URL of a file of the document root and turns a failed request into
+    /// an error.
+    fn curl(&self, arguments: &[&str], relative: &str) -> Result<(), Error> {
+        let url = self.url(relative);
+        let output = Command::new("curl")
+            .args(["-s", "-S", "-f", "--max-time", "60"])
+            .args(self.authentication())
+            .args(arguments)
+            .arg(&url)
+            .output()
+            .map_err(|error| Error::other(format!("could not run curl: {error}")))?;
+        if !output.status.success() {
+            return Err(Error::other(format!(
+                "{url}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the AWS region from the environment, `us-east-1` by default.
+fn region() -> String {
+    env::var("AWS_REGION")
+        .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+        .unwrap_or_else(|_| "us-east-1".to_string())
+}
+
+/// Percent encodes a relative path for a URL, keeping the `/` separators.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+#[test]
+fn test_remote_target() {
+    assert_eq!(RemoteTarget::parse("docs"), None);
+    let webdav = RemoteTarget::parse("https://dav.example.com/docs/").unwrap();
+    assert_eq!(
+        webdav.url("EPIC A/ITEM B.md"),
+        "https://dav.example.com/docs/EPIC%20A/ITEM%20B.md"
+    );
+    assert_eq!(
+        RemoteTarget::parse("s3://bucket/site/docs/"),
+        Some(RemoteTarget::S3 {
+            bucket: "bucket".to_string(),
+            prefix: "site/docs".to_string()
+        })
+    );
+}
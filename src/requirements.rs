@@ -0,0 +1,270 @@
+use crate::block::{Sequences, relative_name};
+use crate::outputs::write_if_changed;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::read_to_string;
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+/// The directive naming the requirement IDs a block documents, e.g. `@requirement: REQ-12`.
+const REQUIREMENT_DIRECTIVE: &str = "requirement";
+
+/// The file in the document root holding the reconciliation of the requirements.
+pub const REQUIREMENTS_FILE: &str = "requirements.md";
+
+/// The requirements the documentation is expected to cover: requirement IDs or block names.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Requirements {
+    ids: Vec<String>,
+}
+
+/// The outcome of comparing the blocks of a run with a requirements list.
+#[derive(Debug, Default, PartialEq)]
+pub struct Reconciliation {
+    /// Every requirement that is covered, with the names of the blocks covering it.
+    pub present: BTreeMap<String, BTreeSet<String>>,
+    /// The requirements no block covers, in list order.
+    pub missing: Vec<String>,
+    /// The names of the blocks that cover no requirement.
+    pub unexpected: BTreeSet<String>,
+}
+
+impl Requirements {
+    /// Reads a requirements list: a JSON file when the name ends with `.json`, a list of
+    /// names when it ends with `.txt`, otherwise a CSV file. See `parse_json`, `parse_lines`
+    /// and `parse_csv` for the formats.
+    pub fn load(file_name: &str) -> Result<Requirements, Error> {
+        let content = read_to_string(file_name)?;
+        if file_name.ends_with(".json") {
+            Requirements::parse_json(&content)
+                .map_err(|error| Error::other(format!("{error} in {file_name}")))
+        } else if file_name.ends_with(".txt") {
+            Ok(Requirements::parse_lines(&content))
+        } else {
+            Ok(Requirements::parse_csv(&content))
+        }
+    }
+    /// Parses a list of required block names or requirement IDs, one per line, such as
+    /// `required-blocks.txt`. Blank lines and lines starting with `#` are skipped, so a name
+    /// may hold commas.
+    pub fn parse_lines(content: &str) -> Requirements {
+        let ids = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        Requirements { ids }
+    }
+    /// Parses a CSV requirements list, taking the first column of every line. A first line
+    /// whose first column is `id` or `name` is a header and is skipped, as are blank lines.
+    /// Values may be double quoted, so they can hold commas, with `""` for a quote.
+    pub fn parse_csv(content: &str) -> Requirements {
+        let mut ids = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let first = first_csv_field(line);
+            let first = first.trim();
+            let header = number == 0
+                && (first.eq_ignore_ascii_case("id") || first.eq_ignore_ascii_case("name"));
+            if !first.is_empty() && !header {
+                ids.push(first.to_string());
+            }
+        }
+        Requirements { ids }
+    }
+    /// Parses a JSON requirements list: an array of strings, or of objects with an `id` or
+    /// `name` string field.
+    pub fn parse_json(content: &str) -> Result<Requirements, Error> {
+        let Value::Array(entries) = serde_json::from_str::<Value>(content)? else {
+            return Err(Error::other("Requirements must be a JSON array"));
+        };
+        let ids = entries
+            .iter()
+            .map(|entry| match entry {
+                Value::String(id) => Some(id.clone()),
+                Value::Object(fields) => fields
+                    .get("id")
+                    .or_else(|| fields.get("name"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                _ => None,
+            })
+            .collect::<Option<Vec<String>>>()
+            .ok_or_else(|| {
+                Error::other("Requirements must be strings or objects with an id or name")
+            })?;
+        Ok(Requirements { ids })
+    }
+    //#EPIC Get Lines.ITEM Reconcile requirements [0]
+    //#
+    //## Reconcile requirements
+    //#Compliance teams keep the requirements of a product in a list outside the code. Every entry of
+    //#the list is looked up among the blocks, by block name or by the `@requirement:` directive,
+    //#and the report shows which requirements are documented, which are missing, and which blocks
+    //#document no requirement on the list.
+    /// Compares the blocks of a run with the requirements.
+    ///
+    /// A requirement is covered by a block whose name, without the document root, equals it,
+    /// or whose `@requirement:` directive lists it among its comma separated IDs.
+    pub fn reconcile(
+        &self,
+        history: &HashMap<String, Sequences>,
+        doc_root: &str,
+    ) -> Reconciliation {
+        let mut covering: HashMap<&str, BTreeSet<String>> = HashMap::new();
+        let mut unexpected = BTreeSet::new();
+        for (key, blocks) in history {
+            let name = relative_name(key, doc_root);
+            let mut covers: BTreeSet<&str> = blocks
+                .values()
+                .filter_map(|block| block.metadata.get(REQUIREMENT_DIRECTIVE))
+                .flat_map(|ids| ids.split(','))
+                .map(str::trim)
+                .collect();
+            covers.insert(name);
+            let mut expected = false;
+            for id in &self.ids {
+                if covers.contains(id.as_str()) {
+                    covering
+                        .entry(id.as_str())
+                        .or_default()
+                        .insert(name.to_string());
+                    expected = true;
+                }
+            }
+            if !expected {
+                unexpected.insert(name.to_string());
+            }
+        }
+        let mut reconciliation = Reconciliation {
+            unexpected,
+            ..Reconciliation::default()
+        };
+        for id in &self.ids {
+            match covering.remove(id.as_str()) {
+                Some(names) => {
+                    reconciliation.present.insert(id.clone(), names);
+                }
+                None if !reconciliation.present.contains_key(id) => {
+                    reconciliation.missing.push(id.clone())
+                }
+                None => {}
+            }
+        }
+        reconciliation
+    }
+}
+
+/// Writes the reconciliation to `requirements.md` in the document root, unless it already
+/// holds it, and returns its path.
+pub fn write_requirements_report(
+    reconciliation: &Reconciliation,
+    doc_root: &str,
+) -> Result<PathBuf, Error> {
+    let mut writer = Vec::new();
+    writeln!(writer, "# Requirements\n")?;
+    writeln!(
+        writer,
+        "{} present, {} missing, {} unexpected\n",
+        reconciliation.present.len(),
+        reconciliation.missing.len(),
+        reconciliation.unexpected.len()
+    )?;
+    writeln!(writer, "## Present\n")?;
+    writeln!(writer, "| Requirement | Blocks |")?;
+    writeln!(writer, "| --- | --- |")?;
+    for (id, names) in &reconciliation.present {
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        writeln!(writer, "| {id} | {} |", names.join(", "))?;
+    }
+    writeln!(writer, "\n## Missing\n")?;
+    for id in &reconciliation.missing {
+        writeln!(writer, "- {id}")?;
+    }
+    writeln!(writer, "\n## Unexpected\n")?;
+    for name in &reconciliation.unexpected {
+        writeln!(writer, "- {name}")?;
+    }
+    let path = Path::new(doc_root).join(REQUIREMENTS_FILE);
+    write_if_changed(&path, &writer)?;
+    Ok(path)
+}
+
+/// Returns the first field of a CSV line. A field in double quotes ends at the closing quote,
+/// so it may hold commas, and `""` inside it is a quote.
+fn first_csv_field(line: &str) -> String {
+    let line = line.trim_start();
+    let Some(quoted) = line.strip_prefix('"') else {
+        return line.split(',').next().unwrap_or_default().to_string();
+    };
+    let mut field = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if chars.as_str().starts_with('"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => break,
+            c => field.push(c),
+        }
+    }
+    field
+}
+
+#[cfg(test)]
+#[test]
+fn test_reconcile_requirements() {
+    use crate::block::Block;
+    use crate::sequence::Sequence;
+    let requirements = Requirements::parse_csv("id,title\nREQ-1,Login\n\"EPIC B\",B\nREQ-3,x\n");
+    assert_eq!(
+        Requirements::parse_json(r#"["REQ-1", {"name": "EPIC B"}, {"id": "REQ-3"}]"#).unwrap(),
+        requirements
+    );
+    assert_eq!(
+        Requirements::parse_lines("# required blocks\nREQ-1\n\n  EPIC B\nREQ-3\n"),
+        requirements
+    );
+    assert!(Requirements::parse_json(r#"[1]"#).is_err());
+    assert_eq!(
+        Requirements::parse_csv("\"REQ-1, login\",x\n \"say \"\"hi\"\"\" ,y\nplain , z\n"),
+        Requirements {
+            ids: vec![
+                "REQ-1, login".to_string(),
+                "say \"hi\"".to_string(),
+                "plain".to_string(),
+            ]
+        }
+    );
+    let block = |requirement: Option<&str>| {
+        let lines = requirement
+            .map(|ids| vec![format!("@requirement: {ids}")])
+            .unwrap_or_default();
+        Sequences::from([(
+            Sequence::from(0),
+            Block::new("a.rs".into(), 1, lines, &BTreeSet::new()),
+        )])
+    };
+    let history: HashMap<String, Sequences> = [
+        ("docs.EPIC A", block(Some("REQ-1, REQ-9"))),
+        ("docs.EPIC B", block(None)),
+        ("docs.EPIC C", block(None)),
+    ]
+    .into_iter()
+    .map(|(key, blocks)| (key.to_string(), blocks))
+    .collect();
+    let reconciliation = requirements.reconcile(&history, "docs");
+    assert_eq!(
+        reconciliation.present,
+        BTreeMap::from([
+            ("EPIC B".to_string(), BTreeSet::from(["EPIC B".to_string()])),
+            ("REQ-1".to_string(), BTreeSet::from(["EPIC A".to_string()])),
+        ])
+    );
+    assert_eq!(reconciliation.missing, ["REQ-3"]);
+    assert_eq!(
+        reconciliation.unexpected,
+        BTreeSet::from(["EPIC C".to_string()])
+    );
+}
@@ -0,0 +1,40 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Crockford's base 32 alphabet used by ULIDs.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Creates a new run ID: a ULID made of the current time in milliseconds followed by 80
+/// random bits, encoded as 26 characters of Crockford base 32.
+///
+/// ULIDs sort by creation time, so the run IDs of consecutive runs sort in run order.
+pub fn new_run_id() -> String {
+    let milliseconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(milliseconds);
+    hasher.write_u32(std::process::id());
+    let high = hasher.finish();
+    hasher.write_u64(high);
+    let low = hasher.finish();
+    let randomness = (u128::from(high) << 16 | u128::from(low & 0xFFFF)) & ((1 << 80) - 1);
+    let value = (milliseconds & ((1 << 48) - 1)) << 80 | randomness;
+
+    (0..26)
+        .rev()
+        .map(|position| ALPHABET[((value >> (position * 5)) & 0x1F) as usize] as char)
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_new_run_id() {
+    let first = new_run_id();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let second = new_run_id();
+    assert_eq!(first.len(), 26);
+    assert!(first < second);
+}
@@ -0,0 +1,268 @@
+use crate::front_matter::FrontMatterValue;
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::io::Error;
+use toml::{Table, Value};
+
+/// The type of a metadata field, which the values of its `@name: value` directives must have.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldType {
+    /// Any text.
+    String,
+    /// A whole number, such as `3` or `-1`.
+    Integer,
+    /// A calendar date written as `YYYY-MM-DD`.
+    Date,
+    /// An absolute URL, such as `https://example.com/issue/1`.
+    Url,
+    /// One of the listed values.
+    Enum(Vec<String>),
+}
+
+impl FieldType {
+    /// Parses a type name as written in a field schema: `string`, `integer`, `date` or `url`.
+    pub fn parse(name: &str) -> Result<FieldType, Error> {
+        match name.trim() {
+            "string" => Ok(FieldType::String),
+            "integer" => Ok(FieldType::Integer),
+            "date" => Ok(FieldType::Date),
+            "url" => Ok(FieldType::Url),
+            other => Err(Error::other(format!("Unknown field type [{other}]"))),
+        }
+    }
+    /// Returns whether the value has this type.
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            FieldType::String => true,
+            FieldType::Integer => value.parse::<i64>().is_ok(),
+            FieldType::Date => is_date(value),
+            FieldType::Url => value.split_once("://").is_some_and(|(scheme, rest)| {
+                !scheme.is_empty()
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                    && !rest.is_empty()
+                    && !rest.contains(char::is_whitespace)
+            }),
+            FieldType::Enum(values) => values.iter().any(|known| known == value),
+        }
+    }
+    /// Describes the accepted values, for error messages.
+    fn expected(&self) -> String {
+        match self {
+            FieldType::String => "text".to_string(),
+            FieldType::Integer => "a whole number".to_string(),
+            FieldType::Date => "a date as YYYY-MM-DD".to_string(),
+            FieldType::Url => "an absolute URL".to_string(),
+            FieldType::Enum(values) => format!("one of {}", values.join(", ")),
+        }
+    }
+}
+
+/// Returns whether the text is a valid `YYYY-MM-DD` date.
+fn is_date(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (
+        year.parse::<u32>(),
+        month.parse::<u32>(),
+        day.parse::<u32>(),
+    ) else {
+        return false;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+//#EPIC Get Lines.ITEM Typed metadata fields [0]
+//#
+//## Typed metadata fields
+//#A field schema declares the `@name: value` directives a block may carry, each with a type: a
+//#string, an integer, a date, a URL or one of a list of values. Directive values are checked
+//#while the source files are parsed, so a mistyped `@due:` date fails the run with the block's
+//#location instead of ending up in a report. Declared fields are written to the front matter.
+/// The declared metadata fields of a run, in declaration order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldSchema {
+    fields: Vec<(String, FieldType)>,
+}
+
+impl FieldSchema {
+    /// Reads a field schema file, see `parse` for the format.
+    pub fn load(file_name: &str) -> Result<FieldSchema, Error> {
+        FieldSchema::parse(&read_to_string(file_name)?)
+            .map_err(|error| Error::other(format!("{error} in {file_name}")))
+    }
+    /// Parses a field schema: a TOML file with one `name = type` pair per field.
+    ///
+    /// The type is a type name, see `FieldType::parse`, or an array of the values of an
+    /// enumeration:
+    /// ```toml
+    /// due = "date"
+    /// priority = "integer"
+    /// ticket = "url"
+    /// status = ["draft", "beta", "stable"]
+    /// ```
+    /// Field names are not case sensitive, as directive names are not. The fields may also be
+    /// given in a `[fields]` table.
+    pub fn parse(content: &str) -> Result<FieldSchema, Error> {
+        let table: Table = toml::from_str(content).map_err(Error::other)?;
+        let mut schema = FieldSchema::default();
+        for (name, value) in table {
+            match (name.as_str(), value) {
+                ("fields", Value::Table(fields)) => {
+                    for (name, value) in fields {
+                        schema.declare(&name, value)?;
+                    }
+                }
+                (_, value) => schema.declare(&name, value)?,
+            }
+        }
+        Ok(schema)
+    }
+    /// Declares a field with the type given by its value in the schema file.
+    fn declare(&mut self, name: &str, value: Value) -> Result<(), Error> {
+        let invalid = || {
+            Error::other(format!(
+                "Invalid field [{name}]: expected `name = \"type\"` or `name = [\"value\", ...]`"
+            ))
+        };
+        let name = name.trim().to_lowercase();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(invalid());
+        }
+        let field_type = match value {
+            Value::String(type_name) => FieldType::parse(&type_name)?,
+            Value::Array(values) if !values.is_empty() => FieldType::Enum(
+                values
+                    .into_iter()
+                    .map(|value| match value {
+                        Value::String(value) => Ok(value),
+                        _ => Err(invalid()),
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => return Err(invalid()),
+        };
+        self.fields.retain(|(known, _)| *known != name);
+        self.fields.push((name, field_type));
+        Ok(())
+    }
+    /// Returns the type of a declared field.
+    pub fn field_type(&self, name: &str) -> Option<&FieldType> {
+        self.fields
+            .iter()
+            .find(|(known, _)| known == name)
+            .map(|(_, field_type)| field_type)
+    }
+    /// Checks the directives of a block against the declared types. Directives that are not
+    /// declared are accepted as they are.
+    ///
+    /// # Returns:
+    /// - `Err(String)` naming the first directive whose value does not have its declared type
+    pub fn validate(&self, metadata: &BTreeMap<String, String>) -> Result<(), String> {
+        for (name, value) in metadata {
+            if let Some(field_type) = self.field_type(name)
+                && !field_type.accepts(value)
+            {
+                return Err(format!(
+                    "[E012] Invalid value [{value}] of field @{name}: expected {}",
+                    field_type.expected()
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// Returns the front matter entries of the declared fields that are set by one of the
+    /// blocks, in declaration order. Integers are written as numbers, other values as
+    /// strings. The first block setting a field wins.
+    pub(crate) fn front_matter<'b>(
+        &self,
+        metadata: impl Iterator<Item = &'b BTreeMap<String, String>> + Clone,
+    ) -> Vec<(String, FrontMatterValue)> {
+        self.fields
+            .iter()
+            .filter_map(|(name, field_type)| {
+                let value = metadata.clone().find_map(|metadata| metadata.get(name))?;
+                Some((
+                    name.clone(),
+                    match field_type {
+                        FieldType::Integer => FrontMatterValue::Number(value.clone()),
+                        _ => FrontMatterValue::Text(value.clone()),
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_field_schema() {
+    let schema = FieldSchema::parse(
+        "[fields]\nDue = \"date\"\npriority = \"integer\"\nticket = \"url\"\nstatus = [\"draft\", \"beta\"]\n",
+    )
+    .unwrap();
+    let metadata = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    };
+    let valid = metadata(&[
+        ("due", "2024-02-29"),
+        ("priority", "-2"),
+        ("ticket", "https://example.com/1"),
+        ("status", "beta"),
+        ("owner", "anyone"),
+    ]);
+    assert_eq!(schema.validate(&valid), Ok(()));
+    let text = |value: &str| FrontMatterValue::Text(value.to_string());
+    assert_eq!(
+        schema.front_matter([&valid].into_iter()),
+        [
+            ("due".to_string(), text("2024-02-29")),
+            (
+                "priority".to_string(),
+                FrontMatterValue::Number("-2".to_string())
+            ),
+            ("ticket".to_string(), text("https://example.com/1")),
+            ("status".to_string(), text("beta"))
+        ]
+    );
+    for (name, value) in [
+        ("due", "2023-02-29"),
+        ("due", "2024-2-1"),
+        ("priority", "high"),
+        ("ticket", "example.com"),
+        ("status", "stable"),
+    ] {
+        assert!(schema.validate(&metadata(&[(name, value)])).is_err());
+    }
+    assert_eq!(
+        schema
+            .validate(&metadata(&[("status", "done")]))
+            .unwrap_err(),
+        "[E012] Invalid value [done] of field @status: expected one of draft, beta"
+    );
+    assert!(FieldSchema::parse("due = \"time\"").is_err());
+    assert!(FieldSchema::parse("status = []").is_err());
+    assert!(FieldSchema::parse("status = [1, 2]").is_err());
+    assert!(FieldSchema::parse("due = date").is_err());
+}
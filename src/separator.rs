@@ -0,0 +1,107 @@
+use crate::format::OutputFormat;
+use std::io::Error;
+
+/// What is written between two blocks of a generated file, on top of the blank line that ends
+/// every block.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockSeparator {
+    /// The given number of blank lines, at least 1. One blank line is the default.
+    BlankLines(usize),
+    /// A horizontal rule: `---` in Markdown and `'''` in AsciiDoc.
+    Rule,
+    /// The given text, followed by a blank line. `\n` in the text is a line break.
+    Text(String),
+}
+
+impl Default for BlockSeparator {
+    fn default() -> Self {
+        BlockSeparator::BlankLines(1)
+    }
+}
+
+impl BlockSeparator {
+    /// Parses a separator as given on the command line: `blank`, `blank:N` for N blank
+    /// lines, `rule`, or `text:` followed by the text, as in `text:<!-- next block -->`.
+    pub fn parse(spec: &str) -> Result<BlockSeparator, Error> {
+        let invalid = || {
+            Error::other(format!(
+                "Invalid separator [{spec}]: expected blank, blank:N, rule or text:..."
+            ))
+        };
+        match spec.trim() {
+            "blank" => Ok(BlockSeparator::BlankLines(1)),
+            "rule" => Ok(BlockSeparator::Rule),
+            other => match other.split_once(':') {
+                Some(("blank", lines)) => match lines.trim().parse::<usize>() {
+                    Ok(lines) if lines > 0 => Ok(BlockSeparator::BlankLines(lines)),
+                    _ => Err(invalid()),
+                },
+                Some(("text", text)) => Ok(BlockSeparator::Text(text.replace("\\n", "\n"))),
+                _ => Err(invalid()),
+            },
+        }
+    }
+    /// Returns the text written between two blocks in the given format.
+    pub fn text(&self, format: OutputFormat) -> String {
+        match (self, format) {
+            (BlockSeparator::BlankLines(lines), _) => "\n".repeat(lines.saturating_sub(1)),
+            (BlockSeparator::Rule, OutputFormat::AsciiDoc) => "'''\n\n".to_string(),
+            (BlockSeparator::Rule, _) => "---\n\n".to_string(),
+            (BlockSeparator::Text(text), _) => format!("{text}\n\n"),
+        }
+    }
+}
+
+/// The block separator of every output format, since downstream renderers differ in how they
+/// treat consecutive blocks.
+#[derive(Clone, Debug, Default)]
+pub struct Separators {
+    default: BlockSeparator,
+    formats: Vec<(OutputFormat, BlockSeparator)>,
+}
+
+impl Separators {
+    /// Parses separators as given on the command line, see `BlockSeparator::parse`. A
+    /// separator prefixed with a format, as in `adoc=rule`, only applies to that format; one
+    /// without a prefix applies to the other formats.
+    pub fn parse(specs: &[String]) -> Result<Separators, Error> {
+        let mut separators = Separators::default();
+        for spec in specs {
+            match spec
+                .split_once('=')
+                .and_then(|(format, rest)| Some((OutputFormat::parse(format).ok()?, rest)))
+            {
+                Some((format, rest)) => separators
+                    .formats
+                    .push((format, BlockSeparator::parse(rest)?)),
+                None => separators.default = BlockSeparator::parse(spec)?,
+            }
+        }
+        Ok(separators)
+    }
+    /// Returns the text written between two blocks in the given format.
+    pub fn text(&self, format: OutputFormat) -> String {
+        self.formats
+            .iter()
+            .find(|(known, _)| *known == format)
+            .map_or(&self.default, |(_, separator)| separator)
+            .text(format)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_separators() {
+    let specs = ["rule", "adoc=blank:3", "html=text:<hr/>"].map(String::from);
+    let separators = Separators::parse(&specs).unwrap();
+    assert_eq!(separators.text(OutputFormat::Markdown), "---\n\n");
+    assert_eq!(separators.text(OutputFormat::AsciiDoc), "\n\n");
+    assert_eq!(separators.text(OutputFormat::Html), "<hr/>\n\n");
+    assert_eq!(Separators::default().text(OutputFormat::Markdown), "");
+    assert_eq!(
+        BlockSeparator::parse("text:a=b\\nc").unwrap(),
+        BlockSeparator::Text("a=b\nc".to_string())
+    );
+    assert!(BlockSeparator::parse("blank:0").is_err());
+    assert!(Separators::parse(&["md=dots".to_string()]).is_err());
+}
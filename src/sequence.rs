@@ -0,0 +1,138 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::io::Error;
+use std::str::FromStr;
+
+/// The largest number of levels of a Sequence number, as in `1.2.3.4.5.6`.
+pub const MAX_LEVELS: usize = 6;
+
+//#EPIC Get Lines.ITEM Nested Sequence numbers [0]
+//#
+//## Nested Sequence numbers
+//#A large specification numbers its blocks like its sections: `[2]`, `[2.1]`, `[2.1.3]`, `[3]`.
+//#Sequence numbers may therefore have up to six dot separated levels, and are ordered level by
+//#level, so `[2.1]` is written after `[2]` and before `[2.2]` and `[3]`.
+/// The Sequence number of a block: one or more levels, each a `u16`.
+///
+/// Sequences are ordered level by level, a Sequence number coming before the ones it is the
+/// start of: `2` < `2.0` < `2.1` < `2.1.5` < `3`. A single level Sequence number is written as
+/// the plain number, so existing headers, files and reports keep their meaning.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sequence {
+    // The unused levels are 0, so comparing `levels` and then `count` orders the Sequence
+    // numbers level by level.
+    levels: [u16; MAX_LEVELS],
+    count: u8,
+}
+
+impl Sequence {
+    /// Returns the levels of the Sequence number, the first one at the top.
+    pub fn levels(&self) -> &[u16] {
+        &self.levels[..self.count as usize]
+    }
+    /// Returns the first level of the Sequence number.
+    pub fn top(&self) -> u16 {
+        self.levels[0]
+    }
+}
+
+impl Default for Sequence {
+    /// The Sequence number `0`.
+    fn default() -> Self {
+        Sequence::from(0)
+    }
+}
+
+impl From<u16> for Sequence {
+    fn from(number: u16) -> Self {
+        let mut levels = [0; MAX_LEVELS];
+        levels[0] = number;
+        Sequence { levels, count: 1 }
+    }
+}
+
+impl FromStr for Sequence {
+    type Err = Error;
+    /// Parses a Sequence number such as `3` or `2.1.3`.
+    ///
+    /// # Returns:
+    /// - `Err(Error)` when a level is not a `u16` or there are more than `MAX_LEVELS` levels
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut sequence = Sequence {
+            levels: [0; MAX_LEVELS],
+            count: 0,
+        };
+        for level in text.trim().split('.') {
+            if sequence.count as usize == MAX_LEVELS {
+                return Err(Error::other(format!(
+                    "Sequence number [{text}] has more than {MAX_LEVELS} levels"
+                )));
+            }
+            sequence.levels[sequence.count as usize] = level
+                .parse()
+                .map_err(|_| Error::other(format!("Invalid Sequence number [{text}]")))?;
+            sequence.count += 1;
+        }
+        Ok(sequence)
+    }
+}
+
+impl Display for Sequence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let levels: Vec<String> = self.levels().iter().map(u16::to_string).collect();
+        f.write_str(&levels.join("."))
+    }
+}
+
+impl Serialize for Sequence {
+    /// Writes a single level Sequence number as a number and a nested one as a string, such
+    /// as `"2.1"`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.count {
+            1 => serializer.serialize_u16(self.top()),
+            _ => serializer.collect_str(self),
+        }
+    }
+}
+
+/// A Sequence number as written by `Serialize`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Written {
+    Number(u16),
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for Sequence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Written::deserialize(deserializer)? {
+            Written::Number(number) => Ok(Sequence::from(number)),
+            Written::Text(text) => text.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_sequence_order() {
+    let parse = |text: &str| text.parse::<Sequence>().unwrap();
+    let mut sequences: Vec<Sequence> = ["3", "2.1.5", "2", "2.1", "10", "2.0"]
+        .into_iter()
+        .map(parse)
+        .collect();
+    sequences.sort();
+    let written: Vec<String> = sequences.iter().map(Sequence::to_string).collect();
+    assert_eq!(written, ["2", "2.0", "2.1", "2.1.5", "3", "10"]);
+    assert_eq!(parse("7"), Sequence::from(7));
+    assert!("1.2.3.4.5.6.7".parse::<Sequence>().is_err());
+    assert!("1..2".parse::<Sequence>().is_err());
+    assert!("70000".parse::<Sequence>().is_err());
+    assert_eq!(serde_json::to_string(&parse("4")).unwrap(), "4");
+    assert_eq!(serde_json::to_string(&parse("2.1")).unwrap(), "\"2.1\"");
+    assert_eq!(
+        serde_json::from_str::<Sequence>("\"2.1\"").unwrap(),
+        parse("2.1")
+    );
+    assert_eq!(serde_json::from_str::<Sequence>("4").unwrap(), parse("4"));
+}
@@ -0,0 +1,130 @@
+use crate::format::OutputFormat;
+use regex::{Captures, Regex};
+use std::collections::BTreeSet;
+use std::sync::LazyLock;
+
+/// Emoji shortcodes that are recognised in block text, with the character they expand to.
+const EMOJI: &[(&str, &str)] = &[
+    ("warning", "\u{26A0}\u{FE0F}"),
+    ("info", "\u{2139}\u{FE0F}"),
+    ("note", "\u{1F4DD}"),
+    ("memo", "\u{1F4DD}"),
+    ("bulb", "\u{1F4A1}"),
+    ("white_check_mark", "\u{2705}"),
+    ("heavy_check_mark", "\u{2714}\u{FE0F}"),
+    ("x", "\u{274C}"),
+    ("question", "\u{2753}"),
+    ("exclamation", "\u{2757}"),
+    ("construction", "\u{1F6A7}"),
+    ("stop_sign", "\u{1F6D1}"),
+    ("bug", "\u{1F41B}"),
+    ("lock", "\u{1F512}"),
+    ("star", "\u{2B50}"),
+    ("fire", "\u{1F525}"),
+    ("rocket", "\u{1F680}"),
+    ("tada", "\u{1F389}"),
+];
+
+/// A badge directive, `@badge[label=message]` or `@badge[message]`.
+static BADGE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"@badge\[([^\]=]*)(?:=([^\]]*))?\]").unwrap());
+
+/// An emoji shortcode such as `:warning:`.
+static SHORTCODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r":([a-z0-9_+-]+):").unwrap());
+
+/// Expands emoji shortcodes and badge directives in a single line of block text, in the
+/// markup of `format`.
+///
+/// # Syntax:
+/// - **Shortcode**: `:warning:` is replaced by the emoji it names. Unknown names, and
+///   text such as `12:30:00`, are left untouched.
+/// - **Badge**: `@badge[status=beta]` is replaced by an image of a shields.io badge
+///   labelled `status` with the message `beta`, a Markdown image or an AsciiDoc `image:`
+///   macro. A directive without a `=` uses the text as the message with the label `badge`.
+///   JSON has no markup, so the directive is kept there.
+///
+/// # Badge Colours:
+/// - `stable`, `done`, `approved` → green
+/// - `beta`, `draft`, `wip` → orange
+/// - `deprecated`, `blocked`, `removed` → red
+/// - anything else → blue
+pub fn expand_line(line: &str, format: OutputFormat) -> String {
+    let line = match format {
+        OutputFormat::Json => line.into(),
+        _ => BADGE.replace_all(line, |capture: &Captures| {
+            let (label, message) = match capture.get(2) {
+                Some(message) => (capture[1].trim(), message.as_str().trim()),
+                None => ("badge", capture[1].trim()),
+            };
+            let url = format!(
+                "https://img.shields.io/badge/{}-{}-{}",
+                shields_escape(label),
+                shields_escape(message),
+                badge_colour(message)
+            );
+            match format {
+                OutputFormat::AsciiDoc => format!("image:{url}[{label}: {message}]"),
+                _ => format!("![{label}: {message}]({url})"),
+            }
+        }),
+    };
+    SHORTCODE
+        .replace_all(&line, |capture: &Captures| {
+            EMOJI
+                .iter()
+                .find(|(name, _)| *name == &capture[1])
+                .map(|(_, emoji)| emoji.to_string())
+                .unwrap_or_else(|| capture[0].to_string())
+        })
+        .to_string()
+}
+
+/// Expands the lines of a block body with `expand_line`, leaving raw lines untouched.
+pub fn expand_lines(lines: &[String], raw: &BTreeSet<usize>, format: OutputFormat) -> Vec<String> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| match raw.contains(&index) {
+            true => line.clone(),
+            false => expand_line(line, format),
+        })
+        .collect()
+}
+
+/// Escapes text for use in a shields.io static badge path segment.
+fn shields_escape(text: &str) -> String {
+    text.replace('-', "--").replace('_', "__").replace(' ', "_")
+}
+
+fn badge_colour(message: &str) -> &'static str {
+    match message.to_lowercase().as_str() {
+        "stable" | "done" | "approved" => "green",
+        "beta" | "draft" | "wip" => "orange",
+        "deprecated" | "blocked" | "removed" => "red",
+        _ => "blue",
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_expand_line() {
+    assert_eq!(
+        expand_line(
+            ":warning: check @badge[status=beta] at 12:30:00",
+            OutputFormat::Markdown
+        ),
+        "\u{26A0}\u{FE0F} check ![status: beta](https://img.shields.io/badge/status-beta-orange) at 12:30:00"
+    );
+    assert_eq!(
+        expand_line("@badge[wip]", OutputFormat::AsciiDoc),
+        "image:https://img.shields.io/badge/badge-wip-orange[badge: wip]"
+    );
+    assert_eq!(
+        expand_line(":bug: @badge[wip]", OutputFormat::Json),
+        "\u{1F41B} @badge[wip]"
+    );
+    assert_eq!(
+        expand_line(":unknown:", OutputFormat::Markdown),
+        ":unknown:"
+    );
+}
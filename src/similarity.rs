@@ -0,0 +1,134 @@
+use crate::block::Sequences;
+use crate::sequence::Sequence;
+use std::collections::HashMap;
+
+/// Two block sequences whose body text is nearly identical.
+#[derive(Debug, PartialEq)]
+pub struct SimilarPair {
+    /// Block name and sequence number of the first block.
+    pub first: (String, Sequence),
+    /// Block name and sequence number of the second block.
+    pub second: (String, Sequence),
+    /// Similarity between 0.0 (nothing in common) and 1.0 (identical).
+    pub similarity: f64,
+}
+
+/// The character bigrams of the normalised text of one block sequence.
+struct Fingerprint {
+    block: (String, Sequence),
+    bigrams: HashMap<(char, char), usize>,
+    count: usize,
+}
+
+/// Blocks with fewer characters than this, after normalisation, are too short to compare.
+const MINIMUM_TEXT_LENGTH: usize = 20;
+
+/// Finds all pairs of block sequences whose text is at least `threshold` similar.
+///
+/// The body text of each sequence is lower cased and its whitespace collapsed, after which
+/// the Sørensen–Dice coefficient of the character bigrams of every pair is computed. Very
+/// short bodies are skipped because they are similar to almost anything.
+///
+/// # Parameters:
+/// - `history`: The comment history, keyed by block path and sequence number
+/// - `threshold`: The lowest similarity, between 0.0 and 1.0, that is reported
+///
+/// # Returns:
+/// - The similar pairs, ordered by block name and sequence number
+pub fn find_similar_blocks(
+    history: &HashMap<String, Sequences>,
+    threshold: f64,
+) -> Vec<SimilarPair> {
+    let mut texts: Vec<Fingerprint> = vec![];
+    for (name, blocks) in history {
+        for (sequence, block) in blocks {
+            let text = normalise(&block.lines.join(" "));
+            if text.chars().count() >= MINIMUM_TEXT_LENGTH {
+                let (bigrams, count) = bigrams(&text);
+                texts.push(Fingerprint {
+                    block: (name.trim().to_string(), *sequence),
+                    bigrams,
+                    count,
+                });
+            }
+        }
+    }
+    texts.sort_by(|left, right| left.block.cmp(&right.block));
+
+    let mut pairs = vec![];
+    for (index, first) in texts.iter().enumerate() {
+        for second in &texts[index + 1..] {
+            let shared: usize = first
+                .bigrams
+                .iter()
+                .map(|(bigram, count)| (*count).min(*second.bigrams.get(bigram).unwrap_or(&0)))
+                .sum();
+            let similarity = 2.0 * shared as f64 / (first.count + second.count) as f64;
+            if similarity >= threshold {
+                pairs.push(SimilarPair {
+                    first: first.block.clone(),
+                    second: second.block.clone(),
+                    similarity,
+                });
+            }
+        }
+    }
+    pairs
+}
+
+fn normalise(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn bigrams(text: &str) -> (HashMap<(char, char), usize>, usize) {
+    let characters: Vec<char> = text.chars().collect();
+    let mut bigrams = HashMap::new();
+    for window in characters.windows(2) {
+        *bigrams.entry((window[0], window[1])).or_insert(0) += 1;
+    }
+    (bigrams, characters.len().saturating_sub(1))
+}
+
+#[cfg(test)]
+#[test]
+fn test_find_similar_blocks() {
+    use crate::block::Block;
+    let block = |text: &str| {
+        Block::new(
+            "a.rs".into(),
+            1,
+            vec![text.to_string()],
+            &Default::default(),
+        )
+    };
+    let mut history: HashMap<String, Sequences> = HashMap::new();
+    let first = history.entry("docs.EPIC a".to_string()).or_default();
+    first.insert(
+        Sequence::from(0),
+        block("The user must be able to reset the password."),
+    );
+    first.insert(
+        Sequence::from(1),
+        block("Something completely different is written here."),
+    );
+    history
+        .entry("docs.EPIC b".to_string())
+        .or_default()
+        .insert(
+            Sequence::from(0),
+            block("The user must be able to reset their password."),
+        );
+    let pairs = find_similar_blocks(&history, 0.9);
+    assert_eq!(pairs.len(), 1);
+    assert_eq!(
+        pairs[0].first,
+        ("docs.EPIC a".to_string(), Sequence::from(0))
+    );
+    assert_eq!(
+        pairs[0].second,
+        ("docs.EPIC b".to_string(), Sequence::from(0))
+    );
+}
@@ -0,0 +1,192 @@
+use crate::block::Block;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The directive of a block that embeds the source lines following it, as in
+/// `@include-code: 10`.
+pub const INCLUDE_CODE: &str = "include-code";
+
+/// Returns the language of a code fence for a source file, by its extension.
+pub fn fence_language(file_name: &str) -> &str {
+    let extension = file_name
+        .rsplit_once('.')
+        .map_or("", |(_, extension)| extension);
+    match extension {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "rb" => "ruby",
+        "sh" | "bash" => "bash",
+        "cs" => "csharp",
+        "kt" | "kts" => "kotlin",
+        "h" | "hpp" | "cc" | "cxx" => "cpp",
+        "md" => "markdown",
+        "yml" => "yaml",
+        other => other,
+    }
+}
+
+//#EPIC Get Lines.ITEM Embed source code [0]
+//#
+//## Embed source code
+//#A block with the directive `@include-code: 10` shows the 10 source lines that follow it in a
+//#fenced code block tagged with the language of the source file, so the documentation shows
+//#the real code instead of a copy that drifts. Fewer lines are taken when the next block or
+//#the end of the file comes first; their common indentation is removed.
+/// Appends the source lines that followed a block to its body as a fenced code block. The
+/// appended lines are raw, so filters, glossary links and heading offsets leave them alone.
+///
+/// # Parameters:
+/// - `block`: The block with the `@include-code` directive
+/// - `file_name`: The source file, which gives the language of the fence
+/// - `code`: The source lines after the block
+pub fn append_code(block: &mut Block, file_name: &str, mut code: Vec<String>) {
+    while code.last().is_some_and(|line| line.trim().is_empty()) {
+        code.pop();
+    }
+    if code.is_empty() {
+        return;
+    }
+    let mut lines = block.lines.to_vec();
+    let first = lines.len();
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines.extend(fenced(file_name, &code));
+    block.raw.extend(first..lines.len());
+    block.lines = lines.into();
+}
+
+/// Returns source lines as a fenced code block tagged with the language of their file, without
+/// their common indentation.
+fn fenced(file_name: &str, code: &[String]) -> Vec<String> {
+    let indent = code
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let fence = match code.iter().any(|line| line.contains("```")) {
+        true => "````",
+        false => "```",
+    };
+    let mut lines = vec![format!("{fence}{}", fence_language(file_name))];
+    lines.extend(
+        code.iter()
+            .map(|line| line.get(indent..).unwrap_or(line.trim_start()).to_string()),
+    );
+    lines.push(fence.to_string());
+    lines
+}
+
+/// A named region of source code, between `snippet-begin name` and `snippet-end name`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snippet {
+    pub source_file: String,
+    /// The line number of the first line of the region.
+    pub start_line: usize,
+    pub lines: Vec<String>,
+}
+
+/// Returns the name of the snippet a body line refers to, as in `{{snippet parse}}`.
+pub fn snippet_reference(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("{{snippet ")?
+        .strip_suffix("}}")
+        .map(str::trim)
+}
+
+//#EPIC Get Lines.ITEM Named snippets [0]
+//#
+//## Named snippets
+//#Code between `//#snippet-begin name` and `//#snippet-end name` is kept as the snippet
+//#`name`. A block line holding only `{{snippet name}}` is replaced by that code in a fenced
+//#code block, so the documentation embeds code from anywhere in the sources and stays up to
+//#date with it. Regions may nest and overlap; the marker lines themselves are left out.
+/// Replaces the body lines of a block that refer to a snippet by the code of the snippet, as
+/// raw lines. Raw lines are not taken for references.
+///
+/// # Returns:
+/// - The names of the snippets referred to that do not exist, whose lines are kept as they are
+pub fn embed_snippets(block: &mut Block, snippets: &BTreeMap<String, Snippet>) -> Vec<String> {
+    let mut unknown = Vec::new();
+    if !block
+        .lines
+        .iter()
+        .any(|line| snippet_reference(line).is_some())
+    {
+        return unknown;
+    }
+    let mut lines = Vec::with_capacity(block.lines.len());
+    let mut raw = BTreeSet::new();
+    for (index, line) in block.lines.iter().enumerate() {
+        let is_raw = block.raw.contains(&index);
+        match snippet_reference(line).filter(|_| !is_raw) {
+            Some(name) if snippets.contains_key(name) => {
+                let snippet = &snippets[name];
+                let first = lines.len();
+                lines.extend(fenced(&snippet.source_file, &snippet.lines));
+                raw.extend(first..lines.len());
+            }
+            reference => {
+                unknown.extend(reference.map(str::to_string));
+                if is_raw {
+                    raw.insert(lines.len());
+                }
+                lines.push(line.clone());
+            }
+        }
+    }
+    block.lines = lines.into();
+    block.raw = raw;
+    unknown
+}
+
+#[cfg(test)]
+#[test]
+fn test_append_code() {
+    let mut block = Block::new("a.rs".into(), 1, vec!["Text".to_string()], &BTreeSet::new());
+    let code = ["    fn a() {", "        b();", "    }", ""].map(String::from);
+    append_code(&mut block, "src/a.rs", code.to_vec());
+    assert_eq!(
+        &*block.lines,
+        ["Text", "", "```rust", "fn a() {", "    b();", "}", "```"]
+    );
+    assert_eq!(block.raw, BTreeSet::from([1, 2, 3, 4, 5, 6]));
+}
+
+#[cfg(test)]
+#[test]
+fn test_embed_snippets() {
+    let snippets = BTreeMap::from([(
+        "parse".to_string(),
+        Snippet {
+            source_file: "src/parse.py".to_string(),
+            start_line: 4,
+            lines: vec!["  def parse():".to_string(), "      pass".to_string()],
+        },
+    )]);
+    let lines = [
+        "Parsing:",
+        "{{snippet parse}}",
+        "{{snippet gone}}",
+        "{{snippet parse}}",
+    ]
+    .map(String::from)
+    .to_vec();
+    let mut block = Block::new("a.rs".into(), 1, lines, &BTreeSet::from([3]));
+    assert_eq!(embed_snippets(&mut block, &snippets), ["gone"]);
+    assert_eq!(
+        &*block.lines,
+        [
+            "Parsing:",
+            "```python",
+            "def parse():",
+            "    pass",
+            "```",
+            "{{snippet gone}}",
+            "{{snippet parse}}"
+        ]
+    );
+    assert_eq!(block.raw, BTreeSet::from([1, 2, 3, 4, 6]));
+}
@@ -0,0 +1,160 @@
+use crate::block::Block;
+use std::env::current_dir;
+use std::ffi::OsString;
+use std::io::{Error, ErrorKind};
+use std::path::{Component, Path};
+
+/// The branch of GitHub links when `-branch` is not given.
+const DEFAULT_BRANCH: &str = "main";
+
+//#EPIC Get Lines.ITEM Source link style [0]
+//#
+//## Source link style
+//#Every block starts with a `[SOURCE FILE:]` link to where it was found. An absolute `file:///`
+//#link only works on the machine that ran the extraction, so the link can instead be written
+//#relative to the generated file, as a GitHub permalink with a line anchor, or left out.
+/// How the `[SOURCE FILE:]` link of a block points to its source file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum LinkStyle {
+    /// A `file:///` URL of the source file, the default.
+    #[default]
+    File,
+    /// The path of the source file relative to the generated file.
+    Relative,
+    /// A link to the source file in the repository web view, with the lines of the block as
+    /// anchor, e.g. `https://github.com/owner/repo/blob/main/src/main.rs#L4-L9`. The path is
+    /// taken relative to the current folder, which is expected to be the repository root.
+    GitHub { repo_url: String, branch: String },
+    /// No link: the source file is written as text.
+    None,
+}
+
+impl LinkStyle {
+    /// Parses a link style as given on the command line: `file`, `relative`, `github` or
+    /// `none`. `github` needs the repository URL; the branch defaults to `main`.
+    pub fn parse(
+        name: &str,
+        repo_url: Option<&str>,
+        branch: Option<&str>,
+    ) -> Result<LinkStyle, Error> {
+        match name.trim() {
+            "file" => Ok(LinkStyle::File),
+            "relative" => Ok(LinkStyle::Relative),
+            "github" => match repo_url {
+                Some(repo_url) => Ok(LinkStyle::GitHub {
+                    repo_url: repo_url.trim().trim_end_matches('/').to_string(),
+                    branch: branch.unwrap_or(DEFAULT_BRANCH).trim().to_string(),
+                }),
+                None => Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Link style [github] needs -repo-url",
+                )),
+            },
+            "none" => Ok(LinkStyle::None),
+            other => Err(Error::other(format!("Unknown link style [{other}]"))),
+        }
+    }
+    /// Returns the target of the source link of a block written to a generated file in
+    /// `folder`, or `None` when no link is written.
+    pub fn target(&self, block: &Block, folder: &Path) -> Option<String> {
+        let path = match block.path.is_empty() {
+            true => &*block.source_file,
+            false => &*block.path,
+        };
+        match self {
+            LinkStyle::File => Some(format!("file:///{}", block.source_file)),
+            LinkStyle::Relative => Some(encode(&relative_path(folder, Path::new(path)))),
+            LinkStyle::GitHub { repo_url, branch } => {
+                let cwd = components(Path::new("."));
+                let file = components(Path::new(path));
+                let path = match file.starts_with(&cwd) {
+                    true => join(&file[cwd.len()..]),
+                    false => path.replace('\\', "/"),
+                };
+                let lines = match block.end_line > block.start_line {
+                    true => format!("L{}-L{}", block.start_line, block.end_line),
+                    false => format!("L{}", block.start_line),
+                };
+                Some(format!(
+                    "{repo_url}/blob/{branch}/{}#{lines}",
+                    encode(&path)
+                ))
+            }
+            LinkStyle::None => None,
+        }
+    }
+}
+
+/// Returns the normal components of a path made absolute against the current folder, with
+/// `.` and `..` resolved.
+fn components(path: &Path) -> Vec<OsString> {
+    let path = match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => current_dir().unwrap_or_default().join(path),
+    };
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop();
+            }
+            other => parts.push(other.as_os_str().to_owned()),
+        }
+    }
+    parts
+}
+
+/// Joins path components with `/`.
+fn join(parts: &[OsString]) -> String {
+    parts
+        .iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns the path of `file` relative to `folder`, with `/` as separator.
+fn relative_path(folder: &Path, file: &Path) -> String {
+    let folder = components(folder);
+    let file = components(file);
+    let common = folder
+        .iter()
+        .zip(&file)
+        .take_while(|(left, right)| left == right)
+        .count();
+    format!(
+        "{}{}",
+        "../".repeat(folder.len() - common),
+        join(&file[common..])
+    )
+}
+
+/// Encodes the spaces of a link target, which would end a Markdown or AsciiDoc link.
+fn encode(target: &str) -> String {
+    target.replace(' ', "%20")
+}
+
+#[cfg(test)]
+#[test]
+fn test_link_targets() {
+    let mut block = Block::new("src/a b.rs".into(), 4, Vec::new(), &Default::default());
+    block.end_line = 9;
+    let folder = Path::new("docs/EPIC A");
+    assert_eq!(
+        LinkStyle::File.target(&block, folder).unwrap(),
+        "file:///src/a b.rs"
+    );
+    assert_eq!(
+        LinkStyle::Relative.target(&block, folder).unwrap(),
+        "../../src/a%20b.rs"
+    );
+    let github = LinkStyle::parse("github", Some("https://github.com/o/r/"), None).unwrap();
+    assert_eq!(
+        github.target(&block, folder).unwrap(),
+        "https://github.com/o/r/blob/main/src/a%20b.rs#L4-L9"
+    );
+    assert_eq!(LinkStyle::None.target(&block, folder), None);
+    assert!(LinkStyle::parse("github", None, Some("dev")).is_err());
+    assert!(LinkStyle::parse("ftp", None, None).is_err());
+}
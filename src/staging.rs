@@ -0,0 +1,165 @@
+use std::fs::{File, copy, create_dir, create_dir_all, read_link, remove_dir_all, rename};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A copy of the document root an atomic run writes into, next to the document root so it
+/// can be renamed into its place. The copy is removed when it is dropped unpublished.
+#[derive(Debug)]
+pub struct StagedRoot {
+    doc_root: PathBuf,
+    staging: String,
+    run_id: String,
+}
+
+/// Creates a symbolic link at `path` pointing where the link at `link` points.
+#[cfg(unix)]
+fn copy_link(link: &Path, path: &Path) -> Result<(), Error> {
+    std::os::unix::fs::symlink(read_link(link)?, path)
+}
+
+/// Creates a symbolic link at `path` pointing where the link at `link` points.
+#[cfg(windows)]
+fn copy_link(link: &Path, path: &Path) -> Result<(), Error> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+    match link.is_dir() {
+        true => symlink_dir(read_link(link)?, path),
+        false => symlink_file(read_link(link)?, path),
+    }
+}
+
+impl StagedRoot {
+    //#EPIC Get Lines.ITEM Atomic runs [0]
+    //#
+    //## Atomic runs
+    //#With `--atomic` a run writes into a copy of the document root, `docs-staging-<run id>`
+    //#next to `docs`, which replaces the document root once everything is written. Readers of the
+    //#document root, such as a web server, never see a half written tree, and a run that fails
+    //#to write a file leaves the document root as it was.
+    /// Creates the staging folder of a document root, `{doc_root}-staging-{run_id}`, holding a
+    /// copy of the document root with its modification times, so the run finds the files the
+    /// previous run left. Symbolic links are copied as links.
+    ///
+    /// # Returns:
+    /// - `Err(Error)` when the staging folder already exists, which is never removed as it
+    ///   is not known to be a staging folder
+    pub fn stage(doc_root: &str, run_id: &str) -> Result<StagedRoot, Error> {
+        let doc_root = PathBuf::from(doc_root.trim_end_matches(['/', '\\']));
+        let staging = format!("{}-staging-{run_id}", doc_root.to_string_lossy());
+        if let Some(parent) = Path::new(&staging).parent() {
+            create_dir_all(parent)?;
+        }
+        create_dir(&staging)?;
+        for entry in WalkDir::new(&doc_root).into_iter().filter_map(Result::ok) {
+            let Ok(relative) = entry.path().strip_prefix(&doc_root) else {
+                continue;
+            };
+            let path = Path::new(&staging).join(relative);
+            if entry.file_type().is_dir() {
+                create_dir_all(&path)?;
+            } else if entry.file_type().is_file() {
+                copy(entry.path(), &path)?;
+                // Unchanged files are not written again, so they keep the time of the original.
+                let modified = entry.metadata()?.modified()?;
+                File::options()
+                    .write(true)
+                    .open(&path)?
+                    .set_modified(modified)?;
+            } else if entry.file_type().is_symlink() {
+                copy_link(entry.path(), &path)?;
+            }
+        }
+        Ok(StagedRoot {
+            doc_root,
+            staging,
+            run_id: run_id.to_string(),
+        })
+    }
+    /// Returns the staging folder, which the run takes as its document root.
+    pub fn path(&self) -> &str {
+        &self.staging
+    }
+    /// Puts the staging folder in the place of the document root. The old document root is
+    /// moved aside to `{doc_root}-previous-{run_id}` first and removed once the staging folder
+    /// took its place.
+    ///
+    /// # Returns:
+    /// - `Err(Error)` when `{doc_root}-previous-{run_id}` already exists
+    pub fn publish(self) -> Result<(), Error> {
+        let previous = format!(
+            "{}-previous-{}",
+            self.doc_root.to_string_lossy(),
+            self.run_id
+        );
+        if Path::new(&previous).symlink_metadata().is_ok() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{previous} already exists, the document root is not moved onto it"),
+            ));
+        }
+        let existed = self.doc_root.exists();
+        if existed {
+            rename(&self.doc_root, &previous)?;
+        }
+        if let Err(error) = rename(&self.staging, &self.doc_root) {
+            if existed {
+                let _ = rename(&previous, &self.doc_root);
+            }
+            return Err(error);
+        }
+        let _ = remove_dir_all(previous);
+        Ok(())
+    }
+}
+
+impl Drop for StagedRoot {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.staging);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_staged_root() {
+    use std::fs::{read_to_string, write};
+    let doc_root = "target/test-staging/docs";
+    let _ = remove_dir_all("target/test-staging");
+    create_dir_all(format!("{doc_root}/EPIC A")).unwrap();
+    write(format!("{doc_root}/EPIC A/ITEM B.md"), "old").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("EPIC A/ITEM B.md", format!("{doc_root}/latest.md")).unwrap();
+
+    let staged = StagedRoot::stage(doc_root, "01RUN").unwrap();
+    assert_eq!(staged.path(), "target/test-staging/docs-staging-01RUN");
+    assert!(StagedRoot::stage(doc_root, "01RUN").is_err());
+    #[cfg(unix)]
+    assert!(
+        Path::new("target/test-staging/docs-staging-01RUN/latest.md")
+            .symlink_metadata()
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+    write(format!("{}/EPIC A/ITEM B.md", staged.path()), "new").unwrap();
+    assert_eq!(
+        read_to_string(format!("{doc_root}/EPIC A/ITEM B.md")).unwrap(),
+        "old"
+    );
+    drop(staged);
+    assert!(!Path::new("target/test-staging/docs-staging-01RUN").exists());
+
+    create_dir_all("target/test-staging/docs-previous-02RUN").unwrap();
+    let staged = StagedRoot::stage(doc_root, "02RUN").unwrap();
+    assert!(staged.publish().is_err());
+    assert!(Path::new("target/test-staging/docs-previous-02RUN").exists());
+
+    let staged = StagedRoot::stage(doc_root, "03RUN").unwrap();
+    write(format!("{}/EPIC A/ITEM B.md", staged.path()), "new").unwrap();
+    staged.publish().unwrap();
+    assert_eq!(
+        read_to_string(format!("{doc_root}/EPIC A/ITEM B.md")).unwrap(),
+        "new"
+    );
+    assert!(!Path::new("target/test-staging/docs-staging-03RUN").exists());
+    assert!(!Path::new("target/test-staging/docs-previous-03RUN").exists());
+}
@@ -0,0 +1,201 @@
+use crate::block::Sequences;
+use crate::clock::{Clock, seconds_since_epoch};
+use crate::git::last_commit_time;
+use crate::outputs::write_if_changed;
+use crate::sequence::Sequence;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, read_to_string};
+use std::io::{BufWriter, Error, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The file in the document root in which the code anchors of the previous run are kept.
+const ANCHOR_FILE: &str = ".get-comments-anchors";
+
+/// The file in the document root holding the table of possibly stale blocks.
+pub const STALE_FILE: &str = "stale.md";
+
+/// A block that is possibly stale, with the reason it was flagged.
+#[derive(Debug)]
+pub struct StaleBlock {
+    pub name: String,
+    pub sequence: Sequence,
+    pub source_file: Arc<str>,
+    pub line: usize,
+    pub reason: String,
+}
+
+/// The code anchors of the previous run, keyed by block path and sequence number.
+pub type Anchors = HashMap<(String, Sequence), String>;
+
+/// Reads the code anchors recorded by the previous run from the document root.
+///
+/// This must be called before the document root is cleared. A missing or unreadable file
+/// simply means there is no previous run to compare with.
+pub fn load_anchors(doc_root: &str) -> Anchors {
+    let mut anchors = Anchors::new();
+    if let Ok(content) = read_to_string(format!("{doc_root}/{ANCHOR_FILE}")) {
+        for line in content.lines() {
+            let fields: Vec<&str> = line.splitn(3, '\t').collect();
+            if let [name, sequence, anchor] = fields[..]
+                && let Ok(sequence) = sequence.parse::<Sequence>()
+            {
+                anchors.insert((name.to_string(), sequence), anchor.to_string());
+            }
+        }
+    }
+    anchors
+}
+
+/// Records the code anchor of every block in the document root for the next run.
+pub fn save_anchors(history: &HashMap<String, Sequences>, doc_root: &str) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(format!("{doc_root}/{ANCHOR_FILE}"))?);
+    let sorted: BTreeMap<&String, &Sequences> = history.iter().collect();
+    for (name, blocks) in sorted {
+        for (sequence, block) in blocks {
+            if let Some(anchor) = &block.code_anchor {
+                writeln!(
+                    writer,
+                    "{}\t{sequence}\t{}",
+                    name.trim(),
+                    anchor.replace('\t', " ")
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+//#EPIC Get Lines.ITEM Find stale documentation [0]
+//#
+//## Find stale documentation
+//#A block is possibly stale when git shows its source file untouched for longer than the configured
+//#number of months, or when the code line that followed the block in the previous run is gone.
+/// Finds blocks whose documentation is possibly stale.
+///
+/// # Rules:
+/// 1. **Untouched Source**: The last commit touching the source file is older than `months`
+///    months (counted as 30 days each). Files without git history are not flagged.
+/// 2. **Deleted Code**: The first line of code that followed the block in the previous run
+///    no longer appears anywhere in the source file.
+///
+/// # Parameters:
+/// - `history`: The comment history of the current run
+/// - `previous`: The code anchors recorded by the previous run
+/// - `months`: The number of months after which an untouched source file is flagged
+/// - `clock`: The clock the age of a source file is measured against
+pub fn find_stale_blocks(
+    history: &HashMap<String, Sequences>,
+    previous: &Anchors,
+    months: u32,
+    clock: &dyn Clock,
+) -> Vec<StaleBlock> {
+    let now = seconds_since_epoch(clock.now());
+    let limit = now - i64::from(months) * 30 * 86_400;
+    let mut commit_times: HashMap<Arc<str>, Option<i64>> = HashMap::new();
+    let mut contents: HashMap<Arc<str>, String> = HashMap::new();
+
+    let mut stale = vec![];
+    let sorted: BTreeMap<&String, &Sequences> = history.iter().collect();
+    for (name, blocks) in sorted {
+        let name = name.trim();
+        for (sequence, block) in blocks {
+            let mut flag = |reason: String| {
+                stale.push(StaleBlock {
+                    name: name.to_string(),
+                    sequence: *sequence,
+                    source_file: block.source_file.clone(),
+                    line: block.start_line,
+                    reason,
+                })
+            };
+            let last_commit = *commit_times
+                .entry(block.path.clone())
+                .or_insert_with(|| last_commit_time(&block.path).ok().flatten());
+            if let Some(time) = last_commit
+                && time < limit
+            {
+                flag(format!("source untouched for more than {months} months"));
+            }
+            if let Some(anchor) = previous.get(&(name.to_string(), *sequence)) {
+                let content = contents
+                    .entry(block.path.clone())
+                    .or_insert_with(|| read_to_string(&*block.path).unwrap_or_default());
+                if !content.lines().any(|line| line.trim() == anchor) {
+                    flag(format!(
+                        "documented code `{anchor}` is no longer in the source"
+                    ));
+                }
+            }
+        }
+    }
+    stale
+}
+
+/// Writes the possibly stale blocks as a table to `stale.md` in the document root, unless it
+/// already holds the table, and returns its path.
+pub fn write_stale_report(stale: &[StaleBlock], doc_root: &str) -> Result<PathBuf, Error> {
+    let mut writer = Vec::new();
+    writeln!(writer, "# Possibly stale documentation\n")?;
+    writeln!(writer, "| Block | Sequence | Source | Reason |")?;
+    writeln!(writer, "| --- | --- | --- | --- |")?;
+    for block in stale {
+        writeln!(
+            writer,
+            "| {} | {} | {} LINE: {} | {} |",
+            block.name, block.sequence, block.source_file, block.line, block.reason
+        )?;
+    }
+    let path = Path::new(doc_root).join(STALE_FILE);
+    write_if_changed(&path, &writer)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+#[test]
+fn test_stale_clock() {
+    use crate::block::Block;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::process::Command;
+    use std::time::{Duration, UNIX_EPOCH};
+    let root = "target/test-stale-clock";
+    let _ = remove_dir_all(root);
+    create_dir_all(root).unwrap();
+    let git = |arguments: &[&str]| {
+        assert!(
+            Command::new("git")
+                .arg("-C")
+                .arg(root)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(arguments)
+                .env("GIT_AUTHOR_DATE", "2024-01-01T00:00:00Z")
+                .env("GIT_COMMITTER_DATE", "2024-01-01T00:00:00Z")
+                .output()
+                .unwrap()
+                .status
+                .success()
+        )
+    };
+    git(&["init", "-q"]);
+    write(format!("{root}/a.rs"), "//# EPIC A [0]\n//#Text\n").unwrap();
+    git(&["add", "a.rs"]);
+    git(&["commit", "-q", "-m", "a"]);
+    let block = Block {
+        source_file: "a.rs".into(),
+        path: format!("{root}/a.rs").into(),
+        start_line: 2,
+        ..Block::default()
+    };
+    let history = HashMap::from([(
+        "docs.EPIC A".to_string(),
+        Sequences::from([(Sequence::from(0), block)]),
+    )]);
+    let committed = UNIX_EPOCH + Duration::from_secs(1_704_067_200);
+    let after_days = |days: u64| move || committed + Duration::from_secs(days * 86_400);
+    let stale = |days| find_stale_blocks(&history, &Anchors::new(), 1, &after_days(days));
+    assert!(stale(29).is_empty());
+    let found = stale(31);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].reason, "source untouched for more than 1 months");
+    remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,144 @@
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fs::write;
+use std::io::Error;
+use std::time::Duration;
+
+/// The summary of a run: what was scanned, what was found and where the time went.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunStatistics {
+    pub files_scanned: usize,
+    /// The files that could not be read or parsed, or that hold `get-comments:ignore-file`.
+    pub files_skipped: usize,
+    /// The number of Sequences of every block name, without the document root.
+    pub sequences: BTreeMap<String, usize>,
+    /// The lines of all blocks as found in the source files.
+    pub comment_lines: usize,
+    /// Every rejected block name with the reason it was rejected.
+    pub rejected: Vec<(String, String)>,
+    /// The wall time of every phase, in run order.
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+impl RunStatistics {
+    /// Returns the number of Sequences of all block names.
+    pub fn total_sequences(&self) -> usize {
+        self.sequences.values().sum()
+    }
+    /// Formats the statistics for the terminal. The Sequences per block are given as an
+    /// average here, the JSON document has them for every block name.
+    pub fn report(&self) -> String {
+        let mut report = String::from("Statistics:\n");
+        let average = match self.sequences.len() {
+            0 => 0.0,
+            blocks => self.total_sequences() as f64 / blocks as f64,
+        };
+        for (name, value) in [
+            ("files scanned", self.files_scanned.to_string()),
+            ("files skipped", self.files_skipped.to_string()),
+            ("blocks", self.sequences.len().to_string()),
+            ("sequences", self.total_sequences().to_string()),
+            ("per block", format!("{average:.1}")),
+            ("comment lines", self.comment_lines.to_string()),
+            ("rejected", self.rejected.len().to_string()),
+        ] {
+            report.push_str(&format!("  {name:<15}{value:>10}\n"));
+        }
+        for (name, reason) in &self.rejected {
+            report.push_str(&format!("    {name}: {reason}\n"));
+        }
+        for (phase, duration) in &self.phases {
+            report.push_str(&format!(
+                "  {phase:<15}{:>10.3} ms\n",
+                duration.as_secs_f64() * 1000.0
+            ));
+        }
+        report
+    }
+    /// Returns the statistics as a JSON document, with the phase times in milliseconds.
+    pub fn to_json(&self) -> String {
+        let document = StatisticsDocument {
+            files_scanned: self.files_scanned,
+            files_skipped: self.files_skipped,
+            blocks: self.sequences.len(),
+            sequences: self.total_sequences(),
+            comment_lines: self.comment_lines,
+            sequences_per_block: &self.sequences,
+            rejected: self
+                .rejected
+                .iter()
+                .map(|(block, reason)| Rejected { block, reason })
+                .collect(),
+            phases_ms: &self.phases,
+        };
+        serde_json::to_string_pretty(&document).unwrap() + "\n"
+    }
+}
+
+/// The JSON document of the statistics of a run, see `RunStatistics::to_json`.
+#[derive(Serialize)]
+struct StatisticsDocument<'a> {
+    files_scanned: usize,
+    files_skipped: usize,
+    blocks: usize,
+    sequences: usize,
+    comment_lines: usize,
+    sequences_per_block: &'a BTreeMap<String, usize>,
+    rejected: Vec<Rejected<'a>>,
+    #[serde(serialize_with = "phases_ms")]
+    phases_ms: &'a [(&'static str, Duration)],
+}
+
+/// A rejected block in the JSON document of the statistics.
+#[derive(Serialize)]
+struct Rejected<'a> {
+    block: &'a str,
+    reason: &'a str,
+}
+
+/// Writes the phases as an object of their times in milliseconds, in run order.
+fn phases_ms<S: Serializer>(
+    phases: &&[(&'static str, Duration)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        phases
+            .iter()
+            .map(|(phase, duration)| (phase, duration.as_secs_f64() * 1000.0)),
+    )
+}
+
+//#EPIC Get Lines.ITEM Run statistics [0]
+//#
+//## Run statistics
+//#`-stats` prints a summary at the end of a run: the files scanned and skipped, the blocks and
+//#Sequences found, the comment lines, the rejected blocks with their reasons and the time of
+//#every phase. `-stats file.json` also writes it as JSON, so documentation coverage can be
+//#followed over time, for example by keeping the file of every CI run.
+/// Writes the statistics of a run as JSON to `file`, see `RunStatistics::to_json`.
+pub fn write_statistics(statistics: &RunStatistics, file: &str) -> Result<(), Error> {
+    write(file, statistics.to_json())
+}
+
+#[cfg(test)]
+#[test]
+fn test_run_statistics() {
+    let statistics = RunStatistics {
+        files_scanned: 4,
+        files_skipped: 1,
+        sequences: BTreeMap::from([("EPIC A".to_string(), 3), ("EPIC B".to_string(), 1)]),
+        comment_lines: 25,
+        rejected: vec![("EPIC B".to_string(), "no \"EPIC\" prefix".to_string())],
+        phases: vec![("parse", Duration::from_millis(12))],
+    };
+    let report = statistics.report();
+    assert!(report.contains("  files skipped           1\n"));
+    assert!(report.contains("  per block             2.0\n"));
+    assert!(report.contains("    EPIC B: no \"EPIC\" prefix\n"));
+    assert!(report.contains("  parse              12.000 ms\n"));
+    let json: serde_json::Value = serde_json::from_str(&statistics.to_json()).unwrap();
+    assert_eq!(json["sequences"], 4);
+    assert_eq!(json["sequences_per_block"]["EPIC B"], 1);
+    assert_eq!(json["rejected"][0]["reason"], "no \"EPIC\" prefix");
+    assert_eq!(json["phases_ms"]["parse"], 12.0);
+}
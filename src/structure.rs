@@ -0,0 +1,109 @@
+use crate::block::{Sequences, relative_name};
+use crate::collation::SortOrder;
+use crate::outputs::write_if_changed;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+/// The file in the document root holding the diagram of the block hierarchy.
+pub const STRUCTURE_FILE: &str = "structure.md";
+
+//#EPIC Get Lines.ITEM Diagram of the document hierarchy [0]
+//#
+//## Diagram of the document hierarchy
+//#Once the history is written `structure.md` in the document root gets a Mermaid graph of the
+//#EPIC → ITEM → TASK tree, every node linked to its generated file, so the structure of the
+//#extracted documentation can be seen at a glance.
+/// Writes `structure.md` to the document root, holding a Mermaid graph of the block
+/// hierarchy, unless it already holds the graph.
+///
+/// Every level of every block name is a node, connected to the level above it. A node whose
+/// generated Markdown file was written links to it, relative to the document root; levels
+/// that only group other blocks have no link.
+///
+/// # Parameters:
+/// - `history`: The comment history, keyed by dot delimited file path and sequence number
+/// - `doc_root`: The document root the history keys start with
+/// - `order`: The order of the nodes below each level
+///
+/// # Returns:
+/// - `Ok(PathBuf)` with the path of `structure.md`, written or not
+/// - `Err(Error)` when the file could not be written
+pub fn write_structure(
+    history: &HashMap<String, Sequences>,
+    doc_root: &str,
+    order: SortOrder,
+) -> Result<PathBuf, Error> {
+    let mut nodes: BTreeSet<Vec<&str>> = BTreeSet::new();
+    for key in history.keys() {
+        let levels: Vec<&str> = relative_name(key, doc_root).split('.').collect();
+        for depth in 1..=levels.len() {
+            nodes.insert(levels[..depth].to_vec());
+        }
+    }
+    let mut nodes: Vec<Vec<&str>> = nodes.into_iter().collect();
+    nodes.sort_by(|left, right| {
+        left.iter()
+            .zip(right)
+            .map(|(left_level, right_level)| order.compare(left_level, right_level))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(left.len().cmp(&right.len()))
+    });
+    let id = |node: &[&str]| {
+        let index = nodes.iter().position(|other| other == node).unwrap_or(0);
+        format!("n{index}")
+    };
+
+    let mut writer = Vec::new();
+    writeln!(writer, "# Structure\n\n```mermaid\ngraph TD")?;
+    for node in &nodes {
+        let label = node[node.len() - 1].replace('"', "#quot;");
+        writeln!(writer, "    {}[\"{label}\"]", id(node))?;
+    }
+    for node in nodes.iter().filter(|node| node.len() > 1) {
+        writeln!(
+            writer,
+            "    {} --> {}",
+            id(&node[..node.len() - 1]),
+            id(node)
+        )?;
+    }
+    for node in &nodes {
+        let file = format!("{}.md", node.join("/"));
+        if Path::new(doc_root).join(&file).exists() {
+            writeln!(
+                writer,
+                "    click {} \"{}\"",
+                id(node),
+                file.replace(' ', "%20")
+            )?;
+        }
+    }
+    writeln!(writer, "```")?;
+    let path = Path::new(doc_root).join(STRUCTURE_FILE);
+    write_if_changed(&path, &writer)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+#[test]
+fn test_structure() {
+    let doc_root = "target/test-structure";
+    let _ = std::fs::remove_dir_all(doc_root);
+    std::fs::create_dir_all(format!("{doc_root}/EPIC A")).unwrap();
+    std::fs::write(format!("{doc_root}/EPIC A/ITEM B.md"), "").unwrap();
+    std::fs::write(format!("{doc_root}/EPIC C.md"), "").unwrap();
+    let history: HashMap<String, Sequences> = [
+        format!("{doc_root}.EPIC C"),
+        format!("{doc_root}.EPIC A.ITEM B"),
+    ]
+    .into_iter()
+    .map(|key| (key, Sequences::new()))
+    .collect();
+    write_structure(&history, doc_root, SortOrder::default()).unwrap();
+    let structure = std::fs::read_to_string(format!("{doc_root}/{STRUCTURE_FILE}")).unwrap();
+    assert_eq!(
+        structure,
+        "# Structure\n\n```mermaid\ngraph TD\n    n0[\"EPIC A\"]\n    n1[\"ITEM B\"]\n    n2[\"EPIC C\"]\n    n0 --> n1\n    click n1 \"EPIC%20A/ITEM%20B.md\"\n    click n2 \"EPIC%20C.md\"\n```\n"
+    );
+}
@@ -0,0 +1,299 @@
+use crate::block::directive;
+use crate::provenance::FOOTER_PREFIX;
+use crate::schema::FieldSchema;
+use crate::snippet::snippet_reference;
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, write};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// The headings after which a generated Markdown file holds no more editable blocks.
+const END_HEADINGS: [&str; 2] = ["## Generated", "## Referenced by"];
+
+/// A block as found in an edited Markdown file.
+#[derive(Debug, PartialEq)]
+struct EditedBlock {
+    /// The source file named by the `[SOURCE FILE:]` line.
+    source: PathBuf,
+    /// The first body line of the block in the source file.
+    start_line: usize,
+    /// The last body line, when the `[SOURCE FILE:]` line holds a range.
+    end_line: Option<usize>,
+    /// The body lines as edited.
+    lines: Vec<String>,
+}
+
+impl EditedBlock {
+    /// Replaces the body lines of the block in the lines of its source file with the edited
+    /// ones, each prefixed with the indentation and marker of the original body lines, and
+    /// the space after the marker when they have one. The
+    /// directives lifted out of the block, see `block::directive`, are kept before the
+    /// edited lines.
+    ///
+    /// # Returns:
+    /// - `Ok(bool)` telling whether the source lines changed
+    /// - `Err(String)` when the source file no longer holds the block at its lines
+    fn rewrite(
+        &self,
+        lines: &mut Vec<String>,
+        marker: &str,
+        fields: Option<&FieldSchema>,
+    ) -> Result<bool, String> {
+        let marked = |line: &str| line.trim_start().starts_with(marker);
+        let Some(header) = self
+            .start_line
+            .checked_sub(2)
+            .and_then(|index| lines.get(index))
+            .filter(|line| marked(line))
+        else {
+            return Err(
+                "No block header above the block, extract again before syncing back".into(),
+            );
+        };
+        let first = self.start_line - 1;
+        let end = match self.end_line {
+            Some(end_line) => end_line,
+            None if lines.get(first).is_some_and(|line| marked(line)) => self.start_line,
+            None => first,
+        };
+        if end > lines.len() || end < first {
+            return Err("The block is past the end of the source file".into());
+        }
+        // the space the extraction strips after the marker, when the comment has one
+        let space = |text: &str| if text.starts_with(' ') { " " } else { "" };
+        let mut prefix = None;
+        let mut kept = Vec::new();
+        for line in &lines[first..end] {
+            let indentation = &line[..line.len() - line.trim_start().len()];
+            let Some(text) = line.trim_start().strip_prefix(marker) else {
+                return Err(
+                    "The source lines of the block changed, extract again before syncing back"
+                        .into(),
+                );
+            };
+            if text.trim().starts_with("get-comments:") {
+                return Err("Blocks holding get-comments directives cannot be synced back".into());
+            }
+            if text.trim().starts_with("@include-code:") || snippet_reference(text).is_some() {
+                return Err("Blocks embedding source code cannot be synced back".into());
+            }
+            if !text.trim().is_empty() {
+                prefix.get_or_insert_with(|| format!("{indentation}{marker}{}", space(text)));
+            }
+            if directive(text, fields).is_some() {
+                kept.push(line.clone());
+            }
+        }
+        let prefix = prefix.unwrap_or_else(|| {
+            let text = header.trim_start();
+            let indentation = &header[..header.len() - text.len()];
+            let text = text.strip_prefix(marker).unwrap_or(text);
+            format!("{indentation}{marker}{}", space(text))
+        });
+        let replacement: Vec<String> = kept
+            .into_iter()
+            .chain(self.lines.iter().map(|line| match line.is_empty() {
+                true => prefix.trim_end().to_string(),
+                false => format!("{prefix}{line}"),
+            }))
+            .collect();
+        if replacement[..] == lines[first..end] {
+            return Ok(false);
+        }
+        lines.splice(first..end, replacement);
+        Ok(true)
+    }
+}
+
+/// Returns the source file a `[SOURCE FILE:]` link points to, for the link styles
+/// `file`, `relative` and `github`. Relative targets are taken from the folder of the
+/// Markdown file, GitHub paths from the current folder.
+fn source_path(target: &str, folder: &Path) -> PathBuf {
+    let decode = |path: &str| path.replace("%20", " ");
+    if let Some(path) = target.strip_prefix("file:///") {
+        return PathBuf::from(path);
+    }
+    if let Some((_, rest)) = target.split_once("/blob/") {
+        let path = rest.split_once('/').map_or(rest, |(_, path)| path);
+        return PathBuf::from(decode(path.split('#').next().unwrap_or(path)));
+    }
+    folder.join(decode(target))
+}
+
+/// Reads the blocks of a generated Markdown file: the front matter is skipped, and every
+/// block runs from its `[SOURCE FILE:]` line up to the next one. Reading stops at the
+/// `Generated` and `Referenced by` headings, the run footer and the links between parts.
+fn edited_blocks(content: &str, folder: &Path) -> Vec<EditedBlock> {
+    let header = Regex::new(
+        r"^(?:\[SOURCE FILE:\]\((.*)\)|SOURCE FILE: (.*)) LINE: (\d+)(?:-(\d+))?(?: COMMIT: .*)?$",
+    )
+    .unwrap();
+    let parts = Regex::new(r"^Part \d+ of \d+: ").unwrap();
+    let mut lines = content.lines();
+    if content.starts_with("---") {
+        lines.next();
+        lines.by_ref().find(|line| *line == "---");
+    }
+    let mut blocks: Vec<EditedBlock> = Vec::new();
+    for line in lines {
+        if let Some(capture) = header.captures(line) {
+            let source = match (capture.get(1), capture.get(2)) {
+                (Some(target), _) => source_path(target.as_str(), folder),
+                (_, Some(source)) => PathBuf::from(source.as_str()),
+                _ => continue,
+            };
+            blocks.push(EditedBlock {
+                source,
+                start_line: capture[3].parse().unwrap_or_default(),
+                end_line: capture.get(4).and_then(|end| end.as_str().parse().ok()),
+                lines: Vec::new(),
+            });
+        } else if END_HEADINGS.contains(&line)
+            || line.starts_with(FOOTER_PREFIX)
+            || parts.is_match(line)
+        {
+            break;
+        } else if let Some(block) = blocks.last_mut() {
+            block.lines.push(line.to_string());
+        }
+    }
+    for block in &mut blocks {
+        // the blank line after the `[SOURCE FILE:]` line and the one closing the block
+        if block.lines.first().is_some_and(String::is_empty) {
+            block.lines.remove(0);
+        }
+        if block.lines.last().is_some_and(String::is_empty) {
+            block.lines.pop();
+        }
+    }
+    blocks
+}
+
+//#EPIC Get Lines.ITEM Sync edits back to the source [0]
+//#
+//## Sync edits back to the source
+//#Every block in a generated Markdown file names its source file and lines, so a block edited
+//#in the Markdown file, for instance in Obsidian, can be written back to the comment it came
+//#from. Each edited line is prefixed with the indentation and marker of the comment again, and
+//#the `@name: value` directives of the comment are kept.
+/// Writes the blocks of an edited Markdown file back into the comments of their source files.
+///
+/// The Markdown file must be written by the last run with the default block layout: no
+/// `-template`, `-filter` or `-glossary`, and blank lines between blocks. Only comments with
+/// a line marker, such as `//#`, are supported. A block is refused when its source file no
+/// longer holds a comment at its lines, which happens when the source changed after the
+/// extraction; extract again before syncing back. `fields` is the field schema of that run,
+/// so the lines of its declared fields are kept as directives.
+///
+/// # Returns:
+/// - `Ok(usize)` with the number of blocks that were changed in their source files
+/// - `Err(Error)` when a file could not be read or written, or a block could not be placed
+pub fn sync_back(
+    file_name: &str,
+    marker: &str,
+    fields: Option<&FieldSchema>,
+) -> Result<usize, Error> {
+    let content = read_to_string(file_name)?;
+    let folder = Path::new(file_name).parent().unwrap_or(Path::new(""));
+    let mut sources: BTreeMap<PathBuf, Vec<EditedBlock>> = BTreeMap::new();
+    for block in edited_blocks(&content, folder) {
+        sources.entry(block.source.clone()).or_default().push(block);
+    }
+    let mut changed = 0;
+    for (source, mut blocks) in sources {
+        let text = read_to_string(&source)
+            .map_err(|error| Error::new(error.kind(), format!("{error}: {}", source.display())))?;
+        let newline = match text.contains("\r\n") {
+            true => "\r\n",
+            false => "\n",
+        };
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        // from the bottom up, so the lines of the blocks above stay where they are
+        blocks.sort_by_key(|block| Reverse(block.start_line));
+        let changed_before = changed;
+        for block in &blocks {
+            let rewritten = block
+                .rewrite(&mut lines, marker, fields)
+                .map_err(|message| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "{message} in {} LINE: {}",
+                            source.display(),
+                            block.start_line
+                        ),
+                    )
+                })?;
+            changed += rewritten as usize;
+        }
+        if changed > changed_before {
+            let mut text = lines.join(newline);
+            text.push_str(newline);
+            write(&source, text)?;
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+#[test]
+fn test_sync_back() {
+    let folder = "target/test-sync-back";
+    let _ = std::fs::remove_dir_all(folder);
+    std::fs::create_dir_all(folder).unwrap();
+    let source = format!("{folder}/a.rs");
+    write(
+        &source,
+        "fn a() {}\n    //#EPIC A [0]\n    //#@status: beta\n    //#old text\n    //#\nfn b() {}\n//#EPIC A [1]\n//#one\n",
+    )
+    .unwrap();
+    let markdown = format!("{folder}/EPIC A.md");
+    write(
+        &markdown,
+        format!(
+            "---\ntitle: \"EPIC A\"\n---\n[SOURCE FILE:](file:///{source}) LINE: 3-5\n\nnew text\n# Heading\n\n[SOURCE FILE:](a.rs) LINE: 8 COMMIT: 1a2b3c4 AUTHOR: Jan DATE: 2024-05-01\n\none\n\n## Referenced by\n\n- [[B]]\n"
+        ),
+    )
+    .unwrap();
+    assert_eq!(sync_back(&markdown, "//#", None).unwrap(), 1);
+    assert_eq!(
+        read_to_string(&source).unwrap(),
+        "fn a() {}\n    //#EPIC A [0]\n    //#@status: beta\n    //#new text\n    //## Heading\nfn b() {}\n//#EPIC A [1]\n//#one\n"
+    );
+    assert_eq!(sync_back(&markdown, "//#", None).unwrap(), 0);
+    write(&source, "fn a() {}\n").unwrap();
+    assert!(sync_back(&markdown, "//#", None).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_sync_back_fenced_code() {
+    let folder = "target/test-sync-back-fenced";
+    let _ = std::fs::remove_dir_all(folder);
+    std::fs::create_dir_all(folder).unwrap();
+    let source = format!("{folder}/a.rs");
+    write(
+        &source,
+        "//# EPIC A [0]\n//# ```sh\n//#   ls\n//# ```\n//#\n//# Done\n",
+    )
+    .unwrap();
+    let markdown = format!("{folder}/EPIC A.md");
+    write(
+        &markdown,
+        format!("[SOURCE FILE:](file:///{source}) LINE: 2-6\n\n```sh\n  ls\n```\n\nDone\n\n"),
+    )
+    .unwrap();
+    assert_eq!(sync_back(&markdown, "//#", None).unwrap(), 0);
+    write(
+        &markdown,
+        format!("[SOURCE FILE:](file:///{source}) LINE: 2-6\n\n```sh\n  ls -l\n```\n\nDone\n\n"),
+    )
+    .unwrap();
+    assert_eq!(sync_back(&markdown, "//#", None).unwrap(), 1);
+    assert_eq!(
+        read_to_string(&source).unwrap(),
+        "//# EPIC A [0]\n//# ```sh\n//#   ls -l\n//# ```\n//#\n//# Done\n"
+    );
+}
@@ -0,0 +1,153 @@
+use crate::block::{Sequences, relative_name};
+use crate::outputs::write_if_changed;
+use crate::sequence::Sequence;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+/// The folder in the document root holding the tag pages.
+const TAGS_FOLDER: &str = "tags";
+
+/// The prefix of the file name of every tag page, so no tag page can be named `index.md`.
+const TAG_PAGE_PREFIX: &str = "tag-";
+
+/// Returns the file name of the page of a tag, without extension. Characters that would
+/// create a folder or a level are replaced by `-`.
+fn tag_file_name(tag: &str) -> String {
+    let name: String = tag
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '.' | ':' => '-',
+            c => c,
+        })
+        .collect();
+    format!("{TAG_PAGE_PREFIX}{name}")
+}
+
+//#EPIC Get Lines.ITEM Write tag pages [0]
+//#
+//## Write tag pages
+//#Blocks are grouped by topic with an `@tags: parser, io` directive. Every tag gets a page in the
+//#`tags` folder of the document root listing the blocks carrying it, and `tags/index.md` lists
+//#all tags, so documentation can be browsed across the EPIC/ITEM/TASK tree.
+/// Writes a page per tag to the `tags` folder of the document root, plus `tags/index.md`.
+///
+/// A tag page lists every block name with a Sequence carrying the tag, in name order, with
+/// the Sequence numbers. Nothing is written when no block has tags, and a page that already
+/// holds its content is not written again.
+///
+/// # Returns:
+/// - `Ok(Vec<PathBuf>)` with the index and every tag page, written or not
+/// - `Err(Error)` when a page could not be written
+pub fn write_tag_pages(
+    history: &HashMap<String, Sequences>,
+    doc_root: &str,
+) -> Result<Vec<PathBuf>, Error> {
+    let tags = tagged_blocks(history, doc_root);
+    if tags.is_empty() {
+        return Ok(Vec::new());
+    }
+    let folder = Path::new(doc_root).join(TAGS_FOLDER);
+    let mut files = Vec::new();
+    let mut index = Vec::new();
+    writeln!(index, "# Tags\n")?;
+    for (tag, names) in &tags {
+        let file_name = tag_file_name(tag);
+        writeln!(index, "- [{tag}](<{file_name}.md>) ({})", names.len())?;
+        let mut page = Vec::new();
+        writeln!(page, "# {tag}\n")?;
+        for (name, sequences) in names {
+            let sequences: Vec<String> = sequences.iter().map(Sequence::to_string).collect();
+            writeln!(
+                page,
+                "- [{name}](<../{}.md>) [{}]",
+                name.replace('.', "/"),
+                sequences.join(", ")
+            )?;
+        }
+        let path = folder.join(format!("{file_name}.md"));
+        write_if_changed(&path, &page)?;
+        files.push(path);
+    }
+    let path = folder.join("index.md");
+    write_if_changed(&path, &index)?;
+    files.push(path);
+    Ok(files)
+}
+
+/// Returns the files `write_tag_pages` writes for a history: `tags/index.md` and the page of
+/// every tag, or none when no block has tags.
+pub fn tag_files(history: &HashMap<String, Sequences>, doc_root: &str) -> Vec<PathBuf> {
+    let tags = tagged_blocks(history, doc_root);
+    let folder = Path::new(doc_root).join(TAGS_FOLDER);
+    let pages = tags
+        .keys()
+        .map(|tag| folder.join(format!("{}.md", tag_file_name(tag))));
+    match tags.is_empty() {
+        true => Vec::new(),
+        false => pages.chain([folder.join("index.md")]).collect(),
+    }
+}
+
+/// Returns the block names with the Sequences carrying each tag, by tag.
+fn tagged_blocks<'h>(
+    history: &'h HashMap<String, Sequences>,
+    doc_root: &str,
+) -> BTreeMap<&'h str, BTreeMap<&'h str, BTreeSet<Sequence>>> {
+    let mut tags: BTreeMap<&str, BTreeMap<&str, BTreeSet<Sequence>>> = BTreeMap::new();
+    for (key, blocks) in history {
+        for (sequence, block) in blocks {
+            for tag in block.tags() {
+                tags.entry(tag)
+                    .or_default()
+                    .entry(relative_name(key, doc_root))
+                    .or_default()
+                    .insert(*sequence);
+            }
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+#[test]
+fn test_tag_pages() {
+    use crate::block::Block;
+    let doc_root = "target/test-tag-pages";
+    let _ = std::fs::remove_dir_all(doc_root);
+    let block = |tags: &str| {
+        let lines = vec![format!("@tags: {tags}"), "text".to_string()];
+        Block::new("a.rs".into(), 1, lines, &BTreeSet::new())
+    };
+    let history: HashMap<String, Sequences> = [
+        (
+            format!("{doc_root}.EPIC A.ITEM B"),
+            Sequences::from([
+                (Sequence::from(0), block("io, parser")),
+                (Sequence::from(3), block("io")),
+            ]),
+        ),
+        (
+            format!("{doc_root}.EPIC C"),
+            Sequences::from([
+                (Sequence::from(1), block("c/c++")),
+                (Sequence::from(2), block("index")),
+            ]),
+        ),
+    ]
+    .into_iter()
+    .collect();
+    let files = write_tag_pages(&history, doc_root).unwrap();
+    assert_eq!(files.len(), 5);
+    assert_eq!(files, tag_files(&history, doc_root));
+    let io = std::fs::read_to_string(format!("{doc_root}/tags/tag-io.md")).unwrap();
+    assert_eq!(
+        io,
+        "# io\n\n- [EPIC A.ITEM B](<../EPIC A/ITEM B.md>) [0, 3]\n"
+    );
+    let index = std::fs::read_to_string(format!("{doc_root}/tags/index.md")).unwrap();
+    assert_eq!(
+        index,
+        "# Tags\n\n- [c/c++](<tag-c-c++.md>) (1)\n- [index](<tag-index.md>) (1)\n- [io](<tag-io.md>) (1)\n- [parser](<tag-parser.md>) (1)\n"
+    );
+}
@@ -0,0 +1,182 @@
+use crate::block::Block;
+use crate::sequence::Sequence;
+use std::fs::read_to_string;
+use std::io::Error;
+
+/// A value a block template can refer to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Placeholder {
+    /// The dot delimited block name, without the document root.
+    Name,
+    /// The last level of the block name.
+    Title,
+    /// The Sequence number.
+    Sequence,
+    /// The path of the source file as it appears in the output.
+    Source,
+    /// The target of the source link, see `LinkStyle`; empty with `-link-style none`.
+    Link,
+    /// The source lines of the block, as `start-end`.
+    Lines,
+    /// The first source line of the block.
+    StartLine,
+    /// The last source line of the block.
+    EndLine,
+    /// The body lines of the block.
+    Body,
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> Option<Placeholder> {
+        match name {
+            "name" => Some(Placeholder::Name),
+            "title" => Some(Placeholder::Title),
+            "sequence" => Some(Placeholder::Sequence),
+            "source" => Some(Placeholder::Source),
+            "link" => Some(Placeholder::Link),
+            "lines" => Some(Placeholder::Lines),
+            "start_line" => Some(Placeholder::StartLine),
+            "end_line" => Some(Placeholder::EndLine),
+            "body" => Some(Placeholder::Body),
+            _ => None,
+        }
+    }
+}
+
+/// A piece of a block template: literal text or a placeholder.
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Text(String),
+    Value(Placeholder),
+}
+
+//#EPIC Get Lines.ITEM Block templates [0]
+//#
+//## Block templates
+//#Every block is written as a `[SOURCE FILE:]` line, its body and a blank line. A block template
+//#replaces that layout with any text holding placeholders such as `{name}`, `{link}` and
+//#`{body}`, so a site generator can get the block in the markup it expects.
+/// The text every block of a generated Markdown file is written as.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockTemplate {
+    pieces: Vec<Piece>,
+}
+
+impl BlockTemplate {
+    /// Reads a block template file, see `parse` for the format.
+    pub fn load(file_name: &str) -> Result<BlockTemplate, Error> {
+        BlockTemplate::parse(&read_to_string(file_name)?)
+            .map_err(|error| Error::other(format!("{error} in {file_name}")))
+    }
+    /// Parses a block template: text in which `{name}`, `{title}`, `{sequence}`, `{source}`,
+    /// `{link}`, `{lines}`, `{start_line}`, `{end_line}` and `{body}` are replaced by the
+    /// values of the block. `{{` and `}}` stand for a literal brace. The template is written
+    /// as it is, so it should end with the blank line that separates blocks:
+    /// ```text
+    /// ### {title} #{sequence}
+    /// {body}
+    ///
+    /// <sub>[{source}:{lines}]({link})</sub>
+    ///
+    /// ```
+    pub fn parse(text: &str) -> Result<BlockTemplate, Error> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut rest = text;
+        while let Some(position) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..position]);
+            let brace = &rest[position..position + 1];
+            rest = &rest[position + 1..];
+            if let Some(after) = rest.strip_prefix(brace) {
+                literal.push_str(brace);
+                rest = after;
+                continue;
+            }
+            let name = match brace {
+                "{" => rest.split_once('}').map(|(name, _)| name),
+                _ => None,
+            };
+            let Some(placeholder) = name.and_then(Placeholder::parse) else {
+                return Err(Error::other(format!(
+                    "Invalid template placeholder at [{}]: expected {{name}}, {{title}}, {{sequence}}, {{source}}, {{link}}, {{lines}}, {{start_line}}, {{end_line}} or {{body}}",
+                    &text[text.len() - rest.len() - 1..]
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                )));
+            };
+            rest = &rest[name.unwrap_or_default().len() + 1..];
+            if !literal.is_empty() {
+                pieces.push(Piece::Text(std::mem::take(&mut literal)));
+            }
+            pieces.push(Piece::Value(placeholder));
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+        Ok(BlockTemplate { pieces })
+    }
+    /// Renders one block with its name, without the document root, its Sequence number and
+    /// the target of its source link.
+    pub fn render(
+        &self,
+        name: &str,
+        sequence: Sequence,
+        block: &Block,
+        link: Option<&str>,
+    ) -> String {
+        let mut text = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(literal) => text.push_str(literal),
+                Piece::Value(placeholder) => text.push_str(&match placeholder {
+                    Placeholder::Name => name.to_string(),
+                    Placeholder::Title => name.rsplit('.').next().unwrap_or(name).to_string(),
+                    Placeholder::Sequence => sequence.to_string(),
+                    Placeholder::Source => block.source_file.to_string(),
+                    Placeholder::Link => link.unwrap_or_default().to_string(),
+                    Placeholder::Lines => block.line_range(),
+                    Placeholder::StartLine => block.start_line.to_string(),
+                    Placeholder::EndLine => block.end_line.to_string(),
+                    Placeholder::Body => block.lines.join("\n"),
+                }),
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_block_template() {
+    let mut block = Block::new(
+        "src/a.rs".into(),
+        4,
+        vec!["first".to_string(), "second".to_string()],
+        &Default::default(),
+    );
+    block.end_line = 6;
+    let template = BlockTemplate::parse(
+        "### {title} #{sequence} {{x}}\n{body}\n\n[{source}:{lines}]({link})\n\n",
+    )
+    .unwrap();
+    assert_eq!(
+        template.render(
+            "EPIC A.ITEM B",
+            "2.1".parse().unwrap(),
+            &block,
+            Some("../a.rs")
+        ),
+        "### ITEM B #2.1 {x}\nfirst\nsecond\n\n[src/a.rs:4-6](../a.rs)\n\n"
+    );
+    assert_eq!(
+        BlockTemplate::parse("{link}")
+            .unwrap()
+            .render("A", Sequence::default(), &block, None),
+        ""
+    );
+    assert!(BlockTemplate::parse("{file}").is_err());
+    assert!(BlockTemplate::parse("{body").is_err());
+    assert!(BlockTemplate::parse("body}").is_err());
+}
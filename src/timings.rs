@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+/// The wall time and item count of one phase of a run.
+struct Phase {
+    name: &'static str,
+    duration: Duration,
+    count: usize,
+    unit: &'static str,
+}
+
+/// The time spent in every phase of a run and on every source file.
+#[derive(Default)]
+pub struct Timings {
+    phases: Vec<Phase>,
+    files: Vec<(String, Duration)>,
+}
+
+impl Timings {
+    /// Records the wall time of a phase and the number of items it handled.
+    pub fn add_phase(
+        &mut self,
+        name: &'static str,
+        duration: Duration,
+        count: usize,
+        unit: &'static str,
+    ) {
+        self.phases.push(Phase {
+            name,
+            duration,
+            count,
+            unit,
+        });
+    }
+    /// Records the time spent reading and parsing one source file.
+    pub fn add_file(&mut self, name: &str, duration: Duration) {
+        self.files.push((name.to_string(), duration));
+    }
+    /// Returns the name and wall time of every phase, in run order.
+    pub fn phases(&self) -> Vec<(&'static str, Duration)> {
+        self.phases
+            .iter()
+            .map(|phase| (phase.name, phase.duration))
+            .collect()
+    }
+    /// Formats the phases in run order followed by the `slowest` files that took the longest.
+    ///
+    /// The walk phase runs on its own thread at the same time as the parse phase, so the phase
+    /// times can add up to more than the wall time of the run.
+    pub fn report(&self, slowest: usize) -> String {
+        let mut report = String::from("Timings:\n");
+        for phase in &self.phases {
+            report.push_str(&format!(
+                "  {:<10}{:>10.3} ms  {} {}\n",
+                phase.name,
+                phase.duration.as_secs_f64() * 1000.0,
+                phase.count,
+                phase.unit
+            ));
+        }
+        let mut files: Vec<&(String, Duration)> = self.files.iter().collect();
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        if slowest > 0 && !files.is_empty() {
+            report.push_str(&format!("Slowest {} files:\n", slowest.min(files.len())));
+            for (name, duration) in files.into_iter().take(slowest) {
+                report.push_str(&format!(
+                    "  {:>10.3} ms  {name}\n",
+                    duration.as_secs_f64() * 1000.0
+                ));
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_timings_report() {
+    let mut timings = Timings::default();
+    timings.add_phase("parse", Duration::from_millis(12), 3, "files");
+    timings.add_file("a.rs", Duration::from_millis(2));
+    timings.add_file("b.rs", Duration::from_millis(9));
+    timings.add_file("c.rs", Duration::from_millis(1));
+    let report = timings.report(2);
+    assert!(report.contains("parse         12.000 ms  3 files"));
+    let b = report.find("b.rs").unwrap();
+    assert!(b < report.find("a.rs").unwrap());
+    assert!(!report.contains("c.rs"));
+}
@@ -0,0 +1,159 @@
+use crate::block::{Sequences, relative_name};
+use crate::outputs::write_if_changed;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+/// The format in which the traceability matrix is written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatrixFormat {
+    Csv,
+    Markdown,
+}
+
+impl MatrixFormat {
+    /// Parses a matrix format as given on the command line: `csv` or `md`.
+    pub fn parse(name: &str) -> Result<MatrixFormat, Error> {
+        match name.trim() {
+            "csv" => Ok(MatrixFormat::Csv),
+            "md" | "markdown" => Ok(MatrixFormat::Markdown),
+            other => Err(Error::other(format!("Unknown matrix format [{other}]"))),
+        }
+    }
+    /// Returns the file name extension of the format: `csv` or `md`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            MatrixFormat::Csv => "csv",
+            MatrixFormat::Markdown => "md",
+        }
+    }
+}
+
+/// Quotes a CSV field when it holds a comma, a quote or a line break.
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+//#EPIC Get Lines.ITEM Traceability matrix [0]
+//#
+//## Traceability matrix
+//#Safety critical projects must show where every requirement is implemented. With
+//#`-trace-matrix csv|md` a matrix is written to the document root with a row per block name, a
+//#column per source file and, in each cell, the lines of the blocks of that name in that file.
+/// Writes the traceability matrix of a history to `trace-matrix.csv` or `trace-matrix.md` in
+/// the document root.
+///
+/// The rows are the block names in byte order and the columns the source files, as written
+/// in the `[SOURCE FILE:]` links, in byte order. A cell lists the line ranges of the
+/// Sequences of the block found in the file, such as `12-18; 40-45`, and is empty when the
+/// file holds none. A matrix file that already holds the matrix is not written again.
+///
+/// # Returns:
+/// - `Ok(PathBuf)` with the path of the matrix file, written or not
+/// - `Err(Error)` when the matrix could not be written
+pub fn write_trace_matrix(
+    history: &HashMap<String, Sequences>,
+    doc_root: &str,
+    format: MatrixFormat,
+) -> Result<PathBuf, Error> {
+    let mut rows: BTreeMap<&str, BTreeMap<&str, Vec<String>>> = BTreeMap::new();
+    let mut files = BTreeSet::new();
+    for (key, blocks) in history {
+        let row = rows.entry(relative_name(key, doc_root)).or_default();
+        for block in blocks.values() {
+            files.insert(&*block.source_file);
+            row.entry(&*block.source_file).or_default().push(
+                match block.end_line > block.start_line {
+                    true => format!("{}-{}", block.start_line, block.end_line),
+                    false => block.start_line.to_string(),
+                },
+            );
+        }
+    }
+    let cell = |row: &BTreeMap<&str, Vec<String>>, file: &str| {
+        row.get(file)
+            .map(|lines| lines.join("; "))
+            .unwrap_or_default()
+    };
+
+    let mut writer = Vec::new();
+    match format {
+        MatrixFormat::Csv => {
+            let header: Vec<String> = files.iter().map(|file| csv_field(file)).collect();
+            writeln!(writer, "Block,{}", header.join(","))?;
+            for (name, row) in &rows {
+                let cells: Vec<String> = files.iter().map(|file| cell(row, file)).collect();
+                writeln!(writer, "{},{}", csv_field(name), cells.join(","))?;
+            }
+        }
+        MatrixFormat::Markdown => {
+            writeln!(writer, "# Traceability matrix\n")?;
+            let header: Vec<String> = files.iter().map(|file| file.replace('|', "\\|")).collect();
+            writeln!(writer, "| Block | {} |", header.join(" | "))?;
+            writeln!(writer, "|{}", " --- |".repeat(files.len() + 1))?;
+            for (name, row) in &rows {
+                let cells: Vec<String> = files.iter().map(|file| cell(row, file)).collect();
+                writeln!(
+                    writer,
+                    "| {} | {} |",
+                    name.replace('|', "\\|"),
+                    cells.join(" | ")
+                )?;
+            }
+        }
+    }
+    let path = trace_matrix_file(doc_root, format);
+    write_if_changed(&path, &writer)?;
+    Ok(path)
+}
+
+/// Returns the path of the traceability matrix in the given format.
+pub fn trace_matrix_file(doc_root: &str, format: MatrixFormat) -> PathBuf {
+    Path::new(doc_root).join(format!("trace-matrix.{}", format.extension()))
+}
+
+#[cfg(test)]
+#[test]
+fn test_trace_matrix() {
+    use crate::block::Block;
+    use crate::sequence::Sequence;
+    let doc_root = "target/test-trace-matrix";
+    let _ = std::fs::remove_dir_all(doc_root);
+    let block = |file: &str, start_line: usize, end_line: usize| Block {
+        source_file: file.into(),
+        start_line,
+        end_line,
+        ..Default::default()
+    };
+    let history: HashMap<String, Sequences> = [
+        (
+            format!("{doc_root}.EPIC A"),
+            Sequences::from([
+                (Sequence::from(0), block("src/a.rs", 12, 18)),
+                (Sequence::from(1), block("src/a.rs", 40, 40)),
+            ]),
+        ),
+        (
+            format!("{doc_root}.EPIC B"),
+            Sequences::from([(Sequence::from(0), block("src/b,c.rs", 3, 5))]),
+        ),
+    ]
+    .into_iter()
+    .collect();
+    write_trace_matrix(&history, doc_root, MatrixFormat::Csv).unwrap();
+    let csv = std::fs::read_to_string(format!("{doc_root}/trace-matrix.csv")).unwrap();
+    assert_eq!(
+        csv,
+        "Block,src/a.rs,\"src/b,c.rs\"\nEPIC A,12-18; 40,\nEPIC B,,3-5\n"
+    );
+    write_trace_matrix(&history, doc_root, MatrixFormat::Markdown).unwrap();
+    let markdown = std::fs::read_to_string(format!("{doc_root}/trace-matrix.md")).unwrap();
+    assert_eq!(
+        markdown,
+        "# Traceability matrix\n\n| Block | src/a.rs | src/b,c.rs |\n| --- | --- | --- |\n| EPIC A | 12-18; 40 |  |\n| EPIC B |  | 3-5 |\n"
+    );
+    assert!(MatrixFormat::parse("xml").is_err());
+}
@@ -0,0 +1,180 @@
+use crate::json::{COMMENTS_FILE, read_blocks, write_blocks};
+use crate::provenance::MANIFEST_FILE;
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, write};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// The version of the layout of the document root written by this release. It is raised
+/// whenever a file get-comments reads back, such as the manifest, changes its format.
+pub const LAYOUT_VERSION: u32 = 2;
+/// The file in the document root holding the layout version. A document root without it was
+/// written with layout version 1.
+pub const VERSION_FILE: &str = ".get-comments-version";
+
+/// One step of an upgrade, from a layout version to the next.
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&Path) -> Result<bool, Error>,
+}
+
+/// Every migration, in the order they are applied.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 1,
+        description: "Lower case the directive names in comments.json",
+        apply: lower_case_directives,
+    },
+    Migration {
+        from: 1,
+        description: "Write the paths in the manifest with / as separator",
+        apply: normalise_manifest_paths,
+    },
+];
+
+/// Returns the layout version of a document root, 1 when it has no version file.
+pub fn layout_version(doc_root: &str) -> Result<u32, Error> {
+    match read_to_string(Path::new(doc_root).join(VERSION_FILE)) {
+        Ok(content) => content.trim().parse::<u32>().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid layout version [{}] in {doc_root}", content.trim()),
+            )
+        }),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(1),
+        Err(error) => Err(error),
+    }
+}
+
+/// Records the current layout version in the document root.
+pub fn write_layout_version(doc_root: &str) -> Result<(), Error> {
+    write(
+        Path::new(doc_root).join(VERSION_FILE),
+        format!("{LAYOUT_VERSION}\n"),
+    )
+}
+
+//#EPIC Get Lines.ITEM Upgrade a document root [0]
+//#
+//## Upgrade a document root
+//#A document root keeps files that later runs read back, such as the manifest with the history
+//#of earlier runs. When a release changes the format of such a file, `upgrade` migrates an
+//#existing document root in place, so the history is kept instead of being lost by generating
+//#the tree again.
+/// Migrates a document root written by an older release to the current layout.
+///
+/// The migrations from the layout version of the document root up to `LAYOUT_VERSION` are
+/// applied in order, and the new version is recorded. A document root that is already
+/// current is left alone.
+///
+/// # Returns:
+/// - `Ok(Vec)` with the descriptions of the migrations that changed a file
+/// - `Err(Error)` when the document root is newer than this release, or a file could not be
+///   migrated
+pub fn upgrade(doc_root: &str) -> Result<Vec<&'static str>, Error> {
+    let version = layout_version(doc_root)?;
+    if version > LAYOUT_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "{doc_root} has layout version {version}, this release only knows up to {LAYOUT_VERSION}"
+            ),
+        ));
+    }
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.from >= version)
+    {
+        if (migration.apply)(Path::new(doc_root))? {
+            applied.push(migration.description);
+        }
+    }
+    if version < LAYOUT_VERSION {
+        write_layout_version(doc_root)?;
+    }
+    Ok(applied)
+}
+
+/// Directive names are matched in lower case since layout version 2. Rewrites the blocks
+/// file of the document root with lower case directive names.
+fn lower_case_directives(doc_root: &Path) -> Result<bool, Error> {
+    let file = doc_root.join(COMMENTS_FILE);
+    if !file.is_file() {
+        return Ok(false);
+    }
+    let mut history = read_blocks(&file)?;
+    let mut changed = false;
+    for block in history.values_mut().flat_map(|blocks| blocks.values_mut()) {
+        if block
+            .metadata
+            .keys()
+            .any(|name| *name != name.to_lowercase())
+        {
+            block.metadata = std::mem::take(&mut block.metadata)
+                .into_iter()
+                .map(|(name, value)| (name.to_lowercase(), value))
+                .collect::<BTreeMap<_, _>>();
+            changed = true;
+        }
+    }
+    if changed {
+        write_blocks(&history, "", &file)?;
+    }
+    Ok(changed)
+}
+
+/// The manifest records paths with `/` as separator since layout version 2, so runs on
+/// different platforms can be compared. Rewrites the `\` separators of older manifests.
+fn normalise_manifest_paths(doc_root: &Path) -> Result<bool, Error> {
+    let file = doc_root.join(MANIFEST_FILE);
+    let Ok(content) = read_to_string(&file) else {
+        return Ok(false);
+    };
+    let upgraded: String = content
+        .lines()
+        .map(|line| match line.splitn(3, '\t').collect::<Vec<_>>()[..] {
+            ["file", path, fingerprint] => {
+                format!("file\t{}\t{fingerprint}\n", path.replace('\\', "/"))
+            }
+            _ => format!("{line}\n"),
+        })
+        .collect();
+    if upgraded == content {
+        return Ok(false);
+    }
+    write(&file, upgraded)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+#[test]
+fn test_upgrade() {
+    let doc_root = "target/test-upgrade";
+    let _ = std::fs::remove_dir_all(doc_root);
+    std::fs::create_dir_all(doc_root).unwrap();
+    write(
+        format!("{doc_root}/{MANIFEST_FILE}"),
+        "run\tA\nfile\tEPIC A\\ITEM B.md\t00\n",
+    )
+    .unwrap();
+    write(
+        format!("{doc_root}/{COMMENTS_FILE}"),
+        r#"{"blocks": [{"name": "EPIC A", "sequence": 0, "metadata": {"Status": "beta"}}]}"#,
+    )
+    .unwrap();
+    assert_eq!(layout_version(doc_root).unwrap(), 1);
+    assert_eq!(upgrade(doc_root).unwrap().len(), 2);
+    assert_eq!(layout_version(doc_root).unwrap(), LAYOUT_VERSION);
+    let manifest = read_to_string(format!("{doc_root}/{MANIFEST_FILE}")).unwrap();
+    assert_eq!(manifest, "run\tA\nfile\tEPIC A/ITEM B.md\t00\n");
+    let history = read_blocks(&Path::new(doc_root).join(COMMENTS_FILE)).unwrap();
+    assert_eq!(
+        history["EPIC A"][&crate::sequence::Sequence::from(0)].metadata["status"],
+        "beta"
+    );
+    assert!(upgrade(doc_root).unwrap().is_empty());
+    write(format!("{doc_root}/{VERSION_FILE}"), "9\n").unwrap();
+    assert!(upgrade(doc_root).is_err());
+}
@@ -0,0 +1,134 @@
+use crate::interrupt::is_interrupted;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher, recommended_watcher};
+use std::collections::BTreeSet;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::time::Duration;
+
+/// How long the source folder must be quiet before changes are reported, so saving many files
+/// at once leads to one run.
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+/// How often waiting for changes checks whether the watch was interrupted.
+const POLL_TIME: Duration = Duration::from_millis(200);
+
+//#EPIC Get Lines.ITEM Watch the source folder [0]
+//#
+//## Watch the source folder
+//#With `--watch` the process keeps running after the first run. Every change to a source file
+//#with one of the scanned extensions triggers an incremental run, so the documentation stays in
+//#sync while the code is edited. Ctrl-C ends the watch.
+/// Watches the source folder for changes to the files that a run scans.
+pub struct SourceWatcher {
+    /// The platform watcher sending to `events`; `None` when the events are sent by a test.
+    _watcher: Option<RecommendedWatcher>,
+    events: Receiver<notify::Result<Event>>,
+    extensions: Vec<String>,
+}
+
+impl SourceWatcher {
+    /// Starts watching every file below `root` whose name ends with one of `extensions`.
+    ///
+    /// # Returns:
+    /// - `Err(Error)` when the platform cannot watch the folder
+    pub fn new(root: &str, extensions: &[String]) -> Result<SourceWatcher, Error> {
+        let (sender, events) = channel();
+        let mut watcher = recommended_watcher(sender).map_err(Error::other)?;
+        watcher
+            .watch(Path::new(root), RecursiveMode::Recursive)
+            .map_err(Error::other)?;
+        Ok(SourceWatcher {
+            _watcher: Some(watcher),
+            ..SourceWatcher::from_events(events, extensions)
+        })
+    }
+    /// Creates a watcher that takes the events of the file system from `events` instead of
+    /// the platform, so waiting for changes can be driven without touching the disk.
+    fn from_events(
+        events: Receiver<notify::Result<Event>>,
+        extensions: &[String],
+    ) -> SourceWatcher {
+        SourceWatcher {
+            _watcher: None,
+            events,
+            extensions: extensions.to_vec(),
+        }
+    }
+    /// Blocks until source files were created, changed or removed and the folder has been quiet
+    /// for a moment. An error of the watch is handed to `warn`, and the watch goes on.
+    ///
+    /// # Returns:
+    /// - The paths of the changed source files
+    /// - `None` when the watch was interrupted or stopped
+    pub fn wait_for_changes(&self, warn: impl Fn(String)) -> Option<BTreeSet<PathBuf>> {
+        let mut changed = BTreeSet::new();
+        loop {
+            let timeout = match changed.is_empty() {
+                true => POLL_TIME,
+                false => SETTLE_TIME,
+            };
+            match self.events.recv_timeout(timeout) {
+                Ok(Ok(event)) => changed.extend(self.source_paths(event)),
+                Ok(Err(error)) => warn(format!("watch {error}")),
+                Err(RecvTimeoutError::Timeout) if !changed.is_empty() => return Some(changed),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+            if is_interrupted() {
+                return None;
+            }
+        }
+    }
+    /// Returns the paths of an event that name scanned source files. Reading a file is not a
+    /// change.
+    fn source_paths(&self, event: Event) -> Vec<PathBuf> {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return vec![];
+        }
+        event
+            .paths
+            .into_iter()
+            .filter(|path| {
+                path.file_name().is_some_and(|name| {
+                    let name = name.to_string_lossy();
+                    self.extensions
+                        .iter()
+                        .any(|extension| name.ends_with(extension))
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_wait_for_changes() {
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+    use std::cell::RefCell;
+    let (sender, events) = channel();
+    let watcher = SourceWatcher::from_events(events, &[".rs".to_string()]);
+    let event = |kind, path: &str| Ok(Event::new(kind).add_path(PathBuf::from(path)));
+    sender
+        .send(event(EventKind::Create(CreateKind::File), "src/notes.txt"))
+        .unwrap();
+    sender
+        .send(event(EventKind::Access(AccessKind::Any), "src/read.rs"))
+        .unwrap();
+    sender
+        .send(event(EventKind::Modify(ModifyKind::Any), "src/main.rs"))
+        .unwrap();
+    sender.send(Err(notify::Error::generic("lost"))).unwrap();
+    sender
+        .send(event(EventKind::Modify(ModifyKind::Any), "src/main.rs"))
+        .unwrap();
+    let warnings = RefCell::new(vec![]);
+    let changed = watcher.wait_for_changes(|warning| warnings.borrow_mut().push(warning));
+    assert_eq!(
+        changed,
+        Some(BTreeSet::from([PathBuf::from("src/main.rs")]))
+    );
+    assert_eq!(*warnings.borrow(), ["watch lost"]);
+    // the watch ends when the events stop
+    drop(sender);
+    assert_eq!(watcher.wait_for_changes(|_| {}), None);
+}